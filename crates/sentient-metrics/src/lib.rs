@@ -0,0 +1,310 @@
+//! Prometheus text exposition (format 0.0.4) for the services' `/metrics` endpoints.
+//!
+//! Deliberately small: process-wide `static` counters/gauges bumped from the hot paths with
+//! relaxed atomics, and an [`Exposition`] writer each service uses to render its scrape. There is
+//! no registry; every service lists its own metrics in one `render_metrics()` function.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write as _};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// `Content-Type` of a scrape response.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Something that can write itself (HELP/TYPE + samples) into a scrape.
+pub trait Metric {
+    fn render(&self, out: &mut Exposition);
+}
+
+/// Monotonic counter without labels.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Counter {
+    fn render(&self, out: &mut Exposition) {
+        out.family(self.name, "counter", self.help);
+        out.sample(self.name, &[], self.get());
+    }
+}
+
+/// Gauge without labels (set from the owning loop, or inc/dec around a scope).
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    pub fn set(&self, v: i64) {
+        self.value.store(v, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    fn render(&self, out: &mut Exposition) {
+        out.family(self.name, "gauge", self.help);
+        out.sample(self.name, &[], self.get());
+    }
+}
+
+/// Counter family keyed by label values; label names are fixed at construction.
+///
+/// Keep label values bounded (outcomes, fault kinds, ops) - every distinct set is a series.
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl CounterVec {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn inc(&self, label_values: &[&str]) {
+        self.add(label_values, 1);
+    }
+
+    pub fn add(&self, label_values: &[&str], n: u64) {
+        debug_assert_eq!(label_values.len(), self.labels.len(), "{}", self.name);
+        let key: Vec<String> = label_values.iter().map(|v| v.to_string()).collect();
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        *values.entry(key).or_insert(0) += n;
+    }
+
+    pub fn get(&self, label_values: &[&str]) -> u64 {
+        let key: Vec<String> = label_values.iter().map(|v| v.to_string()).collect();
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        values.get(&key).copied().unwrap_or(0)
+    }
+}
+
+impl Metric for CounterVec {
+    fn render(&self, out: &mut Exposition) {
+        out.family(self.name, "counter", self.help);
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, v) in values.iter() {
+            let labels: Vec<(&str, &str)> = self
+                .labels
+                .iter()
+                .copied()
+                .zip(key.iter().map(String::as_str))
+                .collect();
+            out.sample(self.name, &labels, v);
+        }
+    }
+}
+
+/// Text being rendered for one scrape.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    buf: String,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn metric(&mut self, metric: &dyn Metric) -> &mut Self {
+        metric.render(self);
+        self
+    }
+
+    /// `# HELP` / `# TYPE` lines; call once per family, before its samples.
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.buf, "# HELP {name} {}", escape_help(help));
+        let _ = writeln!(self.buf, "# TYPE {name} {kind}");
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.buf.push_str(name);
+        push_labels(&mut self.buf, labels, None);
+        let _ = writeln!(self.buf, " {value}");
+    }
+
+    /// One histogram series. `counts` are per bucket (not cumulative) with one extra trailing
+    /// entry for `+Inf`; `bounds` and `sum` are already in the exported unit.
+    pub fn histogram(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        bounds: &[f64],
+        counts: &[u64],
+        sum: f64,
+    ) {
+        debug_assert_eq!(counts.len(), bounds.len() + 1, "{name}");
+        let bucket = format!("{name}_bucket");
+        let mut cumulative = 0;
+        for (le, c) in bounds.iter().zip(counts) {
+            cumulative += c;
+            self.buf.push_str(&bucket);
+            push_labels(&mut self.buf, labels, Some(&le.to_string()));
+            let _ = writeln!(self.buf, " {cumulative}");
+        }
+        let count: u64 = counts.iter().sum();
+        self.buf.push_str(&bucket);
+        push_labels(&mut self.buf, labels, Some("+Inf"));
+        let _ = writeln!(self.buf, " {count}");
+        self.sample(&format!("{name}_sum"), labels, sum);
+        self.sample(&format!("{name}_count"), labels, count);
+    }
+
+    pub fn finish(self) -> String {
+        self.buf
+    }
+}
+
+fn push_labels(buf: &mut String, labels: &[(&str, &str)], le: Option<&str>) {
+    if labels.is_empty() && le.is_none() {
+        return;
+    }
+    buf.push('{');
+    let mut first = true;
+    for (k, v) in labels.iter().copied().chain(le.map(|le| ("le", le))) {
+        if !first {
+            buf.push(',');
+        }
+        first = false;
+        let _ = write!(buf, "{k}=\"{}\"", escape_label(v));
+    }
+    buf.push('}');
+}
+
+fn escape_label(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_help(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_renders_cumulative_buckets() {
+        let mut out = Exposition::new();
+        out.family("lat_seconds", "histogram", "Latency.");
+        out.histogram(
+            "lat_seconds",
+            &[("device_id", "door")],
+            &[0.01, 0.1],
+            &[2, 1, 3],
+            0.75,
+        );
+        assert_eq!(
+            out.finish(),
+            "# HELP lat_seconds Latency.\n\
+             # TYPE lat_seconds histogram\n\
+             lat_seconds_bucket{device_id=\"door\",le=\"0.01\"} 2\n\
+             lat_seconds_bucket{device_id=\"door\",le=\"0.1\"} 3\n\
+             lat_seconds_bucket{device_id=\"door\",le=\"+Inf\"} 6\n\
+             lat_seconds_sum{device_id=\"door\"} 0.75\n\
+             lat_seconds_count{device_id=\"door\"} 6\n"
+        );
+    }
+
+    #[test]
+    fn counter_vec_renders_one_series_per_label_set() {
+        let faults = CounterVec::new("faults_total", "Faults.", &["kind"]);
+        faults.inc(&["B"]);
+        faults.add(&["A"], 2);
+        faults.inc(&["B"]);
+        assert_eq!(faults.get(&["B"]), 2);
+        assert_eq!(faults.get(&["C"]), 0);
+        let mut out = Exposition::new();
+        out.metric(&faults);
+        assert_eq!(
+            out.finish(),
+            "# HELP faults_total Faults.\n\
+             # TYPE faults_total counter\n\
+             faults_total{kind=\"A\"} 2\n\
+             faults_total{kind=\"B\"} 2\n"
+        );
+    }
+
+    #[test]
+    fn labels_and_help_are_escaped() {
+        let mut out = Exposition::new();
+        out.family("m", "gauge", "two\nlines \\ here");
+        out.sample("m", &[("v", "a\"b\\c\nd")], 1);
+        assert_eq!(
+            out.finish(),
+            "# HELP m two\\nlines \\\\ here\n# TYPE m gauge\nm{v=\"a\\\"b\\\\c\\nd\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn gauge_and_counter_values() {
+        let g = Gauge::new("g", "G.");
+        g.set(5);
+        g.inc();
+        g.dec();
+        g.dec();
+        assert_eq!(g.get(), 4);
+        let c = Counter::new("c", "C.");
+        c.inc();
+        c.add(4);
+        assert_eq!(c.get(), 5);
+    }
+}
@@ -0,0 +1,102 @@
+//! Dumps JSON Schemas / TypeScript declarations for the sentient-protocol messages.
+//!
+//! Usage:
+//!   sentient-schema json [NAME]   print one message schema (or all, as a JSON object)
+//!   sentient-schema ts            print TypeScript declarations
+//!   sentient-schema write DIR     (re)write DIR/<Name>.schema.json + DIR/sentient-protocol.d.ts
+//!   sentient-schema check DIR     exit non-zero if DIR is out of date with this build
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use sentient_protocol::schema::{message_schemas, typescript_definitions, TYPESCRIPT_FILE_NAME};
+
+fn usage() -> ExitCode {
+    eprintln!("usage: sentient-schema <json [NAME] | ts | write DIR | check DIR>");
+    ExitCode::from(2)
+}
+
+/// (file name, contents) for everything that lives in the schema directory.
+fn generated_files() -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = message_schemas()
+        .iter()
+        .map(|m| (m.file_name(), m.to_pretty_json()))
+        .collect();
+    files.push((TYPESCRIPT_FILE_NAME.to_string(), typescript_definitions()));
+    files
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["json"] => {
+            let mut all = serde_json::Map::new();
+            for m in message_schemas() {
+                all.insert(m.name.to_string(), m.schema.to_value());
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&all).expect("schemas serialize")
+            );
+            ExitCode::SUCCESS
+        }
+        ["json", name] => match message_schemas().into_iter().find(|m| m.name == *name) {
+            Some(m) => {
+                print!("{}", m.to_pretty_json());
+                ExitCode::SUCCESS
+            }
+            None => {
+                eprintln!("unknown message: {name}");
+                ExitCode::FAILURE
+            }
+        },
+        ["ts"] => {
+            print!("{}", typescript_definitions());
+            ExitCode::SUCCESS
+        }
+        ["write", dir] => {
+            let dir = Path::new(dir);
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                eprintln!("create {}: {err}", dir.display());
+                return ExitCode::FAILURE;
+            }
+            for (name, contents) in generated_files() {
+                let path = dir.join(&name);
+                if let Err(err) = std::fs::write(&path, contents) {
+                    eprintln!("write {}: {err}", path.display());
+                    return ExitCode::FAILURE;
+                }
+                println!("wrote {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        ["check", dir] => {
+            let dir = Path::new(dir);
+            let mut stale = Vec::new();
+            for (name, contents) in generated_files() {
+                let path = dir.join(&name);
+                match std::fs::read_to_string(&path) {
+                    Ok(existing) if existing == contents => {}
+                    Ok(_) => stale.push(format!("{} (differs)", path.display())),
+                    Err(_) => stale.push(format!("{} (missing)", path.display())),
+                }
+            }
+            if stale.is_empty() {
+                println!("protocol schemas up to date");
+                return ExitCode::SUCCESS;
+            }
+            eprintln!("protocol schemas out of date:");
+            for entry in stale {
+                eprintln!("  {entry}");
+            }
+            eprintln!(
+                "regenerate with: cargo run -p sentient-protocol --bin sentient-schema -- write {}",
+                dir.display()
+            );
+            ExitCode::FAILURE
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,277 @@
+//! Wire encodings for MQTT payloads.
+//!
+//! JSON stays the default. Devices that want a compact encoding publish on the same topic with a
+//! codec suffix (`room/{room_id}/device/{device_id}/heartbeat/msgpack`) and subscribe to
+//! `.../cmd/msgpack`; core mirrors whatever the device's heartbeats use.
+//!
+//! MessagePack payloads carry exactly the JSON data model (maps with field names, UUIDs as
+//! strings), so firmware can parse both with the same document code (e.g. ArduinoJson's
+//! `deserializeMsgPack`). HMAC signing never looks at the wire bytes: [`crate::signing_string`]
+//! is built from the decoded fields, so a command verifies identically in either encoding.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::version::{upgrade_payload, ProtocolMessage, SchemaError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WireCodec {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Json(serde_json::Error),
+    MsgPackEncode(rmp_serde::encode::Error),
+    MsgPackDecode(rmp_serde::decode::Error),
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "json: {err}"),
+            Self::MsgPackEncode(err) => write!(f, "msgpack encode: {err}"),
+            Self::MsgPackDecode(err) => write!(f, "msgpack decode: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl WireCodec {
+    pub const ALL: &'static [WireCodec] = &[WireCodec::Json, WireCodec::MsgPack];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::MsgPack => "msgpack",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "msgpack" | "messagepack" => Some(Self::MsgPack),
+            _ => None,
+        }
+    }
+
+    /// MIME type, for the MQTT v5 `content-type` property / HTTP.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MsgPack => "application/msgpack",
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next().unwrap_or("").trim() {
+            "application/json" => Some(Self::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Self::MsgPack)
+            }
+            _ => None,
+        }
+    }
+
+    /// Trailing topic segment selecting this codec (`None` = bare topic, JSON).
+    pub fn topic_suffix(self) -> Option<&'static str> {
+        match self {
+            Self::Json => None,
+            Self::MsgPack => Some("msgpack"),
+        }
+    }
+
+    /// `base` with this codec's suffix appended.
+    pub fn topic(self, base: &str) -> String {
+        match self.topic_suffix() {
+            Some(suffix) => format!("{base}/{suffix}"),
+            None => base.to_string(),
+        }
+    }
+
+    /// Split a codec suffix off `topic`; topics without one are JSON.
+    pub fn split_topic(topic: &str) -> (&str, WireCodec) {
+        for codec in Self::ALL {
+            if let Some(suffix) = codec.topic_suffix() {
+                if let Some(base) = topic.strip_suffix(suffix).and_then(|t| t.strip_suffix('/')) {
+                    return (base, *codec);
+                }
+            }
+        }
+        (topic, WireCodec::Json)
+    }
+
+    pub fn encode<T: Serialize>(self, msg: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(msg)?),
+            Self::MsgPack => {
+                // Go through `Value` so non-human-readable serializers don't change the data
+                // model (e.g. UUIDs would otherwise become 16-byte binaries).
+                let value = serde_json::to_value(msg)?;
+                rmp_serde::to_vec_named(&value).map_err(CodecError::MsgPackEncode)
+            }
+        }
+    }
+
+    pub fn decode_value(self, bytes: &[u8]) -> Result<Value, CodecError> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::MsgPack => rmp_serde::from_slice(bytes).map_err(CodecError::MsgPackDecode),
+        }
+    }
+}
+
+impl std::fmt::Display for WireCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// [`crate::decode_message`] for an explicit wire codec.
+pub fn decode_message_as<T: ProtocolMessage>(
+    codec: WireCodec,
+    payload: &[u8],
+) -> Result<T, SchemaError> {
+    let mut value = codec.decode_value(payload).map_err(SchemaError::Codec)?;
+    upgrade_payload(T::MESSAGE, &mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        sign_command_hmac_sha256, verify_command_hmac_sha256, CommandAction, CommandEnvelope,
+        SafetyClass, SCHEMA_VERSION,
+    };
+
+    const KEY: &[u8] = b"device-secret";
+
+    fn command() -> CommandEnvelope {
+        CommandEnvelope {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: "room1".to_string(),
+            device_id: "door_a".to_string(),
+            command_id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
+            sequence: 42,
+            issued_at_unix_ms: 1_700_000_000_000,
+            action: CommandAction::Move,
+            parameters: json!({"position": 120, "speed": 0.5, "nested": {"b": [1, 2], "a": null}}),
+            safety_class: SafetyClass::Critical,
+            execute_at_unix_ms: Some(1_700_000_000_500),
+            auth: None,
+        }
+    }
+
+    #[test]
+    fn command_round_trips_in_every_codec() {
+        let cmd = command();
+        for codec in WireCodec::ALL {
+            let bytes = codec.encode(&cmd).unwrap();
+            let decoded: CommandEnvelope = decode_message_as(*codec, &bytes).unwrap();
+            assert_eq!(decoded, cmd, "{codec}");
+        }
+    }
+
+    #[test]
+    fn signed_command_verifies_after_round_trip() {
+        let mut cmd = command();
+        sign_command_hmac_sha256(&mut cmd, KEY, Some("k1".to_string())).unwrap();
+        for codec in WireCodec::ALL {
+            let bytes = codec.encode(&cmd).unwrap();
+            let decoded: CommandEnvelope = decode_message_as(*codec, &bytes).unwrap();
+            assert_eq!(decoded.auth, cmd.auth, "{codec}");
+            assert!(
+                verify_command_hmac_sha256(&decoded, KEY).unwrap(),
+                "{codec}"
+            );
+            assert!(
+                !verify_command_hmac_sha256(&decoded, b"other-key").unwrap(),
+                "{codec}"
+            );
+        }
+    }
+
+    #[test]
+    fn signature_from_one_codec_verifies_in_the_other() {
+        let mut cmd = command();
+        sign_command_hmac_sha256(&mut cmd, KEY, None).unwrap();
+        let json = WireCodec::Json.encode(&cmd).unwrap();
+        let via_json: CommandEnvelope = decode_message_as(WireCodec::Json, &json).unwrap();
+        let msgpack = WireCodec::MsgPack.encode(&via_json).unwrap();
+        let via_msgpack: CommandEnvelope = decode_message_as(WireCodec::MsgPack, &msgpack).unwrap();
+        assert!(verify_command_hmac_sha256(&via_msgpack, KEY).unwrap());
+    }
+
+    #[test]
+    fn msgpack_keeps_the_json_data_model() {
+        let cmd = command();
+        let bytes = WireCodec::MsgPack.encode(&cmd).unwrap();
+        let value = WireCodec::MsgPack.decode_value(&bytes).unwrap();
+        assert_eq!(value, serde_json::to_value(&cmd).unwrap());
+        assert_eq!(value["command_id"], Value::from(cmd.command_id.to_string()));
+    }
+
+    #[test]
+    fn decode_with_the_wrong_codec_fails() {
+        let bytes = WireCodec::MsgPack.encode(&command()).unwrap();
+        assert!(decode_message_as::<CommandEnvelope>(WireCodec::Json, &bytes).is_err());
+    }
+
+    #[test]
+    fn topic_suffix_selects_codec() {
+        let base = "room/room1/device/door_a/cmd";
+        assert_eq!(WireCodec::Json.topic(base), base);
+        assert_eq!(WireCodec::MsgPack.topic(base), format!("{base}/msgpack"));
+        for codec in WireCodec::ALL {
+            assert_eq!(WireCodec::split_topic(&codec.topic(base)), (base, *codec));
+        }
+    }
+
+    #[test]
+    fn split_topic_requires_a_whole_segment() {
+        assert_eq!(
+            WireCodec::split_topic("room/r/device/msgpack"),
+            ("room/r/device", WireCodec::MsgPack)
+        );
+        assert_eq!(
+            WireCodec::split_topic("room/r/device/notmsgpack"),
+            ("room/r/device/notmsgpack", WireCodec::Json)
+        );
+        assert_eq!(
+            WireCodec::split_topic("msgpack"),
+            ("msgpack", WireCodec::Json)
+        );
+    }
+
+    #[test]
+    fn parse_and_content_type() {
+        assert_eq!(WireCodec::parse(" MessagePack "), Some(WireCodec::MsgPack));
+        assert_eq!(WireCodec::parse("json"), Some(WireCodec::Json));
+        assert_eq!(WireCodec::parse("cbor"), None);
+        for codec in WireCodec::ALL {
+            assert_eq!(
+                WireCodec::from_content_type(codec.content_type()),
+                Some(*codec)
+            );
+        }
+        assert_eq!(
+            WireCodec::from_content_type("application/json; charset=utf-8"),
+            Some(WireCodec::Json)
+        );
+        assert_eq!(WireCodec::from_content_type("text/plain"), None);
+    }
+}
@@ -0,0 +1,507 @@
+//! Typed `CoreFault` kinds and severities.
+//!
+//! Kinds travel on the wire as SCREAMING_SNAKE_CASE strings (e.g. `"GRAPH_TIMEOUT"`). Consumers
+//! built against an older catalog still deserialize newer kinds: anything unrecognized lands in
+//! [`FaultKind::Other`] with the original string preserved, so it re-serializes unchanged.
+//!
+//! Each variant documents the keys producers put in `CoreFault.details`. Keys are additive: new
+//! keys may appear without a catalog bump, existing keys are never repurposed.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Bumped whenever kinds are added to (or retired from) [`FaultKind`].
+pub const FAULT_KIND_CATALOG_VERSION: u32 = 14;
+
+macro_rules! fault_kinds {
+    ($( $(#[$doc:meta])* $variant:ident => $wire:literal, )*) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum FaultKind {
+            $( $(#[$doc])* $variant, )*
+            /// A kind not known to this build (e.g. published by a newer service).
+            Other(String),
+        }
+
+        impl FaultKind {
+            /// Every kind in the current catalog (excludes [`FaultKind::Other`]).
+            pub const KNOWN: &'static [FaultKind] = &[ $( FaultKind::$variant, )* ];
+
+            /// Wire identifier for this kind.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $( Self::$variant => $wire, )*
+                    Self::Other(s) => s.as_str(),
+                }
+            }
+
+            /// Parse a wire identifier; unknown identifiers map to [`FaultKind::Other`].
+            pub fn parse(s: &str) -> Self {
+                match s {
+                    $( $wire => Self::$variant, )*
+                    other => Self::Other(other.to_string()),
+                }
+            }
+        }
+    };
+}
+
+fault_kinds! {
+    // --- Broker / service health ---
+
+    /// Core lost the MQTT broker; dispatch stays paused until manually resumed.
+    ///
+    /// details: `down_since_unix_ms` (u64), `recovered_at_unix_ms` (u64)
+    BrokerOutage => "BROKER_OUTAGE",
+    /// sentient-notify lost the room broker.
+    ///
+    /// details: `error` (string)
+    BrokerUnreachable => "BROKER_UNREACHABLE",
+    /// sentient-notify reconnected to the room broker.
+    ///
+    /// details: `{}`
+    BrokerRestored => "BROKER_RESTORED",
+    /// `core/status` stopped updating (observed by sentient-notify).
+    ///
+    /// details: `core_status_timeout_ms` (u64), `last_core_status_seen_unix_ms` (u64 | null)
+    CoreUnhealthy => "CORE_UNHEALTHY",
+    /// `core/status` updates resumed after [`FaultKind::CoreUnhealthy`].
+    ///
+    /// details: `{}`
+    CoreRestored => "CORE_RESTORED",
+    /// Share of commands accepted within `CORE_SLO_ACCEPT_MS` over the rolling window fell below
+    /// `CORE_SLO_TARGET_PCT` (needs at least `CORE_SLO_MIN_SAMPLES` accepts in the window).
+    ///
+    /// details: `slo_target_ms` (u64), `slo_target_pct` (f64), `window_ms` (u64),
+    /// `window_samples` (u64), `window_within_target_pct` (f64), `accept_p99_ms` (u64 | null),
+    /// `worst` (array of `{device_id, action, max_ms}`, max since core start)
+    DispatchLatencySloBreached => "DISPATCH_LATENCY_SLO_BREACHED",
+    /// Accept latency back within the SLO after [`FaultKind::DispatchLatencySloBreached`].
+    ///
+    /// details: same keys as `DISPATCH_LATENCY_SLO_BREACHED`
+    DispatchLatencySloRecovered => "DISPATCH_LATENCY_SLO_RECOVERED",
+    /// Core cannot reach Postgres (connect, health ping or insert failed). Events keep queueing
+    /// and CRITICAL faults are spilled to disk until it is back.
+    ///
+    /// details: `down_since_unix_ms` (u64), `error` (string)
+    DbOutage => "DB_OUTAGE",
+    /// Postgres reachable again after [`FaultKind::DbOutage`].
+    ///
+    /// details: `down_since_unix_ms` (u64), `recovered_at_unix_ms` (u64), `outage_ms` (u64),
+    /// `spill_pending` (u64)
+    DbRestored => "DB_RESTORED",
+
+    // --- Control plane ---
+
+    /// A `core/control` request failed the `CORE_CONTROL_TOKEN` check.
+    ///
+    /// details: `op` (string)
+    ControlUnauthorized => "CONTROL_UNAUTHORIZED",
+    /// Room dispatch paused by an operator.
+    ///
+    /// details: `{}`
+    DispatchPaused => "DISPATCH_PAUSED",
+    /// Room dispatch resumed by an operator.
+    ///
+    /// details: `{}`
+    DispatchResumed => "DISPATCH_RESUMED",
+
+    // --- Dispatch gating ---
+
+    /// A `core/dispatch` payload could not be parsed.
+    ///
+    /// details: `error` (string), `topic` (string)
+    DispatchRequestInvalid => "DISPATCH_REQUEST_INVALID",
+    /// Dispatch refused because room dispatch is paused.
+    ///
+    /// details: `device_id` (string), `reason` (string | null)
+    DispatchBlockedPaused => "DISPATCH_BLOCKED_PAUSED",
+    /// Dispatch refused because `CORE_DISPATCH_ENABLED=false`.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedDisabled => "DISPATCH_BLOCKED_DISABLED",
+    /// Dispatch ignored because core runs with `DRY_RUN=true`.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedDryRun => "DISPATCH_BLOCKED_DRY_RUN",
+    /// Dispatch refused because the device is disabled in the registry.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedDeviceDisabled => "DISPATCH_BLOCKED_DEVICE_DISABLED",
+    /// Dispatch refused because the device is offline.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedDeviceOffline => "DISPATCH_BLOCKED_DEVICE_OFFLINE",
+    /// CRITICAL dispatch refused because `CORE_CRITICAL_ARMED=false`.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedCriticalNotArmed => "DISPATCH_BLOCKED_CRITICAL_NOT_ARMED",
+    /// CRITICAL dispatch refused because the device does not report SAFE (or is latched).
+    ///
+    /// details: `device_id` (string), `reported_safety` (string | null), `reported_latched` (bool)
+    DispatchBlockedDeviceNotSafe => "DISPATCH_BLOCKED_DEVICE_NOT_SAFE",
+    /// Dispatch refused because the device's safety zone is latched (other zones keep running).
+    ///
+    /// details: `device_id` (string), `zone` (string), `latched_since_unix_ms` (u64)
+    DispatchBlockedZoneLatched => "DISPATCH_BLOCKED_ZONE_LATCHED",
+    /// Dispatch refused by a graph interlock rule (server-side check before signing).
+    ///
+    /// details: `device_id`, `action` (string), `rule_id` (string), `rule` (object as in the
+    /// graph), `condition` (the failed `{device_id, pointer, equals | not_equals}`), `reason`
+    /// ("MISMATCH" | "NO_STATE" | "DEVICE_OFFLINE"), `actual` (JSON | null),
+    /// `graph_version` (i64 | null)
+    DispatchBlockedInterlock => "DISPATCH_BLOCKED_INTERLOCK",
+    /// Dispatch refused because the device or its zone is in maintenance, or the device itself
+    /// reports `MAINTENANCE`. Only a trusted TECH/ADMIN `operator` gets through (token-checked
+    /// when `CORE_CONTROL_TOKEN` is set); graph/automation dispatches never do.
+    ///
+    /// details: `device_id`, `action` (string), `maintenance` (`MaintenanceStatus` | null, null when
+    /// only the device reports it), `reported_by_device` (bool), `operator` (`{sub, role,
+    /// trusted}` | null; `trusted` false when its token is missing or wrong)
+    DispatchBlockedMaintenance => "DISPATCH_BLOCKED_MAINTENANCE",
+    /// Dispatch refused because core has no HMAC key for the device.
+    ///
+    /// details: `device_id` (string)
+    DispatchBlockedMissingDeviceKey => "DISPATCH_BLOCKED_MISSING_DEVICE_KEY",
+    /// Dispatch refused because the device still has a command in flight (`REJECT` policy, or
+    /// `SUPERSEDE` on a device that cannot be sent `CANCEL`).
+    ///
+    /// details: `device_id`, `correlation_id`, `policy` (string), `inflight_command_ids` (array),
+    /// `command_schema` (string)
+    DispatchBlockedDeviceBusy => "DISPATCH_BLOCKED_DEVICE_BUSY",
+    /// Dispatch refused because the device queue is at its max depth (`QUEUE` policy).
+    ///
+    /// details: `device_id`, `correlation_id`, `max_depth` (u64)
+    DispatchQueueFull => "DISPATCH_QUEUE_FULL",
+    /// Queued dispatches were discarded before reaching the device.
+    ///
+    /// details: `device_id`, `correlation_ids` (array), `reason` (string)
+    DispatchQueueDropped => "DISPATCH_QUEUE_DROPPED",
+    /// A batch dispatch was refused as a whole because at least one member would be blocked;
+    /// nothing was published.
+    ///
+    /// details: `correlation_id`, `blocked` (array of `{device_id, kind}`)
+    DispatchBatchBlocked => "DISPATCH_BATCH_BLOCKED",
+    /// A batch dispatch finished with at least one member not `COMPLETED`.
+    ///
+    /// details: `correlation_id`, `status` (string), `members` (array of `BatchMemberResult`)
+    DispatchBatchFailed => "DISPATCH_BATCH_FAILED",
+    /// A scheduled dispatch could not be released within `CORE_SCHEDULE_MAX_LATE_MS` of its
+    /// execute time (e.g. core was paused or stalled); it was not published.
+    ///
+    /// details: `device_id`, `correlation_id`, `execute_at_unix_ms` (u64), `late_ms` (u64)
+    DispatchScheduleMissed => "DISPATCH_SCHEDULE_MISSED",
+
+    // --- Command lifecycle ---
+
+    /// Device answered `REJECTED`.
+    ///
+    /// details: `device_id`, `command_id`, `correlation_id`, `reason_code` (string | null)
+    CommandRejected => "COMMAND_REJECTED",
+    /// No `ACCEPTED` ack after all retries.
+    ///
+    /// details: `device_id`, `command_id`, `correlation_id`, `ack_timeout_ms` (u64)
+    CommandAckTimeout => "COMMAND_ACK_TIMEOUT",
+    /// No `COMPLETED` ack within the completion window after `ACCEPTED` (or the last
+    /// `IN_PROGRESS`).
+    ///
+    /// details: `device_id`, `command_id`, `correlation_id`, `complete_timeout_ms` (u64),
+    /// `last_progress` (`CommandProgress` | null)
+    CommandCompleteTimeout => "COMMAND_COMPLETE_TIMEOUT",
+    /// Device confirmed a command was aborted (`CANCELLED` ack).
+    ///
+    /// details: `device_id`, `command_id`, `correlation_id`, `cancel_reason` (string | null)
+    CommandCancelled => "COMMAND_CANCELLED",
+    /// Cancel could not be sent (device schema predates `CANCEL`, or no HMAC key).
+    ///
+    /// details: `device_id`, `command_id`, `command_schema` (string), `cancel_reason` (string),
+    /// `error` (string)
+    CommandCancelUnsupported => "COMMAND_CANCEL_UNSUPPORTED",
+    /// A cancel was published but never confirmed: the `CANCEL` envelope got no ack after all
+    /// retries (`CANCEL_NOT_ACKED`), or the cancelled command timed out without a `CANCELLED` ack
+    /// (`CANCEL_NOT_CONFIRMED`). The command may still be running.
+    ///
+    /// details: `device_id`, `command_id` (the cancelled command), `cancel_command_id` (uuid |
+    /// null), `correlation_id` (of the cancelled command, uuid | null), `cancel_reason` (string |
+    /// null), `timeout_ms` (u64), `reason` ("CANCEL_NOT_ACKED" | "CANCEL_NOT_CONFIRMED")
+    CommandCancelTimeout => "COMMAND_CANCEL_TIMEOUT",
+
+    // --- Device liveness / safety ---
+
+    /// Device went offline (presence OFFLINE or heartbeat timeout).
+    ///
+    /// details: `device_id`, `source` ("heartbeat" | "presence" | "sweep"),
+    /// `presence` (string | null), `last_heartbeat_at_unix_ms` (u64 | null), `device_offline_ms` (u64,
+    /// the device's effective threshold)
+    DeviceOffline => "DEVICE_OFFLINE",
+    /// Device came back online.
+    ///
+    /// details: `device_id`, `source`, `presence` (string | null), `last_heartbeat_at_unix_ms` (u64 | null)
+    DeviceOnline => "DEVICE_ONLINE",
+    /// Device changed ONLINE/OFFLINE at least `DEVICE_FLAP_THRESHOLD` times within
+    /// `DEVICE_FLAP_WINDOW_MS`. `WARN` when flapping starts; `DEVICE_ONLINE`/`DEVICE_OFFLINE` are
+    /// then suppressed (offline policy still applies) until a full window passes without a change,
+    /// which emits it again as `INFO` with `flapping: false` and the suppressed counts.
+    ///
+    /// details: `device_id`, `flapping` (bool), `is_offline` (bool), `transitions` (u32, in the
+    /// window), `window_ms` (u64), `threshold` (u32), `since_unix_ms` (u64),
+    /// `suppressed_offline` (u32), `suppressed_online` (u32)
+    DeviceFlapping => "DEVICE_FLAPPING",
+    /// Measured device clock offset exceeds `CORE_CLOCK_OFFSET_MAX_MS` (ping/pong, v8.5+). Core
+    /// stops sending scheduled commands early to the device until it is back in range.
+    ///
+    /// details: `device_id`, `offset_ms` (i64, device minus core), `rtt_ms` (u64), `max_offset_ms` (u64)
+    DeviceClockOffset => "DEVICE_CLOCK_OFFSET",
+    /// Device clock offset back within `CORE_CLOCK_OFFSET_MAX_MS`.
+    ///
+    /// details: `device_id`, `offset_ms` (i64), `rtt_ms` (u64), `max_offset_ms` (u64)
+    DeviceClockSynced => "DEVICE_CLOCK_SYNCED",
+    /// Device's reported `safety_state` changed (heartbeat, state or ACK); emitted once per
+    /// transition, `INFO` when back to SAFE, `WARN` otherwise. A first report only counts if not SAFE.
+    ///
+    /// details: `device_id`, `safety_state` (string), `previous_safety_state` (string | null),
+    /// `reason_code` (string | null), `latched` (bool), `source` (`HEARTBEAT` | `STATE` | `ACK`)
+    DeviceSafetyState => "DEVICE_SAFETY_STATE",
+    /// Safety latched until an explicit reset. With `zone` null the whole room is latched and
+    /// dispatch paused; otherwise only dispatch to that zone's devices is blocked.
+    ///
+    /// details: `device_id`, `safety_kind` (string), `reason_code` (string | null), `latched` (bool),
+    /// `zone` (string | null), `escalated_from_zone` (string | null, zone of a device whose event
+    /// latched the room per `CORE_SAFETY_ESCALATION`)
+    SafetyLatched => "SAFETY_LATCHED",
+    /// `RESET_SAFETY_LATCH` refused because devices are not SAFE/online.
+    ///
+    /// details: `blockers` (array of `"{device_id}:{state}"` strings), `zone` (string | null),
+    /// `checklist` (array of `{device_id, online, safety_kind, latched, reason_code, ok}`, one per
+    /// device checked), `operators` (`{reset_id, requested_by, confirmed_by, reason}`, null fields
+    /// when not sent through sentient-api)
+    SafetyResetDenied => "SAFETY_RESET_DENIED",
+    /// Safety latch cleared by an operator (one zone, or the room together with every zone).
+    ///
+    /// details: `zone` (string | null), `zones` (array of `{zone, device_id, safety_kind,
+    /// latched_since_unix_ms}` for the zone latches cleared), `checklist` and `operators` (as for
+    /// `SAFETY_RESET_DENIED`)
+    SafetyLatchReset => "SAFETY_LATCH_RESET",
+    /// Device or zone put into maintenance (or its note updated by the same owner). Raised once
+    /// the lock is stored in the room DB.
+    ///
+    /// details: `maintenance` (`MaintenanceStatus`)
+    MaintenanceSet => "MAINTENANCE_SET",
+    /// Maintenance lock released (and deleted from the room DB).
+    ///
+    /// details: `maintenance` (the released `MaintenanceStatus`), `released_by` (string),
+    /// `released_by_role` (string)
+    MaintenanceReleased => "MAINTENANCE_RELEASED",
+    /// `SET_MAINTENANCE` / `RELEASE_MAINTENANCE` refused.
+    ///
+    /// details: `op` (string), `scope` (string | null), `target` (string | null), `reason`
+    /// ("INVALID_TARGET" | "MISSING_OPERATOR" | "WRITE_IN_PROGRESS" | "LOCKED_BY_OTHER" |
+    /// "NOT_LOCKED" | "NOT_OWNER" | "DB_UNAVAILABLE" | "NOT_PERSISTED"), `actor` (string | null),
+    /// `maintenance` (existing `MaintenanceStatus` | null), `error` (string, `NOT_PERSISTED` only)
+    MaintenanceDenied => "MAINTENANCE_DENIED",
+    /// A device missing from the registry sent its first heartbeat since core started. Core still
+    /// tracks it (as NON_CRITICAL) and lists it in the `unregistered_devices` inbox until adopted.
+    ///
+    /// details: `device_id`, `firmware_version` (string), `supported_schemas` (string[]),
+    /// `topic` (string)
+    DeviceUnregistered => "DEVICE_UNREGISTERED",
+    /// Device registry and DB-provisioned HMAC keys re-read (`RELOAD_DEVICE_REGISTRY`).
+    ///
+    /// details: `devices` (u64, registry entries), `hmac_keys` (u64), `adopted` (string[], devices
+    /// that were unregistered and now are)
+    DeviceRegistryReloaded => "DEVICE_REGISTRY_RELOADED",
+
+    // --- Graph runtime ---
+
+    /// Graph execution started.
+    ///
+    /// details: `version` (i64 | null), `starts` (array of node ids)
+    GraphStarted => "GRAPH_STARTED",
+    /// Graph execution stopped by an operator.
+    ///
+    /// details: `version` (i64 | null)
+    GraphStopped => "GRAPH_STOPPED",
+    /// `START_GRAPH` refused (dispatch paused or no graph loaded).
+    ///
+    /// details: `{}`
+    GraphStartDenied => "GRAPH_START_DENIED",
+    /// Active graph reloaded from the room DB.
+    ///
+    /// details: `version` (i64)
+    GraphReloaded => "GRAPH_RELOADED",
+    /// `RELOAD_GRAPH` refused (graph running or dispatch not paused).
+    ///
+    /// details: `{}`
+    GraphReloadDenied => "GRAPH_RELOAD_DENIED",
+    /// `RELOAD_GRAPH` failed.
+    ///
+    /// details: optional `error` (string), optional `graph_schema` / `graph_room_id` (string)
+    GraphReloadFailed => "GRAPH_RELOAD_FAILED",
+    /// A `WAIT_STATE_EQUALS` node hit its timeout; the graph stops.
+    ///
+    /// details: `node_id`, `device_id`, `pointer`, `equals` (any), `timeout_ms` (u64)
+    GraphTimeout => "GRAPH_TIMEOUT",
+    /// A `DISPATCH` / `DISPATCH_BATCH` node did not produce an inflight command; the graph stops.
+    ///
+    /// details: `node_id`, `device_id` (`device_ids` for batches), `correlation_id`
+    GraphDispatchFailed => "GRAPH_DISPATCH_FAILED",
+
+    // --- Audio ---
+
+    /// osc-bridge exhausted retries delivering a cue to SCS.
+    ///
+    /// details: `cue_id`, `correlation_id`, `attempts` (u32), `error` (string)
+    OscSendFailed => "OSC_SEND_FAILED",
+}
+
+impl JsonSchema for FaultKind {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "FaultKind".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let known: Vec<&str> = Self::KNOWN.iter().map(|k| k.as_str()).collect();
+        schemars::json_schema!({
+            "description": "Machine-readable fault identifier. Consumers must tolerate kinds outside the known set.",
+            "anyOf": [
+                { "type": "string", "enum": known },
+                { "type": "string" }
+            ]
+        })
+    }
+}
+
+impl std::fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for FaultKind {
+    fn from(s: &str) -> Self {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for FaultKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FaultKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::parse(&s))
+    }
+}
+
+/// Fault severity. Ordered so consumers can filter with `severity >= Severity::Warn`.
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Severity {
+    Info,
+    Warn,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Critical => "CRITICAL",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn known_kinds_serialize_to_wire_strings() {
+        let cases = [
+            (FaultKind::BrokerOutage, "BROKER_OUTAGE"),
+            (FaultKind::DispatchBlockedPaused, "DISPATCH_BLOCKED_PAUSED"),
+            (
+                FaultKind::DispatchBlockedZoneLatched,
+                "DISPATCH_BLOCKED_ZONE_LATCHED",
+            ),
+            (FaultKind::MaintenanceDenied, "MAINTENANCE_DENIED"),
+            (FaultKind::DeviceUnregistered, "DEVICE_UNREGISTERED"),
+            (FaultKind::GraphTimeout, "GRAPH_TIMEOUT"),
+            (FaultKind::OscSendFailed, "OSC_SEND_FAILED"),
+        ];
+        for (kind, wire) in cases {
+            assert_eq!(serde_json::to_value(&kind).unwrap(), Value::from(wire));
+            assert_eq!(
+                serde_json::from_value::<FaultKind>(Value::from(wire)).unwrap(),
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn every_known_kind_round_trips() {
+        for kind in FaultKind::KNOWN {
+            let wire = serde_json::to_string(kind).unwrap();
+            assert_eq!(&serde_json::from_str::<FaultKind>(&wire).unwrap(), kind);
+            assert_eq!(FaultKind::parse(kind.as_str()), *kind);
+            assert!(!matches!(kind, FaultKind::Other(_)));
+        }
+    }
+
+    #[test]
+    fn known_wire_strings_are_unique() {
+        let mut seen = std::collections::HashSet::new();
+        for kind in FaultKind::KNOWN {
+            assert!(seen.insert(kind.as_str()), "duplicate wire string {kind}");
+        }
+    }
+
+    #[test]
+    fn unknown_kind_lands_in_other_and_reserializes_unchanged() {
+        let kind: FaultKind = serde_json::from_str("\"SOME_FUTURE_FAULT\"").unwrap();
+        assert_eq!(kind, FaultKind::Other("SOME_FUTURE_FAULT".to_string()));
+        assert_eq!(
+            serde_json::to_string(&kind).unwrap(),
+            "\"SOME_FUTURE_FAULT\""
+        );
+        assert_eq!(kind.to_string(), "SOME_FUTURE_FAULT");
+    }
+
+    #[test]
+    fn severity_orders_info_warn_critical() {
+        assert!(Severity::Info < Severity::Warn);
+        assert!(Severity::Warn < Severity::Critical);
+        let mut all = vec![Severity::Critical, Severity::Info, Severity::Warn];
+        all.sort();
+        assert_eq!(all, [Severity::Info, Severity::Warn, Severity::Critical]);
+    }
+
+    #[test]
+    fn severity_wire_strings() {
+        for (severity, wire) in [
+            (Severity::Info, "INFO"),
+            (Severity::Warn, "WARN"),
+            (Severity::Critical, "CRITICAL"),
+        ] {
+            assert_eq!(serde_json::to_value(severity).unwrap(), Value::from(wire));
+            assert_eq!(severity.as_str(), wire);
+            assert_eq!(
+                serde_json::from_value::<Severity>(Value::from(wire)).unwrap(),
+                severity
+            );
+        }
+    }
+}
@@ -1,7 +1,22 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-pub const SCHEMA_VERSION: &str = "v8";
+mod codec;
+mod fault;
+pub mod schema;
+mod version;
+
+pub use codec::{decode_message_as, CodecError, WireCodec};
+pub use fault::{FaultKind, Severity, FAULT_KIND_CATALOG_VERSION};
+pub use version::{
+    decode_message, is_accepted_schema, negotiate_schema, schema_at_least, upgrade_payload,
+    ProtocolMessage, SchemaError, ACCEPTED_SCHEMA_VERSIONS, CANCEL_MIN_SCHEMA,
+    DEFAULT_DEVICE_SCHEMA, PROGRESS_MIN_SCHEMA, SCHEDULE_MIN_SCHEMA, SCHEMA_V8, SCHEMA_V8_1,
+    SCHEMA_V8_2, SCHEMA_V8_3, SCHEMA_V8_4, SCHEMA_V8_5, SCHEMA_V8_6, SCHEMA_VERSION,
+    TIME_SYNC_MIN_SCHEMA, WATCHDOG_MIN_SCHEMA,
+};
+
 pub const AUTH_ALG_HMAC_SHA256: &str = "HMAC-SHA256";
 pub const CORE_CONTROL_OP_PAUSE_DISPATCH: &str = "PAUSE_DISPATCH";
 pub const CORE_CONTROL_OP_RESUME_DISPATCH: &str = "RESUME_DISPATCH";
@@ -9,15 +24,35 @@ pub const CORE_CONTROL_OP_RESET_SAFETY_LATCH: &str = "RESET_SAFETY_LATCH";
 pub const CORE_CONTROL_OP_START_GRAPH: &str = "START_GRAPH";
 pub const CORE_CONTROL_OP_STOP_GRAPH: &str = "STOP_GRAPH";
 pub const CORE_CONTROL_OP_RELOAD_GRAPH: &str = "RELOAD_GRAPH";
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// `parameters.command_id`: cancel one pending command.
+pub const CORE_CONTROL_OP_CANCEL_COMMAND: &str = "CANCEL_COMMAND";
+/// `parameters.device_id`: cancel every pending command for a device.
+pub const CORE_CONTROL_OP_CANCEL_DEVICE_COMMANDS: &str = "CANCEL_DEVICE_COMMANDS";
+/// `parameters.device_id` or `parameters.zone`, `owner`, `owner_role`, optional `note`: take a
+/// device/zone out of show control (see [`MaintenanceStatus`]).
+pub const CORE_CONTROL_OP_SET_MAINTENANCE: &str = "SET_MAINTENANCE";
+/// `parameters.device_id` or `parameters.zone`, `released_by`, `released_by_role`: only the owner
+/// or an `ADMIN` may release.
+pub const CORE_CONTROL_OP_RELEASE_MAINTENANCE: &str = "RELEASE_MAINTENANCE";
+/// Re-read the device registry and provisioned HMAC keys from the room DB (e.g. after adopting
+/// an unregistered device).
+pub const CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY: &str = "RELOAD_DEVICE_REGISTRY";
+
+/// `SafetyState.reason_code` a device reports while in its safe state because the
+/// [`CoreWatchdog`] went silent.
+pub const SAFETY_REASON_CORE_LOST: &str = "CORE_LOST";
+
+/// `parameters` key of a `CANCEL` command naming the command to abort.
+pub const CANCEL_PARAM_COMMAND_ID: &str = "command_id";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SafetyClass {
     Critical,
     NonCritical,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SafetyStateKind {
     Safe,
@@ -27,7 +62,7 @@ pub enum SafetyStateKind {
     Maintenance,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct SafetyState {
     pub kind: SafetyStateKind,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -35,16 +70,19 @@ pub struct SafetyState {
     pub latched: bool,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum CommandAction {
     Open,
     Close,
     Move,
     Set,
+    /// Abort the command named by `parameters.command_id` (v8.2+). The device acks the target
+    /// with `CANCELLED` once it has stopped, then completes the cancel itself.
+    Cancel,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct CommandAuth {
     /// Authentication scheme identifier.
     /// v8 default: "HMAC-SHA256"
@@ -56,7 +94,7 @@ pub struct CommandAuth {
     pub mac_hex: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct CommandEnvelope {
     pub schema: String,
     pub room_id: String,
@@ -69,17 +107,42 @@ pub struct CommandEnvelope {
     #[serde(default)]
     pub parameters: serde_json::Value,
     pub safety_class: SafetyClass,
+    /// Room time (unix ms) at which the device should execute (`v8.4`+, devices with synced
+    /// clocks). Absent means "now". Signed when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_at_unix_ms: Option<u64>,
     /// Optional at the protocol layer; required for real hardware deployments.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auth: Option<CommandAuth>,
 }
 
+impl CommandEnvelope {
+    /// For `CANCEL` commands, the command being cancelled.
+    pub fn cancel_target(&self) -> Option<Uuid> {
+        if self.action != CommandAction::Cancel {
+            return None;
+        }
+        self.parameters
+            .get(CANCEL_PARAM_COMMAND_ID)
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+    }
+}
+
+impl CommandAction {
+    /// Wire spelling (e.g. `"OPEN"`).
+    pub fn as_str(self) -> &'static str {
+        command_action_str(self)
+    }
+}
+
 fn command_action_str(a: CommandAction) -> &'static str {
     match a {
         CommandAction::Open => "OPEN",
         CommandAction::Close => "CLOSE",
         CommandAction::Move => "MOVE",
         CommandAction::Set => "SET",
+        CommandAction::Cancel => "CANCEL",
     }
 }
 
@@ -117,7 +180,7 @@ pub fn canonical_parameters_json(parameters: &serde_json::Value) -> serde_json::
 pub fn signing_string(cmd: &CommandEnvelope) -> serde_json::Result<String> {
     let params = canonical_parameters_json(&cmd.parameters)?;
 
-    Ok(format!(
+    let mut out = format!(
         "schema={}\nroom_id={}\ndevice_id={}\ncommand_id={}\ncorrelation_id={}\nsequence={}\nissued_at_unix_ms={}\naction={}\nsafety_class={}\nparameters={}",
         cmd.schema,
         cmd.room_id,
@@ -129,7 +192,12 @@ pub fn signing_string(cmd: &CommandEnvelope) -> serde_json::Result<String> {
         command_action_str(cmd.action),
         safety_class_str(cmd.safety_class),
         params
-    ))
+    );
+    // Appended only when present so signatures of unscheduled commands are unchanged.
+    if let Some(execute_at) = cmd.execute_at_unix_ms {
+        out.push_str(&format!("\nexecute_at_unix_ms={execute_at}"));
+    }
+    Ok(out)
 }
 
 pub fn hmac_sha256_hex(key: &[u8], signing_bytes: &[u8]) -> String {
@@ -169,20 +237,80 @@ pub fn verify_command_hmac_sha256(cmd: &CommandEnvelope, key: &[u8]) -> serde_js
     Ok(constant_time_eq_hex(&expected, &auth.mac_hex))
 }
 
+/// Canonical string a [`CoreWatchdog`] MAC is computed over. The leading `message=` line keeps a
+/// watchdog MAC from ever verifying as a command signature.
+pub fn watchdog_signing_string(msg: &CoreWatchdog) -> String {
+    format!(
+        "message=CoreWatchdog\nschema={}\nroom_id={}\ndevice_id={}\nboot_id={}\ncounter={}\ninterval_ms={}\ntimeout_ms={}\nissued_at_unix_ms={}",
+        msg.schema,
+        msg.room_id,
+        msg.device_id,
+        msg.boot_id,
+        msg.counter,
+        msg.interval_ms,
+        msg.timeout_ms,
+        msg.issued_at_unix_ms
+    )
+}
+
+pub fn sign_watchdog_hmac_sha256(msg: &mut CoreWatchdog, key: &[u8], kid: Option<String>) {
+    let mac_hex = hmac_sha256_hex(key, watchdog_signing_string(msg).as_bytes());
+    msg.auth = Some(CommandAuth {
+        alg: AUTH_ALG_HMAC_SHA256.to_string(),
+        kid,
+        mac_hex,
+    });
+}
+
+pub fn verify_watchdog_hmac_sha256(msg: &CoreWatchdog, key: &[u8]) -> bool {
+    let Some(auth) = &msg.auth else {
+        return false;
+    };
+    if auth.alg != AUTH_ALG_HMAC_SHA256 {
+        return false;
+    }
+    let expected = hmac_sha256_hex(key, watchdog_signing_string(msg).as_bytes());
+    constant_time_eq_hex(&expected, &auth.mac_hex)
+}
+
 fn constant_time_eq_hex(a: &str, b: &str) -> bool {
     use subtle::ConstantTimeEq;
     a.as_bytes().ct_eq(b.as_bytes()).into()
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AckStatus {
     Accepted,
     Rejected,
     Completed,
+    /// The command was aborted by a `CANCEL` command before completing (v8.2+).
+    Cancelled,
+    /// Still executing; carries [`CommandAck::progress`] (v8.3+). Each one restarts core's
+    /// completion timeout.
+    InProgress,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Progress of a long-running command, reported with `IN_PROGRESS` acks.
+///
+/// All fields are optional; devices report whatever they can measure.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+pub struct CommandProgress {
+    /// Percent complete (0..=100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<u8>,
+    /// Current position in device units (e.g. mm, steps).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
+    /// Position the command is driving towards, in the same units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_position: Option<i64>,
+    /// Device's estimate of the time left.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eta_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct CommandAck {
     pub schema: String,
     pub room_id: String,
@@ -192,11 +320,14 @@ pub struct CommandAck {
     pub status: AckStatus,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reason_code: Option<String>,
+    /// Only on `IN_PROGRESS` acks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<CommandProgress>,
     pub safety_state: SafetyState,
     pub observed_at_unix_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct Heartbeat {
     pub schema: String,
     pub room_id: String,
@@ -206,17 +337,21 @@ pub struct Heartbeat {
     pub safety_state: SafetyState,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_error: Option<String>,
+    /// Schema versions this device can parse (v8.1+). Absent on v8 firmware, which core treats
+    /// as v8-only; see [`negotiate_schema`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supported_schemas: Vec<String>,
     pub observed_at_unix_ms: u64,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PresenceStatus {
     Online,
     Offline,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct Presence {
     pub schema: String,
     pub room_id: String,
@@ -225,11 +360,88 @@ pub struct Presence {
     pub observed_at_unix_ms: u64,
 }
 
+/// Clock probe from core (v8.5+), on `room/{room_id}/device/{device_id}/ping`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TimePing {
+    pub schema: String,
+    pub room_id: String,
+    pub device_id: String,
+    pub ping_id: Uuid,
+    /// Core clock when the ping was sent (t1).
+    pub core_sent_at_unix_ms: u64,
+}
+
+/// Device answer to a [`TimePing`], on `room/{room_id}/device/{device_id}/pong`. Sent
+/// immediately; both device timestamps are read from the device's own wall clock.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TimePong {
+    pub schema: String,
+    pub room_id: String,
+    pub device_id: String,
+    /// Echoed from the ping.
+    pub ping_id: Uuid,
+    /// Echoed from the ping (t1).
+    pub core_sent_at_unix_ms: u64,
+    /// Device clock when the ping arrived (t2).
+    pub device_received_at_unix_ms: u64,
+    /// Device clock when the pong was sent (t3).
+    pub device_sent_at_unix_ms: u64,
+}
+
+/// Core liveness beacon (v8.6+), on `room/{room_id}/device/{device_id}/watchdog`. Sent every
+/// `interval_ms` and signed with the device's HMAC key; a device that receives no valid beacon for
+/// `timeout_ms` must assume core is gone and go to its safe state (dead-man switch).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CoreWatchdog {
+    pub schema: String,
+    pub room_id: String,
+    pub device_id: String,
+    /// Random per core process; a new value means core restarted and `counter` starts over.
+    pub boot_id: Uuid,
+    /// Starts at 1 and strictly increases within one `boot_id`. Devices drop beacons that do not
+    /// advance it (replays, duplicates).
+    pub counter: u64,
+    /// How often core sends the beacon (`CORE_WATCHDOG_INTERVAL_MS`).
+    pub interval_ms: u64,
+    /// Silence after which the device should treat core as lost (`CORE_WATCHDOG_TIMEOUT_MS`).
+    pub timeout_ms: u64,
+    pub issued_at_unix_ms: u64,
+    /// HMAC over [`watchdog_signing_string`]; absent only when core has no key for the device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<CommandAuth>,
+}
+
+/// One ping/pong measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// Device clock minus core clock (positive = device ahead).
+    pub offset_ms: i64,
+    /// Network round trip, excluding the device's own turnaround time.
+    pub rtt_ms: u64,
+}
+
+/// NTP-style estimate from a pong received at `core_received_at_unix_ms` (t4). `core_sent_at` is
+/// core's own record of t1, not the echoed value.
+pub fn clock_sample(
+    pong: &TimePong,
+    core_sent_at: u64,
+    core_received_at_unix_ms: u64,
+) -> ClockSample {
+    let t1 = core_sent_at as i64;
+    let t2 = pong.device_received_at_unix_ms as i64;
+    let t3 = pong.device_sent_at_unix_ms as i64;
+    let t4 = core_received_at_unix_ms as i64;
+    ClockSample {
+        offset_ms: ((t2 - t1) + (t3 - t4)) / 2,
+        rtt_ms: ((t4 - t1) - (t3 - t2)).max(0) as u64,
+    }
+}
+
 /// Generic device state snapshot.
 ///
 /// Each controller can publish a retained "last known" state for UIs/tools and
 /// for core restart recovery. The `state` object is device-specific.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct DeviceState {
     pub schema: String,
     pub room_id: String,
@@ -244,7 +456,7 @@ pub struct DeviceState {
 ///
 /// This is an MQTT-only control plane intended for commissioning and early
 /// integration before the HTTP/WebSocket APIs exist.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct CoreDispatchRequest {
     pub schema: String,
     pub room_id: String,
@@ -266,13 +478,210 @@ pub struct CoreDispatchRequest {
     /// Override completion timeout (ms).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub complete_timeout_ms: Option<u64>,
+    /// Run at this room time (unix ms) instead of now. Core holds the request until it is due, or
+    /// sends it early with `CommandEnvelope.execute_at_unix_ms` to devices that support it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_at_unix_ms: Option<u64>,
+    /// Like `execute_at_unix_ms`, relative to when core receives the request. Ignored when
+    /// `execute_at_unix_ms` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_in_ms: Option<u64>,
+    /// Where core publishes [`CoreDispatchResult`]s for this request. Must start with
+    /// [`dispatch_reply_prefix`]; ignored otherwise. Core assigns a `correlation_id` if omitted.
+    /// An MQTT v5 response topic on the request takes precedence; this field is for 3.1.1 clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_topic: Option<String>,
+    /// Operator the request is sent for (set by sentient-api from the caller's credentials);
+    /// absent for graph/automation dispatches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<DispatchOperator>,
+}
+
+/// Who asked for a manual dispatch. A TECH/ADMIN operator may drive devices under maintenance.
+///
+/// Core trusts it under the same rule as control ops: when `CORE_CONTROL_TOKEN` is set, only if
+/// `token` matches it; otherwise any publisher on `core/dispatch` can claim a role.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DispatchOperator {
+    pub sub: String,
+    pub role: String,
+    /// `CORE_CONTROL_TOKEN`, added by sentient-api when configured. Never echoed in faults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// One device command inside a [`CoreBatchDispatchRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct BatchCommand {
+    pub device_id: String,
+    pub action: CommandAction,
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+    #[serde(default = "default_safety_class_non_critical")]
+    pub safety_class: SafetyClass,
+}
+
+/// Several device commands gated together ("scene" cue): either every member passes the dispatch
+/// gates and is published, or none is. All members share `correlation_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct CoreBatchDispatchRequest {
+    pub schema: String,
+    pub room_id: String,
+    /// Shared by every member command; core assigns one if omitted. Also the idempotency key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
+    /// At most one command per device.
+    pub commands: Vec<BatchCommand>,
+    /// Override retry count for every member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Override ack timeout (ms) for every member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ack_timeout_ms: Option<u64>,
+    /// Override completion timeout (ms) for every member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complete_timeout_ms: Option<u64>,
+    /// Where core publishes the [`CoreBatchDispatchResult`]; same rules as
+    /// [`CoreDispatchRequest::reply_topic`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_topic: Option<String>,
+    /// Applies to every member; see [`CoreDispatchRequest::operator`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<DispatchOperator>,
+}
+
+/// Topic prefix core accepts for [`CoreDispatchRequest::reply_topic`]
+/// (`room/{room_id}/core/dispatch/reply/`).
+pub fn dispatch_reply_prefix(room_id: &str) -> String {
+    format!("room/{room_id}/core/dispatch/reply/")
+}
+
+/// MQTT v5 user property naming the trace a message belongs to. Set by sentient-api on
+/// dispatches, copied by core onto the device commands and results, echoed back on acks.
+pub const MQTT_USER_PROPERTY_TRACE_ID: &str = "trace_id";
+
+/// Longest trace id services accept or forward.
+pub const TRACE_ID_MAX_LEN: usize = 128;
+
+/// The [`MQTT_USER_PROPERTY_TRACE_ID`] value among MQTT v5 user properties, if present and sane
+/// (non-empty, at most [`TRACE_ID_MAX_LEN`] bytes, printable ASCII).
+pub fn trace_id_from_user_properties(user_properties: &[(String, String)]) -> Option<&str> {
+    user_properties
+        .iter()
+        .find(|(k, _)| k == MQTT_USER_PROPERTY_TRACE_ID)
+        .map(|(_, v)| v.as_str())
+        .filter(|v| is_valid_trace_id(v))
+}
+
+/// Whether `trace_id` may be carried as a [`MQTT_USER_PROPERTY_TRACE_ID`].
+pub fn is_valid_trace_id(trace_id: &str) -> bool {
+    !trace_id.is_empty()
+        && trace_id.len() <= TRACE_ID_MAX_LEN
+        && trace_id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// How far a dispatch got, as reported in [`CoreDispatchResult`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DispatchResultStatus {
+    /// Device acked `ACCEPTED` (or `IN_PROGRESS`).
+    Accepted,
+    /// Device acked `REJECTED`; see `reason_code`.
+    Rejected,
+    /// Device acked `COMPLETED` (before core saw `ACCEPTED`, or for a finished duplicate).
+    Completed,
+    /// Device acked `CANCELLED`, or the queued request was dropped.
+    Cancelled,
+    /// Core refused to publish; see `fault_kind`.
+    Blocked,
+    /// Held in the device queue (`QUEUE` policy); another result follows once it is published.
+    Queued,
+    /// Held until `execute_at_unix_ms`; another result follows once it is published.
+    Scheduled,
+    /// No ack in time; see `fault_kind`.
+    Timeout,
+}
+
+impl DispatchResultStatus {
+    /// Wire spelling (e.g. `"ACCEPTED"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Accepted => "ACCEPTED",
+            Self::Rejected => "REJECTED",
+            Self::Completed => "COMPLETED",
+            Self::Cancelled => "CANCELLED",
+            Self::Blocked => "BLOCKED",
+            Self::Queued => "QUEUED",
+            Self::Scheduled => "SCHEDULED",
+            Self::Timeout => "TIMEOUT",
+        }
+    }
+}
+
+/// Core's answer to a [`CoreDispatchRequest`] that set `reply_topic`.
+///
+/// One result is published when the outcome is known: the device's first decisive ack, a
+/// blocking fault, or the ack timeout. Queued requests get a `QUEUED` result first.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct CoreDispatchResult {
+    pub schema: String,
+    pub room_id: String,
+    pub device_id: String,
+    pub correlation_id: Uuid,
+    pub status: DispatchResultStatus,
+    /// Set once core has published a command for this correlation id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_id: Option<Uuid>,
+    /// Blocking fault (`BLOCKED`), timeout fault (`TIMEOUT`) or `DISPATCH_QUEUE_DROPPED`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_kind: Option<FaultKind>,
+    /// Device `reason_code` for `REJECTED`; cancel / drop reason for `CANCELLED`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+    /// The request repeated a correlation id core had already seen; this reports the original.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+    pub observed_at_unix_ms: u64,
+}
+
+/// Per-device outcome inside a [`CoreBatchDispatchResult`].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct BatchMemberResult {
+    pub device_id: String,
+    /// `None` while the member is still running (or, for a blocked batch, was not the blocker).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DispatchResultStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_kind: Option<FaultKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+}
+
+/// Core's answer to a [`CoreBatchDispatchRequest`] that set `reply_topic`.
+///
+/// Published once: `BLOCKED` when gating refused the batch, otherwise when every member has
+/// finished. `status` is `COMPLETED` only if every member completed; otherwise the worst member
+/// outcome (`REJECTED` > `TIMEOUT` > `CANCELLED`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct CoreBatchDispatchResult {
+    pub schema: String,
+    pub room_id: String,
+    pub correlation_id: Uuid,
+    pub status: DispatchResultStatus,
+    pub members: Vec<BatchMemberResult>,
+    /// The request repeated a correlation id core had already seen; this reports the original.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+    pub observed_at_unix_ms: u64,
 }
 
 /// Request payload for tools/UIs to control core runtime gates (pause/resume).
 ///
 /// This is a commissioning/ops control plane intended to be replaced by the
 /// authenticated HTTP/WebSocket APIs later.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct CoreControlRequest {
     pub schema: String,
     pub room_id: String,
@@ -281,10 +690,13 @@ pub struct CoreControlRequest {
     /// Current ops:
     /// - "PAUSE_DISPATCH"
     /// - "RESUME_DISPATCH"
-    /// - "RESET_SAFETY_LATCH"
+    /// - "RESET_SAFETY_LATCH" (optional `zone` parameter: reset only that safety zone)
     /// - "START_GRAPH"
     /// - "STOP_GRAPH"
     /// - "RELOAD_GRAPH"
+    /// - "CANCEL_COMMAND"
+    /// - "CANCEL_DEVICE_COMMANDS"
+    /// - "SET_MAINTENANCE" / "RELEASE_MAINTENANCE" (`device_id` or `zone`)
     pub op: String,
     /// Optional parameters for future ops.
     #[serde(default)]
@@ -293,14 +705,13 @@ pub struct CoreControlRequest {
 }
 
 /// Core fault/incident message intended for tools/UIs/notification systems.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct CoreFault {
     pub schema: String,
     pub room_id: String,
-    /// Machine-readable identifier (e.g. "BROKER_OUTAGE").
-    pub kind: String,
-    /// Severity level: "INFO" | "WARN" | "CRITICAL"
-    pub severity: String,
+    /// Machine-readable identifier (e.g. "BROKER_OUTAGE"); see [`FaultKind`] for `details` keys.
+    pub kind: FaultKind,
+    pub severity: Severity,
     pub message: String,
     pub observed_at_unix_ms: u64,
     #[serde(default)]
@@ -308,7 +719,7 @@ pub struct CoreFault {
 }
 
 /// Core status snapshot intended for tools/UIs/health dashboards.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct CoreStatus {
     pub schema: String,
     pub room_id: String,
@@ -322,8 +733,15 @@ pub struct CoreStatus {
     pub broker_outage_since_unix_ms: Option<u64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub safety_latched_since_unix_ms: Option<u64>,
+    /// Room-wide safety: devices without a zone, plus the room latch.
     #[serde(default = "default_safety_state_safe")]
     pub room_safety: SafetyState,
+    /// Per-zone safety for devices assigned a `zone` in the registry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_zones: Vec<SafetyZoneStatus>,
+    /// Devices and zones currently in maintenance (lockout/tagout).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceStatus>,
     pub device_count: u64,
     pub offline_device_count: u64,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -333,9 +751,127 @@ pub struct CoreStatus {
     pub graph_active_nodes: Vec<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub graph_version: Option<i64>,
+    /// Dispatch latency / tick jitter summary (full histograms: `core/metrics`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispatch_latency: Option<DispatchLatencySummary>,
+    /// Event/graph database health; absent when core runs with `CORE_DB_ENABLED=false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db: Option<DbHealthStatus>,
+    /// Device watchdog beacon settings; absent when `CORE_WATCHDOG_INTERVAL_MS=0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watchdog: Option<CoreWatchdogStatus>,
     pub observed_at_unix_ms: u64,
 }
 
+/// What devices should expect from [`CoreWatchdog`] beacons, carried in [`CoreStatus`].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CoreWatchdogStatus {
+    pub boot_id: Uuid,
+    pub interval_ms: u64,
+    pub timeout_ms: u64,
+    /// Counter of the last beacon round.
+    pub counter: u64,
+}
+
+/// Rolling dispatch latency SLO summary carried in [`CoreStatus`].
+///
+/// Latency is measured from the first publish of a command to its `ACCEPTED` ack (retries
+/// included). Percentiles are exact over the SLO window; jitter covers the last status interval.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DispatchLatencySummary {
+    /// Publish -> `ACCEPTED` target (`CORE_SLO_ACCEPT_MS`).
+    pub slo_target_ms: u64,
+    /// Required share of accepts within target, in percent (`CORE_SLO_TARGET_PCT`).
+    pub slo_target_pct: f64,
+    /// Rolling window length (`CORE_SLO_WINDOW_MS`).
+    pub window_ms: u64,
+    /// Accepted commands observed inside the window.
+    pub window_samples: u64,
+    /// Share of `window_samples` accepted within `slo_target_ms`, in percent (100 when empty).
+    pub window_within_target_pct: f64,
+    /// Latched by `DISPATCH_LATENCY_SLO_BREACHED`, cleared by `DISPATCH_LATENCY_SLO_RECOVERED`.
+    pub slo_breached: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_p50_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_p95_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_p99_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_max_ms: Option<u64>,
+    /// Tick-loop lateness vs `tick_ms`: p99 bucket bound and max, in microseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick_jitter_p99_us: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick_jitter_max_us: Option<u64>,
+}
+
+/// Safety state of one named zone (a physical area), carried in [`CoreStatus`].
+///
+/// `safety` is the worst state reported by the zone's devices; while the zone is latched it
+/// reads `latched=true` (kind `FAULT`, or `E_STOP`) until a `RESET_SAFETY_LATCH` for the zone.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct SafetyZoneStatus {
+    pub zone: String,
+    pub safety: SafetyState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latched_since_unix_ms: Option<u64>,
+    /// Registry devices assigned to the zone.
+    #[serde(default)]
+    pub device_ids: Vec<String>,
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaintenanceScope {
+    Device,
+    /// A safety zone: covers every registry device assigned to it.
+    Zone,
+}
+
+/// Maintenance lock (lockout/tagout) on a device or safety zone, carried in [`CoreStatus`] and
+/// the core device status.
+///
+/// While held, graph and other automated dispatches to the covered devices are refused with
+/// `DISPATCH_BLOCKED_MAINTENANCE`; manual dispatches from a trusted TECH/ADMIN operator (see
+/// [`DispatchOperator`]) still go through.
+/// Only `owner` or an ADMIN can release it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct MaintenanceStatus {
+    pub scope: MaintenanceScope,
+    /// Device id or zone name.
+    pub target: String,
+    pub owner: String,
+    pub owner_role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub since_unix_ms: u64,
+}
+
+/// Core's Postgres connection pool and event writer, carried in [`CoreStatus`].
+///
+/// `up` goes false when a connect, health ping or insert fails at the connection level (not on
+/// rejected rows) and back to true on the next success; `DB_OUTAGE` / `DB_RESTORED` follow it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DbHealthStatus {
+    pub up: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_since_unix_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_ok_unix_ms: Option<u64>,
+    /// Max connections (`CORE_DB_POOL_SIZE`), connections open now, and how many are idle.
+    pub pool_size: u32,
+    pub pool_open: u32,
+    pub pool_idle: u32,
+    /// Events waiting for the writer, and CRITICAL faults waiting in the spill file.
+    pub queue_depth: u64,
+    pub spill_pending: u64,
+}
+
 fn default_safety_state_safe() -> SafetyState {
     SafetyState {
         kind: SafetyStateKind::Safe,
@@ -348,7 +884,7 @@ fn default_safety_class_non_critical() -> SafetyClass {
     SafetyClass::NonCritical
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct OscCue {
     pub schema: String,
     pub room_id: String,
@@ -360,7 +896,7 @@ pub struct OscCue {
     pub issued_at_unix_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(tag = "type", content = "value", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OscArg {
     Int(i32),
@@ -369,7 +905,7 @@ pub enum OscArg {
     Bool(bool),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OscAckStatus {
     Sent,
@@ -0,0 +1,309 @@
+//! JSON Schema / TypeScript export for the wire messages.
+//!
+//! Schemas are derived from the Rust types (serde attributes and doc comments included), so the
+//! checked-in copies under `docs/protocol/schema/` go stale the moment a field changes. The
+//! `sentient-schema check` binary regenerates them in memory and diffs, which CI runs to make
+//! protocol drift visible in review.
+
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde_json::{Map, Value};
+
+use crate::{
+    CommandAck, CommandEnvelope, CoreBatchDispatchRequest, CoreBatchDispatchResult,
+    CoreControlRequest, CoreDispatchRequest, CoreDispatchResult, CoreFault, CoreStatus,
+    CoreWatchdog, DeviceState, Heartbeat, OscCue, Presence, TimePing, TimePong,
+};
+
+/// File name of the combined TypeScript declarations.
+pub const TYPESCRIPT_FILE_NAME: &str = "sentient-protocol.d.ts";
+
+/// A top-level message that travels on its own MQTT topic.
+pub struct MessageSchema {
+    pub name: &'static str,
+    /// Topic pattern(s) carrying this message (see `docs/protocol/MQTT_TOPICS.md`).
+    pub topics: &'static [&'static str],
+    pub schema: Schema,
+}
+
+impl MessageSchema {
+    /// File name used under `docs/protocol/schema/`.
+    pub fn file_name(&self) -> String {
+        format!("{}.schema.json", self.name)
+    }
+
+    /// Pretty JSON with a trailing newline (the checked-in format).
+    pub fn to_pretty_json(&self) -> String {
+        let mut out = serde_json::to_string_pretty(&self.schema).expect("schema serializes");
+        out.push('\n');
+        out
+    }
+}
+
+fn settings() -> SchemaSettings {
+    SchemaSettings::draft2020_12()
+}
+
+fn message<T: JsonSchema>(name: &'static str, topics: &'static [&'static str]) -> MessageSchema {
+    let mut schema = settings().into_generator().into_root_schema_for::<T>();
+    schema.insert(
+        "x-sentient-topics".to_string(),
+        Value::from(topics.to_vec()),
+    );
+    MessageSchema {
+        name,
+        topics,
+        schema,
+    }
+}
+
+/// Every top-level protocol message, in topic-doc order.
+pub fn message_schemas() -> Vec<MessageSchema> {
+    vec![
+        message::<CommandEnvelope>(
+            "CommandEnvelope",
+            &["room/{room_id}/device/{device_id}/cmd"],
+        ),
+        message::<CommandAck>(
+            "CommandAck",
+            &[
+                "room/{room_id}/device/{device_id}/ack",
+                "room/{room_id}/audio/ack",
+            ],
+        ),
+        message::<DeviceState>("DeviceState", &["room/{room_id}/device/{device_id}/state"]),
+        message::<Heartbeat>(
+            "Heartbeat",
+            &["room/{room_id}/device/{device_id}/heartbeat"],
+        ),
+        message::<Presence>("Presence", &["room/{room_id}/device/{device_id}/presence"]),
+        message::<TimePing>("TimePing", &["room/{room_id}/device/{device_id}/ping"]),
+        message::<TimePong>("TimePong", &["room/{room_id}/device/{device_id}/pong"]),
+        message::<CoreWatchdog>(
+            "CoreWatchdog",
+            &["room/{room_id}/device/{device_id}/watchdog"],
+        ),
+        message::<CoreStatus>("CoreStatus", &["room/{room_id}/core/status"]),
+        message::<CoreFault>(
+            "CoreFault",
+            &[
+                "room/{room_id}/core/fault",
+                "room/{room_id}/core/device/{device_id}/fault",
+                "room/{room_id}/audio/fault",
+            ],
+        ),
+        message::<CoreControlRequest>("CoreControlRequest", &["room/{room_id}/core/control"]),
+        message::<CoreDispatchRequest>("CoreDispatchRequest", &["room/{room_id}/core/dispatch"]),
+        message::<CoreDispatchResult>(
+            "CoreDispatchResult",
+            &["room/{room_id}/core/dispatch/reply/{client_id}"],
+        ),
+        message::<CoreBatchDispatchRequest>(
+            "CoreBatchDispatchRequest",
+            &["room/{room_id}/core/dispatch/batch"],
+        ),
+        message::<CoreBatchDispatchResult>(
+            "CoreBatchDispatchResult",
+            &["room/{room_id}/core/dispatch/reply/{client_id}"],
+        ),
+        message::<OscCue>("OscCue", &["room/{room_id}/audio/cue"]),
+    ]
+}
+
+/// Collects every message (and everything it references) into one definitions map.
+fn all_definitions() -> Map<String, Value> {
+    let mut generator: SchemaGenerator = settings().into_generator();
+    generator.subschema_for::<CommandEnvelope>();
+    generator.subschema_for::<CommandAck>();
+    generator.subschema_for::<DeviceState>();
+    generator.subschema_for::<Heartbeat>();
+    generator.subschema_for::<Presence>();
+    generator.subschema_for::<TimePing>();
+    generator.subschema_for::<TimePong>();
+    generator.subschema_for::<CoreWatchdog>();
+    generator.subschema_for::<CoreStatus>();
+    generator.subschema_for::<CoreFault>();
+    generator.subschema_for::<CoreControlRequest>();
+    generator.subschema_for::<CoreDispatchRequest>();
+    generator.subschema_for::<CoreDispatchResult>();
+    generator.subschema_for::<CoreBatchDispatchRequest>();
+    generator.subschema_for::<CoreBatchDispatchResult>();
+    generator.subschema_for::<OscCue>();
+    let mut defs: Vec<(String, Value)> = generator.take_definitions(true).into_iter().collect();
+    defs.sort_by(|a, b| a.0.cmp(&b.0));
+    defs.into_iter().collect()
+}
+
+/// TypeScript declarations for every message and its referenced types.
+pub fn typescript_definitions() -> String {
+    let mut out = String::new();
+    out.push_str(
+        "// Generated by `sentient-schema ts` from crates/sentient-protocol. Do not edit.\n",
+    );
+    out.push_str(&format!(
+        "// Fault kind catalog version: {}\n",
+        crate::FAULT_KIND_CATALOG_VERSION
+    ));
+
+    for (name, schema) in all_definitions() {
+        out.push('\n');
+        push_doc(&mut out, schema.get("description"), "");
+        if schema.get("properties").is_some() && schema.get("type") == Some(&Value::from("object"))
+        {
+            out.push_str(&format!("export interface {name} "));
+            out.push_str(&ts_object(&schema, ""));
+            out.push('\n');
+        } else {
+            let ty = ts_type(&schema, "");
+            if ty.len() > 96 && !ty.contains('\n') {
+                // Long literal unions (e.g. FaultKind) read better one member per line.
+                out.push_str(&format!("export type {name} =\n"));
+                for member in ty.split(" | ") {
+                    out.push_str(&format!("  | {member}\n"));
+                }
+                out.pop();
+                out.push_str(";\n");
+            } else {
+                out.push_str(&format!("export type {name} = {ty};\n"));
+            }
+        }
+    }
+    out
+}
+
+fn push_doc(out: &mut String, description: Option<&Value>, indent: &str) {
+    let Some(text) = description.and_then(Value::as_str) else {
+        return;
+    };
+    out.push_str(indent);
+    out.push_str("/**\n");
+    for line in text.lines() {
+        out.push_str(indent);
+        if line.is_empty() {
+            out.push_str(" *\n");
+        } else {
+            out.push_str(&format!(" * {}\n", line.replace("*/", "*\\/")));
+        }
+    }
+    out.push_str(indent);
+    out.push_str(" */\n");
+}
+
+fn ts_object(schema: &Value, indent: &str) -> String {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let inner = format!("{indent}  ");
+
+    let mut out = String::from("{\n");
+    if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+        for (prop, prop_schema) in props {
+            push_doc(&mut out, prop_schema.get("description"), &inner);
+            let optional = if required.contains(&prop.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            out.push_str(&format!(
+                "{inner}{prop}{optional}: {};\n",
+                ts_type(prop_schema, &inner)
+            ));
+        }
+    }
+    out.push_str(indent);
+    out.push('}');
+    out
+}
+
+fn ts_union(parts: Vec<String>) -> String {
+    let mut seen: Vec<String> = Vec::new();
+    for part in parts {
+        if !seen.contains(&part) {
+            seen.push(part);
+        }
+    }
+    if seen.is_empty() {
+        return "never".to_string();
+    }
+    seen.join(" | ")
+}
+
+fn ts_type(schema: &Value, indent: &str) -> String {
+    let obj = match schema {
+        Value::Bool(true) => return "unknown".to_string(),
+        Value::Bool(false) => return "never".to_string(),
+        Value::Object(obj) => obj,
+        _ => return "unknown".to_string(),
+    };
+
+    if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or(reference)
+            .to_string();
+    }
+    if let Some(value) = obj.get("const") {
+        return value.to_string();
+    }
+    if let Some(values) = obj.get("enum").and_then(Value::as_array) {
+        return ts_union(values.iter().map(Value::to_string).collect());
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(variants) = obj.get(key).and_then(Value::as_array) {
+            return ts_union(variants.iter().map(|v| ts_type(v, indent)).collect());
+        }
+    }
+    if let Some([single]) = obj
+        .get("allOf")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        return ts_type(single, indent);
+    }
+
+    match obj.get("type") {
+        Some(Value::Array(types)) => ts_union(
+            types
+                .iter()
+                .map(|t| {
+                    let mut narrowed = obj.clone();
+                    narrowed.insert("type".to_string(), t.clone());
+                    ts_type(&Value::Object(narrowed), indent)
+                })
+                .collect(),
+        ),
+        Some(Value::String(t)) => match t.as_str() {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => {
+                let item = obj
+                    .get("items")
+                    .map(|items| ts_type(items, indent))
+                    .unwrap_or_else(|| "unknown".to_string());
+                if item.contains(' ') {
+                    format!("Array<{item}>")
+                } else {
+                    format!("{item}[]")
+                }
+            }
+            "object" => {
+                if obj.contains_key("properties") {
+                    ts_object(schema, indent)
+                } else {
+                    let value = obj
+                        .get("additionalProperties")
+                        .map(|v| ts_type(v, indent))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    format!("Record<string, {value}>")
+                }
+            }
+            _ => "unknown".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
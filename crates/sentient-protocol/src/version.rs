@@ -0,0 +1,354 @@
+//! Schema version compatibility matrix.
+//!
+//! Every payload carries a `schema` string. Receivers accept anything listed in
+//! [`ACCEPTED_SCHEMA_VERSIONS`] and upgrade older payloads to the newest shape before
+//! deserializing, so a room can run mixed firmware during a rolling upgrade.
+//!
+//! Devices advertise what they understand in `Heartbeat.supported_schemas`; core stamps each
+//! command with [`negotiate_schema`] for that device. Devices that predate v8.1 never advertise
+//! and are treated as v8-only.
+//!
+//! | schema | changes                                               |
+//! |--------|-------------------------------------------------------|
+//! | `v8`   | baseline                                              |
+//! | `v8.1` | `Heartbeat.supported_schemas` (devices advertise support) |
+//! | `v8.2` | `CommandAction::Cancel`, `AckStatus::Cancelled`           |
+//! | `v8.3` | `AckStatus::InProgress`, `CommandAck.progress`            |
+//! | `v8.4` | `CommandEnvelope.execute_at_unix_ms` (scheduled execution) |
+//! | `v8.5` | `TimePing` / `TimePong` (clock offset + latency measurement) |
+//! | `v8.6` | `CoreWatchdog` (signed core liveness beacon; devices go safe when it stops) |
+//!
+//! Adding a version: append it to [`ACCEPTED_SCHEMA_VERSIONS`], point [`SCHEMA_VERSION`] at it,
+//! add an upgrade step to `UPGRADES`, and make sure `CommandEnvelope` stays shape-compatible with
+//! every version a device may still negotiate (commands are never down-converted field by field).
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    CommandAck, CommandEnvelope, CoreBatchDispatchRequest, CoreBatchDispatchResult,
+    CoreControlRequest, CoreDispatchRequest, CoreDispatchResult, CoreFault, CoreStatus,
+    CoreWatchdog, DeviceState, Heartbeat, OscCue, Presence, TimePing, TimePong,
+};
+
+pub const SCHEMA_V8: &str = "v8";
+pub const SCHEMA_V8_1: &str = "v8.1";
+pub const SCHEMA_V8_2: &str = "v8.2";
+pub const SCHEMA_V8_3: &str = "v8.3";
+pub const SCHEMA_V8_4: &str = "v8.4";
+pub const SCHEMA_V8_5: &str = "v8.5";
+pub const SCHEMA_V8_6: &str = "v8.6";
+
+/// Newest schema; stamped on everything this build publishes (except negotiated commands).
+pub const SCHEMA_VERSION: &str = SCHEMA_V8_6;
+
+/// Schema strings this build accepts, oldest first.
+pub const ACCEPTED_SCHEMA_VERSIONS: &[&str] = &[
+    SCHEMA_V8,
+    SCHEMA_V8_1,
+    SCHEMA_V8_2,
+    SCHEMA_V8_3,
+    SCHEMA_V8_4,
+    SCHEMA_V8_5,
+    SCHEMA_V8_6,
+];
+
+/// Oldest device schema that understands `CANCEL` commands.
+pub const CANCEL_MIN_SCHEMA: &str = SCHEMA_V8_2;
+
+/// Oldest command schema for which a device may answer with `IN_PROGRESS` acks.
+pub const PROGRESS_MIN_SCHEMA: &str = SCHEMA_V8_3;
+
+/// Oldest device schema that honors `CommandEnvelope.execute_at_unix_ms`.
+pub const SCHEDULE_MIN_SCHEMA: &str = SCHEMA_V8_4;
+
+/// Oldest device schema that answers `TimePing`s.
+pub const TIME_SYNC_MIN_SCHEMA: &str = SCHEMA_V8_5;
+
+/// Oldest device schema that receives `CoreWatchdog` beacons.
+pub const WATCHDOG_MIN_SCHEMA: &str = SCHEMA_V8_6;
+
+/// Assumed for devices that have not advertised `supported_schemas`.
+pub const DEFAULT_DEVICE_SCHEMA: &str = SCHEMA_V8;
+
+type UpgradeFn = fn(message: &str, payload: &mut Map<String, Value>);
+
+/// One step per adjacent version pair, applied in order.
+const UPGRADES: &[(&str, &str, UpgradeFn)] = &[
+    (SCHEMA_V8, SCHEMA_V8_1, upgrade_v8_to_v8_1),
+    (SCHEMA_V8_1, SCHEMA_V8_2, upgrade_additive),
+    (SCHEMA_V8_2, SCHEMA_V8_3, upgrade_additive),
+    (SCHEMA_V8_3, SCHEMA_V8_4, upgrade_additive),
+    (SCHEMA_V8_4, SCHEMA_V8_5, upgrade_additive),
+    (SCHEMA_V8_5, SCHEMA_V8_6, upgrade_additive),
+];
+
+/// For versions that only add enum variants / optional fields.
+fn upgrade_additive(_message: &str, _payload: &mut Map<String, Value>) {}
+
+fn upgrade_v8_to_v8_1(message: &str, payload: &mut Map<String, Value>) {
+    if message == "Heartbeat" && !payload.contains_key("supported_schemas") {
+        payload.insert(
+            "supported_schemas".to_string(),
+            Value::from(vec![SCHEMA_V8]),
+        );
+    }
+}
+
+pub fn is_accepted_schema(schema: &str) -> bool {
+    ACCEPTED_SCHEMA_VERSIONS.contains(&schema)
+}
+
+fn schema_rank(schema: &str) -> Option<usize> {
+    ACCEPTED_SCHEMA_VERSIONS.iter().position(|s| *s == schema)
+}
+
+/// Whether `schema` is an accepted version at or after `min`.
+pub fn schema_at_least(schema: &str, min: &str) -> bool {
+    match (schema_rank(schema), schema_rank(min)) {
+        (Some(have), Some(need)) => have >= need,
+        _ => false,
+    }
+}
+
+/// Newest schema both sides understand. Falls back to [`DEFAULT_DEVICE_SCHEMA`] when the device
+/// advertised nothing we accept.
+pub fn negotiate_schema<S: AsRef<str>>(device_supported: &[S]) -> &'static str {
+    device_supported
+        .iter()
+        .filter_map(|s| schema_rank(s.as_ref()))
+        .max()
+        .map(|rank| ACCEPTED_SCHEMA_VERSIONS[rank])
+        .unwrap_or(DEFAULT_DEVICE_SCHEMA)
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Json(serde_json::Error),
+    Codec(crate::codec::CodecError),
+    MissingSchema,
+    Unsupported(String),
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid json: {err}"),
+            Self::Codec(err) => write!(f, "invalid payload: {err}"),
+            Self::MissingSchema => f.write_str("missing `schema` field"),
+            Self::Unsupported(s) => write!(
+                f,
+                "unsupported schema {s:?} (accepted: {})",
+                ACCEPTED_SCHEMA_VERSIONS.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// A top-level message that [`decode_message`] can upgrade.
+pub trait ProtocolMessage: DeserializeOwned {
+    const MESSAGE: &'static str;
+}
+
+macro_rules! protocol_messages {
+    ($($ty:ident),* $(,)?) => {
+        $( impl ProtocolMessage for $ty { const MESSAGE: &'static str = stringify!($ty); } )*
+    };
+}
+
+protocol_messages!(
+    CommandEnvelope,
+    CommandAck,
+    DeviceState,
+    Heartbeat,
+    Presence,
+    TimePing,
+    TimePong,
+    CoreWatchdog,
+    CoreStatus,
+    CoreFault,
+    CoreControlRequest,
+    CoreDispatchRequest,
+    CoreDispatchResult,
+    CoreBatchDispatchRequest,
+    CoreBatchDispatchResult,
+    OscCue,
+);
+
+/// Upgrade `payload` in place to [`SCHEMA_VERSION`]. The original `schema` string is kept so
+/// receivers can still see what the sender spoke.
+pub fn upgrade_payload(message: &str, payload: &mut Value) -> Result<(), SchemaError> {
+    let Some(obj) = payload.as_object_mut() else {
+        return Err(SchemaError::MissingSchema);
+    };
+    let schema = obj
+        .get("schema")
+        .and_then(Value::as_str)
+        .ok_or(SchemaError::MissingSchema)?
+        .to_string();
+    let Some(mut rank) = schema_rank(&schema) else {
+        return Err(SchemaError::Unsupported(schema));
+    };
+    for (from, to, upgrade) in UPGRADES {
+        if ACCEPTED_SCHEMA_VERSIONS[rank] == *from {
+            upgrade(message, obj);
+            rank = schema_rank(to).expect("upgrade target is accepted");
+        }
+    }
+    Ok(())
+}
+
+/// Parse (JSON), version-check and upgrade a payload in one step.
+pub fn decode_message<T: ProtocolMessage>(payload: &[u8]) -> Result<T, SchemaError> {
+    crate::codec::decode_message_as(crate::codec::WireCodec::Json, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn schema_at_least_follows_matrix_order() {
+        for (i, have) in ACCEPTED_SCHEMA_VERSIONS.iter().enumerate() {
+            for (j, min) in ACCEPTED_SCHEMA_VERSIONS.iter().enumerate() {
+                assert_eq!(schema_at_least(have, min), i >= j, "{have} >= {min}");
+            }
+        }
+    }
+
+    #[test]
+    fn schema_at_least_rejects_unknown_versions() {
+        assert!(!schema_at_least("v9", SCHEMA_V8));
+        assert!(!schema_at_least(SCHEMA_V8_6, "v9"));
+        assert!(!schema_at_least("", SCHEMA_V8));
+    }
+
+    #[test]
+    fn feature_gates_are_ordered() {
+        let gates = [
+            CANCEL_MIN_SCHEMA,
+            PROGRESS_MIN_SCHEMA,
+            SCHEDULE_MIN_SCHEMA,
+            TIME_SYNC_MIN_SCHEMA,
+            WATCHDOG_MIN_SCHEMA,
+        ];
+        for gate in gates {
+            assert!(is_accepted_schema(gate));
+            assert!(!schema_at_least(DEFAULT_DEVICE_SCHEMA, gate));
+            assert!(schema_at_least(SCHEMA_VERSION, gate));
+        }
+        for pair in gates.windows(2) {
+            assert!(schema_at_least(pair[1], pair[0]));
+        }
+    }
+
+    #[test]
+    fn matrix_ends_at_current_version() {
+        assert_eq!(ACCEPTED_SCHEMA_VERSIONS.last(), Some(&SCHEMA_VERSION));
+        assert_eq!(ACCEPTED_SCHEMA_VERSIONS.first(), Some(&SCHEMA_V8));
+    }
+
+    #[test]
+    fn negotiate_picks_newest_shared_version() {
+        assert_eq!(
+            negotiate_schema(&[SCHEMA_V8, SCHEMA_V8_3, SCHEMA_V8_1]),
+            SCHEMA_V8_3
+        );
+        assert_eq!(negotiate_schema(&["v9", SCHEMA_V8_2]), SCHEMA_V8_2);
+        assert_eq!(negotiate_schema(&[SCHEMA_V8_6]), SCHEMA_V8_6);
+    }
+
+    #[test]
+    fn negotiate_falls_back_to_default() {
+        assert_eq!(negotiate_schema::<&str>(&[]), DEFAULT_DEVICE_SCHEMA);
+        assert_eq!(negotiate_schema(&["v9", "v7"]), DEFAULT_DEVICE_SCHEMA);
+    }
+
+    #[test]
+    fn upgrades_chain_every_adjacent_pair() {
+        assert_eq!(UPGRADES.len(), ACCEPTED_SCHEMA_VERSIONS.len() - 1);
+        for (step, (from, to, _)) in UPGRADES.iter().enumerate() {
+            assert_eq!(*from, ACCEPTED_SCHEMA_VERSIONS[step]);
+            assert_eq!(*to, ACCEPTED_SCHEMA_VERSIONS[step + 1]);
+        }
+    }
+
+    fn heartbeat(schema: &str) -> Value {
+        json!({
+            "schema": schema,
+            "room_id": "room1",
+            "device_id": "dev1",
+            "uptime_ms": 1,
+            "firmware_version": "1.0.0",
+            "safety_state": {"kind": "SAFE", "latched": false},
+            "observed_at_unix_ms": 2,
+        })
+    }
+
+    #[test]
+    fn v8_heartbeat_upgrades_to_v8_only_support() {
+        let mut payload = heartbeat(SCHEMA_V8);
+        upgrade_payload("Heartbeat", &mut payload).unwrap();
+        assert_eq!(payload["supported_schemas"], json!([SCHEMA_V8]));
+        assert_eq!(
+            payload["schema"], SCHEMA_V8,
+            "original schema string is kept"
+        );
+    }
+
+    #[test]
+    fn upgrade_keeps_advertised_support() {
+        let mut payload = heartbeat(SCHEMA_V8);
+        payload["supported_schemas"] = json!([SCHEMA_V8, SCHEMA_V8_4]);
+        upgrade_payload("Heartbeat", &mut payload).unwrap();
+        assert_eq!(
+            payload["supported_schemas"],
+            json!([SCHEMA_V8, SCHEMA_V8_4])
+        );
+    }
+
+    #[test]
+    fn upgrade_from_newer_versions_is_a_no_op() {
+        for schema in &ACCEPTED_SCHEMA_VERSIONS[1..] {
+            let mut payload = heartbeat(schema);
+            upgrade_payload("Heartbeat", &mut payload).unwrap();
+            assert_eq!(payload, heartbeat(schema));
+        }
+    }
+
+    #[test]
+    fn upgrade_rejects_missing_or_unknown_schema() {
+        let mut payload = heartbeat(SCHEMA_V8);
+        payload.as_object_mut().unwrap().remove("schema");
+        assert!(matches!(
+            upgrade_payload("Heartbeat", &mut payload),
+            Err(SchemaError::MissingSchema)
+        ));
+        assert!(matches!(
+            upgrade_payload("Heartbeat", &mut json!([1, 2])),
+            Err(SchemaError::MissingSchema)
+        ));
+        assert!(matches!(
+            upgrade_payload("Heartbeat", &mut heartbeat("v9")),
+            Err(SchemaError::Unsupported(s)) if s == "v9"
+        ));
+    }
+
+    #[test]
+    fn decode_message_upgrades_before_deserializing() {
+        let bytes = serde_json::to_vec(&heartbeat(SCHEMA_V8)).unwrap();
+        let hb: Heartbeat = decode_message(&bytes).unwrap();
+        assert_eq!(hb.schema, SCHEMA_V8);
+        assert_eq!(hb.supported_schemas, vec![SCHEMA_V8.to_string()]);
+    }
+}
@@ -0,0 +1,708 @@
+// Generated by `sentient-schema ts` from crates/sentient-protocol. Do not edit.
+// Fault kind catalog version: 14
+
+export type AckStatus = "ACCEPTED" | "REJECTED" | "COMPLETED" | "CANCELLED" | "IN_PROGRESS";
+
+/**
+ * One device command inside a [`CoreBatchDispatchRequest`].
+ */
+export interface BatchCommand {
+  action: CommandAction;
+  device_id: string;
+  parameters?: unknown;
+  safety_class?: SafetyClass;
+}
+
+/**
+ * Per-device outcome inside a [`CoreBatchDispatchResult`].
+ */
+export interface BatchMemberResult {
+  command_id?: string | null;
+  device_id: string;
+  fault_kind?: FaultKind | null;
+  reason_code?: string | null;
+  /**
+   * `None` while the member is still running (or, for a blocked batch, was not the blocker).
+   */
+  status?: DispatchResultStatus | null;
+}
+
+export interface CommandAck {
+  command_id: string;
+  correlation_id: string;
+  device_id: string;
+  observed_at_unix_ms: number;
+  /**
+   * Only on `IN_PROGRESS` acks.
+   */
+  progress?: CommandProgress | null;
+  reason_code?: string | null;
+  room_id: string;
+  safety_state: SafetyState;
+  schema: string;
+  status: AckStatus;
+}
+
+export type CommandAction = "OPEN" | "CLOSE" | "MOVE" | "SET" | "CANCEL";
+
+export interface CommandAuth {
+  /**
+   * Authentication scheme identifier.
+   * v8 default: "HMAC-SHA256"
+   */
+  alg: string;
+  /**
+   * Key identifier (device-side), to support rotation.
+   */
+  kid?: string | null;
+  /**
+   * Hex-encoded MAC over the canonical signing bytes.
+   */
+  mac_hex: string;
+}
+
+export interface CommandEnvelope {
+  action: CommandAction;
+  /**
+   * Optional at the protocol layer; required for real hardware deployments.
+   */
+  auth?: CommandAuth | null;
+  command_id: string;
+  correlation_id: string;
+  device_id: string;
+  /**
+   * Room time (unix ms) at which the device should execute (`v8.4`+, devices with synced
+   * clocks). Absent means "now". Signed when present.
+   */
+  execute_at_unix_ms?: number | null;
+  issued_at_unix_ms: number;
+  parameters?: unknown;
+  room_id: string;
+  safety_class: SafetyClass;
+  schema: string;
+  sequence: number;
+}
+
+/**
+ * Progress of a long-running command, reported with `IN_PROGRESS` acks.
+ *
+ * All fields are optional; devices report whatever they can measure.
+ */
+export interface CommandProgress {
+  /**
+   * Device's estimate of the time left.
+   */
+  eta_ms?: number | null;
+  /**
+   * Percent complete (0..=100).
+   */
+  percent?: number | null;
+  /**
+   * Current position in device units (e.g. mm, steps).
+   */
+  position?: number | null;
+  /**
+   * Position the command is driving towards, in the same units.
+   */
+  target_position?: number | null;
+}
+
+/**
+ * Several device commands gated together ("scene" cue): either every member passes the dispatch
+ * gates and is published, or none is. All members share `correlation_id`.
+ */
+export interface CoreBatchDispatchRequest {
+  /**
+   * Override ack timeout (ms) for every member.
+   */
+  ack_timeout_ms?: number | null;
+  /**
+   * At most one command per device.
+   */
+  commands: BatchCommand[];
+  /**
+   * Override completion timeout (ms) for every member.
+   */
+  complete_timeout_ms?: number | null;
+  /**
+   * Shared by every member command; core assigns one if omitted. Also the idempotency key.
+   */
+  correlation_id?: string | null;
+  /**
+   * Applies to every member; see [`CoreDispatchRequest::operator`].
+   */
+  operator?: DispatchOperator | null;
+  /**
+   * Where core publishes the [`CoreBatchDispatchResult`]; same rules as
+   * [`CoreDispatchRequest::reply_topic`].
+   */
+  reply_topic?: string | null;
+  /**
+   * Override retry count for every member.
+   */
+  retries?: number | null;
+  room_id: string;
+  schema: string;
+}
+
+/**
+ * Core's answer to a [`CoreBatchDispatchRequest`] that set `reply_topic`.
+ *
+ * Published once: `BLOCKED` when gating refused the batch, otherwise when every member has
+ * finished. `status` is `COMPLETED` only if every member completed; otherwise the worst member
+ * outcome (`REJECTED` > `TIMEOUT` > `CANCELLED`).
+ */
+export interface CoreBatchDispatchResult {
+  correlation_id: string;
+  /**
+   * The request repeated a correlation id core had already seen; this reports the original.
+   */
+  duplicate?: boolean;
+  members: BatchMemberResult[];
+  observed_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+  status: DispatchResultStatus;
+}
+
+/**
+ * Request payload for tools/UIs to control core runtime gates (pause/resume).
+ *
+ * This is a commissioning/ops control plane intended to be replaced by the
+ * authenticated HTTP/WebSocket APIs later.
+ */
+export interface CoreControlRequest {
+  /**
+   * Operation identifier (string) to keep the control plane flexible.
+   *
+   * Current ops:
+   * - "PAUSE_DISPATCH"
+   * - "RESUME_DISPATCH"
+   * - "RESET_SAFETY_LATCH" (optional `zone` parameter: reset only that safety zone)
+   * - "START_GRAPH"
+   * - "STOP_GRAPH"
+   * - "RELOAD_GRAPH"
+   * - "CANCEL_COMMAND"
+   * - "CANCEL_DEVICE_COMMANDS"
+   * - "SET_MAINTENANCE" / "RELEASE_MAINTENANCE" (`device_id` or `zone`)
+   */
+  op: string;
+  /**
+   * Optional parameters for future ops.
+   */
+  parameters?: unknown;
+  requested_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+}
+
+/**
+ * Request payload for tools/UIs to ask `sentient-core` to dispatch a device command.
+ *
+ * This is an MQTT-only control plane intended for commissioning and early
+ * integration before the HTTP/WebSocket APIs exist.
+ */
+export interface CoreDispatchRequest {
+  /**
+   * Override ack timeout (ms).
+   */
+  ack_timeout_ms?: number | null;
+  action: CommandAction;
+  /**
+   * Override completion timeout (ms).
+   */
+  complete_timeout_ms?: number | null;
+  correlation_id?: string | null;
+  device_id: string;
+  /**
+   * Run at this room time (unix ms) instead of now. Core holds the request until it is due, or
+   * sends it early with `CommandEnvelope.execute_at_unix_ms` to devices that support it.
+   */
+  execute_at_unix_ms?: number | null;
+  /**
+   * Like `execute_at_unix_ms`, relative to when core receives the request. Ignored when
+   * `execute_at_unix_ms` is set.
+   */
+  execute_in_ms?: number | null;
+  /**
+   * Operator the request is sent for (set by sentient-api from the caller's credentials);
+   * absent for graph/automation dispatches.
+   */
+  operator?: DispatchOperator | null;
+  /**
+   * Device-specific parameters (JSON object preferred; may be `{}`).
+   */
+  parameters?: unknown;
+  /**
+   * Where core publishes [`CoreDispatchResult`]s for this request. Must start with
+   * [`dispatch_reply_prefix`]; ignored otherwise. Core assigns a `correlation_id` if omitted.
+   * An MQTT v5 response topic on the request takes precedence; this field is for 3.1.1 clients.
+   */
+  reply_topic?: string | null;
+  /**
+   * Override retry count (defaults are core-configured).
+   */
+  retries?: number | null;
+  room_id: string;
+  safety_class?: SafetyClass;
+  schema: string;
+}
+
+/**
+ * Core's answer to a [`CoreDispatchRequest`] that set `reply_topic`.
+ *
+ * One result is published when the outcome is known: the device's first decisive ack, a
+ * blocking fault, or the ack timeout. Queued requests get a `QUEUED` result first.
+ */
+export interface CoreDispatchResult {
+  /**
+   * Set once core has published a command for this correlation id.
+   */
+  command_id?: string | null;
+  correlation_id: string;
+  device_id: string;
+  /**
+   * The request repeated a correlation id core had already seen; this reports the original.
+   */
+  duplicate?: boolean;
+  /**
+   * Blocking fault (`BLOCKED`), timeout fault (`TIMEOUT`) or `DISPATCH_QUEUE_DROPPED`.
+   */
+  fault_kind?: FaultKind | null;
+  observed_at_unix_ms: number;
+  /**
+   * Device `reason_code` for `REJECTED`; cancel / drop reason for `CANCELLED`.
+   */
+  reason_code?: string | null;
+  room_id: string;
+  schema: string;
+  status: DispatchResultStatus;
+}
+
+/**
+ * Core fault/incident message intended for tools/UIs/notification systems.
+ */
+export interface CoreFault {
+  details?: unknown;
+  /**
+   * Machine-readable identifier (e.g. "BROKER_OUTAGE"); see [`FaultKind`] for `details` keys.
+   */
+  kind: FaultKind;
+  message: string;
+  observed_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+  severity: Severity;
+}
+
+/**
+ * Core status snapshot intended for tools/UIs/health dashboards.
+ */
+export interface CoreStatus {
+  broker_outage_since_unix_ms?: number | null;
+  /**
+   * Event/graph database health; absent when core runs with `CORE_DB_ENABLED=false`.
+   */
+  db?: DbHealthStatus | null;
+  device_count: number;
+  dispatch_enabled: boolean;
+  /**
+   * Dispatch latency / tick jitter summary (full histograms: `core/metrics`).
+   */
+  dispatch_latency?: DispatchLatencySummary | null;
+  dispatch_paused_reason?: string | null;
+  dry_run: boolean;
+  graph_active_node?: string | null;
+  /**
+   * Optional richer graph state for UIs/tools (v8).
+   */
+  graph_active_nodes?: string[];
+  graph_version?: number | null;
+  /**
+   * Devices and zones currently in maintenance (lockout/tagout).
+   */
+  maintenance?: MaintenanceStatus[];
+  observed_at_unix_ms: number;
+  offline_device_count: number;
+  room_id: string;
+  /**
+   * Room-wide safety: devices without a zone, plus the room latch.
+   */
+  room_safety?: SafetyState;
+  safety_latched_since_unix_ms?: number | null;
+  /**
+   * Per-zone safety for devices assigned a `zone` in the registry.
+   */
+  safety_zones?: SafetyZoneStatus[];
+  schema: string;
+  tick_ms: number;
+  uptime_ms: number;
+  /**
+   * Device watchdog beacon settings; absent when `CORE_WATCHDOG_INTERVAL_MS=0`.
+   */
+  watchdog?: CoreWatchdogStatus | null;
+}
+
+/**
+ * Core liveness beacon (v8.6+), on `room/{room_id}/device/{device_id}/watchdog`. Sent every
+ * `interval_ms` and signed with the device's HMAC key; a device that receives no valid beacon for
+ * `timeout_ms` must assume core is gone and go to its safe state (dead-man switch).
+ */
+export interface CoreWatchdog {
+  /**
+   * HMAC over [`watchdog_signing_string`]; absent only when core has no key for the device.
+   */
+  auth?: CommandAuth | null;
+  /**
+   * Random per core process; a new value means core restarted and `counter` starts over.
+   */
+  boot_id: string;
+  /**
+   * Starts at 1 and strictly increases within one `boot_id`. Devices drop beacons that do not
+   * advance it (replays, duplicates).
+   */
+  counter: number;
+  device_id: string;
+  /**
+   * How often core sends the beacon (`CORE_WATCHDOG_INTERVAL_MS`).
+   */
+  interval_ms: number;
+  issued_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+  /**
+   * Silence after which the device should treat core as lost (`CORE_WATCHDOG_TIMEOUT_MS`).
+   */
+  timeout_ms: number;
+}
+
+/**
+ * What devices should expect from [`CoreWatchdog`] beacons, carried in [`CoreStatus`].
+ */
+export interface CoreWatchdogStatus {
+  boot_id: string;
+  /**
+   * Counter of the last beacon round.
+   */
+  counter: number;
+  interval_ms: number;
+  timeout_ms: number;
+}
+
+/**
+ * Core's Postgres connection pool and event writer, carried in [`CoreStatus`].
+ *
+ * `up` goes false when a connect, health ping or insert fails at the connection level (not on
+ * rejected rows) and back to true on the next success; `DB_OUTAGE` / `DB_RESTORED` follow it.
+ */
+export interface DbHealthStatus {
+  down_since_unix_ms?: number | null;
+  last_error?: string | null;
+  last_ok_unix_ms?: number | null;
+  pool_idle: number;
+  pool_open: number;
+  /**
+   * Max connections (`CORE_DB_POOL_SIZE`), connections open now, and how many are idle.
+   */
+  pool_size: number;
+  /**
+   * Events waiting for the writer, and CRITICAL faults waiting in the spill file.
+   */
+  queue_depth: number;
+  spill_pending: number;
+  up: boolean;
+}
+
+/**
+ * Generic device state snapshot.
+ *
+ * Each controller can publish a retained "last known" state for UIs/tools and
+ * for core restart recovery. The `state` object is device-specific.
+ */
+export interface DeviceState {
+  device_id: string;
+  observed_at_unix_ms: number;
+  room_id: string;
+  safety_state: SafetyState;
+  schema: string;
+  state?: unknown;
+}
+
+/**
+ * Rolling dispatch latency SLO summary carried in [`CoreStatus`].
+ *
+ * Latency is measured from the first publish of a command to its `ACCEPTED` ack (retries
+ * included). Percentiles are exact over the SLO window; jitter covers the last status interval.
+ */
+export interface DispatchLatencySummary {
+  accept_max_ms?: number | null;
+  accept_p50_ms?: number | null;
+  accept_p95_ms?: number | null;
+  accept_p99_ms?: number | null;
+  /**
+   * Latched by `DISPATCH_LATENCY_SLO_BREACHED`, cleared by `DISPATCH_LATENCY_SLO_RECOVERED`.
+   */
+  slo_breached: boolean;
+  /**
+   * Publish -> `ACCEPTED` target (`CORE_SLO_ACCEPT_MS`).
+   */
+  slo_target_ms: number;
+  /**
+   * Required share of accepts within target, in percent (`CORE_SLO_TARGET_PCT`).
+   */
+  slo_target_pct: number;
+  tick_jitter_max_us?: number | null;
+  /**
+   * Tick-loop lateness vs `tick_ms`: p99 bucket bound and max, in microseconds.
+   */
+  tick_jitter_p99_us?: number | null;
+  /**
+   * Rolling window length (`CORE_SLO_WINDOW_MS`).
+   */
+  window_ms: number;
+  /**
+   * Accepted commands observed inside the window.
+   */
+  window_samples: number;
+  /**
+   * Share of `window_samples` accepted within `slo_target_ms`, in percent (100 when empty).
+   */
+  window_within_target_pct: number;
+}
+
+/**
+ * Who asked for a manual dispatch. A TECH/ADMIN operator may drive devices under maintenance.
+ *
+ * Core trusts it under the same rule as control ops: when `CORE_CONTROL_TOKEN` is set, only if
+ * `token` matches it; otherwise any publisher on `core/dispatch` can claim a role.
+ */
+export interface DispatchOperator {
+  role: string;
+  sub: string;
+  /**
+   * `CORE_CONTROL_TOKEN`, added by sentient-api when configured. Never echoed in faults.
+   */
+  token?: string | null;
+}
+
+/**
+ * How far a dispatch got, as reported in [`CoreDispatchResult`].
+ */
+export type DispatchResultStatus =
+  | "ACCEPTED"
+  | "REJECTED"
+  | "COMPLETED"
+  | "CANCELLED"
+  | "BLOCKED"
+  | "QUEUED"
+  | "SCHEDULED"
+  | "TIMEOUT";
+
+/**
+ * Machine-readable fault identifier. Consumers must tolerate kinds outside the known set.
+ */
+export type FaultKind =
+  | "BROKER_OUTAGE"
+  | "BROKER_UNREACHABLE"
+  | "BROKER_RESTORED"
+  | "CORE_UNHEALTHY"
+  | "CORE_RESTORED"
+  | "DISPATCH_LATENCY_SLO_BREACHED"
+  | "DISPATCH_LATENCY_SLO_RECOVERED"
+  | "DB_OUTAGE"
+  | "DB_RESTORED"
+  | "CONTROL_UNAUTHORIZED"
+  | "DISPATCH_PAUSED"
+  | "DISPATCH_RESUMED"
+  | "DISPATCH_REQUEST_INVALID"
+  | "DISPATCH_BLOCKED_PAUSED"
+  | "DISPATCH_BLOCKED_DISABLED"
+  | "DISPATCH_BLOCKED_DRY_RUN"
+  | "DISPATCH_BLOCKED_DEVICE_DISABLED"
+  | "DISPATCH_BLOCKED_DEVICE_OFFLINE"
+  | "DISPATCH_BLOCKED_CRITICAL_NOT_ARMED"
+  | "DISPATCH_BLOCKED_DEVICE_NOT_SAFE"
+  | "DISPATCH_BLOCKED_ZONE_LATCHED"
+  | "DISPATCH_BLOCKED_INTERLOCK"
+  | "DISPATCH_BLOCKED_MAINTENANCE"
+  | "DISPATCH_BLOCKED_MISSING_DEVICE_KEY"
+  | "DISPATCH_BLOCKED_DEVICE_BUSY"
+  | "DISPATCH_QUEUE_FULL"
+  | "DISPATCH_QUEUE_DROPPED"
+  | "DISPATCH_BATCH_BLOCKED"
+  | "DISPATCH_BATCH_FAILED"
+  | "DISPATCH_SCHEDULE_MISSED"
+  | "COMMAND_REJECTED"
+  | "COMMAND_ACK_TIMEOUT"
+  | "COMMAND_COMPLETE_TIMEOUT"
+  | "COMMAND_CANCELLED"
+  | "COMMAND_CANCEL_UNSUPPORTED"
+  | "COMMAND_CANCEL_TIMEOUT"
+  | "DEVICE_OFFLINE"
+  | "DEVICE_ONLINE"
+  | "DEVICE_FLAPPING"
+  | "DEVICE_CLOCK_OFFSET"
+  | "DEVICE_CLOCK_SYNCED"
+  | "DEVICE_SAFETY_STATE"
+  | "SAFETY_LATCHED"
+  | "SAFETY_RESET_DENIED"
+  | "SAFETY_LATCH_RESET"
+  | "MAINTENANCE_SET"
+  | "MAINTENANCE_RELEASED"
+  | "MAINTENANCE_DENIED"
+  | "DEVICE_UNREGISTERED"
+  | "DEVICE_REGISTRY_RELOADED"
+  | "GRAPH_STARTED"
+  | "GRAPH_STOPPED"
+  | "GRAPH_START_DENIED"
+  | "GRAPH_RELOADED"
+  | "GRAPH_RELOAD_DENIED"
+  | "GRAPH_RELOAD_FAILED"
+  | "GRAPH_TIMEOUT"
+  | "GRAPH_DISPATCH_FAILED"
+  | "OSC_SEND_FAILED"
+  | string;
+
+export interface Heartbeat {
+  device_id: string;
+  firmware_version: string;
+  last_error?: string | null;
+  observed_at_unix_ms: number;
+  room_id: string;
+  safety_state: SafetyState;
+  schema: string;
+  /**
+   * Schema versions this device can parse (v8.1+). Absent on v8 firmware, which core treats
+   * as v8-only; see [`negotiate_schema`].
+   */
+  supported_schemas?: string[];
+  uptime_ms: number;
+}
+
+export type MaintenanceScope = "DEVICE" | "ZONE";
+
+/**
+ * Maintenance lock (lockout/tagout) on a device or safety zone, carried in [`CoreStatus`] and
+ * the core device status.
+ *
+ * While held, graph and other automated dispatches to the covered devices are refused with
+ * `DISPATCH_BLOCKED_MAINTENANCE`; manual dispatches from a trusted TECH/ADMIN operator (see
+ * [`DispatchOperator`]) still go through.
+ * Only `owner` or an ADMIN can release it.
+ */
+export interface MaintenanceStatus {
+  note?: string | null;
+  owner: string;
+  owner_role: string;
+  scope: MaintenanceScope;
+  since_unix_ms: number;
+  /**
+   * Device id or zone name.
+   */
+  target: string;
+}
+
+export type OscArg = {
+  type: "INT";
+  value: number;
+} | {
+  type: "FLOAT";
+  value: number;
+} | {
+  type: "STRING";
+  value: string;
+} | {
+  type: "BOOL";
+  value: boolean;
+};
+
+export interface OscCue {
+  address: string;
+  args?: OscArg[];
+  correlation_id: string;
+  cue_id: string;
+  issued_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+}
+
+export interface Presence {
+  device_id: string;
+  observed_at_unix_ms: number;
+  room_id: string;
+  schema: string;
+  status: PresenceStatus;
+}
+
+export type PresenceStatus = "ONLINE" | "OFFLINE";
+
+export type SafetyClass = "CRITICAL" | "NON_CRITICAL";
+
+export interface SafetyState {
+  kind: SafetyStateKind;
+  latched: boolean;
+  reason_code?: string | null;
+}
+
+export type SafetyStateKind = "SAFE" | "BLOCKED" | "FAULT" | "E_STOP" | "MAINTENANCE";
+
+/**
+ * Safety state of one named zone (a physical area), carried in [`CoreStatus`].
+ *
+ * `safety` is the worst state reported by the zone's devices; while the zone is latched it
+ * reads `latched=true` (kind `FAULT`, or `E_STOP`) until a `RESET_SAFETY_LATCH` for the zone.
+ */
+export interface SafetyZoneStatus {
+  /**
+   * Registry devices assigned to the zone.
+   */
+  device_ids?: string[];
+  latched_since_unix_ms?: number | null;
+  safety: SafetyState;
+  zone: string;
+}
+
+/**
+ * Fault severity. Ordered so consumers can filter with `severity >= Severity::Warn`.
+ */
+export type Severity = "INFO" | "WARN" | "CRITICAL";
+
+/**
+ * Clock probe from core (v8.5+), on `room/{room_id}/device/{device_id}/ping`.
+ */
+export interface TimePing {
+  /**
+   * Core clock when the ping was sent (t1).
+   */
+  core_sent_at_unix_ms: number;
+  device_id: string;
+  ping_id: string;
+  room_id: string;
+  schema: string;
+}
+
+/**
+ * Device answer to a [`TimePing`], on `room/{room_id}/device/{device_id}/pong`. Sent
+ * immediately; both device timestamps are read from the device's own wall clock.
+ */
+export interface TimePong {
+  /**
+   * Echoed from the ping (t1).
+   */
+  core_sent_at_unix_ms: number;
+  device_id: string;
+  /**
+   * Device clock when the ping arrived (t2).
+   */
+  device_received_at_unix_ms: number;
+  /**
+   * Device clock when the pong was sent (t3).
+   */
+  device_sent_at_unix_ms: number;
+  /**
+   * Echoed from the ping.
+   */
+  ping_id: string;
+  room_id: string;
+  schema: string;
+}
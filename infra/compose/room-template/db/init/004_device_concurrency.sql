@@ -0,0 +1,13 @@
+-- Sentient v8 per-device command concurrency policy.
+--
+-- What sentient-core does with a dispatch for a device that still has a
+-- command in flight. NULL = room default (CORE_DEVICE_CONCURRENCY_DEFAULT /
+-- CORE_DEVICE_QUEUE_MAX_DEPTH). Safe to re-run on existing room DBs.
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS concurrency_policy TEXT NULL
+    CHECK (concurrency_policy IN ('PARALLEL','REJECT','QUEUE','SUPERSEDE'));
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS queue_max_depth INTEGER NULL
+    CHECK (queue_max_depth > 0);
@@ -0,0 +1,13 @@
+-- Sentient v8 safety zones.
+--
+-- Named physical area a device belongs to. A FAULT/E_STOP from a zoned device
+-- latches only its zone (dispatch to the zone's devices is blocked until a
+-- zone reset) unless CORE_SAFETY_ESCALATION sends it room-wide. NULL = the
+-- device's safety events latch the whole room. Safe to re-run on existing
+-- room DBs.
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS zone TEXT NULL
+    CHECK (zone IS NULL OR length(btrim(zone)) > 0);
+
+CREATE INDEX IF NOT EXISTS devices_zone_idx ON devices (zone);
@@ -0,0 +1,12 @@
+-- Sentient v8 per-device offline policy.
+--
+-- What core does when a device goes offline (presence OFFLINE or heartbeat
+-- timeout): IGNORE keeps dispatching, BLOCK_DEVICE refuses dispatch to it,
+-- LATCH_ZONE latches its safety zone (the room if it has none), LATCH_ROOM
+-- latches room safety. NULL = BLOCK_DEVICE. Safe to re-run on existing room
+-- DBs.
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS offline_policy TEXT NULL
+    CHECK (offline_policy IS NULL
+           OR offline_policy IN ('IGNORE', 'BLOCK_DEVICE', 'LATCH_ZONE', 'LATCH_ROOM'));
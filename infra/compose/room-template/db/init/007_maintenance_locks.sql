@@ -0,0 +1,16 @@
+-- Sentient v8 maintenance mode (lockout/tagout).
+--
+-- One row per device or safety zone an operator has taken out of show control.
+-- Core loads these at startup so locks survive restarts; graph/automated
+-- dispatch to covered devices is refused until the owner (or an ADMIN)
+-- releases the lock. Safe to re-run on existing room DBs.
+
+CREATE TABLE IF NOT EXISTS maintenance_locks (
+  scope TEXT NOT NULL CHECK (scope IN ('DEVICE', 'ZONE')),
+  target TEXT NOT NULL CHECK (length(btrim(target)) > 0),
+  owner TEXT NOT NULL,
+  owner_role TEXT NOT NULL,
+  note TEXT NULL,
+  since TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, target)
+);
@@ -0,0 +1,14 @@
+-- Sentient v8 per-device liveness thresholds.
+--
+-- heartbeat_interval_ms: how often the device heartbeats; the offline
+-- threshold defaults to 3x this. offline_ms: heartbeat silence before core
+-- marks the device offline (wins over the derived value). Both NULL =
+-- DEVICE_OFFLINE_MS (default 3000). Safe to re-run on existing room DBs.
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS heartbeat_interval_ms INT NULL
+    CHECK (heartbeat_interval_ms IS NULL OR heartbeat_interval_ms > 0);
+
+ALTER TABLE devices
+  ADD COLUMN IF NOT EXISTS offline_ms INT NULL
+    CHECK (offline_ms IS NULL OR offline_ms > 0);
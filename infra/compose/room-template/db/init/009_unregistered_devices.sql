@@ -0,0 +1,29 @@
+-- Sentient v8 unregistered device inbox + provisioned device keys.
+--
+-- Core upserts a row into unregistered_devices when a device missing from the
+-- `devices` registry heartbeats in the room namespace. Adopting it through
+-- sentient-api (POST .../devices/unregistered/{device_id}/adopt) creates the
+-- registry row, stores a fresh HMAC key in device_keys and marks the inbox row
+-- adopted. Core reads device_keys together with the registry (they override
+-- DEVICE_HMAC_KEYS_JSON). Safe to re-run on existing room DBs.
+
+CREATE TABLE IF NOT EXISTS unregistered_devices (
+  device_id TEXT PRIMARY KEY,
+  first_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+  firmware_version TEXT NULL,
+  supported_schemas JSONB NOT NULL DEFAULT '[]'::jsonb,
+  adopted_at TIMESTAMPTZ NULL,
+  adopted_by TEXT NULL
+);
+
+CREATE INDEX IF NOT EXISTS unregistered_devices_pending_idx
+  ON unregistered_devices (last_seen DESC) WHERE adopted_at IS NULL;
+
+-- Key material is hex; keep room DB access restricted accordingly.
+CREATE TABLE IF NOT EXISTS device_keys (
+  device_id TEXT PRIMARY KEY,
+  key_hex TEXT NOT NULL CHECK (length(key_hex) >= 32),
+  created_by TEXT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -1,8 +1,14 @@
 use std::time::Duration;
 
+use rumqttc::v5::mqttbytes::v5::{LastWill, Packet, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, MqttOptions};
 use sentient_protocol::{
-    AckStatus, CommandAck, CommandEnvelope, DeviceState, Heartbeat, Presence, PresenceStatus,
-    SafetyState, SafetyStateKind, SCHEMA_VERSION,
+    schema_at_least, trace_id_from_user_properties, AckStatus, CommandAck, CommandAction,
+    CommandEnvelope, CommandProgress, CoreWatchdog, DeviceState, Heartbeat, Presence,
+    PresenceStatus, SafetyState, SafetyStateKind, TimePing, TimePong, WireCodec,
+    ACCEPTED_SCHEMA_VERSIONS, MQTT_USER_PROPERTY_TRACE_ID, PROGRESS_MIN_SCHEMA,
+    SAFETY_REASON_CORE_LOST, SCHEDULE_MIN_SCHEMA, WATCHDOG_MIN_SCHEMA,
 };
 use tokio::time::MissedTickBehavior;
 use tracing::{info, warn};
@@ -17,6 +23,28 @@ struct AuthConfig {
 #[derive(Debug, Clone)]
 struct SimBehavior {
     drop_first_accepted_ack: bool,
+    /// Schemas advertised in heartbeats; commands stamped with anything else are rejected.
+    supported_schemas: Vec<String>,
+    /// Wire encoding for every topic this sim publishes/subscribes (topic suffix selects it).
+    codec: WireCodec,
+    /// Simulated execution time; long values make `CANCEL` testable.
+    execution_ms: u64,
+    /// Interval between `IN_PROGRESS` acks while executing (0 disables).
+    progress_interval_ms: u64,
+    /// Simulated clock skew (ms, may be negative) applied to pongs and scheduled execution.
+    clock_offset_ms: i64,
+}
+
+impl SimBehavior {
+    /// Newest advertised schema; used for everything the sim publishes on its own.
+    fn schema(&self) -> String {
+        sentient_protocol::negotiate_schema(&self.supported_schemas).to_string()
+    }
+
+    /// The simulated device's wall clock.
+    fn clock_ms(&self) -> u64 {
+        unix_ms_now().saturating_add_signed(self.clock_offset_ms)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -81,11 +109,105 @@ impl SimSafetyConfig {
     }
 }
 
+/// What the sim does when `CoreWatchdog` beacons stop (`SIM_CORE_LOST_ACTION`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoreLostAction {
+    /// Log only (default; matches firmware that predates v8.6).
+    None,
+    /// Abort running commands, refuse new ones and report `BLOCKED` / `CORE_LOST` until beacons
+    /// resume.
+    SafeState,
+}
+
+/// Reference dead-man switch for firmware: tracks `CoreWatchdog` beacons and decides when core
+/// counts as lost. Armed by the first valid beacon.
+#[derive(Debug)]
+struct CoreWatchdogMonitor {
+    action: CoreLostAction,
+    /// `SIM_CORE_LOST_TIMEOUT_MS`; otherwise the beacon's own `timeout_ms`.
+    timeout_override_ms: Option<u64>,
+    /// (boot_id, counter, issued_at_unix_ms) of the last accepted beacon.
+    last: Option<(Uuid, u64, u64)>,
+    last_seen: Option<tokio::time::Instant>,
+    timeout_ms: u64,
+    /// Safety state before core was lost; restored when beacons resume.
+    lost: Option<SafetyState>,
+}
+
+impl CoreWatchdogMonitor {
+    fn from_env() -> anyhow::Result<Self> {
+        let action = match std::env::var("SIM_CORE_LOST_ACTION") {
+            Ok(v) if v == "SAFE_STATE" => CoreLostAction::SafeState,
+            Ok(v) if v.is_empty() || v == "NONE" => CoreLostAction::None,
+            Ok(v) => anyhow::bail!("SIM_CORE_LOST_ACTION must be NONE or SAFE_STATE (got {v:?})"),
+            Err(_) => CoreLostAction::None,
+        };
+        Ok(Self {
+            action,
+            timeout_override_ms: std::env::var("SIM_CORE_LOST_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0),
+            last: None,
+            last_seen: None,
+            timeout_ms: 0,
+            lost: None,
+        })
+    }
+
+    /// Validates a beacon; `Err` carries the reason it was dropped. Within one `boot_id` the
+    /// counter must advance; a new `boot_id` must be issued later than the last accepted beacon,
+    /// so replaying an earlier core run cannot keep the device alive.
+    fn accept(
+        &mut self,
+        beacon: &CoreWatchdog,
+        device_id: &str,
+        auth: &AuthConfig,
+    ) -> Result<(), &'static str> {
+        if beacon.device_id != device_id {
+            return Err("WRONG_DEVICE");
+        }
+        if auth.enforce {
+            let Some(key) = auth.hmac_key.as_deref() else {
+                return Err("AUTH_ERROR");
+            };
+            if !sentient_protocol::verify_watchdog_hmac_sha256(beacon, key) {
+                return Err("AUTH_INVALID");
+            }
+        }
+        if let Some((boot_id, counter, issued_at)) = self.last {
+            if beacon.boot_id == boot_id && beacon.counter <= counter {
+                return Err("STALE_COUNTER");
+            }
+            if beacon.boot_id != boot_id && beacon.issued_at_unix_ms <= issued_at {
+                return Err("STALE_BOOT");
+            }
+        }
+        self.last = Some((beacon.boot_id, beacon.counter, beacon.issued_at_unix_ms));
+        self.last_seen = Some(tokio::time::Instant::now());
+        self.timeout_ms = self.timeout_override_ms.unwrap_or(beacon.timeout_ms);
+        Ok(())
+    }
+
+    fn expired(&self, now: tokio::time::Instant) -> bool {
+        self.last_seen
+            .is_some_and(|at| now.duration_since(at) >= Duration::from_millis(self.timeout_ms))
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 struct CommandRecord {
     accepted_sent: bool,
     completed_sent: bool,
     completed: bool,
+    cancelled: bool,
+    /// Set while the simulated execution is running.
+    executing_until: Option<tokio::time::Instant>,
+    started_at: Option<tokio::time::Instant>,
+    last_progress_at: Option<tokio::time::Instant>,
+    cmd: Option<CommandEnvelope>,
+    /// MQTT v5 trace id the command arrived with; echoed on every ack for it.
+    trace_id: Option<String>,
 }
 
 #[tokio::main]
@@ -132,8 +254,43 @@ async fn main() -> anyhow::Result<()> {
         hmac_key,
     };
 
+    // Emulate older firmware with e.g. SIM_SUPPORTED_SCHEMAS=v8.
+    let supported_schemas: Vec<String> = std::env::var("SIM_SUPPORTED_SCHEMAS")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_else(|| {
+            ACCEPTED_SCHEMA_VERSIONS
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        });
+    let codec = match std::env::var("SIM_CODEC") {
+        Ok(v) if !v.trim().is_empty() => WireCodec::parse(&v)
+            .ok_or_else(|| anyhow::anyhow!("SIM_CODEC must be json or msgpack (got {v:?})"))?,
+        _ => WireCodec::Json,
+    };
     let behavior = SimBehavior {
         drop_first_accepted_ack: parse_bool_env("SIM_DROP_FIRST_ACCEPTED_ACK").unwrap_or(false),
+        supported_schemas,
+        codec,
+        execution_ms: std::env::var("SIM_EXECUTION_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50),
+        progress_interval_ms: std::env::var("SIM_PROGRESS_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(500),
+        clock_offset_ms: std::env::var("SIM_CLOCK_OFFSET_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
     };
     let mut dropped_first_accepted_ack = false;
     let mut commands: std::collections::HashMap<Uuid, CommandRecord> =
@@ -141,6 +298,7 @@ async fn main() -> anyhow::Result<()> {
 
     let safety_cfg = SimSafetyConfig::from_env();
     let mut current_safety = safety_cfg.initial_state();
+    let mut watchdog = CoreWatchdogMonitor::from_env()?;
 
     info!(
         room_id = %room_id,
@@ -149,57 +307,83 @@ async fn main() -> anyhow::Result<()> {
         mqtt_port,
         enforce_cmd_auth = auth.enforce,
         drop_first_accepted_ack = behavior.drop_first_accepted_ack,
+        supported_schemas = ?behavior.supported_schemas,
+        codec = %behavior.codec,
+        execution_ms = behavior.execution_ms,
+        progress_interval_ms = behavior.progress_interval_ms,
+        clock_offset_ms = behavior.clock_offset_ms,
         sim_safety_kind = ?safety_cfg.kind,
         sim_safety_latched = safety_cfg.latched,
         sim_trigger_fault_after_ms = ?safety_cfg.trigger_fault_after_ms,
+        sim_core_lost_action = ?watchdog.action,
+        sim_core_lost_timeout_ms = ?watchdog.timeout_override_ms,
         "controller-sim starting"
     );
 
-    let presence_topic = format!("room/{}/device/{}/presence", room_id, device_id);
+    let presence_topic = codec.topic(&format!("room/{}/device/{}/presence", room_id, device_id));
     let last_will = Presence {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: behavior.schema(),
         room_id: room_id.clone(),
         device_id: device_id.clone(),
         status: PresenceStatus::Offline,
         observed_at_unix_ms: unix_ms_now(),
     };
-    let last_will_payload = serde_json::to_vec(&last_will)?;
+    let last_will_payload = codec.encode(&last_will)?;
 
     let (client, mut eventloop) = {
-        let mut options = rumqttc::MqttOptions::new(client_id, mqtt_host, mqtt_port);
+        let mut options = MqttOptions::new(client_id, mqtt_host, mqtt_port);
         options.set_keep_alive(Duration::from_secs(5));
-        options.set_last_will(rumqttc::LastWill::new(
+        options.set_last_will(LastWill::new(
             presence_topic.clone(),
             last_will_payload,
-            rumqttc::QoS::AtLeastOnce,
+            QoS::AtLeastOnce,
             true, // retained
+            None,
         ));
         if let (Some(user), Some(pass)) = (mqtt_username.as_deref(), mqtt_password.as_deref()) {
             options.set_credentials(user, pass);
         }
-        rumqttc::AsyncClient::new(options, 200)
+        AsyncClient::new(options, 200)
     };
 
-    let cmd_topic = format!("room/{}/device/{}/cmd", room_id, device_id);
+    let cmd_topic = codec.topic(&format!("room/{}/device/{}/cmd", room_id, device_id));
     client
-        .subscribe(cmd_topic.clone(), rumqttc::QoS::AtLeastOnce)
+        .subscribe(cmd_topic.clone(), QoS::AtLeastOnce)
         .await?;
 
-    let hb_topic = format!("room/{}/device/{}/heartbeat", room_id, device_id);
-    let ack_topic = format!("room/{}/device/{}/ack", room_id, device_id);
-    let state_topic = format!("room/{}/device/{}/state", room_id, device_id);
+    let ping_topic = codec.topic(&format!("room/{}/device/{}/ping", room_id, device_id));
+    client
+        .subscribe(ping_topic.clone(), QoS::AtMostOnce)
+        .await?;
+    let pong_topic = codec.topic(&format!("room/{}/device/{}/pong", room_id, device_id));
+
+    // Core only sends beacons to v8.6+ devices; older emulated firmware never listens.
+    let watchdog_topic = codec.topic(&format!("room/{}/device/{}/watchdog", room_id, device_id));
+    if behavior
+        .supported_schemas
+        .iter()
+        .any(|s| schema_at_least(s, WATCHDOG_MIN_SCHEMA))
+    {
+        client
+            .subscribe(watchdog_topic.clone(), QoS::AtMostOnce)
+            .await?;
+    }
+
+    let hb_topic = codec.topic(&format!("room/{}/device/{}/heartbeat", room_id, device_id));
+    let ack_topic = codec.topic(&format!("room/{}/device/{}/ack", room_id, device_id));
+    let state_topic = codec.topic(&format!("room/{}/device/{}/state", room_id, device_id));
 
     // Publish retained ONLINE presence on startup.
     let online = Presence {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: behavior.schema(),
         room_id: room_id.clone(),
         device_id: device_id.clone(),
         status: PresenceStatus::Online,
         observed_at_unix_ms: unix_ms_now(),
     };
-    if let Ok(payload) = serde_json::to_vec(&online) {
+    if let Ok(payload) = codec.encode(&online) {
         if let Err(err) = client
-            .publish(&presence_topic, rumqttc::QoS::AtLeastOnce, true, payload)
+            .publish(&presence_topic, QoS::AtLeastOnce, true, payload)
             .await
         {
             warn!(error = %err, "failed to publish ONLINE presence");
@@ -210,6 +394,7 @@ async fn main() -> anyhow::Result<()> {
     publish_state(
         &client,
         &state_topic,
+        &behavior.schema(),
         &room_id,
         &device_id,
         &current_safety,
@@ -222,6 +407,8 @@ async fn main() -> anyhow::Result<()> {
     let start = tokio::time::Instant::now();
     let mut hb = tokio::time::interval(Duration::from_millis(1000));
     hb.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut exec_tick = tokio::time::interval(Duration::from_millis(10));
+    exec_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
     loop {
         tokio::select! {
@@ -229,6 +416,33 @@ async fn main() -> anyhow::Result<()> {
                 warn!("shutdown requested (ctrl-c)");
                 break;
             }
+            _ = exec_tick.tick() => {
+                if watchdog.lost.is_none() && watchdog.expired(tokio::time::Instant::now()) {
+                    enter_core_lost(
+                        &client,
+                        &ack_topic,
+                        &state_topic,
+                        &room_id,
+                        &device_id,
+                        &behavior,
+                        &mut watchdog,
+                        &mut commands,
+                        &mut current_safety,
+                    )
+                    .await;
+                }
+                complete_due_commands(
+                    &client,
+                    &ack_topic,
+                    &state_topic,
+                    &room_id,
+                    &device_id,
+                    &behavior,
+                    &mut commands,
+                    &current_safety,
+                )
+                .await;
+            }
             _ = hb.tick() => {
                 if let Some(ms) = safety_cfg.trigger_fault_after_ms {
                     if start.elapsed().as_millis() as u64 >= ms
@@ -243,6 +457,7 @@ async fn main() -> anyhow::Result<()> {
                         publish_state(
                             &client,
                             &state_topic,
+                            &behavior.schema(),
                             &room_id,
                             &device_id,
                             &current_safety,
@@ -252,29 +467,33 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
                 let msg = Heartbeat {
-                    schema: SCHEMA_VERSION.to_string(),
+                    schema: behavior.schema(),
                     room_id: room_id.clone(),
                     device_id: device_id.clone(),
                     uptime_ms: start.elapsed().as_millis() as u64,
                     firmware_version: "sim-0.1.0".to_string(),
                     safety_state: current_safety.clone(),
                     last_error: None,
+                    supported_schemas: behavior.supported_schemas.clone(),
                     observed_at_unix_ms: unix_ms_now(),
                 };
-                if let Ok(payload) = serde_json::to_vec(&msg) {
-                    if let Err(err) = client.publish(&hb_topic, rumqttc::QoS::AtMostOnce, false, payload).await {
+                if let Ok(payload) = behavior.codec.encode(&msg) {
+                    if let Err(err) = client.publish(&hb_topic, QoS::AtMostOnce, false, payload).await {
                         warn!(error = %err, "failed to publish heartbeat");
                     }
                 }
             }
             ev = eventloop.poll() => {
                 match ev {
-                    Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(p))) => {
+                    Ok(Event::Incoming(Packet::Publish(p))) => {
                         if p.topic == cmd_topic {
+                            let trace_id = p
+                                .properties
+                                .as_ref()
+                                .and_then(|props| trace_id_from_user_properties(&props.user_properties));
                             handle_command(
                                 &client,
                                 &ack_topic,
-                                &state_topic,
                                 &room_id,
                                 &device_id,
                                 &auth,
@@ -282,6 +501,22 @@ async fn main() -> anyhow::Result<()> {
                                 &mut dropped_first_accepted_ack,
                                 &mut commands,
                                 &mut current_safety,
+                                trace_id,
+                                &p.payload,
+                            )
+                            .await;
+                        } else if p.topic == ping_topic {
+                            answer_time_ping(&client, &pong_topic, &behavior, &p.payload).await;
+                        } else if p.topic == watchdog_topic {
+                            handle_watchdog(
+                                &client,
+                                &state_topic,
+                                &room_id,
+                                &device_id,
+                                &auth,
+                                &behavior,
+                                &mut watchdog,
+                                &mut current_safety,
                                 &p.payload,
                             )
                             .await;
@@ -300,10 +535,10 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_command(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     ack_topic: &str,
-    state_topic: &str,
     room_id: &str,
     device_id: &str,
     auth: &AuthConfig,
@@ -311,12 +546,13 @@ async fn handle_command(
     dropped_first_accepted_ack: &mut bool,
     commands: &mut std::collections::HashMap<Uuid, CommandRecord>,
     current_safety: &mut SafetyState,
+    trace_id: Option<&str>,
     payload: &[u8],
 ) {
-    let cmd: CommandEnvelope = match serde_json::from_slice(payload) {
+    let cmd: CommandEnvelope = match sentient_protocol::decode_message_as(behavior.codec, payload) {
         Ok(v) => v,
         Err(err) => {
-            warn!(error = %err, "invalid command payload (json)");
+            warn!(error = %err, "invalid command payload");
             return;
         }
     };
@@ -327,9 +563,26 @@ async fn handle_command(
         command_id = %cmd.command_id,
         correlation_id = %cmd.correlation_id,
         sequence = cmd.sequence,
+        trace_id,
         "received command"
     );
 
+    if !behavior.supported_schemas.contains(&cmd.schema) {
+        warn!(schema = %cmd.schema, "command schema not supported by this device");
+        publish_rejected_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            &cmd,
+            "BAD_SCHEMA",
+            trace_id,
+        )
+        .await;
+        return;
+    }
+
     if auth.enforce {
         let Some(key) = auth.hmac_key.as_deref() else {
             warn!("command auth enforced but no key present");
@@ -347,6 +600,7 @@ async fn handle_command(
                     current_safety,
                     &cmd,
                     "AUTH_INVALID",
+                    trace_id,
                 )
                 .await;
                 return;
@@ -361,6 +615,7 @@ async fn handle_command(
                     current_safety,
                     &cmd,
                     "AUTH_ERROR",
+                    trace_id,
                 )
                 .await;
                 return;
@@ -368,7 +623,59 @@ async fn handle_command(
         }
     }
 
+    if current_safety.reason_code.as_deref() == Some(SAFETY_REASON_CORE_LOST)
+        && cmd.action != CommandAction::Cancel
+    {
+        // Stay safe until the watchdog confirms core is back.
+        publish_rejected_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            &cmd,
+            SAFETY_REASON_CORE_LOST,
+            trace_id,
+        )
+        .await;
+        return;
+    }
+
+    if cmd.action == CommandAction::Cancel {
+        handle_cancel(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            behavior,
+            dropped_first_accepted_ack,
+            commands,
+            current_safety,
+            &cmd,
+            trace_id,
+        )
+        .await;
+        return;
+    }
+
     let record = commands.entry(cmd.command_id).or_default();
+    if record.trace_id.is_none() {
+        record.trace_id = trace_id.map(str::to_string);
+    }
+    if record.cancelled {
+        // Duplicate delivery of a command we already aborted.
+        publish_cancelled_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            &cmd,
+            record.trace_id.as_deref(),
+        )
+        .await;
+        return;
+    }
     if record.completed {
         // Duplicate delivery (e.g., core retry). Re-ack without re-executing.
         maybe_publish_accepted_ack(
@@ -409,9 +716,107 @@ async fn handle_command(
     )
     .await;
 
-    // Simulate execution duration.
-    tokio::time::sleep(Duration::from_millis(50)).await;
+    if record.executing_until.is_some() {
+        // Duplicate delivery while still executing.
+        return;
+    }
+    // Simulate execution duration; `complete_due_commands` finishes it unless cancelled first.
+    // Scheduled commands (v8.4+) start at their execute time (the sim trusts the host clock).
+    let wait_ms = cmd
+        .execute_at_unix_ms
+        .filter(|_| schema_at_least(&cmd.schema, SCHEDULE_MIN_SCHEMA))
+        .map_or(0, |at| at.saturating_sub(behavior.clock_ms()));
+    let start = tokio::time::Instant::now() + Duration::from_millis(wait_ms);
+    record.started_at = Some(start);
+    record.last_progress_at = Some(start);
+    record.executing_until = Some(start + Duration::from_millis(behavior.execution_ms));
+    record.cmd = Some(cmd);
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_cancel(
+    client: &AsyncClient,
+    ack_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    behavior: &SimBehavior,
+    dropped_first_accepted_ack: &mut bool,
+    commands: &mut std::collections::HashMap<Uuid, CommandRecord>,
+    current_safety: &SafetyState,
+    cancel: &CommandEnvelope,
+    trace_id: Option<&str>,
+) {
+    let Some(target_id) = cancel.cancel_target() else {
+        publish_rejected_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            cancel,
+            "INVALID_PARAMS",
+            trace_id,
+        )
+        .await;
+        return;
+    };
+
+    if commands
+        .get(&cancel.command_id)
+        .is_some_and(|r| r.completed)
+    {
+        // Duplicate delivery of a cancel we already handled.
+        return;
+    }
+
+    let target = commands
+        .get_mut(&target_id)
+        .filter(|r| r.executing_until.is_some() && !r.cancelled);
+    let Some(target) = target else {
+        warn!(command_id = %target_id, "cancel: command not executing");
+        publish_rejected_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            cancel,
+            "NOT_CANCELLABLE",
+            trace_id,
+        )
+        .await;
+        return;
+    };
+    target.executing_until = None;
+    target.cancelled = true;
+    warn!(command_id = %target_id, "command cancelled");
+    if let Some(target_cmd) = target.cmd.as_ref() {
+        publish_cancelled_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            target_cmd,
+            target.trace_id.as_deref(),
+        )
+        .await;
+    }
 
+    let record = commands.entry(cancel.command_id).or_default();
+    record.trace_id = trace_id.map(str::to_string);
+    maybe_publish_accepted_ack(
+        client,
+        ack_topic,
+        room_id,
+        device_id,
+        current_safety,
+        behavior,
+        dropped_first_accepted_ack,
+        record,
+        cancel,
+    )
+    .await;
     record.completed = true;
     maybe_publish_completed_ack(
         client,
@@ -420,29 +825,223 @@ async fn handle_command(
         device_id,
         current_safety,
         record,
-        &cmd,
+        cancel,
     )
     .await;
+}
+
+/// Finishes simulated executions whose time is up (and reports progress on the rest).
+#[allow(clippy::too_many_arguments)]
+async fn complete_due_commands(
+    client: &AsyncClient,
+    ack_topic: &str,
+    state_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    behavior: &SimBehavior,
+    commands: &mut std::collections::HashMap<Uuid, CommandRecord>,
+    current_safety: &SafetyState,
+) {
+    let now = tokio::time::Instant::now();
+    for record in commands.values_mut() {
+        let Some(until) = record.executing_until else {
+            continue;
+        };
+        if until > now {
+            maybe_publish_progress_ack(
+                client,
+                ack_topic,
+                room_id,
+                device_id,
+                behavior,
+                current_safety,
+                record,
+                now,
+            )
+            .await;
+            continue;
+        }
+        record.executing_until = None;
+        record.completed = true;
+        let Some(cmd) = record.cmd.clone() else {
+            continue;
+        };
+        maybe_publish_completed_ack(
+            client,
+            ack_topic,
+            room_id,
+            device_id,
+            current_safety,
+            record,
+            &cmd,
+        )
+        .await;
 
-    // Update retained device state after completing the command.
+        // Update retained device state after completing the command.
+        publish_state(
+            client,
+            state_topic,
+            &cmd.schema,
+            room_id,
+            device_id,
+            current_safety,
+            serde_json::json!({
+                "last_completed": {
+                    "command_id": cmd.command_id,
+                    "correlation_id": cmd.correlation_id,
+                    "sequence": cmd.sequence,
+                    "action": format!("{:?}", cmd.action),
+                }
+            }),
+        )
+        .await;
+    }
+}
+
+/// Checks a `CoreWatchdog` beacon and, if core had been lost, leaves the safe state again.
+#[allow(clippy::too_many_arguments)]
+async fn handle_watchdog(
+    client: &AsyncClient,
+    state_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    auth: &AuthConfig,
+    behavior: &SimBehavior,
+    watchdog: &mut CoreWatchdogMonitor,
+    current_safety: &mut SafetyState,
+    payload: &[u8],
+) {
+    let beacon: CoreWatchdog = match sentient_protocol::decode_message_as(behavior.codec, payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid watchdog payload");
+            return;
+        }
+    };
+    if let Err(reason) = watchdog.accept(&beacon, device_id, auth) {
+        warn!(
+            reason,
+            boot_id = %beacon.boot_id,
+            counter = beacon.counter,
+            "watchdog beacon dropped"
+        );
+        return;
+    }
+    let Some(previous) = watchdog.lost.take() else {
+        return;
+    };
+    info!(boot_id = %beacon.boot_id, counter = beacon.counter, "core watchdog restored");
+    if watchdog.action != CoreLostAction::SafeState {
+        return;
+    }
+    *current_safety = previous;
     publish_state(
         client,
         state_topic,
+        &behavior.schema(),
         room_id,
         device_id,
         current_safety,
-        serde_json::json!({
-            "last_completed": {
-                "command_id": cmd.command_id,
-                "correlation_id": cmd.correlation_id,
-                "sequence": cmd.sequence,
-                "action": format!("{:?}", cmd.action),
-            }
-        }),
+        serde_json::json!({"core_lost": false}),
     )
     .await;
 }
 
+/// Core went silent for longer than the watchdog timeout. With `SAFE_STATE`, running commands are
+/// aborted with `REJECTED` / `CORE_LOST` and the device reports `BLOCKED` until beacons resume.
+#[allow(clippy::too_many_arguments)]
+async fn enter_core_lost(
+    client: &AsyncClient,
+    ack_topic: &str,
+    state_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    behavior: &SimBehavior,
+    watchdog: &mut CoreWatchdogMonitor,
+    commands: &mut std::collections::HashMap<Uuid, CommandRecord>,
+    current_safety: &mut SafetyState,
+) {
+    warn!(
+        timeout_ms = watchdog.timeout_ms,
+        action = ?watchdog.action,
+        "core watchdog expired: core lost"
+    );
+    watchdog.lost = Some(current_safety.clone());
+    if watchdog.action != CoreLostAction::SafeState {
+        return;
+    }
+    *current_safety = SafetyState {
+        kind: SafetyStateKind::Blocked,
+        reason_code: Some(SAFETY_REASON_CORE_LOST.to_string()),
+        latched: false,
+    };
+    for record in commands.values_mut() {
+        if record.executing_until.take().is_none() {
+            continue;
+        }
+        // Treated like a cancel from here on, so a redelivery is not executed again.
+        record.cancelled = true;
+        if let Some(cmd) = record.cmd.as_ref() {
+            warn!(command_id = %cmd.command_id, "command aborted: core lost");
+            publish_rejected_ack(
+                client,
+                ack_topic,
+                room_id,
+                device_id,
+                current_safety,
+                cmd,
+                SAFETY_REASON_CORE_LOST,
+                record.trace_id.as_deref(),
+            )
+            .await;
+        }
+    }
+    publish_state(
+        client,
+        state_topic,
+        &behavior.schema(),
+        room_id,
+        device_id,
+        current_safety,
+        serde_json::json!({"core_lost": true}),
+    )
+    .await;
+}
+
+/// Answers a core `TimePing` right away, stamped with the (possibly skewed) sim clock.
+async fn answer_time_ping(
+    client: &AsyncClient,
+    pong_topic: &str,
+    behavior: &SimBehavior,
+    payload: &[u8],
+) {
+    let received_at = behavior.clock_ms();
+    let ping: TimePing = match sentient_protocol::decode_message_as(behavior.codec, payload) {
+        Ok(ping) => ping,
+        Err(err) => {
+            warn!(error = %err, "invalid time ping");
+            return;
+        }
+    };
+    let pong = TimePong {
+        schema: ping.schema,
+        room_id: ping.room_id,
+        device_id: ping.device_id,
+        ping_id: ping.ping_id,
+        core_sent_at_unix_ms: ping.core_sent_at_unix_ms,
+        device_received_at_unix_ms: received_at,
+        device_sent_at_unix_ms: behavior.clock_ms(),
+    };
+    if let Ok(bytes) = behavior.codec.encode(&pong) {
+        if let Err(err) = client
+            .publish(pong_topic, QoS::AtMostOnce, false, bytes)
+            .await
+        {
+            warn!(error = %err, "failed to publish pong");
+        }
+    }
+}
+
 fn unix_ms_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -457,29 +1056,38 @@ fn parse_bool_env(key: &str) -> Option<bool> {
         .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn publish_rejected_ack(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     ack_topic: &str,
     room_id: &str,
     device_id: &str,
     safety: &SafetyState,
     cmd: &CommandEnvelope,
     reason_code: &str,
+    trace_id: Option<&str>,
 ) {
     let rejected = CommandAck {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: cmd.schema.clone(),
         room_id: room_id.to_string(),
         device_id: device_id.to_string(),
         command_id: cmd.command_id,
         correlation_id: cmd.correlation_id,
         status: AckStatus::Rejected,
         reason_code: Some(reason_code.to_string()),
+        progress: None,
         safety_state: safety.clone(),
         observed_at_unix_ms: unix_ms_now(),
     };
-    if let Ok(bytes) = serde_json::to_vec(&rejected) {
+    if let Ok(bytes) = WireCodec::split_topic(ack_topic).1.encode(&rejected) {
         if let Err(err) = client
-            .publish(ack_topic, rumqttc::QoS::AtLeastOnce, false, bytes)
+            .publish_with_properties(
+                ack_topic,
+                QoS::AtLeastOnce,
+                false,
+                bytes,
+                ack_properties(trace_id),
+            )
             .await
         {
             warn!(error = %err, "failed to publish REJECTED ack");
@@ -487,8 +1095,126 @@ async fn publish_rejected_ack(
     }
 }
 
+/// MQTT v5 properties for an ack: the command's trace id, if it carried one.
+fn ack_properties(trace_id: Option<&str>) -> PublishProperties {
+    PublishProperties {
+        user_properties: trace_id
+            .map(|t| vec![(MQTT_USER_PROPERTY_TRACE_ID.to_string(), t.to_string())])
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Reports simulated travel for commands whose schema allows `IN_PROGRESS` acks.
+#[allow(clippy::too_many_arguments)]
+async fn maybe_publish_progress_ack(
+    client: &AsyncClient,
+    ack_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    behavior: &SimBehavior,
+    safety: &SafetyState,
+    record: &mut CommandRecord,
+    now: tokio::time::Instant,
+) {
+    if behavior.progress_interval_ms == 0 {
+        return;
+    }
+    let (Some(cmd), Some(started_at), Some(until)) = (
+        record.cmd.as_ref(),
+        record.started_at,
+        record.executing_until,
+    ) else {
+        return;
+    };
+    if !schema_at_least(&cmd.schema, PROGRESS_MIN_SCHEMA) {
+        return;
+    }
+    let due = record.last_progress_at.is_none_or(|last| {
+        now.duration_since(last) >= Duration::from_millis(behavior.progress_interval_ms)
+    });
+    if !due {
+        return;
+    }
+    record.last_progress_at = Some(now);
+
+    let elapsed_ms = now.duration_since(started_at).as_millis() as u64;
+    let total_ms = behavior.execution_ms.max(1);
+    let percent = (elapsed_ms.min(total_ms) * 100 / total_ms) as u8;
+    let progress = CommandAck {
+        schema: cmd.schema.clone(),
+        room_id: room_id.to_string(),
+        device_id: device_id.to_string(),
+        command_id: cmd.command_id,
+        correlation_id: cmd.correlation_id,
+        status: AckStatus::InProgress,
+        reason_code: None,
+        progress: Some(CommandProgress {
+            percent: Some(percent),
+            // Pretend the prop travels one unit per millisecond.
+            position: Some(elapsed_ms as i64),
+            target_position: Some(total_ms as i64),
+            eta_ms: Some(until.saturating_duration_since(now).as_millis() as u64),
+        }),
+        safety_state: safety.clone(),
+        observed_at_unix_ms: unix_ms_now(),
+    };
+    if let Ok(bytes) = WireCodec::split_topic(ack_topic).1.encode(&progress) {
+        if let Err(err) = client
+            .publish_with_properties(
+                ack_topic,
+                QoS::AtLeastOnce,
+                false,
+                bytes,
+                ack_properties(record.trace_id.as_deref()),
+            )
+            .await
+        {
+            warn!(error = %err, "failed to publish IN_PROGRESS ack");
+        }
+    }
+}
+
+async fn publish_cancelled_ack(
+    client: &AsyncClient,
+    ack_topic: &str,
+    room_id: &str,
+    device_id: &str,
+    safety: &SafetyState,
+    cmd: &CommandEnvelope,
+    trace_id: Option<&str>,
+) {
+    let cancelled = CommandAck {
+        schema: cmd.schema.clone(),
+        room_id: room_id.to_string(),
+        device_id: device_id.to_string(),
+        command_id: cmd.command_id,
+        correlation_id: cmd.correlation_id,
+        status: AckStatus::Cancelled,
+        reason_code: None,
+        progress: None,
+        safety_state: safety.clone(),
+        observed_at_unix_ms: unix_ms_now(),
+    };
+    if let Ok(bytes) = WireCodec::split_topic(ack_topic).1.encode(&cancelled) {
+        if let Err(err) = client
+            .publish_with_properties(
+                ack_topic,
+                QoS::AtLeastOnce,
+                false,
+                bytes,
+                ack_properties(trace_id),
+            )
+            .await
+        {
+            warn!(error = %err, "failed to publish CANCELLED ack");
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn maybe_publish_accepted_ack(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     ack_topic: &str,
     room_id: &str,
     device_id: &str,
@@ -509,19 +1235,26 @@ async fn maybe_publish_accepted_ack(
     }
 
     let accepted = CommandAck {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: cmd.schema.clone(),
         room_id: room_id.to_string(),
         device_id: device_id.to_string(),
         command_id: cmd.command_id,
         correlation_id: cmd.correlation_id,
         status: AckStatus::Accepted,
         reason_code: None,
+        progress: None,
         safety_state: safety.clone(),
         observed_at_unix_ms: unix_ms_now(),
     };
-    if let Ok(bytes) = serde_json::to_vec(&accepted) {
+    if let Ok(bytes) = WireCodec::split_topic(ack_topic).1.encode(&accepted) {
         if let Err(err) = client
-            .publish(ack_topic, rumqttc::QoS::AtLeastOnce, false, bytes)
+            .publish_with_properties(
+                ack_topic,
+                QoS::AtLeastOnce,
+                false,
+                bytes,
+                ack_properties(record.trace_id.as_deref()),
+            )
             .await
         {
             warn!(error = %err, "failed to publish ACCEPTED ack");
@@ -532,7 +1265,7 @@ async fn maybe_publish_accepted_ack(
 }
 
 async fn maybe_publish_completed_ack(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     ack_topic: &str,
     room_id: &str,
     device_id: &str,
@@ -545,19 +1278,26 @@ async fn maybe_publish_completed_ack(
     }
 
     let completed = CommandAck {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: cmd.schema.clone(),
         room_id: room_id.to_string(),
         device_id: device_id.to_string(),
         command_id: cmd.command_id,
         correlation_id: cmd.correlation_id,
         status: AckStatus::Completed,
         reason_code: None,
+        progress: None,
         safety_state: safety.clone(),
         observed_at_unix_ms: unix_ms_now(),
     };
-    if let Ok(bytes) = serde_json::to_vec(&completed) {
+    if let Ok(bytes) = WireCodec::split_topic(ack_topic).1.encode(&completed) {
         if let Err(err) = client
-            .publish(ack_topic, rumqttc::QoS::AtLeastOnce, false, bytes)
+            .publish_with_properties(
+                ack_topic,
+                QoS::AtLeastOnce,
+                false,
+                bytes,
+                ack_properties(record.trace_id.as_deref()),
+            )
             .await
         {
             warn!(error = %err, "failed to publish COMPLETED ack");
@@ -568,25 +1308,26 @@ async fn maybe_publish_completed_ack(
 }
 
 async fn publish_state(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     state_topic: &str,
+    schema: &str,
     room_id: &str,
     device_id: &str,
     safety_state: &SafetyState,
     state: serde_json::Value,
 ) {
     let msg = DeviceState {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: schema.to_string(),
         room_id: room_id.to_string(),
         device_id: device_id.to_string(),
         safety_state: safety_state.clone(),
         state,
         observed_at_unix_ms: unix_ms_now(),
     };
-    match serde_json::to_vec(&msg) {
+    match WireCodec::split_topic(state_topic).1.encode(&msg) {
         Ok(bytes) => {
             if let Err(err) = client
-                .publish(state_topic, rumqttc::QoS::AtLeastOnce, true, bytes)
+                .publish(state_topic, QoS::AtLeastOnce, true, bytes)
                 .await
             {
                 warn!(error=%err, "failed to publish device state");
@@ -1,10 +1,61 @@
 use std::{net::SocketAddr, time::Duration};
 
 use rosc::{encoder, OscMessage, OscPacket, OscType};
-use sentient_protocol::{CoreFault, OscAckStatus, OscCue, SCHEMA_VERSION};
+use rumqttc::v5::mqttbytes::v5::{Packet, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, EventLoop, MqttOptions};
+use sentient_metrics::{Counter, CounterVec, Exposition};
+use sentient_protocol::{
+    trace_id_from_user_properties, CoreFault, FaultKind, OscAckStatus, OscCue, Severity,
+    MQTT_USER_PROPERTY_TRACE_ID, SCHEMA_VERSION,
+};
 use tokio::net::UdpSocket;
 use tracing::{info, warn};
 
+static CUES: CounterVec = CounterVec::new(
+    "sentient_osc_cues_total",
+    "Audio cues handled by result (SENT, FAILED, INVALID).",
+    &["result"],
+);
+static SEND_RETRIES: Counter = Counter::new(
+    "sentient_osc_send_retries_total",
+    "OSC sends retried after a UDP send error.",
+);
+static MQTT_RECONNECTS: Counter = Counter::new(
+    "sentient_osc_mqtt_reconnects_total",
+    "Broker reconnections after the first connect.",
+);
+
+async fn serve_metrics(bind: SocketAddr) {
+    let app = axum::Router::new().route(
+        "/metrics",
+        axum::routing::get(|| async {
+            let mut out = Exposition::new();
+            out.metric(&CUES)
+                .metric(&SEND_RETRIES)
+                .metric(&MQTT_RECONNECTS);
+            (
+                [(
+                    axum::http::header::CONTENT_TYPE,
+                    sentient_metrics::CONTENT_TYPE,
+                )],
+                out.finish(),
+            )
+        }),
+    );
+    let listener = match tokio::net::TcpListener::bind(bind).await {
+        Ok(l) => l,
+        Err(err) => {
+            warn!(error=%err, bind=%bind, "metrics endpoint disabled (bind failed)");
+            return;
+        }
+    };
+    info!(bind=%bind, "metrics listening");
+    if let Err(err) = axum::serve(listener, app).await {
+        warn!(error=%err, "metrics endpoint stopped");
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -40,6 +91,12 @@ async fn main() -> anyhow::Result<()> {
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(200);
+    // Prometheus scrape endpoint; empty disables it.
+    let metrics_bind: Option<SocketAddr> = match std::env::var("OSC_BRIDGE_METRICS_BIND") {
+        Ok(v) if v.trim().is_empty() => None,
+        Ok(v) => Some(v.trim().parse()?),
+        Err(_) => Some(SocketAddr::from(([0, 0, 0, 0], 9102))),
+    };
 
     info!(
         room_id = %room_id,
@@ -49,8 +106,12 @@ async fn main() -> anyhow::Result<()> {
         mqtt_port,
         osc_retries,
         osc_retry_base_ms,
+        ?metrics_bind,
         "osc-bridge starting"
     );
+    if let Some(bind) = metrics_bind {
+        tokio::spawn(serve_metrics(bind));
+    }
 
     let target: SocketAddr = format!("{}:{}", scs_host, scs_port).parse()?;
     let socket = UdpSocket::bind("0.0.0.0:0").await?;
@@ -66,11 +127,11 @@ async fn main() -> anyhow::Result<()> {
     let cue_topic = format!("room/{}/audio/cue", room_id);
     let ack_topic = format!("room/{}/audio/ack", room_id);
     let fault_topic = format!("room/{}/audio/fault", room_id);
-    mqtt.subscribe(cue_topic.clone(), rumqttc::QoS::AtLeastOnce)
-        .await?;
+    mqtt.subscribe(cue_topic.clone(), QoS::AtLeastOnce).await?;
 
     info!(topic = %cue_topic, "subscribed for audio cues");
 
+    let mut connected_once = false;
     loop {
         tokio::select! {
             _ = tokio::signal::ctrl_c() => {
@@ -79,8 +140,12 @@ async fn main() -> anyhow::Result<()> {
             }
             ev = mqtt_eventloop.poll() => {
                 match ev {
-                    Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(p))) => {
+                    Ok(Event::Incoming(Packet::Publish(p))) => {
                         if p.topic == cue_topic {
+                            let trace_id = p
+                                .properties
+                                .as_ref()
+                                .and_then(|props| trace_id_from_user_properties(&props.user_properties));
                             handle_cue_message(
                                 &mqtt,
                                 &ack_topic,
@@ -88,12 +153,19 @@ async fn main() -> anyhow::Result<()> {
                                 &socket,
                                 target,
                                 &p.payload,
+                                trace_id,
                                 osc_retries,
                                 osc_retry_base_ms,
                             )
                             .await;
                         }
                     }
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        if connected_once {
+                            MQTT_RECONNECTS.inc();
+                        }
+                        connected_once = true;
+                    }
                     Ok(_) => {}
                     Err(err) => {
                         warn!(error = %err, "mqtt eventloop error");
@@ -113,29 +185,33 @@ async fn connect_mqtt(
     port: u16,
     username: Option<String>,
     password: Option<String>,
-) -> anyhow::Result<(rumqttc::AsyncClient, rumqttc::EventLoop)> {
-    let mut options = rumqttc::MqttOptions::new(client_id, host, port);
+) -> anyhow::Result<(AsyncClient, EventLoop)> {
+    let mut options = MqttOptions::new(client_id, host, port);
     options.set_keep_alive(Duration::from_secs(5));
     if let (Some(user), Some(pass)) = (username.as_deref(), password.as_deref()) {
         options.set_credentials(user, pass);
     }
-    Ok(rumqttc::AsyncClient::new(options, 200))
+    Ok(AsyncClient::new(options, 200))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_cue_message(
-    mqtt: &rumqttc::AsyncClient,
+    mqtt: &AsyncClient,
     ack_topic: &str,
     fault_topic: &str,
     socket: &UdpSocket,
     target: SocketAddr,
     payload: &[u8],
+    // Echoed on the ack/fault so the sender can follow the cue.
+    trace_id: Option<&str>,
     retries: u32,
     retry_base_ms: u64,
 ) {
-    let cue: OscCue = match serde_json::from_slice(payload) {
+    let cue: OscCue = match sentient_protocol::decode_message(payload) {
         Ok(v) => v,
         Err(err) => {
             warn!(error = %err, "invalid OSC cue payload (json)");
+            CUES.inc(&["INVALID"]);
             return;
         }
     };
@@ -144,6 +220,7 @@ async fn handle_cue_message(
         room_id = %cue.room_id,
         cue_id = %cue.cue_id,
         correlation_id = %cue.correlation_id,
+        trace_id,
         address = %cue.address,
         args = cue.args.len(),
         "sending OSC cue"
@@ -169,6 +246,7 @@ async fn handle_cue_message(
         Ok(v) => v,
         Err(err) => {
             warn!(error = %err, "failed to encode OSC packet");
+            CUES.inc(&["INVALID"]);
             return;
         }
     };
@@ -179,6 +257,7 @@ async fn handle_cue_message(
         attempt = attempt.saturating_add(1);
         match socket.send_to(&buf, target).await {
             Ok(_) => {
+                CUES.inc(&["SENT"]);
                 let _ = publish_ack(
                     mqtt,
                     ack_topic,
@@ -188,6 +267,7 @@ async fn handle_cue_message(
                     OscAckStatus::Sent,
                     None,
                     attempt,
+                    trace_id,
                 )
                 .await;
                 break;
@@ -196,6 +276,7 @@ async fn handle_cue_message(
                 last_err = Some(err.to_string());
                 warn!(error=%err, attempt, "failed to send OSC packet");
                 if attempt > retries {
+                    CUES.inc(&["FAILED"]);
                     let _ = publish_ack(
                         mqtt,
                         ack_topic,
@@ -205,6 +286,7 @@ async fn handle_cue_message(
                         OscAckStatus::Failed,
                         last_err.clone(),
                         attempt,
+                        trace_id,
                     )
                     .await;
                     let _ = publish_fault(
@@ -215,11 +297,13 @@ async fn handle_cue_message(
                         cue.correlation_id,
                         last_err.unwrap_or_else(|| "send failed".to_string()),
                         attempt,
+                        trace_id,
                     )
                     .await;
                     break;
                 }
 
+                SEND_RETRIES.inc();
                 let delay = retry_base_ms.saturating_mul(1_u64 << (attempt.saturating_sub(1)));
                 tokio::time::sleep(Duration::from_millis(delay.min(5000))).await;
             }
@@ -227,8 +311,9 @@ async fn handle_cue_message(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn publish_ack(
-    mqtt: &rumqttc::AsyncClient,
+    mqtt: &AsyncClient,
     topic: &str,
     room_id: &str,
     cue_id: &str,
@@ -236,6 +321,7 @@ async fn publish_ack(
     status: OscAckStatus,
     error: Option<String>,
     attempts: u32,
+    trace_id: Option<&str>,
 ) -> anyhow::Result<()> {
     let payload = serde_json::json!({
         "schema": SCHEMA_VERSION,
@@ -247,30 +333,33 @@ async fn publish_ack(
         "error": error,
         "observed_at_unix_ms": unix_ms_now(),
     });
-    mqtt.publish(
+    mqtt.publish_with_properties(
         topic,
-        rumqttc::QoS::AtLeastOnce,
+        QoS::AtLeastOnce,
         false,
         serde_json::to_vec(&payload)?,
+        trace_properties(trace_id),
     )
     .await?;
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn publish_fault(
-    mqtt: &rumqttc::AsyncClient,
+    mqtt: &AsyncClient,
     topic: &str,
     room_id: &str,
     cue_id: &str,
     correlation_id: uuid::Uuid,
     error: String,
     attempts: u32,
+    trace_id: Option<&str>,
 ) -> anyhow::Result<()> {
     let fault = CoreFault {
         schema: SCHEMA_VERSION.to_string(),
         room_id: room_id.to_string(),
-        kind: "OSC_SEND_FAILED".to_string(),
-        severity: "CRITICAL".to_string(),
+        kind: FaultKind::OscSendFailed,
+        severity: Severity::Critical,
         message: "osc-bridge failed to deliver OSC cue to SCS".to_string(),
         observed_at_unix_ms: unix_ms_now(),
         details: serde_json::json!({
@@ -280,16 +369,27 @@ async fn publish_fault(
             "error": error,
         }),
     };
-    mqtt.publish(
+    mqtt.publish_with_properties(
         topic,
-        rumqttc::QoS::AtLeastOnce,
+        QoS::AtLeastOnce,
         true,
         serde_json::to_vec(&fault)?,
+        trace_properties(trace_id),
     )
     .await?;
     Ok(())
 }
 
+/// MQTT v5 properties echoing the cue's trace id (empty when the cue had none).
+fn trace_properties(trace_id: Option<&str>) -> PublishProperties {
+    PublishProperties {
+        user_properties: trace_id
+            .map(|t| vec![(MQTT_USER_PROPERTY_TRACE_ID.to_string(), t.to_string())])
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
 fn unix_ms_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -8,12 +8,21 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use rumqttc::v5::mqttbytes::v5::{Packet, Publish, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, MqttOptions};
+use sentient_metrics::{Counter, CounterVec, Exposition, Gauge};
 use sentient_protocol::{
-    CoreControlRequest, CoreDispatchRequest, CoreFault, CoreStatus, OscCue,
-    CORE_CONTROL_OP_RELOAD_GRAPH, SCHEMA_VERSION,
+    decode_message_as, dispatch_reply_prefix, is_accepted_schema, is_valid_trace_id,
+    trace_id_from_user_properties, AckStatus, BatchCommand, CommandAck, CoreBatchDispatchRequest,
+    CoreBatchDispatchResult, CoreControlRequest, CoreDispatchRequest, CoreDispatchResult,
+    CoreFault, CoreStatus, DispatchOperator, DispatchResultStatus, FaultKind, MaintenanceScope,
+    OscCue, WireCodec, CORE_CONTROL_OP_RELEASE_MAINTENANCE, CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY,
+    CORE_CONTROL_OP_RELOAD_GRAPH, CORE_CONTROL_OP_SET_MAINTENANCE, MQTT_USER_PROPERTY_TRACE_ID,
+    SCHEMA_VERSION,
 };
-use tokio::sync::Mutex;
 use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{oneshot, Mutex};
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -79,21 +88,150 @@ impl Config {
 #[derive(Debug, Default, Clone)]
 struct Cache {
     core_status: Option<CoreStatus>,
+    core_metrics: Option<serde_json::Value>,
     core_fault: Option<CoreFault>,
     audio_fault: Option<CoreFault>,
     last_audio_ack: Option<serde_json::Value>,
     device_status: HashMap<String, serde_json::Value>,
     device_fault: HashMap<String, CoreFault>,
+    device_queue: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Clone)]
 struct AppState {
     config: Arc<Config>,
-    mqtt: rumqttc::AsyncClient,
+    mqtt: AsyncClient,
     cache: Arc<RwLock<Cache>>,
     stream: broadcast::Sender<serde_json::Value>,
     db: Option<Arc<tokio_postgres::Client>>,
     safety_reset_tokens: Arc<Mutex<HashMap<Uuid, PendingSafetyReset>>>,
+    /// This instance's core dispatch reply topic (unique per process).
+    dispatch_reply_topic: Arc<str>,
+    /// correlation_id -> HTTP requests waiting for core's dispatch result.
+    dispatch_waiters: DispatchWaiters,
+}
+
+type DispatchWaiters = Arc<Mutex<HashMap<Uuid, Vec<oneshot::Sender<DispatchReply>>>>>;
+
+/// An incoming MQTT v5 publish with its topic decoded (the wire carries it as bytes).
+#[derive(Debug)]
+struct InboundPublish {
+    topic: String,
+    payload: bytes::Bytes,
+    properties: Option<PublishProperties>,
+}
+
+impl InboundPublish {
+    /// `None` for a topic that is not UTF-8 (a protocol violation; the broker should not send it).
+    fn from_packet(p: Publish) -> Option<Self> {
+        Some(Self {
+            topic: String::from_utf8(p.topic.to_vec()).ok()?,
+            payload: p.payload,
+            properties: p.properties,
+        })
+    }
+
+    /// The publisher's trace id user property, if any.
+    fn trace_id(&self) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .and_then(|p| trace_id_from_user_properties(&p.user_properties))
+    }
+
+    /// MQTT v5 correlation data, if the publisher set any.
+    fn correlation_data(&self) -> Option<&[u8]> {
+        self.properties
+            .as_ref()
+            .and_then(|p| p.correlation_data.as_deref())
+    }
+}
+
+/// Anything core publishes on a dispatch reply topic.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(untagged)]
+enum DispatchReply {
+    Single(CoreDispatchResult),
+    Batch(CoreBatchDispatchResult),
+}
+
+impl DispatchReply {
+    fn decode(payload: &[u8]) -> Result<Self, sentient_protocol::SchemaError> {
+        match sentient_protocol::decode_message::<CoreDispatchResult>(payload) {
+            Ok(result) => Ok(Self::Single(result)),
+            Err(_) => sentient_protocol::decode_message::<CoreBatchDispatchResult>(payload)
+                .map(Self::Batch),
+        }
+    }
+
+    fn correlation_id(&self) -> Uuid {
+        match self {
+            Self::Single(r) => r.correlation_id,
+            Self::Batch(r) => r.correlation_id,
+        }
+    }
+
+    fn status(&self) -> DispatchResultStatus {
+        match self {
+            Self::Single(r) => r.status,
+            Self::Batch(r) => r.status,
+        }
+    }
+}
+
+/// Default / maximum time `POST .../dispatch` waits for core's verdict.
+const DISPATCH_WAIT_DEFAULT_MS: u64 = 2_000;
+const DISPATCH_WAIT_MAX_MS: u64 = 10_000;
+
+static DISPATCH_REQUESTS: CounterVec = CounterVec::new(
+    "sentient_api_dispatch_requests_total",
+    "POST dispatch / dispatch/batch by kind and result (core status, PENDING or PUBLISH_FAILED).",
+    &["kind", "result"],
+);
+static CONTROL_REQUESTS: CounterVec = CounterVec::new(
+    "sentient_api_control_requests_total",
+    "Control ops forwarded to core (unknown ops counted as OTHER).",
+    &["op"],
+);
+static UNAUTHORIZED: Counter = Counter::new(
+    "sentient_api_unauthorized_total",
+    "Requests refused for a missing/invalid token or insufficient role.",
+);
+static WS_CLIENTS: Gauge = Gauge::new(
+    "sentient_api_ws_clients",
+    "Open WebSocket stream connections.",
+);
+static MQTT_RECONNECTS: Counter = Counter::new(
+    "sentient_api_mqtt_reconnects_total",
+    "Broker reconnections after the first connect.",
+);
+
+const KNOWN_CONTROL_OPS: &[&str] = &[
+    sentient_protocol::CORE_CONTROL_OP_PAUSE_DISPATCH,
+    sentient_protocol::CORE_CONTROL_OP_RESUME_DISPATCH,
+    sentient_protocol::CORE_CONTROL_OP_RESET_SAFETY_LATCH,
+    sentient_protocol::CORE_CONTROL_OP_START_GRAPH,
+    sentient_protocol::CORE_CONTROL_OP_STOP_GRAPH,
+    sentient_protocol::CORE_CONTROL_OP_RELOAD_GRAPH,
+    sentient_protocol::CORE_CONTROL_OP_CANCEL_COMMAND,
+    sentient_protocol::CORE_CONTROL_OP_CANCEL_DEVICE_COMMANDS,
+    CORE_CONTROL_OP_SET_MAINTENANCE,
+    CORE_CONTROL_OP_RELEASE_MAINTENANCE,
+];
+
+async fn get_metrics() -> impl IntoResponse {
+    let mut out = Exposition::new();
+    out.metric(&DISPATCH_REQUESTS)
+        .metric(&CONTROL_REQUESTS)
+        .metric(&UNAUTHORIZED)
+        .metric(&WS_CLIENTS)
+        .metric(&MQTT_RECONNECTS);
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            sentient_metrics::CONTENT_TYPE,
+        )],
+        out.finish(),
+    )
 }
 
 fn unix_ms_now() -> u64 {
@@ -124,8 +262,6 @@ fn auth_ok(headers: &HeaderMap, token: &Option<String>) -> bool {
 struct Claims {
     sub: String,
     role: String,
-    exp: usize,
-    iat: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -136,16 +272,15 @@ struct Actor {
 
 #[derive(Debug, Clone)]
 struct PendingSafetyReset {
-    created_at_unix_ms: u64,
     expires_at_unix_ms: u64,
     actor: Actor,
     reason: Option<String>,
+    /// Safety zone to reset; `None` resets the room and every zone.
+    zone: Option<String>,
 }
 
 fn jwt_claims(headers: &HeaderMap, jwt_secret: &Option<Vec<u8>>) -> Option<Claims> {
-    let Some(secret) = jwt_secret.as_deref() else {
-        return None;
-    };
+    let secret = jwt_secret.as_deref()?;
     let h = headers
         .get(axum::http::header::AUTHORIZATION)?
         .to_str()
@@ -163,7 +298,11 @@ fn authorized(headers: &HeaderMap, cfg: &Config) -> bool {
     if auth_ok(headers, &cfg.api_token) {
         return true;
     }
-    jwt_claims(headers, &cfg.jwt_secret).is_some()
+    let ok = jwt_claims(headers, &cfg.jwt_secret).is_some();
+    if !ok {
+        UNAUTHORIZED.inc();
+    }
+    ok
 }
 
 fn require_role(headers: &HeaderMap, cfg: &Config, allowed: &[&str]) -> bool {
@@ -171,25 +310,29 @@ fn require_role(headers: &HeaderMap, cfg: &Config, allowed: &[&str]) -> bool {
     if auth_ok(headers, &cfg.api_token) {
         return true;
     }
-    let Some(claims) = jwt_claims(headers, &cfg.jwt_secret) else {
-        return false;
-    };
-    allowed.iter().any(|r| *r == claims.role)
+    let ok =
+        jwt_claims(headers, &cfg.jwt_secret).is_some_and(|c| allowed.contains(&c.role.as_str()));
+    if !ok {
+        UNAUTHORIZED.inc();
+    }
+    ok
 }
 
 fn actor_from_headers(headers: &HeaderMap, cfg: &Config) -> Actor {
-    if auth_ok(headers, &cfg.api_token) {
-        return Actor {
-            sub: "API_TOKEN".to_string(),
-            role: "ADMIN".to_string(),
-        };
-    }
+    // JWT first: with no API_TOKEN configured `auth_ok` accepts anyone, which would otherwise hide
+    // who the operator is.
     if let Some(c) = jwt_claims(headers, &cfg.jwt_secret) {
         return Actor {
             sub: c.sub,
             role: c.role,
         };
     }
+    if auth_ok(headers, &cfg.api_token) {
+        return Actor {
+            sub: "API_TOKEN".to_string(),
+            role: "ADMIN".to_string(),
+        };
+    }
     Actor {
         sub: "UNKNOWN".to_string(),
         role: "UNKNOWN".to_string(),
@@ -225,6 +368,15 @@ async fn main() -> anyhow::Result<()> {
     };
 
     subscribe_defaults(&mqtt, &config.room_id).await?;
+    let dispatch_reply_topic: Arc<str> = format!(
+        "{}{}",
+        dispatch_reply_prefix(&config.room_id),
+        Uuid::new_v4()
+    )
+    .into();
+    mqtt.subscribe(dispatch_reply_topic.as_ref(), QoS::AtLeastOnce)
+        .await?;
+    let dispatch_waiters: DispatchWaiters = Arc::new(Mutex::new(HashMap::new()));
 
     let state = AppState {
         config: config.clone(),
@@ -233,11 +385,42 @@ async fn main() -> anyhow::Result<()> {
         stream: stream_tx.clone(),
         db,
         safety_reset_tokens: Arc::new(Mutex::new(HashMap::new())),
+        dispatch_reply_topic: dispatch_reply_topic.clone(),
+        dispatch_waiters: dispatch_waiters.clone(),
     };
 
     let cache_room_id = config.room_id.clone();
     tokio::spawn(async move {
         while let Some(p) = events.recv().await {
+            // Dispatch results go to whoever is waiting on them; they are not streamed.
+            if p.topic == *dispatch_reply_topic {
+                match DispatchReply::decode(&p.payload) {
+                    Ok(result) => {
+                        // Core echoes our correlation data; the payload id covers 3.1.1 paths.
+                        let correlation_id = p
+                            .correlation_data()
+                            .and_then(|d| std::str::from_utf8(d).ok())
+                            .and_then(|d| Uuid::parse_str(d).ok())
+                            .unwrap_or_else(|| result.correlation_id());
+                        let waiters = dispatch_waiters.lock().await.remove(&correlation_id);
+                        for tx in waiters.into_iter().flatten() {
+                            let _ = tx.send(result.clone());
+                        }
+                    }
+                    Err(err) => warn!(error=%err, "invalid dispatch result"),
+                }
+                continue;
+            }
+            // Device acks are only subscribed for progress; the rest of that traffic stays off
+            // the stream.
+            if let Some(codec) = device_ack_codec(&cache_room_id, &p.topic) {
+                if let Ok(ack) = decode_message_as::<CommandAck>(codec, &p.payload) {
+                    if ack.status == AckStatus::InProgress {
+                        let _ = stream_tx.send(command_progress_event(&ack));
+                    }
+                }
+                continue;
+            }
             let ev = mqtt_publish_event(&p);
             let _ = stream_tx.send(ev);
             apply_cache_update(&cache, &cache_room_id, p).await;
@@ -246,9 +429,11 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(|| async { "ok" }))
+        .route("/metrics", get(get_metrics))
         .route("/v8/room/{room_id}/ws", get(ws_stream))
         .route("/v8/room/{room_id}/core/status", get(get_core_status))
         .route("/v8/room/{room_id}/core/fault", get(get_core_fault))
+        .route("/v8/room/{room_id}/core/metrics", get(get_core_metrics))
         .route("/v8/room/{room_id}/audio/fault", get(get_audio_fault))
         .route("/v8/room/{room_id}/audio/ack", get(get_audio_ack))
         .route(
@@ -269,6 +454,14 @@ async fn main() -> anyhow::Result<()> {
             post(post_graph_activate),
         )
         .route("/v8/room/{room_id}/devices", get(list_devices))
+        .route(
+            "/v8/room/{room_id}/devices/unregistered",
+            get(get_unregistered_devices),
+        )
+        .route(
+            "/v8/room/{room_id}/devices/unregistered/{device_id}/adopt",
+            post(post_adopt_device),
+        )
         .route(
             "/v8/room/{room_id}/devices/{device_id}/status",
             get(get_device_status),
@@ -277,8 +470,28 @@ async fn main() -> anyhow::Result<()> {
             "/v8/room/{room_id}/devices/{device_id}/fault",
             get(get_device_fault),
         )
+        .route(
+            "/v8/room/{room_id}/devices/{device_id}/queue",
+            get(get_device_queue),
+        )
+        .route(
+            "/v8/room/{room_id}/devices/{device_id}/maintenance",
+            post(post_device_maintenance).delete(delete_device_maintenance),
+        )
+        .route(
+            "/v8/room/{room_id}/zones/{zone}/maintenance",
+            post(post_zone_maintenance).delete(delete_zone_maintenance),
+        )
         .route("/v8/room/{room_id}/events", get(get_events))
+        .route(
+            "/v8/room/{room_id}/incidents/report",
+            get(get_incident_report),
+        )
         .route("/v8/room/{room_id}/dispatch", post(post_dispatch))
+        .route(
+            "/v8/room/{room_id}/dispatch/batch",
+            post(post_dispatch_batch),
+        )
         .route("/v8/room/{room_id}/control", post(post_control))
         .route("/v8/room/{room_id}/audio/cue", post(post_audio_cue))
         .with_state(state);
@@ -392,7 +605,7 @@ async fn post_graph(
     let schema_ok = graph
         .get("schema")
         .and_then(|v| v.as_str())
-        .is_some_and(|s| s == SCHEMA_VERSION);
+        .is_some_and(is_accepted_schema);
     let room_ok = graph
         .get("room_id")
         .and_then(|v| v.as_str())
@@ -529,71 +742,82 @@ async fn post_graph_activate(
         let topic = format!("room/{}/core/control", state.config.room_id);
         let _ = state
             .mqtt
-            .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+            .publish(topic, QoS::AtLeastOnce, false, payload)
             .await;
     }
     StatusCode::OK.into_response()
 }
 
-async fn subscribe_defaults(client: &rumqttc::AsyncClient, room_id: &str) -> anyhow::Result<()> {
+async fn subscribe_defaults(client: &AsyncClient, room_id: &str) -> anyhow::Result<()> {
     client
-        .subscribe(
-            format!("room/{}/core/status", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
+        .subscribe(format!("room/{}/core/status", room_id), QoS::AtLeastOnce)
         .await?;
     client
-        .subscribe(
-            format!("room/{}/core/fault", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
+        .subscribe(format!("room/{}/core/fault", room_id), QoS::AtLeastOnce)
         .await?;
     client
-        .subscribe(
-            format!("room/{}/audio/ack", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
+        .subscribe(format!("room/{}/audio/ack", room_id), QoS::AtLeastOnce)
         .await?;
     client
-        .subscribe(
-            format!("room/{}/audio/fault", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
+        .subscribe(format!("room/{}/core/metrics", room_id), QoS::AtLeastOnce)
+        .await?;
+    client
+        .subscribe(format!("room/{}/audio/fault", room_id), QoS::AtLeastOnce)
         .await?;
     client
         .subscribe(
             format!("room/{}/core/device/+/status", room_id),
-            rumqttc::QoS::AtLeastOnce,
+            QoS::AtLeastOnce,
         )
         .await?;
     client
         .subscribe(
             format!("room/{}/core/device/+/fault", room_id),
-            rumqttc::QoS::AtLeastOnce,
+            QoS::AtLeastOnce,
+        )
+        .await?;
+    client
+        .subscribe(
+            format!("room/{}/core/device/+/queue", room_id),
+            QoS::AtLeastOnce,
         )
         .await?;
+    for codec in WireCodec::ALL {
+        client
+            .subscribe(
+                codec.topic(&format!("room/{}/device/+/ack", room_id)),
+                QoS::AtLeastOnce,
+            )
+            .await?;
+    }
     Ok(())
 }
 
-async fn apply_cache_update(cache: &Arc<RwLock<Cache>>, room_id: &str, p: rumqttc::Publish) {
+async fn apply_cache_update(cache: &Arc<RwLock<Cache>>, room_id: &str, p: InboundPublish) {
     let mut c = cache.write().await;
     let topic = p.topic.clone();
     let bytes = p.payload.as_ref();
 
     if topic == format!("room/{}/core/status", room_id) {
-        if let Ok(v) = serde_json::from_slice::<CoreStatus>(bytes) {
+        if let Ok(v) = sentient_protocol::decode_message::<CoreStatus>(bytes) {
             c.core_status = Some(v);
         }
         return;
     }
+    if topic == format!("room/{}/core/metrics", room_id) {
+        if let Ok(v) = serde_json::from_slice::<serde_json::Value>(bytes) {
+            c.core_metrics = Some(v);
+        }
+        return;
+    }
     if topic == format!("room/{}/core/fault", room_id) {
-        if let Ok(v) = serde_json::from_slice::<CoreFault>(bytes) {
+        if let Ok(v) = sentient_protocol::decode_message::<CoreFault>(bytes) {
             c.core_fault = Some(v);
         }
         return;
     }
     if topic == format!("room/{}/audio/fault", room_id) {
-        if let Ok(v) = serde_json::from_slice::<CoreFault>(bytes) {
+        if let Ok(v) = sentient_protocol::decode_message::<CoreFault>(bytes) {
             c.audio_fault = Some(v);
         }
         return;
@@ -605,7 +829,7 @@ async fn apply_cache_update(cache: &Arc<RwLock<Cache>>, room_id: &str, p: rumqtt
         return;
     }
 
-    // room/{room}/core/device/{device}/status|fault
+    // room/{room}/core/device/{device}/status|fault|queue
     let prefix = format!("room/{}/core/device/", room_id);
     if let Some(rest) = topic.strip_prefix(&prefix) {
         let mut parts = rest.split('/');
@@ -619,25 +843,53 @@ async fn apply_cache_update(cache: &Arc<RwLock<Cache>>, room_id: &str, p: rumqtt
                 c.device_status.insert(device_id, v);
             }
         } else if kind == "fault" {
-            if let Ok(v) = serde_json::from_slice::<CoreFault>(bytes) {
+            if let Ok(v) = sentient_protocol::decode_message::<CoreFault>(bytes) {
                 c.device_fault.insert(device_id, v);
             }
+        } else if kind == "queue" {
+            if let Ok(v) = serde_json::from_slice::<serde_json::Value>(bytes) {
+                c.device_queue.insert(device_id, v);
+            }
         }
     }
 }
 
-fn mqtt_publish_event(p: &rumqttc::Publish) -> serde_json::Value {
+/// Codec of a `room/{room}/device/{device}/ack[/codec]` topic, `None` for any other topic.
+fn device_ack_codec(room_id: &str, topic: &str) -> Option<WireCodec> {
+    let (base, codec) = WireCodec::split_topic(topic);
+    let rest = base.strip_prefix(&format!("room/{}/device/", room_id))?;
+    let (device_id, kind) = rest.split_once('/')?;
+    (!device_id.is_empty() && kind == "ack").then_some(codec)
+}
+
+fn command_progress_event(ack: &CommandAck) -> serde_json::Value {
+    serde_json::json!({
+        "type": "COMMAND_PROGRESS",
+        "received_at_unix_ms": unix_ms_now(),
+        "device_id": ack.device_id,
+        "command_id": ack.command_id,
+        "correlation_id": ack.correlation_id,
+        "progress": ack.progress,
+        "observed_at_unix_ms": ack.observed_at_unix_ms,
+    })
+}
+
+fn mqtt_publish_event(p: &InboundPublish) -> serde_json::Value {
     let mut payload: serde_json::Value =
         serde_json::json!({ "raw": String::from_utf8_lossy(p.payload.as_ref()).to_string() });
     if let Ok(v) = serde_json::from_slice::<serde_json::Value>(p.payload.as_ref()) {
         payload = v;
     }
-    serde_json::json!({
+    let mut ev = serde_json::json!({
         "type": "MQTT_PUBLISH",
         "topic": p.topic,
         "received_at_unix_ms": unix_ms_now(),
         "payload": payload,
-    })
+    });
+    if let Some(trace_id) = p.trace_id() {
+        ev["trace_id"] = serde_json::json!(trace_id);
+    }
+    ev
 }
 
 async fn ws_stream(
@@ -667,6 +919,7 @@ async fn ws_stream_inner(state: AppState, mut socket: WebSocket) {
             "core_fault": c.core_fault,
             "device_status": c.device_status,
             "device_fault": c.device_fault,
+            "device_queue": c.device_queue,
         })
     };
     let _ = socket
@@ -675,6 +928,7 @@ async fn ws_stream_inner(state: AppState, mut socket: WebSocket) {
 
     let mut rx = state.stream.subscribe();
 
+    WS_CLIENTS.inc();
     loop {
         tokio::select! {
             msg = socket.recv() => {
@@ -699,6 +953,7 @@ async fn ws_stream_inner(state: AppState, mut socket: WebSocket) {
             }
         }
     }
+    WS_CLIENTS.dec();
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -757,6 +1012,699 @@ async fn get_events(
     (StatusCode::OK, Json(out)).into_response()
 }
 
+/// Longest window one incident report may cover.
+const INCIDENT_REPORT_MAX_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+/// Events read per report; beyond this the report is marked `truncated`.
+const INCIDENT_REPORT_MAX_EVENTS: i64 = 20_000;
+/// How far a `SAFETY_LATCH_SNAPSHOT` may be from its `SAFETY_LATCHED` (device vs core clock).
+const INCIDENT_SNAPSHOT_MATCH_MS: i64 = 5_000;
+
+/// Fault kinds an incident report reads; the first four drive its sections, the rest only show in
+/// the timeline.
+const INCIDENT_FAULT_KINDS: &[FaultKind] = &[
+    FaultKind::DeviceSafetyState,
+    FaultKind::SafetyLatched,
+    FaultKind::SafetyLatchReset,
+    FaultKind::SafetyResetDenied,
+    FaultKind::DeviceOffline,
+    FaultKind::DeviceOnline,
+    FaultKind::DeviceFlapping,
+    FaultKind::DeviceUnregistered,
+    FaultKind::DeviceRegistryReloaded,
+    FaultKind::DispatchPaused,
+    FaultKind::DispatchResumed,
+    FaultKind::CommandCancelled,
+    FaultKind::CommandRejected,
+    FaultKind::MaintenanceSet,
+    FaultKind::MaintenanceReleased,
+    FaultKind::GraphStarted,
+    FaultKind::GraphStopped,
+    FaultKind::GraphReloaded,
+];
+
+#[derive(Debug, serde::Deserialize)]
+struct IncidentReportQuery {
+    from_unix_ms: u64,
+    to_unix_ms: u64,
+    /// `json` (default), `markdown` or `html`.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentReport {
+    room_id: String,
+    from_unix_ms: u64,
+    to_unix_ms: u64,
+    generated_at_unix_ms: u64,
+    generated_by: String,
+    /// More than `INCIDENT_REPORT_MAX_EVENTS` matched; narrow the window.
+    truncated: bool,
+    safety_transitions: Vec<IncidentSafetyTransition>,
+    latches: Vec<IncidentLatch>,
+    resets: Vec<IncidentReset>,
+    operator_actions: Vec<IncidentOperatorAction>,
+    timeline: Vec<IncidentTimelineEntry>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentSafetyTransition {
+    observed_at_unix_ms: i64,
+    device_id: Option<String>,
+    safety_state: Option<String>,
+    previous_safety_state: Option<String>,
+    reason_code: Option<String>,
+    latched: bool,
+    /// Message that carried it (`HEARTBEAT` / `STATE` / `ACK`).
+    source: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentLatch {
+    observed_at_unix_ms: i64,
+    device_id: Option<String>,
+    safety_kind: Option<String>,
+    reason_code: Option<String>,
+    /// `None`: the room latched.
+    zone: Option<String>,
+    escalated_from_zone: Option<String>,
+    /// From the matching `SAFETY_LATCH_SNAPSHOT`; absent if core recorded none.
+    snapshot_at_unix_ms: Option<i64>,
+    in_flight_commands: Vec<serde_json::Value>,
+    graph_version: Option<i64>,
+    graph_active_nodes: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentReset {
+    observed_at_unix_ms: i64,
+    /// `RESET` or `DENIED`.
+    outcome: &'static str,
+    zone: Option<String>,
+    requested_by: Option<String>,
+    confirmed_by: Option<String>,
+    reason: Option<String>,
+    blockers: Vec<String>,
+    checklist: serde_json::Value,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentOperatorAction {
+    observed_at_unix_ms: i64,
+    /// Event kind (`CORE_CONTROL`, `API_SAFETY_RESET_REQUEST`, ...).
+    kind: String,
+    /// Control op, or the event kind for API audit events.
+    action: String,
+    actor: Option<String>,
+    actor_role: Option<String>,
+    details: serde_json::Value,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct IncidentTimelineEntry {
+    observed_at_unix_ms: i64,
+    kind: String,
+    device_id: Option<String>,
+    summary: String,
+}
+
+/// Incident reconstruction for a time window, assembled from the events table: device safety
+/// transitions, latches (with what was in flight), resets and operator actions.
+async fn get_incident_report(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    axum::extract::Query(q): axum::extract::Query<IncidentReportQuery>,
+) -> axum::response::Response {
+    if !require_role(&headers, &state.config, &["ADMIN", "TECH"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let format = q.format.as_deref().unwrap_or("json");
+    if !matches!(format, "json" | "markdown" | "html")
+        || q.to_unix_ms < q.from_unix_ms
+        || q.to_unix_ms - q.from_unix_ms > INCIDENT_REPORT_MAX_WINDOW_MS
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let Some(db) = state.db.as_ref() else {
+        return StatusCode::NOT_IMPLEMENTED.into_response();
+    };
+
+    let fault_kinds: Vec<&str> = INCIDENT_FAULT_KINDS.iter().map(|k| k.as_str()).collect();
+    let rows = match db
+        .query(
+            "SELECT kind, device_id, (extract(epoch from observed_at) * 1000)::bigint AS observed_at_unix_ms, payload \
+             FROM events WHERE room_id = $1 \
+             AND observed_at >= to_timestamp($2::bigint / 1000.0) AND observed_at <= to_timestamp($3::bigint / 1000.0) \
+             AND (kind IN ('CORE_CONTROL', 'SAFETY_LATCH_SNAPSHOT') OR kind LIKE 'API\\_%' \
+                  OR (kind IN ('CORE_FAULT', 'DEVICE_FAULT') AND payload->>'kind' = ANY($4))) \
+             ORDER BY observed_at ASC, id ASC LIMIT $5",
+            &[
+                &room_id,
+                &(q.from_unix_ms as i64),
+                &(q.to_unix_ms as i64),
+                &fault_kinds,
+                &(INCIDENT_REPORT_MAX_EVENTS + 1),
+            ],
+        )
+        .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(error=%err, "failed to query incident events");
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+
+    let truncated = rows.len() as i64 > INCIDENT_REPORT_MAX_EVENTS;
+    let events: Vec<(String, Option<String>, i64, serde_json::Value)> = rows
+        .into_iter()
+        .take(INCIDENT_REPORT_MAX_EVENTS as usize)
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect();
+    let report = build_incident_report(
+        &room_id,
+        q.from_unix_ms,
+        q.to_unix_ms,
+        actor_from_headers(&headers, &state.config).sub,
+        truncated,
+        events,
+    );
+
+    let file_stem = format!(
+        "incident-{}-{}-{}",
+        room_id, report.from_unix_ms, report.to_unix_ms
+    );
+    let (content_type, body, ext) = match format {
+        "markdown" => (
+            "text/markdown; charset=utf-8",
+            render_incident_markdown(&report),
+            "md",
+        ),
+        "html" => (
+            "text/html; charset=utf-8",
+            render_incident_html(&report),
+            "html",
+        ),
+        _ => return (StatusCode::OK, Json(report)).into_response(),
+    };
+    (
+        StatusCode::OK,
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{file_stem}.{ext}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn json_str(v: &serde_json::Value, key: &str) -> Option<String> {
+    v.get(key).and_then(|x| x.as_str()).map(str::to_string)
+}
+
+fn build_incident_report(
+    room_id: &str,
+    from_unix_ms: u64,
+    to_unix_ms: u64,
+    generated_by: String,
+    truncated: bool,
+    events: Vec<(String, Option<String>, i64, serde_json::Value)>,
+) -> IncidentReport {
+    let mut report = IncidentReport {
+        room_id: room_id.to_string(),
+        from_unix_ms,
+        to_unix_ms,
+        generated_at_unix_ms: unix_ms_now(),
+        generated_by,
+        truncated,
+        safety_transitions: Vec::new(),
+        latches: Vec::new(),
+        resets: Vec::new(),
+        operator_actions: Vec::new(),
+        timeline: Vec::new(),
+    };
+    let mut snapshots: Vec<(i64, serde_json::Value)> = Vec::new();
+
+    for (kind, device_id, at, mut payload) in events {
+        // Control tokens never belong in a report (older rows may still carry one).
+        if let Some(params) = payload
+            .get_mut("parameters")
+            .and_then(|p| p.as_object_mut())
+        {
+            params.remove("token");
+        }
+        let summary = match kind.as_str() {
+            "CORE_FAULT" | "DEVICE_FAULT" => {
+                let fault_kind = json_str(&payload, "kind").unwrap_or_default();
+                let details = payload.get("details").cloned().unwrap_or_default();
+                match FaultKind::parse(&fault_kind) {
+                    FaultKind::DeviceSafetyState => {
+                        report.safety_transitions.push(IncidentSafetyTransition {
+                            observed_at_unix_ms: at,
+                            device_id: json_str(&details, "device_id").or(device_id.clone()),
+                            safety_state: json_str(&details, "safety_state"),
+                            previous_safety_state: json_str(&details, "previous_safety_state"),
+                            reason_code: json_str(&details, "reason_code"),
+                            latched: details
+                                .get("latched")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false),
+                            source: json_str(&details, "source"),
+                        });
+                    }
+                    FaultKind::SafetyLatched => {
+                        report.latches.push(IncidentLatch {
+                            observed_at_unix_ms: at,
+                            device_id: json_str(&details, "device_id"),
+                            safety_kind: json_str(&details, "safety_kind"),
+                            reason_code: json_str(&details, "reason_code"),
+                            zone: json_str(&details, "zone"),
+                            escalated_from_zone: json_str(&details, "escalated_from_zone"),
+                            snapshot_at_unix_ms: None,
+                            in_flight_commands: Vec::new(),
+                            graph_version: None,
+                            graph_active_nodes: Vec::new(),
+                        });
+                    }
+                    FaultKind::SafetyLatchReset | FaultKind::SafetyResetDenied => {
+                        let operators = details.get("operators").cloned().unwrap_or_default();
+                        report.resets.push(IncidentReset {
+                            observed_at_unix_ms: at,
+                            outcome: if FaultKind::parse(&fault_kind) == FaultKind::SafetyLatchReset
+                            {
+                                "RESET"
+                            } else {
+                                "DENIED"
+                            },
+                            zone: json_str(&details, "zone"),
+                            requested_by: json_str(&operators, "requested_by"),
+                            confirmed_by: json_str(&operators, "confirmed_by"),
+                            reason: json_str(&operators, "reason"),
+                            blockers: details
+                                .get("blockers")
+                                .and_then(|b| b.as_array())
+                                .map(|b| {
+                                    b.iter()
+                                        .filter_map(|x| x.as_str().map(str::to_string))
+                                        .collect()
+                                })
+                                .unwrap_or_default(),
+                            checklist: details.get("checklist").cloned().unwrap_or_default(),
+                        });
+                    }
+                    _ => {}
+                }
+                json_str(&payload, "message").unwrap_or_default()
+            }
+            "SAFETY_LATCH_SNAPSHOT" => {
+                let count = payload
+                    .get("in_flight_commands")
+                    .and_then(|c| c.as_array())
+                    .map_or(0, Vec::len);
+                snapshots.push((at, payload.clone()));
+                format!("{count} command(s) in flight at latch")
+            }
+            "CORE_CONTROL" => {
+                let op = json_str(&payload, "op").unwrap_or_default();
+                let params = payload.get("parameters").cloned().unwrap_or_default();
+                let actor = json_str(&params, "actor")
+                    .or_else(|| json_str(&params, "confirmed_by"))
+                    .or_else(|| json_str(&params, "owner"))
+                    .or_else(|| json_str(&params, "released_by"));
+                let summary = format!("{op} by {}", actor.as_deref().unwrap_or("unknown"));
+                report.operator_actions.push(IncidentOperatorAction {
+                    observed_at_unix_ms: at,
+                    kind: kind.clone(),
+                    action: op,
+                    actor,
+                    actor_role: json_str(&params, "actor_role"),
+                    details: params,
+                });
+                summary
+            }
+            _ => {
+                // sentient-api audit events (`API_*`).
+                let actor =
+                    json_str(&payload, "actor").or_else(|| json_str(&payload, "confirmed_by"));
+                let actor_role = json_str(&payload, "actor_role")
+                    .or_else(|| json_str(&payload, "confirmed_role"));
+                let summary = format!("{kind} by {}", actor.as_deref().unwrap_or("unknown"));
+                report.operator_actions.push(IncidentOperatorAction {
+                    observed_at_unix_ms: at,
+                    kind: kind.clone(),
+                    action: kind.clone(),
+                    actor,
+                    actor_role,
+                    details: payload.clone(),
+                });
+                summary
+            }
+        };
+        let timeline_kind = match kind.as_str() {
+            "CORE_FAULT" | "DEVICE_FAULT" => json_str(&payload, "kind").unwrap_or(kind),
+            _ => kind,
+        };
+        report.timeline.push(IncidentTimelineEntry {
+            observed_at_unix_ms: at,
+            kind: timeline_kind,
+            device_id,
+            summary,
+        });
+    }
+
+    // Each latch takes the nearest snapshot covering its scope (the room, or its zone).
+    for latch in &mut report.latches {
+        let covering = snapshots.iter().filter(|(at, snap)| {
+            (at - latch.observed_at_unix_ms).abs() <= INCIDENT_SNAPSHOT_MATCH_MS
+                && match latch.zone.as_deref() {
+                    None => snap.get("room_latched").and_then(|v| v.as_bool()) == Some(true),
+                    Some(zone) => snap
+                        .get("zones")
+                        .and_then(|z| z.as_array())
+                        .is_some_and(|z| z.iter().any(|v| v.as_str() == Some(zone))),
+                }
+        });
+        let Some((at, snap)) =
+            covering.min_by_key(|(at, _)| (at - latch.observed_at_unix_ms).abs())
+        else {
+            continue;
+        };
+        latch.snapshot_at_unix_ms = Some(*at);
+        latch.in_flight_commands = snap
+            .get("in_flight_commands")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default();
+        latch.graph_version = snap.get("graph_version").and_then(|v| v.as_i64());
+        latch.graph_active_nodes = snap
+            .get("graph_active_nodes")
+            .and_then(|n| n.as_array())
+            .map(|n| {
+                n.iter()
+                    .filter_map(|x| x.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+    report
+}
+
+/// `2026-10-18T12:34:56.789Z` (UTC) without pulling in a date crate.
+fn format_unix_ms_utc(unix_ms: i64) -> String {
+    let secs = unix_ms.div_euclid(1000);
+    let millis = unix_ms.rem_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let sod = secs.rem_euclid(86_400);
+    // Howard Hinnant's days -> civil date.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        sod / 3600,
+        (sod / 60) % 60,
+        sod % 60
+    )
+}
+
+/// Printable cell text: one line, no table-breaking pipes.
+fn md_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn opt_or_dash(v: &Option<String>) -> String {
+    v.as_deref().map_or_else(|| "-".to_string(), md_cell)
+}
+
+fn render_incident_markdown(r: &IncidentReport) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# Incident report: {}\n", md_cell(&r.room_id));
+    let _ = writeln!(
+        out,
+        "- Window: {} to {} (UTC)",
+        format_unix_ms_utc(r.from_unix_ms as i64),
+        format_unix_ms_utc(r.to_unix_ms as i64)
+    );
+    let _ = writeln!(
+        out,
+        "- Generated: {} by {}",
+        format_unix_ms_utc(r.generated_at_unix_ms as i64),
+        md_cell(&r.generated_by)
+    );
+    let denied = r.resets.iter().filter(|x| x.outcome == "DENIED").count();
+    let _ = writeln!(
+        out,
+        "- Safety transitions: {}; latches: {}; resets: {} ({} denied); operator actions: {}",
+        r.safety_transitions.len(),
+        r.latches.len(),
+        r.resets.len() - denied,
+        denied,
+        r.operator_actions.len()
+    );
+    if r.truncated {
+        let _ = writeln!(
+            out,
+            "\n> **Truncated**: more than {INCIDENT_REPORT_MAX_EVENTS} events matched; narrow the window."
+        );
+    }
+
+    let _ = writeln!(out, "\n## Latches\n");
+    if r.latches.is_empty() {
+        let _ = writeln!(out, "None.");
+    }
+    for l in &r.latches {
+        let scope = l
+            .zone
+            .as_deref()
+            .map_or_else(|| "room".to_string(), |z| format!("zone {}", md_cell(z)));
+        let _ = writeln!(
+            out,
+            "### {}: {} latched by {} ({}, reason {})\n",
+            format_unix_ms_utc(l.observed_at_unix_ms),
+            scope,
+            opt_or_dash(&l.device_id),
+            opt_or_dash(&l.safety_kind),
+            opt_or_dash(&l.reason_code)
+        );
+        if let Some(zone) = &l.escalated_from_zone {
+            let _ = writeln!(out, "- Escalated from zone {}", md_cell(zone));
+        }
+        if l.snapshot_at_unix_ms.is_none() {
+            let _ = writeln!(out, "- No snapshot recorded (in-flight commands unknown)\n");
+            continue;
+        }
+        let nodes = if l.graph_active_nodes.is_empty() {
+            "none".to_string()
+        } else {
+            md_cell(&l.graph_active_nodes.join(", "))
+        };
+        let _ = writeln!(
+            out,
+            "- Graph version: {}; active nodes: {}",
+            l.graph_version
+                .map_or_else(|| "-".to_string(), |v| v.to_string()),
+            nodes
+        );
+        if l.in_flight_commands.is_empty() {
+            let _ = writeln!(out, "- In-flight commands: none\n");
+            continue;
+        }
+        let _ = writeln!(out, "- In-flight commands:\n");
+        let _ = writeln!(out, "| issued | device | action | command_id | accepted |");
+        let _ = writeln!(out, "|---|---|---|---|---|");
+        for c in &l.in_flight_commands {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                c.get("issued_at_unix_ms")
+                    .and_then(|v| v.as_i64())
+                    .map_or_else(|| "-".to_string(), format_unix_ms_utc),
+                opt_or_dash(&json_str(c, "device_id")),
+                opt_or_dash(&json_str(c, "action")),
+                opt_or_dash(&json_str(c, "command_id")),
+                c.get("accepted").and_then(|v| v.as_bool()).unwrap_or(false)
+            );
+        }
+        let _ = writeln!(out);
+    }
+
+    let _ = writeln!(out, "\n## Device safety transitions\n");
+    if r.safety_transitions.is_empty() {
+        let _ = writeln!(out, "None.");
+    } else {
+        let _ = writeln!(
+            out,
+            "| time | device | from | to | reason | latched | source |"
+        );
+        let _ = writeln!(out, "|---|---|---|---|---|---|---|");
+        for t in &r.safety_transitions {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                format_unix_ms_utc(t.observed_at_unix_ms),
+                opt_or_dash(&t.device_id),
+                opt_or_dash(&t.previous_safety_state),
+                opt_or_dash(&t.safety_state),
+                opt_or_dash(&t.reason_code),
+                t.latched,
+                opt_or_dash(&t.source)
+            );
+        }
+    }
+
+    let _ = writeln!(out, "\n## Resets\n");
+    if r.resets.is_empty() {
+        let _ = writeln!(out, "None.");
+    } else {
+        let _ = writeln!(
+            out,
+            "| time | outcome | zone | requested by | confirmed by | reason | blockers |"
+        );
+        let _ = writeln!(out, "|---|---|---|---|---|---|---|");
+        for x in &r.resets {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                format_unix_ms_utc(x.observed_at_unix_ms),
+                x.outcome,
+                x.zone
+                    .as_deref()
+                    .map_or_else(|| "room".to_string(), md_cell),
+                opt_or_dash(&x.requested_by),
+                opt_or_dash(&x.confirmed_by),
+                opt_or_dash(&x.reason),
+                if x.blockers.is_empty() {
+                    "-".to_string()
+                } else {
+                    md_cell(&x.blockers.join(", "))
+                }
+            );
+        }
+    }
+
+    let _ = writeln!(out, "\n## Operator actions\n");
+    if r.operator_actions.is_empty() {
+        let _ = writeln!(out, "None.");
+    } else {
+        let _ = writeln!(out, "| time | action | actor | role |");
+        let _ = writeln!(out, "|---|---|---|---|");
+        for a in &r.operator_actions {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                format_unix_ms_utc(a.observed_at_unix_ms),
+                md_cell(&a.action),
+                opt_or_dash(&a.actor),
+                opt_or_dash(&a.actor_role)
+            );
+        }
+    }
+
+    let _ = writeln!(out, "\n## Timeline\n");
+    if r.timeline.is_empty() {
+        let _ = writeln!(out, "No events in this window.");
+    } else {
+        let _ = writeln!(out, "| time | event | device | summary |");
+        let _ = writeln!(out, "|---|---|---|---|");
+        for e in &r.timeline {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                format_unix_ms_utc(e.observed_at_unix_ms),
+                md_cell(&e.kind),
+                opt_or_dash(&e.device_id),
+                md_cell(&e.summary)
+            );
+        }
+    }
+    out
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Printable HTML: the Markdown report's tables, rendered without client-side scripts.
+fn render_incident_html(r: &IncidentReport) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\n<style>\
+         body{font-family:sans-serif;font-size:12px}table{border-collapse:collapse;margin:6px 0}\
+         td,th{border:1px solid #999;padding:2px 6px;text-align:left}\
+         @media print{h2,h3{page-break-after:avoid}tr{page-break-inside:avoid}}\
+         </style>\n",
+    );
+    let mut in_table = false;
+    for line in render_incident_markdown(r).lines() {
+        if let Some(row) = line.strip_prefix('|') {
+            if line.starts_with("|---") {
+                continue;
+            }
+            let cells: Vec<String> = row
+                .trim_end_matches('|')
+                .split(" | ")
+                .map(|c| html_escape(c.trim().replace("\\|", "|").as_str()))
+                .collect();
+            let tag = if in_table { "td" } else { "th" };
+            if !in_table {
+                out.push_str("<table>\n");
+                in_table = true;
+            }
+            out.push_str("<tr>");
+            for c in cells {
+                out.push_str(&format!("<{tag}>{c}</{tag}>"));
+            }
+            out.push_str("</tr>\n");
+            continue;
+        }
+        if in_table {
+            out.push_str("</table>\n");
+            in_table = false;
+        }
+        if let Some(h) = line.strip_prefix("### ") {
+            out.push_str(&format!("<h3>{}</h3>\n", html_escape(h)));
+        } else if let Some(h) = line.strip_prefix("## ") {
+            out.push_str(&format!("<h2>{}</h2>\n", html_escape(h)));
+        } else if let Some(h) = line.strip_prefix("# ") {
+            out.push_str(&format!(
+                "<title>{0}</title></head><body>\n<h1>{0}</h1>\n",
+                html_escape(h)
+            ));
+        } else if let Some(item) = line.strip_prefix("- ") {
+            out.push_str(&format!("<p>{}</p>\n", html_escape(item)));
+        } else if let Some(note) = line.strip_prefix("> ") {
+            out.push_str(&format!(
+                "<p><strong>{}</strong></p>\n",
+                html_escape(note.replace("**", "").as_str())
+            ));
+        } else if !line.trim().is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", html_escape(line)));
+        }
+    }
+    if in_table {
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
 async fn get_core_status(
     headers: HeaderMap,
     State(state): State<AppState>,
@@ -775,6 +1723,25 @@ async fn get_core_status(
     }
 }
 
+/// Latest `core/metrics` snapshot: dispatch latency histograms, tick jitter and SLO state.
+async fn get_core_metrics(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> impl IntoResponse {
+    if !authorized(&headers, &state.config) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let c = state.cache.read().await;
+    match &c.core_metrics {
+        Some(v) => (StatusCode::OK, Json(v)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
 async fn get_core_fault(
     headers: HeaderMap,
     State(state): State<AppState>,
@@ -882,9 +1849,28 @@ async fn get_device_fault(
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct DispatchBody {
-    device_id: String,
+/// Latest `core/device/{device_id}/queue` snapshot (404 until core has queued anything for it).
+async fn get_device_queue(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, device_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if !authorized(&headers, &state.config) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let c = state.cache.read().await;
+    match c.device_queue.get(&device_id) {
+        Some(v) => (StatusCode::OK, Json(v)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DispatchBody {
+    device_id: String,
     action: sentient_protocol::CommandAction,
     #[serde(default)]
     parameters: serde_json::Value,
@@ -898,12 +1884,35 @@ struct DispatchBody {
     ack_timeout_ms: Option<u64>,
     #[serde(default)]
     complete_timeout_ms: Option<u64>,
+    #[serde(default)]
+    execute_at_unix_ms: Option<u64>,
+    #[serde(default)]
+    execute_in_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DispatchQuery {
+    /// How long to wait for core's verdict (0 = fire and forget).
+    #[serde(default)]
+    wait_ms: Option<u64>,
+}
+
+/// Correlation id for an `Idempotency-Key` header: the key itself when it is a UUID, otherwise a
+/// v5 UUID derived from room + key so every API instance maps it the same way.
+fn idempotency_correlation_id(room_id: &str, key: &str) -> Uuid {
+    Uuid::parse_str(key).unwrap_or_else(|_| {
+        Uuid::new_v5(
+            &Uuid::NAMESPACE_OID,
+            format!("sentient/{room_id}/dispatch/{key}").as_bytes(),
+        )
+    })
 }
 
 async fn post_dispatch(
     headers: HeaderMap,
     State(state): State<AppState>,
     Path(room_id): Path<String>,
+    axum::extract::Query(q): axum::extract::Query<DispatchQuery>,
     Json(body): Json<DispatchBody>,
 ) -> impl IntoResponse {
     if !require_role(&headers, &state.config, &["ADMIN", "TECH", "GM"]) {
@@ -912,6 +1921,19 @@ async fn post_dispatch(
     if room_id != state.config.room_id {
         return StatusCode::NOT_FOUND.into_response();
     }
+    // Cancels are core-issued only; operators use the CANCEL_* control ops.
+    if body.action == sentient_protocol::CommandAction::Cancel {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let correlation_id = match dispatch_correlation_id(&headers, &room_id, body.correlation_id) {
+        Ok(id) => id,
+        Err(code) => return code.into_response(),
+    };
+    let wait_ms = q
+        .wait_ms
+        .unwrap_or(DISPATCH_WAIT_DEFAULT_MS)
+        .min(DISPATCH_WAIT_MAX_MS);
 
     let req = CoreDispatchRequest {
         schema: SCHEMA_VERSION.to_string(),
@@ -922,25 +1944,264 @@ async fn post_dispatch(
         safety_class: body
             .safety_class
             .unwrap_or(sentient_protocol::SafetyClass::NonCritical),
-        correlation_id: body.correlation_id,
+        correlation_id: Some(correlation_id),
         retries: body.retries,
         ack_timeout_ms: body.ack_timeout_ms,
         complete_timeout_ms: body.complete_timeout_ms,
+        // Replies are requested with the v5 response topic instead (see publish_dispatch_and_wait).
+        reply_topic: None,
+        execute_at_unix_ms: body.execute_at_unix_ms,
+        execute_in_ms: body.execute_in_ms,
+        operator: Some(dispatch_operator(&headers, &state.config)),
     };
     let payload = match serde_json::to_vec(&req) {
         Ok(v) => v,
         Err(_) => return StatusCode::BAD_REQUEST.into_response(),
     };
     let topic = format!("room/{}/core/dispatch", state.config.room_id);
+    let trace_id = request_trace_id(&headers);
+    publish_dispatch_and_wait(
+        &state,
+        "single",
+        topic,
+        payload,
+        correlation_id,
+        &trace_id,
+        wait_ms,
+    )
+    .await
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BatchDispatchBody {
+    commands: Vec<BatchCommand>,
+    #[serde(default)]
+    correlation_id: Option<Uuid>,
+    #[serde(default)]
+    retries: Option<u32>,
+    #[serde(default)]
+    ack_timeout_ms: Option<u64>,
+    #[serde(default)]
+    complete_timeout_ms: Option<u64>,
+}
+
+async fn post_dispatch_batch(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    axum::extract::Query(q): axum::extract::Query<DispatchQuery>,
+    Json(body): Json<BatchDispatchBody>,
+) -> impl IntoResponse {
+    if !require_role(&headers, &state.config, &["ADMIN", "TECH", "GM"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if body.commands.is_empty()
+        || body
+            .commands
+            .iter()
+            .any(|c| c.action == sentient_protocol::CommandAction::Cancel)
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let correlation_id = match dispatch_correlation_id(&headers, &room_id, body.correlation_id) {
+        Ok(id) => id,
+        Err(code) => return code.into_response(),
+    };
+    let wait_ms = q
+        .wait_ms
+        .unwrap_or(DISPATCH_WAIT_DEFAULT_MS)
+        .min(DISPATCH_WAIT_MAX_MS);
+
+    let req = CoreBatchDispatchRequest {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id,
+        correlation_id: Some(correlation_id),
+        commands: body.commands,
+        retries: body.retries,
+        ack_timeout_ms: body.ack_timeout_ms,
+        complete_timeout_ms: body.complete_timeout_ms,
+        reply_topic: None,
+        operator: Some(dispatch_operator(&headers, &state.config)),
+    };
+    let payload = match serde_json::to_vec(&req) {
+        Ok(v) => v,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let topic = format!("room/{}/core/dispatch/batch", state.config.room_id);
+    let trace_id = request_trace_id(&headers);
+    publish_dispatch_and_wait(
+        &state,
+        "batch",
+        topic,
+        payload,
+        correlation_id,
+        &trace_id,
+        wait_ms,
+    )
+    .await
+}
+
+/// The caller, as core sees it on dispatches (lets TECH/ADMIN drive devices in maintenance).
+/// Carries `CORE_CONTROL_TOKEN` so core can trust it.
+fn dispatch_operator(headers: &HeaderMap, cfg: &Config) -> DispatchOperator {
+    let actor = actor_from_headers(headers, cfg);
+    DispatchOperator {
+        sub: actor.sub,
+        role: actor.role,
+        token: cfg.core_control_token.clone(),
+    }
+}
+
+/// `Idempotency-Key` (if any) mapped to a correlation id; must agree with a body `correlation_id`.
+fn dispatch_correlation_id(
+    headers: &HeaderMap,
+    room_id: &str,
+    body_id: Option<Uuid>,
+) -> Result<Uuid, StatusCode> {
+    let idempotency_key = match headers.get("idempotency-key").map(|v| v.to_str()) {
+        None => None,
+        Some(Ok(key)) if !key.trim().is_empty() && key.len() <= 255 => Some(key.trim()),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    match (idempotency_key, body_id) {
+        (Some(key), body_id) => {
+            let id = idempotency_correlation_id(room_id, key);
+            if body_id.is_some_and(|b| b != id) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Ok(id)
+        }
+        (None, Some(id)) => Ok(id),
+        (None, None) => Ok(Uuid::new_v4()),
+    }
+}
+
+/// Trace id for an HTTP request: the caller's `X-Trace-Id` when usable, otherwise a fresh one.
+fn request_trace_id(headers: &HeaderMap) -> String {
+    headers
+        .get("x-trace-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|t| is_valid_trace_id(t))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// MQTT v5 properties carrying `trace_id` as a user property.
+fn trace_properties(trace_id: &str) -> PublishProperties {
+    PublishProperties {
+        user_properties: vec![(
+            MQTT_USER_PROPERTY_TRACE_ID.to_string(),
+            trace_id.to_string(),
+        )],
+        ..Default::default()
+    }
+}
+
+/// Publishes a (batch) dispatch request and waits up to `wait_ms` for core's result.
+///
+/// Waiting requests name this instance's reply topic as the MQTT v5 response topic and the
+/// correlation id as correlation data; core echoes the latter on its result.
+#[allow(clippy::too_many_arguments)]
+async fn publish_dispatch_and_wait(
+    state: &AppState,
+    // Metrics label: "single" or "batch".
+    kind: &'static str,
+    topic: String,
+    payload: Vec<u8>,
+    correlation_id: Uuid,
+    trace_id: &str,
+    wait_ms: u64,
+) -> axum::response::Response {
+    // Register before publishing so a fast reply cannot slip past.
+    let waiter = if wait_ms > 0 {
+        let (tx, rx) = oneshot::channel();
+        state
+            .dispatch_waiters
+            .lock()
+            .await
+            .entry(correlation_id)
+            .or_default()
+            .push(tx);
+        Some(rx)
+    } else {
+        None
+    };
+
+    let mut properties = trace_properties(trace_id);
+    if waiter.is_some() {
+        properties.response_topic = Some(state.dispatch_reply_topic.to_string());
+        properties.correlation_data = Some(correlation_id.to_string().into_bytes().into());
+    }
     if let Err(err) = state
         .mqtt
-        .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+        .publish_with_properties(topic, QoS::AtLeastOnce, false, payload, properties)
         .await
     {
-        warn!(error=%err, "failed to publish dispatch");
+        warn!(error=%err, trace_id, "failed to publish dispatch");
+        DISPATCH_REQUESTS.inc(&[kind, "PUBLISH_FAILED"]);
+        state.dispatch_waiters.lock().await.remove(&correlation_id);
         return StatusCode::SERVICE_UNAVAILABLE.into_response();
     }
-    StatusCode::ACCEPTED.into_response()
+
+    let pending = (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "correlation_id": correlation_id })),
+    );
+    let mut response = match waiter {
+        None => {
+            DISPATCH_REQUESTS.inc(&[kind, "PENDING"]);
+            pending.into_response()
+        }
+        Some(rx) => await_dispatch_result(state, kind, correlation_id, wait_ms, rx, pending).await,
+    };
+    if let Ok(v) = axum::http::HeaderValue::from_str(trace_id) {
+        response.headers_mut().insert("x-trace-id", v);
+    }
+    response
+}
+
+async fn await_dispatch_result(
+    state: &AppState,
+    kind: &'static str,
+    correlation_id: Uuid,
+    wait_ms: u64,
+    rx: oneshot::Receiver<DispatchReply>,
+    pending: impl IntoResponse,
+) -> axum::response::Response {
+    match tokio::time::timeout(Duration::from_millis(wait_ms), rx).await {
+        Ok(Ok(result)) => {
+            DISPATCH_REQUESTS.inc(&[kind, result.status().as_str()]);
+            let code = match result.status() {
+                DispatchResultStatus::Accepted | DispatchResultStatus::Completed => StatusCode::OK,
+                DispatchResultStatus::Queued | DispatchResultStatus::Scheduled => {
+                    StatusCode::ACCEPTED
+                }
+                DispatchResultStatus::Rejected
+                | DispatchResultStatus::Cancelled
+                | DispatchResultStatus::Blocked
+                | DispatchResultStatus::Timeout => StatusCode::CONFLICT,
+            };
+            (code, Json(result)).into_response()
+        }
+        _ => {
+            // Core did not answer in time; the dispatch may still go ahead. Retry with the same
+            // Idempotency-Key to learn the outcome.
+            DISPATCH_REQUESTS.inc(&[kind, "PENDING"]);
+            let mut waiters = state.dispatch_waiters.lock().await;
+            if let Some(list) = waiters.get_mut(&correlation_id) {
+                list.retain(|tx| !tx.is_closed());
+                if list.is_empty() {
+                    waiters.remove(&correlation_id);
+                }
+            }
+            pending.into_response()
+        }
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -954,10 +2215,13 @@ async fn post_control(
     headers: HeaderMap,
     State(state): State<AppState>,
     Path(room_id): Path<String>,
-    Json(body): Json<ControlBody>,
+    Json(mut body): Json<ControlBody>,
 ) -> impl IntoResponse {
     let allowed_roles: &[&str] = match body.op.as_str() {
-        sentient_protocol::CORE_CONTROL_OP_RESET_SAFETY_LATCH => &["ADMIN", "TECH"],
+        sentient_protocol::CORE_CONTROL_OP_RESET_SAFETY_LATCH
+        | CORE_CONTROL_OP_SET_MAINTENANCE
+        | CORE_CONTROL_OP_RELEASE_MAINTENANCE
+        | CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY => &["ADMIN", "TECH"],
         _ => &["ADMIN", "TECH", "GM"],
     };
     if !require_role(&headers, &state.config, allowed_roles) {
@@ -977,34 +2241,73 @@ async fn post_control(
         let Some(reset_id) = reset_id else {
             return StatusCode::BAD_REQUEST.into_response();
         };
-        let now = unix_ms_now();
-        let mut guard = state.safety_reset_tokens.lock().await;
-        let Some(pending) = guard.get(&reset_id).cloned() else {
-            return StatusCode::BAD_REQUEST.into_response();
+        let confirmer = actor_from_headers(&headers, &state.config);
+        let pending = match take_safety_reset(&state, reset_id, &confirmer).await {
+            Ok(p) => p,
+            Err(status) => return status.into_response(),
         };
-        if pending.expires_at_unix_ms < now {
-            guard.remove(&reset_id);
+        // The zone and the operators were fixed by the request/confirm; the body cannot change them.
+        if let Some(obj) = body.parameters.as_object_mut() {
+            match pending.zone.as_deref() {
+                Some(zone) => {
+                    obj.insert("zone".to_string(), serde_json::json!(zone));
+                }
+                None => {
+                    obj.remove("zone");
+                }
+            }
+            obj.insert(
+                "requested_by".to_string(),
+                serde_json::json!(pending.actor.sub),
+            );
+            obj.insert("confirmed_by".to_string(), serde_json::json!(confirmer.sub));
+            obj.insert("reason".to_string(), serde_json::json!(pending.reason));
+        }
+    }
+
+    // Maintenance locks are owned by whoever calls; the body cannot claim someone else.
+    let identity_keys = match body.op.as_str() {
+        CORE_CONTROL_OP_SET_MAINTENANCE => Some(("owner", "owner_role")),
+        CORE_CONTROL_OP_RELEASE_MAINTENANCE => Some(("released_by", "released_by_role")),
+        _ => None,
+    };
+    if let Some((sub_key, role_key)) = identity_keys {
+        let actor = actor_from_headers(&headers, &state.config);
+        let Some(obj) = body.parameters.as_object_mut() else {
             return StatusCode::BAD_REQUEST.into_response();
+        };
+        obj.insert(sub_key.to_string(), serde_json::json!(actor.sub));
+        obj.insert(role_key.to_string(), serde_json::json!(actor.role));
+    }
+
+    // Every op carries its caller, so `CORE_CONTROL` events (and incident reports) show who sent it.
+    if body.parameters.is_null() {
+        body.parameters = serde_json::json!({});
+    }
+    if let Some(obj) = body.parameters.as_object_mut() {
+        let caller = actor_from_headers(&headers, &state.config);
+        obj.insert("actor".to_string(), serde_json::json!(caller.sub));
+        obj.insert("actor_role".to_string(), serde_json::json!(caller.role));
+    }
+
+    // Cancel ops must name their target; core would otherwise just log and drop the request.
+    let cancel_param = match body.op.as_str() {
+        sentient_protocol::CORE_CONTROL_OP_CANCEL_COMMAND => {
+            Some(sentient_protocol::CANCEL_PARAM_COMMAND_ID)
         }
-        // Consume token on use (single-use).
-        guard.remove(&reset_id);
-
-        if let Some(db) = state.db.as_deref() {
-            let _ = insert_event(
-                db,
-                &state.config.room_id,
-                None,
-                "http://sentient-api/v8/safety/reset/confirm",
-                "API_SAFETY_RESET_CONFIRM",
-                unix_ms_now(),
-                serde_json::json!({
-                    "reset_id": reset_id,
-                    "requested_by": pending.actor.sub,
-                    "requested_role": pending.actor.role,
-                    "reason": pending.reason,
-                }),
-            )
-            .await;
+        sentient_protocol::CORE_CONTROL_OP_CANCEL_DEVICE_COMMANDS => Some("device_id"),
+        _ => None,
+    };
+    if let Some(key) = cancel_param {
+        let value = body.parameters.get(key).and_then(|v| v.as_str());
+        let valid = match key {
+            sentient_protocol::CANCEL_PARAM_COMMAND_ID => {
+                value.is_some_and(|s| Uuid::parse_str(s).is_ok())
+            }
+            _ => value.is_some_and(|s| !s.trim().is_empty()),
+        };
+        if !valid {
+            return StatusCode::BAD_REQUEST.into_response();
         }
     }
 
@@ -1020,6 +2323,7 @@ async fn post_control(
         }
     }
 
+    let req_op = body.op.clone();
     let req = CoreControlRequest {
         schema: SCHEMA_VERSION.to_string(),
         room_id,
@@ -1034,12 +2338,412 @@ async fn post_control(
     let topic = format!("room/{}/core/control", state.config.room_id);
     if let Err(err) = state
         .mqtt
-        .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+        .publish(topic, QoS::AtLeastOnce, false, payload)
         .await
     {
         warn!(error=%err, "failed to publish control");
         return StatusCode::SERVICE_UNAVAILABLE.into_response();
     }
+    let op = KNOWN_CONTROL_OPS
+        .iter()
+        .copied()
+        .find(|known| *known == req_op)
+        .unwrap_or("OTHER");
+    CONTROL_REQUESTS.inc(&[op]);
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MaintenanceBody {
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AdoptDeviceBody {
+    /// `CRITICAL` or `NON_CRITICAL`; required, there is no default for a device nobody has vetted.
+    safety_class: String,
+    #[serde(default)]
+    zone: Option<String>,
+    /// `IGNORE` | `BLOCK_DEVICE` | `LATCH_ZONE` | `LATCH_ROOM`; `None` = `BLOCK_DEVICE`.
+    #[serde(default)]
+    offline_policy: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// Devices that heartbeat in the room namespace without a registry row (core's inbox), newest first.
+async fn get_unregistered_devices(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> impl IntoResponse {
+    if !require_role(&headers, &state.config, &["ADMIN", "TECH"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let Some(db) = state.db.as_ref() else {
+        return StatusCode::NOT_IMPLEMENTED.into_response();
+    };
+    let rows = match db
+        .query(
+            "SELECT device_id, (extract(epoch from first_seen) * 1000)::bigint, \
+                    (extract(epoch from last_seen) * 1000)::bigint, firmware_version, supported_schemas \
+             FROM unregistered_devices u \
+             WHERE adopted_at IS NULL \
+               AND NOT EXISTS (SELECT 1 FROM devices d WHERE d.device_id = u.device_id) \
+             ORDER BY last_seen DESC",
+            &[],
+        )
+        .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(error=%err, "failed to query unregistered devices");
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+    let out: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let first_seen: i64 = row.get(1);
+            let last_seen: i64 = row.get(2);
+            let firmware_version: Option<String> = row.get(3);
+            let supported_schemas: serde_json::Value = row.get(4);
+            serde_json::json!({
+                "device_id": row.get::<_, String>(0),
+                "first_seen_at_unix_ms": first_seen,
+                "last_seen_at_unix_ms": last_seen,
+                "firmware_version": firmware_version,
+                "supported_schemas": supported_schemas,
+            })
+        })
+        .collect();
+    (StatusCode::OK, Json(out)).into_response()
+}
+
+/// Adopts an unregistered device: registry row with the chosen safety class, a fresh HMAC key
+/// (returned once, to flash into the controller), then asks core to reload its registry.
+async fn post_adopt_device(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, device_id)): Path<(String, String)>,
+    Json(body): Json<AdoptDeviceBody>,
+) -> axum::response::Response {
+    if !require_role(&headers, &state.config, &["ADMIN"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let Some(db) = state.db.as_ref() else {
+        return StatusCode::NOT_IMPLEMENTED.into_response();
+    };
+    let safety_class = body.safety_class.trim().to_ascii_uppercase();
+    let offline_policy = body
+        .offline_policy
+        .as_deref()
+        .map(|p| p.trim().to_ascii_uppercase())
+        .filter(|p| !p.is_empty());
+    let zone = body
+        .zone
+        .as_deref()
+        .map(str::trim)
+        .filter(|z| !z.is_empty())
+        .map(str::to_string);
+    let notes = body.notes.filter(|n| !n.trim().is_empty());
+    if !matches!(safety_class.as_str(), "CRITICAL" | "NON_CRITICAL")
+        || offline_policy
+            .as_deref()
+            .is_some_and(|p| !matches!(p, "IGNORE" | "BLOCK_DEVICE" | "LATCH_ZONE" | "LATCH_ROOM"))
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let actor = actor_from_headers(&headers, &state.config);
+    let mut key = [0u8; 32];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut key);
+    let key_hex = hex::encode(key);
+
+    // One statement, so the inbox, registry and key rows change together or not at all.
+    let adopted = match db
+        .query_one(
+            "WITH inbox AS ( \
+                 UPDATE unregistered_devices SET adopted_at = now(), adopted_by = $2 \
+                 WHERE device_id = $1 AND adopted_at IS NULL \
+                   AND NOT EXISTS (SELECT 1 FROM devices WHERE device_id = $1) \
+                 RETURNING device_id), \
+             dev AS ( \
+                 INSERT INTO devices (device_id, safety_class, zone, offline_policy, notes) \
+                 SELECT device_id, $3, $4, $5, $6 FROM inbox RETURNING device_id), \
+             keys AS ( \
+                 INSERT INTO device_keys (device_id, key_hex, created_by) \
+                 SELECT device_id, $7, $2 FROM dev \
+                 ON CONFLICT (device_id) DO UPDATE \
+                 SET key_hex = EXCLUDED.key_hex, created_by = EXCLUDED.created_by, created_at = now() \
+                 RETURNING device_id) \
+             SELECT count(*) FROM keys",
+            &[
+                &device_id,
+                &actor.sub,
+                &safety_class,
+                &zone,
+                &offline_policy,
+                &notes,
+                &key_hex,
+            ],
+        )
+        .await
+    {
+        Ok(row) => row.get::<_, i64>(0) > 0,
+        Err(err) => {
+            warn!(error=%err, device_id=%device_id, "failed to adopt device");
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+    if !adopted {
+        let registered = db
+            .query_opt("SELECT 1 FROM devices WHERE device_id = $1", &[&device_id])
+            .await
+            .ok()
+            .flatten()
+            .is_some();
+        return if registered {
+            StatusCode::CONFLICT.into_response()
+        } else {
+            StatusCode::NOT_FOUND.into_response()
+        };
+    }
+
+    let audit = serde_json::json!({
+        "actor": actor.sub,
+        "actor_role": actor.role,
+        "device_id": device_id,
+        "safety_class": safety_class,
+        "zone": zone,
+        "offline_policy": offline_policy,
+    });
+    let _ = insert_event(
+        db,
+        &state.config.room_id,
+        Some(&device_id),
+        "http://sentient-api/v8/devices/unregistered/adopt",
+        "API_DEVICE_ADOPT",
+        unix_ms_now(),
+        audit,
+    )
+    .await;
+
+    let reload = publish_control_op(
+        &state,
+        CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY,
+        serde_json::json!({"actor": actor.sub, "actor_role": actor.role}),
+    )
+    .await;
+    info!(device_id=%device_id, actor=%actor.sub, "device adopted");
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "device_id": device_id,
+            "safety_class": safety_class,
+            "zone": zone,
+            "offline_policy": offline_policy,
+            "hmac_key_hex": key_hex,
+            // false: core has not been told; it picks the device up on its next restart.
+            "registry_reload_requested": reload.status() == StatusCode::ACCEPTED,
+        })),
+    )
+        .into_response()
+}
+
+async fn post_device_maintenance(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, device_id)): Path<(String, String)>,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    let Some(note) = maintenance_note(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    set_maintenance(
+        &headers,
+        &state,
+        &room_id,
+        MaintenanceScope::Device,
+        &device_id,
+        note,
+    )
+    .await
+}
+
+async fn delete_device_maintenance(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, device_id)): Path<(String, String)>,
+) -> axum::response::Response {
+    release_maintenance(
+        &headers,
+        &state,
+        &room_id,
+        MaintenanceScope::Device,
+        &device_id,
+    )
+    .await
+}
+
+async fn post_zone_maintenance(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, zone)): Path<(String, String)>,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    let Some(note) = maintenance_note(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    set_maintenance(
+        &headers,
+        &state,
+        &room_id,
+        MaintenanceScope::Zone,
+        &zone,
+        note,
+    )
+    .await
+}
+
+async fn delete_zone_maintenance(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, zone)): Path<(String, String)>,
+) -> axum::response::Response {
+    release_maintenance(&headers, &state, &room_id, MaintenanceScope::Zone, &zone).await
+}
+
+/// Note from an optional `{"note": ...}` body; `None` if the body is present but invalid.
+fn maintenance_note(body: &[u8]) -> Option<Option<String>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Some(None);
+    }
+    serde_json::from_slice::<MaintenanceBody>(body)
+        .ok()
+        .map(|b| b.note)
+}
+
+fn maintenance_target_param(scope: MaintenanceScope) -> &'static str {
+    match scope {
+        MaintenanceScope::Device => "device_id",
+        MaintenanceScope::Zone => "zone",
+    }
+}
+
+/// Takes the device/zone into maintenance for the caller (ADMIN/TECH). Core owns the lock and
+/// answers with `MAINTENANCE_SET` / `MAINTENANCE_DENIED` on `core/fault`.
+async fn set_maintenance(
+    headers: &HeaderMap,
+    state: &AppState,
+    room_id: &str,
+    scope: MaintenanceScope,
+    target: &str,
+    note: Option<String>,
+) -> axum::response::Response {
+    if !require_role(headers, &state.config, &["ADMIN", "TECH"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let actor = actor_from_headers(headers, &state.config);
+    if let Some(lock) = cached_maintenance(state, scope, target).await {
+        if lock.owner != actor.sub {
+            return (StatusCode::CONFLICT, Json(lock)).into_response();
+        }
+    }
+    let mut parameters = serde_json::json!({
+        "owner": actor.sub,
+        "owner_role": actor.role,
+        "note": note.filter(|n| !n.trim().is_empty()),
+    });
+    parameters[maintenance_target_param(scope)] = serde_json::json!(target);
+    publish_control_op(state, CORE_CONTROL_OP_SET_MAINTENANCE, parameters).await
+}
+
+/// Releases a maintenance lock; only its owner or an ADMIN (checked here against the cached
+/// core status when available, and always by core).
+async fn release_maintenance(
+    headers: &HeaderMap,
+    state: &AppState,
+    room_id: &str,
+    scope: MaintenanceScope,
+    target: &str,
+) -> axum::response::Response {
+    if !require_role(headers, &state.config, &["ADMIN", "TECH"]) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if room_id != state.config.room_id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let actor = actor_from_headers(headers, &state.config);
+    let have_status = state.cache.read().await.core_status.is_some();
+    match cached_maintenance(state, scope, target).await {
+        Some(lock) if lock.owner != actor.sub && actor.role != "ADMIN" => {
+            return (StatusCode::FORBIDDEN, Json(lock)).into_response();
+        }
+        None if have_status => return StatusCode::NOT_FOUND.into_response(),
+        _ => {}
+    }
+    let mut parameters = serde_json::json!({
+        "released_by": actor.sub,
+        "released_by_role": actor.role,
+    });
+    parameters[maintenance_target_param(scope)] = serde_json::json!(target);
+    publish_control_op(state, CORE_CONTROL_OP_RELEASE_MAINTENANCE, parameters).await
+}
+
+async fn cached_maintenance(
+    state: &AppState,
+    scope: MaintenanceScope,
+    target: &str,
+) -> Option<sentient_protocol::MaintenanceStatus> {
+    let c = state.cache.read().await;
+    c.core_status
+        .as_ref()?
+        .maintenance
+        .iter()
+        .find(|m| m.scope == scope && m.target == target)
+        .cloned()
+}
+
+async fn publish_control_op(
+    state: &AppState,
+    op: &'static str,
+    mut parameters: serde_json::Value,
+) -> axum::response::Response {
+    if let Some(token) = state.config.core_control_token.as_deref() {
+        parameters["token"] = serde_json::Value::String(token.to_string());
+    }
+    let req = CoreControlRequest {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: state.config.room_id.clone(),
+        op: op.to_string(),
+        parameters,
+        requested_at_unix_ms: unix_ms_now(),
+    };
+    let payload = match serde_json::to_vec(&req) {
+        Ok(v) => v,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let topic = format!("room/{}/core/control", state.config.room_id);
+    if let Err(err) = state
+        .mqtt
+        .publish(topic, QoS::AtLeastOnce, false, payload)
+        .await
+    {
+        warn!(error=%err, op, "failed to publish control");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    CONTROL_REQUESTS.inc(&[op]);
     StatusCode::ACCEPTED.into_response()
 }
 
@@ -1064,21 +2768,31 @@ async fn post_audio_cue(
         Err(_) => return StatusCode::BAD_REQUEST.into_response(),
     };
     let topic = format!("room/{}/audio/cue", state.config.room_id);
+    // osc-bridge echoes the trace id on its ack/fault.
+    let trace_id = request_trace_id(&headers);
     if let Err(err) = state
         .mqtt
-        .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+        .publish_with_properties(
+            topic,
+            QoS::AtLeastOnce,
+            false,
+            payload,
+            trace_properties(&trace_id),
+        )
         .await
     {
         warn!(error=%err, "failed to publish audio cue");
         return StatusCode::SERVICE_UNAVAILABLE.into_response();
     }
-    StatusCode::ACCEPTED.into_response()
+    (StatusCode::ACCEPTED, [("x-trace-id", trace_id)]).into_response()
 }
 
 #[derive(Debug, serde::Deserialize)]
 struct SafetyResetRequestBody {
     #[serde(default)]
     reason: Option<String>,
+    #[serde(default)]
+    zone: Option<String>,
 }
 
 async fn post_safety_reset_request(
@@ -1095,6 +2809,12 @@ async fn post_safety_reset_request(
     }
 
     let actor = actor_from_headers(&headers, &state.config);
+    let zone = body
+        .zone
+        .as_deref()
+        .map(str::trim)
+        .filter(|z| !z.is_empty())
+        .map(str::to_string);
     let reset_id = Uuid::new_v4();
     let now = unix_ms_now();
     let expires_at_unix_ms = now + 60_000; // 60s
@@ -1102,10 +2822,10 @@ async fn post_safety_reset_request(
     state.safety_reset_tokens.lock().await.insert(
         reset_id,
         PendingSafetyReset {
-            created_at_unix_ms: now,
             expires_at_unix_ms,
             actor: actor.clone(),
             reason: body.reason.clone().filter(|s| !s.trim().is_empty()),
+            zone: zone.clone(),
         },
     );
 
@@ -1123,6 +2843,7 @@ async fn post_safety_reset_request(
                 "actor": actor.sub,
                 "actor_role": actor.role,
                 "reason": body.reason,
+                "zone": zone,
             }),
         )
         .await;
@@ -1133,6 +2854,7 @@ async fn post_safety_reset_request(
         Json(serde_json::json!({
             "reset_id": reset_id,
             "expires_at_unix_ms": expires_at_unix_ms,
+            "zone": zone,
         })),
     )
         .into_response()
@@ -1162,39 +2884,11 @@ async fn post_safety_reset_confirm(
 
     let now = unix_ms_now();
     let confirmer = actor_from_headers(&headers, &state.config);
-    let pending = {
-        let mut guard = state.safety_reset_tokens.lock().await;
-        let Some(p) = guard.get(&reset_id).cloned() else {
-            return StatusCode::BAD_REQUEST.into_response();
-        };
-        if p.expires_at_unix_ms < now {
-            guard.remove(&reset_id);
-            return StatusCode::BAD_REQUEST.into_response();
-        }
-        guard.remove(&reset_id);
-        p
+    let pending = match take_safety_reset(&state, reset_id, &confirmer).await {
+        Ok(p) => p,
+        Err(status) => return status.into_response(),
     };
 
-    if let Some(db) = state.db.as_deref() {
-        let _ = insert_event(
-            db,
-            &state.config.room_id,
-            None,
-            "http://sentient-api/v8/safety/reset/confirm",
-            "API_SAFETY_RESET_CONFIRM",
-            now,
-            serde_json::json!({
-                "reset_id": reset_id,
-                "requested_by": pending.actor.sub,
-                "requested_role": pending.actor.role,
-                "confirmed_by": confirmer.sub,
-                "confirmed_role": confirmer.role,
-                "reason": pending.reason,
-            }),
-        )
-        .await;
-    }
-
     let req = CoreControlRequest {
         schema: SCHEMA_VERSION.to_string(),
         room_id: state.config.room_id.clone(),
@@ -1204,6 +2898,7 @@ async fn post_safety_reset_confirm(
             "requested_by": pending.actor.sub,
             "confirmed_by": confirmer.sub,
             "reason": pending.reason,
+            "zone": pending.zone,
         }),
         requested_at_unix_ms: now,
     };
@@ -1223,7 +2918,7 @@ async fn post_safety_reset_confirm(
     let topic = format!("room/{}/core/control", state.config.room_id);
     if let Err(err) = state
         .mqtt
-        .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+        .publish(topic, QoS::AtLeastOnce, false, payload)
         .await
     {
         warn!(error=%err, "failed to publish safety reset confirm");
@@ -1232,11 +2927,82 @@ async fn post_safety_reset_confirm(
     StatusCode::ACCEPTED.into_response()
 }
 
+/// Second step of the dual-confirm reset: the confirmer must be ADMIN/TECH and not the requester.
+/// Consumes the token on success only, so a refused confirm leaves it for a colleague. Both outcomes are audited.
+async fn take_safety_reset(
+    state: &AppState,
+    reset_id: Uuid,
+    confirmer: &Actor,
+) -> Result<PendingSafetyReset, StatusCode> {
+    let now = unix_ms_now();
+    let (pending, denied) = {
+        let mut guard = state.safety_reset_tokens.lock().await;
+        let Some(p) = guard.get(&reset_id).cloned() else {
+            return Err(StatusCode::BAD_REQUEST);
+        };
+        if p.expires_at_unix_ms < now {
+            guard.remove(&reset_id);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let denied = if !["ADMIN", "TECH"].contains(&confirmer.role.as_str()) {
+            Some("CONFIRMER_ROLE")
+        } else if confirmer.sub == p.actor.sub {
+            Some("SAME_OPERATOR")
+        } else {
+            None
+        };
+        if denied.is_none() {
+            // Single-use.
+            guard.remove(&reset_id);
+        }
+        (p, denied)
+    };
+
+    if let Some(reason) = denied {
+        warn!(
+            reset_id=%reset_id,
+            requested_by=%pending.actor.sub,
+            confirmed_by=%confirmer.sub,
+            reason,
+            "safety reset confirm denied"
+        );
+    }
+    if let Some(db) = state.db.as_deref() {
+        let kind = match denied {
+            Some(_) => "API_SAFETY_RESET_CONFIRM_DENIED",
+            None => "API_SAFETY_RESET_CONFIRM",
+        };
+        let _ = insert_event(
+            db,
+            &state.config.room_id,
+            None,
+            "http://sentient-api/v8/safety/reset/confirm",
+            kind,
+            now,
+            serde_json::json!({
+                "reset_id": reset_id,
+                "requested_by": pending.actor.sub,
+                "requested_role": pending.actor.role,
+                "confirmed_by": confirmer.sub,
+                "confirmed_role": confirmer.role,
+                "reason": pending.reason,
+                "zone": pending.zone,
+                "denied": denied,
+            }),
+        )
+        .await;
+    }
+    match denied {
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Ok(pending),
+    }
+}
+
 async fn connect_mqtt(
     config: &Config,
-) -> anyhow::Result<(rumqttc::AsyncClient, mpsc::Receiver<rumqttc::Publish>)> {
+) -> anyhow::Result<(AsyncClient, mpsc::Receiver<InboundPublish>)> {
     let client_id = format!("sentient-api-{}-{}", config.room_id, Uuid::new_v4());
-    let mut opts = rumqttc::MqttOptions::new(client_id, config.mqtt_host.clone(), config.mqtt_port);
+    let mut opts = MqttOptions::new(client_id, config.mqtt_host.clone(), config.mqtt_port);
     opts.set_keep_alive(Duration::from_secs(5));
     if let (Some(user), Some(pass)) = (
         config.mqtt_username.as_deref(),
@@ -1244,16 +3010,26 @@ async fn connect_mqtt(
     ) {
         opts.set_credentials(user, pass);
     }
-    let (client, mut eventloop) = rumqttc::AsyncClient::new(opts, 200);
-    let (tx, rx) = mpsc::channel::<rumqttc::Publish>(2048);
+    let (client, mut eventloop) = AsyncClient::new(opts, 200);
+    let (tx, rx) = mpsc::channel::<InboundPublish>(2048);
     tokio::spawn(async move {
+        let mut connected_once = false;
         loop {
             match eventloop.poll().await {
-                Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(p))) => {
+                Ok(Event::Incoming(Packet::Publish(p))) => {
+                    let Some(p) = InboundPublish::from_packet(p) else {
+                        continue;
+                    };
                     if tx.send(p).await.is_err() {
                         break;
                     }
                 }
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    if connected_once {
+                        MQTT_RECONNECTS.inc();
+                    }
+                    connected_once = true;
+                }
                 Ok(_) => {}
                 Err(err) => {
                     warn!(error=%err, "mqtt eventloop error (api)");
@@ -1287,7 +3063,7 @@ async fn insert_event(
     let observed_at_ms_i64: i64 = observed_at_unix_ms as i64;
     db.execute(
         "INSERT INTO events (room_id, device_id, topic, kind, observed_at, payload) \
-         VALUES ($1,$2,$3,$4,to_timestamp($5/1000.0),$6)",
+         VALUES ($1,$2,$3,$4,to_timestamp($5::bigint / 1000.0),$6)",
         &[
             &room_id,
             &device_id,
@@ -14,6 +14,7 @@ use axum::{
 };
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 use rand::rngs::OsRng;
+use sentient_metrics::{Counter, CounterVec, Exposition};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tokio_postgres::NoTls;
@@ -222,6 +223,7 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/health", get(|| async { "ok" }))
+        .route("/metrics", get(get_metrics))
         .route("/v8/auth/login", post(login))
         .route("/v8/auth/bootstrap", post(bootstrap))
         .route("/v8/auth/users", get(list_users).post(create_user))
@@ -238,6 +240,28 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+static LOGIN_ATTEMPTS: CounterVec = CounterVec::new(
+    "sentient_auth_login_attempts_total",
+    "Login attempts by audit outcome (LOGIN_OK, LOGIN_BAD_PASSWORD, LOGIN_RATE_LIMIT, ...).",
+    &["result"],
+);
+static AUDIT_WRITE_FAILURES: Counter = Counter::new(
+    "sentient_auth_audit_write_failures_total",
+    "audit_log INSERTs that failed.",
+);
+
+async fn get_metrics() -> impl IntoResponse {
+    let mut out = Exposition::new();
+    out.metric(&LOGIN_ATTEMPTS).metric(&AUDIT_WRITE_FAILURES);
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            sentient_metrics::CONTENT_TYPE,
+        )],
+        out.finish(),
+    )
+}
+
 async fn login(
     ConnectInfo(connect): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
@@ -589,7 +613,7 @@ async fn set_password(
         )
         .await
     {
-        Ok(n) if n == 1 => StatusCode::OK.into_response(),
+        Ok(1) => StatusCode::OK.into_response(),
         Ok(_) => StatusCode::NOT_FOUND.into_response(),
         Err(err) => {
             warn!(error=%err, "failed to update password");
@@ -637,7 +661,7 @@ async fn set_enabled(
         )
         .await
     {
-        Ok(n) if n == 1 => StatusCode::OK.into_response(),
+        Ok(1) => StatusCode::OK.into_response(),
         Ok(_) => StatusCode::NOT_FOUND.into_response(),
         Err(err) => {
             warn!(error=%err, "failed to update enabled");
@@ -704,6 +728,7 @@ async fn create_user_inner(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn audit(
     db: &tokio_postgres::Client,
     event_type: &str,
@@ -714,8 +739,11 @@ async fn audit(
     success: bool,
     details: serde_json::Value,
 ) {
+    if event_type.starts_with("LOGIN_") {
+        LOGIN_ATTEMPTS.inc(&[event_type]);
+    }
     let details_json = serde_json::to_string(&details).unwrap_or_else(|_| "{}".to_string());
-    let _ = db
+    if db
         .execute(
             "INSERT INTO audit_log (event_type, username, role, ip, user_agent, success, details) \
              VALUES ($1,$2,$3,$4,$5,$6,$7::jsonb)",
@@ -729,5 +757,9 @@ async fn audit(
                 &details_json,
             ],
         )
-        .await;
+        .await
+        .is_err()
+    {
+        AUDIT_WRITE_FAILURES.inc();
+    }
 }
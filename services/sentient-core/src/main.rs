@@ -1,13 +1,27 @@
 use std::time::{Duration, Instant};
 
 use anyhow::Context;
+use rumqttc::v5::mqttbytes::v5::{Packet, Publish, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, MqttOptions};
+use sentient_metrics::{Counter, CounterVec, Exposition, Gauge};
 use sentient_protocol::{
-    sign_command_hmac_sha256, CommandAck, CommandAction, CommandEnvelope, CoreControlRequest,
-    CoreDispatchRequest, CoreFault, CoreStatus, DeviceState, Heartbeat, Presence, PresenceStatus,
-    SafetyClass, SafetyState, SafetyStateKind, CORE_CONTROL_OP_PAUSE_DISPATCH,
+    clock_sample, decode_message, decode_message_as, dispatch_reply_prefix, is_accepted_schema,
+    negotiate_schema, schema_at_least, sign_command_hmac_sha256, sign_watchdog_hmac_sha256,
+    trace_id_from_user_properties, BatchCommand, BatchMemberResult, ClockSample, CommandAck,
+    CommandAction, CommandEnvelope, CommandProgress, CoreBatchDispatchRequest,
+    CoreBatchDispatchResult, CoreControlRequest, CoreDispatchRequest, CoreDispatchResult,
+    CoreFault, CoreStatus, CoreWatchdog, CoreWatchdogStatus, DbHealthStatus, DeviceState,
+    DispatchOperator, DispatchResultStatus, FaultKind, Heartbeat, MaintenanceScope,
+    MaintenanceStatus, Presence, PresenceStatus, SafetyClass, SafetyState, SafetyStateKind,
+    SafetyZoneStatus, Severity, TimePing, TimePong, WireCodec, CANCEL_MIN_SCHEMA,
+    CANCEL_PARAM_COMMAND_ID, CORE_CONTROL_OP_CANCEL_COMMAND,
+    CORE_CONTROL_OP_CANCEL_DEVICE_COMMANDS, CORE_CONTROL_OP_PAUSE_DISPATCH,
+    CORE_CONTROL_OP_RELEASE_MAINTENANCE, CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY,
     CORE_CONTROL_OP_RELOAD_GRAPH, CORE_CONTROL_OP_RESET_SAFETY_LATCH,
-    CORE_CONTROL_OP_RESUME_DISPATCH, CORE_CONTROL_OP_START_GRAPH, CORE_CONTROL_OP_STOP_GRAPH,
-    SCHEMA_VERSION,
+    CORE_CONTROL_OP_RESUME_DISPATCH, CORE_CONTROL_OP_SET_MAINTENANCE, CORE_CONTROL_OP_START_GRAPH,
+    CORE_CONTROL_OP_STOP_GRAPH, DEFAULT_DEVICE_SCHEMA, MQTT_USER_PROPERTY_TRACE_ID,
+    SCHEDULE_MIN_SCHEMA, SCHEMA_VERSION, TIME_SYNC_MIN_SCHEMA, WATCHDOG_MIN_SCHEMA,
 };
 use serde::Deserialize;
 use tokio::{sync::mpsc, time::MissedTickBehavior};
@@ -26,6 +40,10 @@ struct Config {
     dry_run: bool,
     tick_ms: u64,
     device_offline_ms: u64,
+    /// `0` disables flapping detection.
+    device_flap_threshold: u32,
+    device_flap_window_ms: u64,
+    /// `DEVICE_HMAC_KEYS_JSON` only; use `RuntimeState::device_hmac_keys` (adds DB-provisioned keys).
     device_hmac_keys: std::collections::HashMap<String, Vec<u8>>,
     dev_test_command_device_id: Option<String>,
     dev_test_command_interval_ms: u64,
@@ -38,6 +56,32 @@ struct Config {
     graph_autostart: bool,
     db_enabled: bool,
     device_safety_class_json: Option<String>,
+    device_concurrency_json: Option<String>,
+    device_zone_json: Option<String>,
+    device_offline_policy_json: Option<String>,
+    device_liveness_json: Option<String>,
+    safety_escalation: SafetyEscalation,
+    device_concurrency_default: ConcurrencyPolicy,
+    device_queue_max_depth: usize,
+    schedule_lead_ms: u64,
+    schedule_max_ahead_ms: u64,
+    schedule_max_late_ms: u64,
+    time_sync_interval_ms: u64,
+    clock_offset_max_ms: u64,
+    watchdog_interval_ms: u64,
+    watchdog_timeout_ms: u64,
+    slo_accept_ms: u64,
+    slo_window_ms: u64,
+    slo_target_pct: f64,
+    slo_min_samples: u64,
+    metrics_bind: Option<std::net::SocketAddr>,
+    db_queue_capacity: usize,
+    db_batch_max: usize,
+    db_spill_path: Option<String>,
+    db_spill_max_bytes: u64,
+    db_pool_size: usize,
+    db_health_interval_ms: u64,
+    db_reconnect_max_ms: u64,
     core_control_token: Option<String>,
 }
 
@@ -71,6 +115,20 @@ impl Config {
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(3000);
+        // ONLINE/OFFLINE changes within the window that make a device "flapping".
+        let device_flap_threshold: u32 = std::env::var("DEVICE_FLAP_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(6);
+        let device_flap_window_ms: u64 = std::env::var("DEVICE_FLAP_WINDOW_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60_000);
+        if device_flap_threshold == 1 || (device_flap_threshold > 0 && device_flap_window_ms == 0) {
+            anyhow::bail!(
+                "DEVICE_FLAP_THRESHOLD must be 0 (off) or >= 2, with DEVICE_FLAP_WINDOW_MS > 0"
+            );
+        }
 
         let device_hmac_keys =
             load_device_hmac_keys_from_env().context("load DEVICE_HMAC_KEYS_JSON")?;
@@ -120,6 +178,149 @@ impl Config {
             .ok()
             .filter(|v| !v.trim().is_empty());
 
+        let device_concurrency_json = std::env::var("DEVICE_CONCURRENCY_JSON")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        // Safety zones: `{"device_id": "zone"}` (the DB `devices.zone` column wins), and which
+        // zone-level latches also latch the whole room.
+        let device_zone_json = std::env::var("DEVICE_ZONE_JSON")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        // `{"device_id": "LATCH_ROOM"}` (the DB `devices.offline_policy` column wins).
+        let device_offline_policy_json = std::env::var("DEVICE_OFFLINE_POLICY_JSON")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        // `{"device_id": {"heartbeat_interval_ms": 2000, "offline_ms": 6000}}` (DB columns win).
+        let device_liveness_json = std::env::var("DEVICE_LIVENESS_JSON")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        let safety_escalation = match std::env::var("CORE_SAFETY_ESCALATION") {
+            Ok(v) if !v.trim().is_empty() => SafetyEscalation::parse(&v).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "CORE_SAFETY_ESCALATION must be NONE, ESTOP or CRITICAL (got {v:?})"
+                )
+            })?,
+            _ => SafetyEscalation::EStop,
+        };
+        let device_concurrency_default = match std::env::var("CORE_DEVICE_CONCURRENCY_DEFAULT") {
+            Ok(v) if !v.trim().is_empty() => ConcurrencyPolicy::parse(&v).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "CORE_DEVICE_CONCURRENCY_DEFAULT must be PARALLEL, REJECT, QUEUE or SUPERSEDE (got {v:?})"
+                )
+            })?,
+            _ => ConcurrencyPolicy::Parallel,
+        };
+        let device_queue_max_depth = std::env::var("CORE_DEVICE_QUEUE_MAX_DEPTH")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(8);
+
+        // 0 = always hold scheduled dispatches in core until due, even for v8.4 devices.
+        let schedule_lead_ms = std::env::var("CORE_SCHEDULE_LEAD_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let schedule_max_ahead_ms = std::env::var("CORE_SCHEDULE_MAX_AHEAD_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3_600_000);
+        let schedule_max_late_ms = std::env::var("CORE_SCHEDULE_MAX_LATE_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(250);
+
+        // 0 disables ping/pong clock measurement (v8.5+ devices only).
+        let time_sync_interval_ms = std::env::var("CORE_TIME_SYNC_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5000);
+        let clock_offset_max_ms = std::env::var("CORE_CLOCK_OFFSET_MAX_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+
+        // Dead-man beacon to v8.6+ devices; 0 disables. Devices go safe after the timeout.
+        let watchdog_interval_ms: u64 = std::env::var("CORE_WATCHDOG_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1000);
+        let watchdog_timeout_ms = std::env::var("CORE_WATCHDOG_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(watchdog_interval_ms.saturating_mul(3));
+
+        // Publish -> ACCEPTED latency SLO (the sub-50ms orchestration target).
+        let slo_accept_ms = std::env::var("CORE_SLO_ACCEPT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(50);
+        let slo_window_ms = std::env::var("CORE_SLO_WINDOW_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(60_000);
+        let slo_target_pct = std::env::var("CORE_SLO_TARGET_PCT")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v > 0.0 && *v <= 100.0)
+            .unwrap_or(99.0);
+        let slo_min_samples = std::env::var("CORE_SLO_MIN_SAMPLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+
+        // Prometheus scrape endpoint; empty disables it.
+        let metrics_bind = match std::env::var("CORE_METRICS_BIND") {
+            Ok(v) if v.trim().is_empty() => None,
+            Ok(v) => Some(
+                v.trim()
+                    .parse()
+                    .with_context(|| format!("CORE_METRICS_BIND must be ip:port (got {v:?})"))?,
+            ),
+            Err(_) => Some(std::net::SocketAddr::from(([0, 0, 0, 0], 9101))),
+        };
+
+        // DbWriter: bounded queue, multi-row INSERT batches, and a local JSONL spill file for
+        // CRITICAL faults that cannot reach Postgres (empty path disables spilling).
+        let db_queue_capacity = std::env::var("CORE_DB_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(4096);
+        let db_batch_max = std::env::var("CORE_DB_BATCH_MAX")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(256)
+            .clamp(1, DB_BATCH_MAX_LIMIT);
+        let db_spill_path = match std::env::var("CORE_DB_SPILL_PATH") {
+            Ok(v) if v.trim().is_empty() => None,
+            Ok(v) => Some(v.trim().to_string()),
+            Err(_) => Some("/var/lib/sentient-core/db-spill.jsonl".to_string()),
+        };
+        let db_spill_max_bytes = std::env::var("CORE_DB_SPILL_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(64 * 1024 * 1024);
+        // Supervised pool: max connections, health ping interval while up, and the cap of the
+        // reconnect backoff while down.
+        let db_pool_size = std::env::var("CORE_DB_POOL_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(4);
+        let db_health_interval_ms = std::env::var("CORE_DB_HEALTH_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(5000);
+        let db_reconnect_max_ms = std::env::var("CORE_DB_RECONNECT_MAX_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30_000);
+
         let core_control_token = std::env::var("CORE_CONTROL_TOKEN")
             .ok()
             .filter(|v| !v.trim().is_empty());
@@ -135,6 +336,8 @@ impl Config {
             dry_run,
             tick_ms,
             device_offline_ms,
+            device_flap_threshold,
+            device_flap_window_ms,
             device_hmac_keys,
             dev_test_command_device_id,
             dev_test_command_interval_ms,
@@ -147,6 +350,32 @@ impl Config {
             graph_autostart,
             db_enabled,
             device_safety_class_json,
+            device_concurrency_json,
+            device_zone_json,
+            device_offline_policy_json,
+            device_liveness_json,
+            safety_escalation,
+            device_concurrency_default,
+            device_queue_max_depth,
+            schedule_lead_ms,
+            schedule_max_ahead_ms,
+            schedule_max_late_ms,
+            time_sync_interval_ms,
+            clock_offset_max_ms,
+            watchdog_interval_ms,
+            watchdog_timeout_ms,
+            slo_accept_ms,
+            slo_window_ms,
+            slo_target_pct,
+            slo_min_samples,
+            metrics_bind,
+            db_queue_capacity,
+            db_batch_max,
+            db_spill_path,
+            db_spill_max_bytes,
+            db_pool_size,
+            db_health_interval_ms,
+            db_reconnect_max_ms,
             core_control_token,
         })
     }
@@ -194,6 +423,18 @@ enum GraphNode {
         parameters: serde_json::Value,
         #[serde(default = "default_safety_class_non_critical")]
         safety_class: SafetyClass,
+        /// Run this long after the node is entered instead of immediately.
+        #[serde(default)]
+        execute_in_ms: Option<u64>,
+        /// Run at this room time (unix ms); wins over `execute_in_ms`.
+        #[serde(default)]
+        execute_at_unix_ms: Option<u64>,
+        #[serde(default)]
+        next: Option<NextRef>,
+    },
+    /// Several devices gated and published together (see `core/dispatch/batch`).
+    DispatchBatch {
+        commands: Vec<BatchCommand>,
         #[serde(default)]
         next: Option<NextRef>,
     },
@@ -224,6 +465,126 @@ struct Graph {
     room_id: String,
     start: StartRef,
     nodes: std::collections::HashMap<String, GraphNode>,
+    /// Server-side safety interlocks; versioned with the graph, enforced while it is loaded
+    /// (running or not) for every dispatch path.
+    #[serde(default)]
+    interlocks: Vec<InterlockRule>,
+}
+
+impl Graph {
+    fn validate_interlocks(&self) -> anyhow::Result<()> {
+        let mut ids = std::collections::HashSet::new();
+        for rule in &self.interlocks {
+            if rule.id.trim().is_empty() {
+                anyhow::bail!("interlock with empty id");
+            }
+            if !ids.insert(rule.id.as_str()) {
+                anyhow::bail!("duplicate interlock id {:?}", rule.id);
+            }
+            if rule.devices.is_empty() || rule.devices.iter().any(|d| d.trim().is_empty()) {
+                anyhow::bail!(
+                    "interlock {:?}: devices must be non-empty ids/patterns",
+                    rule.id
+                );
+            }
+            if rule.require.is_empty() {
+                anyhow::bail!("interlock {:?}: require is empty", rule.id);
+            }
+            for cond in &rule.require {
+                if cond.equals.is_some() == cond.not_equals.is_some() {
+                    anyhow::bail!(
+                        "interlock {:?}: condition on {:?} needs exactly one of equals / not_equals",
+                        rule.id,
+                        cond.device_id
+                    );
+                }
+                if !cond.pointer.is_empty() && !cond.pointer.starts_with('/') {
+                    anyhow::bail!(
+                        "interlock {:?}: pointer {:?} is not a JSON pointer",
+                        rule.id,
+                        cond.pointer
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// "Never `actions` on `devices` unless every `require` condition holds."
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+struct InterlockRule {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Guarded device ids; a trailing `*` matches by prefix (`"motor_*"`, or `"*"` for all).
+    devices: Vec<String>,
+    /// Guarded actions; empty guards every action.
+    #[serde(default)]
+    actions: Vec<CommandAction>,
+    require: Vec<InterlockCondition>,
+}
+
+/// Check against another device's last retained `DeviceState.state` (same JSON pointer rules as
+/// `WAIT_STATE_EQUALS`). Exactly one of `equals` / `not_equals`.
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+struct InterlockCondition {
+    device_id: String,
+    pointer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    equals: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_equals: Option<serde_json::Value>,
+}
+
+impl InterlockRule {
+    fn guards(&self, device_id: &str, action: CommandAction) -> bool {
+        let device_matches = self
+            .devices
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => device_id.starts_with(prefix),
+                None => pattern == device_id,
+            });
+        device_matches && (self.actions.is_empty() || self.actions.contains(&action))
+    }
+}
+
+impl InterlockCondition {
+    /// `None` when the condition holds, else why not. Unknown state (offline, never reported,
+    /// pointer missing) fails: an interlock must be proven, not assumed.
+    fn failure(
+        &self,
+        devices: &std::collections::HashMap<String, DeviceStatus>,
+    ) -> Option<(&'static str, Option<serde_json::Value>)> {
+        let Some(status) = devices.get(&self.device_id) else {
+            return Some(("NO_STATE", None));
+        };
+        if status.is_offline {
+            return Some(("DEVICE_OFFLINE", None));
+        }
+        let Some(actual) = status
+            .last_state
+            .as_ref()
+            .and_then(|st| st.pointer(&self.pointer))
+        else {
+            return Some(("NO_STATE", None));
+        };
+        let holds = match (&self.equals, &self.not_equals) {
+            (Some(expected), _) => actual == expected,
+            (None, Some(unexpected)) => actual != unexpected,
+            (None, None) => false,
+        };
+        (!holds).then(|| ("MISMATCH", Some(actual.clone())))
+    }
+}
+
+/// A dispatch refused by an interlock: the first guarding rule with a failing condition.
+struct InterlockViolation<'a> {
+    rule: &'a InterlockRule,
+    condition: &'a InterlockCondition,
+    reason: &'static str,
+    actual: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Default)]
@@ -231,6 +592,11 @@ struct ActiveNodeState {
     node_id: String,
     entered_at: Option<Instant>,
     waiting_on_command_id: Option<Uuid>,
+    /// DISPATCH held in a device queue or until its execute time; becomes
+    /// `waiting_on_command_id` once published.
+    waiting_on_queued_correlation_id: Option<Uuid>,
+    /// DISPATCH_BATCH still running (until every member has finished).
+    waiting_on_batch_correlation_id: Option<Uuid>,
     next_after_wait: Option<NextRef>,
 }
 
@@ -250,6 +616,8 @@ impl GraphRunner {
         let raw = std::fs::read_to_string(path)
             .with_context(|| format!("read CORE_GRAPH_PATH={}", path))?;
         let g: Graph = serde_json::from_str(&raw).context("parse graph json")?;
+        g.validate_interlocks()
+            .context("invalid graph interlocks")?;
         Ok(g)
     }
 
@@ -276,16 +644,10 @@ impl GraphRunner {
 }
 
 async fn load_active_graph_from_db(
-    database_url: &str,
+    pool: &DbPool,
     room_id: &str,
 ) -> anyhow::Result<Option<(Graph, i64)>> {
-    let (client, connection) = tokio_postgres::connect(database_url, tokio_postgres::NoTls).await?;
-    tokio::spawn(async move {
-        if let Err(err) = connection.await {
-            warn!(error=%err, "postgres connection error (graph loader)");
-        }
-    });
-
+    let client = pool.get().await?;
     let row = client
         .query_opt(
             "SELECT g.graph, g.version \
@@ -294,7 +656,9 @@ async fn load_active_graph_from_db(
              WHERE ga.room_id = $1",
             &[&room_id],
         )
-        .await?;
+        .await;
+    pool.note_result(&row);
+    let row = row?;
 
     let Some(row) = row else {
         return Ok(None);
@@ -302,9 +666,144 @@ async fn load_active_graph_from_db(
     let graph_json: serde_json::Value = row.get(0);
     let version: i64 = row.get(1);
     let g: Graph = serde_json::from_value(graph_json).context("parse graph json from DB")?;
+    g.validate_interlocks()
+        .context("invalid graph interlocks (DB)")?;
     Ok(Some((g, version)))
 }
 
+static DISPATCHES: CounterVec = CounterVec::new(
+    "sentient_core_dispatches_total",
+    "Dispatch attempts by outcome (queued/scheduled requests count again when released).",
+    &["outcome"],
+);
+static FAULTS: CounterVec = CounterVec::new(
+    "sentient_core_faults_total",
+    "Faults raised by core (room and device topics) by kind and severity.",
+    &["kind", "severity"],
+);
+static PENDING_COMMANDS: Gauge = Gauge::new(
+    "sentient_core_pending_commands",
+    "Commands published and not yet finished (incl. CANCEL envelopes).",
+);
+static DEVICES: Gauge = Gauge::new("sentient_core_devices", "Devices known to core.");
+static OFFLINE_DEVICES: Gauge = Gauge::new(
+    "sentient_core_offline_devices",
+    "Devices currently offline.",
+);
+static DISPATCH_PAUSED: Gauge = Gauge::new(
+    "sentient_core_dispatch_paused",
+    "1 while room dispatch is paused (manual, broker outage or safety latch).",
+);
+static MQTT_RECONNECTS: Counter = Counter::new(
+    "sentient_core_mqtt_reconnects_total",
+    "Broker reconnections after an outage.",
+);
+static DB_QUEUE_DEPTH: Gauge = Gauge::new(
+    "sentient_core_db_queue_depth",
+    "Events waiting in the DbWriter channel.",
+);
+static DB_EVENTS_DROPPED: CounterVec = CounterVec::new(
+    "sentient_core_db_events_dropped_total",
+    "Events lost before reaching Postgres, by reason (queue_full/rejected/spill_failed/spill_corrupt).",
+    &["reason"],
+);
+static DB_INSERT_FAILURES: Counter = Counter::new(
+    "sentient_core_db_insert_failures_total",
+    "Event batch INSERTs that failed (each retry counts).",
+);
+static DB_EVENTS_SPILLED: Counter = Counter::new(
+    "sentient_core_db_events_spilled_total",
+    "CRITICAL fault events written to the local spill file.",
+);
+static DB_EVENTS_REPLAYED: Counter = Counter::new(
+    "sentient_core_db_events_replayed_total",
+    "Spilled events inserted into Postgres after it came back.",
+);
+static DB_UP: Gauge = Gauge::new(
+    "sentient_core_db_up",
+    "1 while core's Postgres pool is healthy (last connect/ping/insert succeeded).",
+);
+static DB_POOL_OPEN: Gauge = Gauge::new(
+    "sentient_core_db_pool_connections",
+    "Open Postgres connections in core's pool.",
+);
+static DB_SPILL_PENDING: Gauge = Gauge::new(
+    "sentient_core_db_spill_pending",
+    "Events in the spill file waiting for replay.",
+);
+/// Latency histograms rendered by the main loop (they live in `RuntimeState`).
+static LATENCY_EXPOSITION: std::sync::Mutex<String> = std::sync::Mutex::new(String::new());
+
+fn render_metrics() -> String {
+    let mut out = Exposition::new();
+    out.metric(&DISPATCHES)
+        .metric(&FAULTS)
+        .metric(&PENDING_COMMANDS)
+        .metric(&DEVICES)
+        .metric(&OFFLINE_DEVICES)
+        .metric(&DISPATCH_PAUSED)
+        .metric(&MQTT_RECONNECTS)
+        .metric(&DB_QUEUE_DEPTH)
+        .metric(&DB_EVENTS_DROPPED)
+        .metric(&DB_INSERT_FAILURES)
+        .metric(&DB_EVENTS_SPILLED)
+        .metric(&DB_EVENTS_REPLAYED)
+        .metric(&DB_SPILL_PENDING)
+        .metric(&DB_UP)
+        .metric(&DB_POOL_OPEN);
+    let mut text = out.finish();
+    text.push_str(&LATENCY_EXPOSITION.lock().unwrap_or_else(|e| e.into_inner()));
+    text
+}
+
+async fn serve_metrics(bind: std::net::SocketAddr) {
+    let app = axum::Router::new().route(
+        "/metrics",
+        axum::routing::get(|| async {
+            (
+                [(
+                    axum::http::header::CONTENT_TYPE,
+                    sentient_metrics::CONTENT_TYPE,
+                )],
+                render_metrics(),
+            )
+        }),
+    );
+    let listener = match tokio::net::TcpListener::bind(bind).await {
+        Ok(l) => l,
+        Err(err) => {
+            warn!(error=%err, bind=%bind, "metrics endpoint disabled (bind failed)");
+            return;
+        }
+    };
+    info!(bind=%bind, "metrics listening");
+    if let Err(err) = axum::serve(listener, app).await {
+        warn!(error=%err, "metrics endpoint stopped");
+    }
+}
+
+/// Refreshes the gauges and latency histograms scraped from `/metrics`.
+fn update_metrics(
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    pending: &std::collections::HashMap<Uuid, PendingCommand>,
+) {
+    PENDING_COMMANDS.set(pending.len() as i64);
+    DEVICES.set(devices.len() as i64);
+    OFFLINE_DEVICES.set(devices.values().filter(|d| d.is_offline).count() as i64);
+    DISPATCH_PAUSED.set(runtime.dispatch_is_paused() as i64);
+    if let Some(db) = db {
+        DB_QUEUE_DEPTH.set(db.queue_depth() as i64);
+        DB_SPILL_PENDING.set(db.spill_pending() as i64);
+        DB_UP.set((db.pool.health().up == Some(true)) as i64);
+        DB_POOL_OPEN.set(db.pool.open_count() as i64);
+        db.report_drops();
+    }
+    let text = runtime.latency.exposition();
+    *LATENCY_EXPOSITION.lock().unwrap_or_else(|e| e.into_inner()) = text;
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -317,12 +816,19 @@ async fn main() -> anyhow::Result<()> {
     if config.tick_ms == 0 {
         anyhow::bail!("TICK_MS must be >= 1");
     }
+    if config.watchdog_interval_ms > 0 && config.watchdog_timeout_ms <= config.watchdog_interval_ms
+    {
+        anyhow::bail!("CORE_WATCHDOG_TIMEOUT_MS must be greater than CORE_WATCHDOG_INTERVAL_MS");
+    }
+    if let Some(bind) = config.metrics_bind {
+        tokio::spawn(serve_metrics(bind));
+    }
 
     let mut graph_runner = GraphRunner::default();
     if let Some(path) = config.graph_path.as_deref() {
         match GraphRunner::load_from_path(path) {
             Ok(g) => {
-                if g.schema != SCHEMA_VERSION || g.room_id != config.room_id {
+                if !is_accepted_schema(&g.schema) || g.room_id != config.room_id {
                     warn!(
                         graph_schema=%g.schema,
                         graph_room_id=%g.room_id,
@@ -338,10 +844,22 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    if graph_runner.graph.is_none() && config.db_enabled {
-        match load_active_graph_from_db(&config.database_url, &config.room_id).await {
+    // One pool for the event writer and the graph/registry loaders; created even if Postgres is
+    // down at startup (it reconnects in the background).
+    let db_pool = config.db_enabled.then(|| {
+        let pool = DbPool::new(&config);
+        tokio::spawn(supervise_db(
+            pool.clone(),
+            Duration::from_millis(config.db_health_interval_ms),
+            Duration::from_millis(config.db_reconnect_max_ms),
+        ));
+        pool
+    });
+
+    if let Some(pool) = db_pool.as_ref().filter(|_| graph_runner.graph.is_none()) {
+        match load_active_graph_from_db(pool, &config.room_id).await {
             Ok(Some((g, version))) => {
-                if g.schema != SCHEMA_VERSION || g.room_id != config.room_id {
+                if !is_accepted_schema(&g.schema) || g.room_id != config.room_id {
                     warn!(
                         graph_schema=%g.schema,
                         graph_room_id=%g.room_id,
@@ -366,23 +884,22 @@ async fn main() -> anyhow::Result<()> {
     let mut mqtt = connect_mqtt(&config).await?;
     subscribe_default_topics(&mqtt.client, &config.room_id).await?;
 
-    let db = if config.db_enabled {
-        match DbWriter::connect(&config.database_url).await {
-            Ok(w) => {
-                info!("db writer connected");
-                Some(w)
-            }
-            Err(err) => {
-                warn!(error=%err, "db writer disabled (connect failed)");
-                None
-            }
-        }
-    } else {
-        None
-    };
+    let db = db_pool.map(|pool| DbWriter::start(&config, pool));
 
     let mut runtime = RuntimeState::default();
     load_device_registry(&config, db.as_ref(), &mut runtime).await;
+    runtime.set_interlocks(&graph_runner);
+    if let Some(db) = db.as_ref() {
+        match load_maintenance_from_db(&db.pool).await {
+            Ok(locks) => {
+                if !locks.is_empty() {
+                    info!(count = locks.len(), "maintenance locks restored");
+                }
+                runtime.maintenance = locks;
+            }
+            Err(err) => warn!(error=%err, "failed to load maintenance locks from DB"),
+        }
+    }
     runtime.room_safety = SafetyState {
         kind: SafetyStateKind::Safe,
         reason_code: None,
@@ -399,6 +916,8 @@ async fn main() -> anyhow::Result<()> {
     let mut devices: std::collections::HashMap<String, DeviceStatus> =
         std::collections::HashMap::new();
     let mut last_device_sweep = Instant::now();
+    let mut last_time_sync = Instant::now();
+    let mut last_watchdog = Instant::now();
     let mut last_dev_test_cmd = Instant::now();
     let mut device_sequences: std::collections::HashMap<String, u64> =
         std::collections::HashMap::new();
@@ -410,6 +929,19 @@ async fn main() -> anyhow::Result<()> {
         tokio::select! {
             _ = tick.tick() => {
                 ticks = ticks.wrapping_add(1);
+                runtime.latency.observe_tick(&config);
+
+                // First, so scheduled dispatches go out on the tick they are due.
+                release_scheduled_dispatches(
+                    &config,
+                    &mqtt.client,
+                    &runtime,
+                    db.as_ref(),
+                    &devices,
+                    &mut device_sequences,
+                    &mut pending,
+                    &mut dispatch_tracker,
+                ).await;
 
                 graph_runner.maybe_autostart(&config);
                 tick_graph_runner(
@@ -435,9 +967,66 @@ async fn main() -> anyhow::Result<()> {
                 ).await;
 
                 tick_pending_commands(&config, &mqtt.client, &runtime, db.as_ref(), &mut pending, &mut dispatch_tracker).await;
+                finish_maintenance_writes(&config, &mqtt.client, &mut runtime, db.as_ref(), &mut devices).await;
+                finish_device_registry_reload(&config, &mqtt.client, &mut runtime).await;
+                tick_dispatch_batches(&config, &mqtt.client, db.as_ref(), &mut dispatch_tracker).await;
+                drain_dispatch_queues(
+                    &config,
+                    &mqtt.client,
+                    &runtime,
+                    db.as_ref(),
+                    &devices,
+                    &mut device_sequences,
+                    &mut pending,
+                    &mut dispatch_tracker,
+                ).await;
+
+                if config.time_sync_interval_ms > 0
+                    && last_time_sync.elapsed() >= Duration::from_millis(config.time_sync_interval_ms)
+                {
+                    publish_time_pings(&config, &mqtt.client, &mut devices).await;
+                    last_time_sync = Instant::now();
+                }
+
+                if config.watchdog_interval_ms > 0
+                    && last_watchdog.elapsed() >= Duration::from_millis(config.watchdog_interval_ms)
+                {
+                    publish_watchdogs(&config, &mqtt.client, &mut runtime, &devices).await;
+                    last_watchdog = Instant::now();
+                }
 
                 if last_device_sweep.elapsed() >= Duration::from_millis(500) {
-                    sweep_device_offline(&config, &mqtt.client, db.as_ref(), &mut devices).await;
+                    let went_offline =
+                        sweep_device_offline(&config, &mqtt.client, &runtime, db.as_ref(), &mut devices).await;
+                    let mut latched_now: Vec<SafetyLatchScope> = Vec::new();
+                    for device_id in &went_offline {
+                        latched_now.extend(
+                            apply_offline_policy(
+                                &config,
+                                &mqtt.client,
+                                &mut runtime,
+                                db.as_ref(),
+                                device_id,
+                                unix_ms_now(),
+                            )
+                            .await,
+                        );
+                    }
+                    if !latched_now.is_empty() {
+                        abort_for_safety_latches(
+                            &config,
+                            &mqtt.client,
+                            &runtime,
+                            db.as_ref(),
+                            &graph_runner,
+                            &devices,
+                            &mut device_sequences,
+                            &mut pending,
+                            &mut dispatch_tracker,
+                            latched_now,
+                        )
+                        .await;
+                    }
                     last_device_sweep = Instant::now();
                 }
 
@@ -445,9 +1034,11 @@ async fn main() -> anyhow::Result<()> {
                     let uptime = start.elapsed();
                     runtime.room_safety = compute_room_safety(
                         &devices,
+                        &runtime.device_registry,
                         runtime.safety_latched_since_unix_ms.is_some(),
                     );
-                    publish_core_heartbeat(&mqtt, &config.room_id, uptime, &runtime.room_safety).await;
+                    publish_core_heartbeat(&mqtt, &config, uptime, &runtime).await;
+                    publish_core_metrics(&config, &mqtt.client, &runtime).await;
                     info!(
                         room_id = %config.room_id,
                         ticks,
@@ -464,9 +1055,14 @@ async fn main() -> anyhow::Result<()> {
                     let uptime = start.elapsed();
                     runtime.room_safety = compute_room_safety(
                         &devices,
+                        &runtime.device_registry,
                         runtime.safety_latched_since_unix_ms.is_some(),
                     );
-                    publish_core_status(&mqtt.client, &config, &runtime, uptime, &graph_runner, &devices).await;
+                    evaluate_latency_slo(&config, &mqtt.client, db.as_ref(), &mut runtime).await;
+                    evaluate_db_health(&config, &mqtt.client, db.as_ref(), &mut runtime).await;
+                    publish_core_status(&mqtt.client, &config, &runtime, uptime, &graph_runner, &devices, db.as_ref()).await;
+                    runtime.latency.tick_jitter_recent = Histogram::new(TICK_JITTER_BUCKETS_US);
+                    update_metrics(&runtime, db.as_ref(), &devices, &pending);
                     last_status = Instant::now();
                 }
             }
@@ -498,7 +1094,7 @@ async fn main() -> anyhow::Result<()> {
 }
 
 struct MqttHandle {
-    client: rumqttc::AsyncClient,
+    client: AsyncClient,
     events: mpsc::Receiver<MqttEvent>,
 }
 
@@ -506,11 +1102,37 @@ struct MqttHandle {
 enum MqttEvent {
     Connected,
     Disconnected(String),
-    Publish(rumqttc::Publish),
+    Publish(InboundPublish),
+}
+
+/// An incoming MQTT v5 publish with its topic decoded (the wire carries it as bytes).
+#[derive(Debug)]
+struct InboundPublish {
+    topic: String,
+    payload: bytes::Bytes,
+    properties: Option<PublishProperties>,
+}
+
+impl InboundPublish {
+    /// `None` for a topic that is not UTF-8 (a protocol violation; the broker should not send it).
+    fn from_packet(p: Publish) -> Option<Self> {
+        Some(Self {
+            topic: String::from_utf8(p.topic.to_vec()).ok()?,
+            payload: p.payload,
+            properties: p.properties,
+        })
+    }
+
+    /// The publisher's trace id user property, if any.
+    fn trace_id(&self) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .and_then(|p| trace_id_from_user_properties(&p.user_properties))
+    }
 }
 
 async fn connect_mqtt(config: &Config) -> anyhow::Result<MqttHandle> {
-    let mut mqtt_config = rumqttc::MqttOptions::new(
+    let mut mqtt_config = MqttOptions::new(
         config.mqtt_client_id.clone(),
         config.mqtt_host.clone(),
         config.mqtt_port,
@@ -520,19 +1142,23 @@ async fn connect_mqtt(config: &Config) -> anyhow::Result<MqttHandle> {
         mqtt_config.set_credentials(user, pass);
     }
 
-    let (client, mut eventloop) = rumqttc::AsyncClient::new(mqtt_config, 200);
+    let (client, mut eventloop) = AsyncClient::new(mqtt_config, 200);
     let (tx, rx) = mpsc::channel::<MqttEvent>(1024);
 
     tokio::spawn(async move {
         let mut is_connected = false;
         loop {
             match eventloop.poll().await {
-                Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(p))) => {
+                Ok(Event::Incoming(Packet::Publish(p))) => {
+                    let Some(p) = InboundPublish::from_packet(p) else {
+                        warn!("dropping MQTT publish with a non-UTF-8 topic");
+                        continue;
+                    };
                     if tx.send(MqttEvent::Publish(p)).await.is_err() {
                         break;
                     }
                 }
-                Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
                     if !is_connected {
                         is_connected = true;
                         if tx.send(MqttEvent::Connected).await.is_err() {
@@ -540,7 +1166,7 @@ async fn connect_mqtt(config: &Config) -> anyhow::Result<MqttHandle> {
                         }
                     }
                 }
-                Ok(rumqttc::Event::Incoming(rumqttc::Packet::Disconnect)) => {
+                Ok(Event::Incoming(Packet::Disconnect(_))) => {
                     if is_connected {
                         is_connected = false;
                         if tx
@@ -576,6 +1202,36 @@ struct RuntimeState {
     room_safety: SafetyState,
     manual_pause: bool,
     safety_latched_since_unix_ms: Option<u64>,
+    latency: LatencyMetrics,
+    /// Set while a `DB_OUTAGE` is open (value: when the pool went down).
+    db_outage_since_unix_ms: Option<u64>,
+    /// Latched safety zones; unlike the room latch they only block their own devices.
+    zone_latches: std::collections::BTreeMap<String, ZoneLatch>,
+    /// Interlocks of the loaded graph (see [`RuntimeState::set_interlocks`]).
+    interlocks: Vec<InterlockRule>,
+    interlocks_graph_version: Option<i64>,
+    /// Maintenance locks (lockout/tagout) by device / zone; persisted in `maintenance_locks`.
+    maintenance: std::collections::BTreeMap<(MaintenanceScope, String), MaintenanceStatus>,
+    /// Accepted maintenance ops whose DB write is still running.
+    maintenance_writes: Vec<MaintenanceWrite>,
+    /// `RELOAD_DEVICE_REGISTRY` still loading from the DB.
+    device_registry_reload: Option<tokio::task::JoinHandle<LoadedDeviceRegistry>>,
+    /// Identifies this process in `CoreWatchdog` beacons so devices can tell a restart from a replay.
+    watchdog_boot_id: Uuid,
+    /// Last `CoreWatchdog.counter` sent (one value per round, shared by all devices).
+    watchdog_counter: u64,
+    /// `DEVICE_HMAC_KEYS_JSON` merged with keys provisioned in the DB (`device_keys`, which wins).
+    device_hmac_keys: std::collections::HashMap<String, Vec<u8>>,
+    /// Devices heard from but missing from the registry -> when their inbox row was last written.
+    unregistered_devices: std::collections::HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone)]
+struct ZoneLatch {
+    since_unix_ms: u64,
+    /// Device whose safety report latched the zone.
+    device_id: String,
+    safety_kind: SafetyStateKind,
 }
 
 impl Default for RuntimeState {
@@ -591,6 +1247,18 @@ impl Default for RuntimeState {
             },
             manual_pause: false,
             safety_latched_since_unix_ms: None,
+            latency: LatencyMetrics::default(),
+            db_outage_since_unix_ms: None,
+            zone_latches: std::collections::BTreeMap::new(),
+            interlocks: Vec::new(),
+            interlocks_graph_version: None,
+            maintenance: std::collections::BTreeMap::new(),
+            maintenance_writes: Vec::new(),
+            device_registry_reload: None,
+            watchdog_boot_id: Uuid::new_v4(),
+            watchdog_counter: 0,
+            device_hmac_keys: std::collections::HashMap::new(),
+            unregistered_devices: std::collections::HashMap::new(),
         }
     }
 }
@@ -600,6 +1268,76 @@ impl RuntimeState {
         self.dispatch_paused_reason.is_some()
     }
 
+    /// The latch on `device_id`'s safety zone, if the zone is latched.
+    fn zone_latch_for(&self, device_id: &str) -> Option<(&str, &ZoneLatch)> {
+        let zone = self.device_registry.get(device_id)?.zone.as_deref()?;
+        self.zone_latches
+            .get_key_value(zone)
+            .map(|(z, l)| (z.as_str(), l))
+    }
+
+    /// Takes the interlocks of the graph now loaded in `runner` (none without a graph).
+    fn set_interlocks(&mut self, runner: &GraphRunner) {
+        self.interlocks = runner
+            .graph
+            .as_ref()
+            .map(|g| g.interlocks.clone())
+            .unwrap_or_default();
+        self.interlocks_graph_version = runner.graph_version;
+        if !self.interlocks.is_empty() {
+            info!(
+                rules = self.interlocks.len(),
+                graph_version = ?self.interlocks_graph_version,
+                "interlocks loaded"
+            );
+        }
+    }
+
+    fn interlock_violation(
+        &self,
+        device_id: &str,
+        action: CommandAction,
+        devices: &std::collections::HashMap<String, DeviceStatus>,
+    ) -> Option<InterlockViolation<'_>> {
+        self.interlocks
+            .iter()
+            .filter(|rule| rule.guards(device_id, action))
+            .find_map(|rule| {
+                rule.require.iter().find_map(|condition| {
+                    condition
+                        .failure(devices)
+                        .map(|(reason, actual)| InterlockViolation {
+                            rule,
+                            condition,
+                            reason,
+                            actual,
+                        })
+                })
+            })
+    }
+
+    /// Maintenance lock covering `device_id`: its own, else its zone's.
+    fn maintenance_for(&self, device_id: &str) -> Option<&MaintenanceStatus> {
+        self.maintenance
+            .get(&(MaintenanceScope::Device, device_id.to_string()))
+            .or_else(|| {
+                let zone = self.device_registry.get(device_id)?.zone.clone()?;
+                self.maintenance.get(&(MaintenanceScope::Zone, zone))
+            })
+    }
+
+    /// Registry devices assigned to `zone`, sorted.
+    fn zone_device_ids(&self, zone: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .device_registry
+            .iter()
+            .filter(|(_, r)| r.zone.as_deref() == Some(zone))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
     fn recompute_dispatch_pause_reason(&mut self) {
         self.dispatch_paused_reason = if self.broker_outage_since_unix_ms.is_some() {
             Some("BROKER_DOWN".to_string())
@@ -613,1122 +1351,4143 @@ impl RuntimeState {
     }
 }
 
-async fn load_device_registry(config: &Config, db: Option<&DbWriter>, runtime: &mut RuntimeState) {
-    let mut merged: std::collections::HashMap<String, DeviceRegistryEntry> =
-        std::collections::HashMap::new();
+/// Upper bounds (ms) of the dispatch latency buckets; an implicit `+Inf` bucket follows.
+const LATENCY_BUCKETS_MS: &[u64] = &[1, 2, 5, 10, 20, 35, 50, 75, 100, 250, 500, 1000, 2500, 5000];
+/// Upper bounds (us) of the tick jitter buckets.
+const TICK_JITTER_BUCKETS_US: &[u64] =
+    &[50, 100, 250, 500, 1000, 2500, 5000, 10_000, 50_000, 250_000];
+/// Upper bound on accept samples kept for the rolling SLO window.
+const SLO_WINDOW_MAX_SAMPLES: usize = 20_000;
 
-    if let Some(raw) = config.device_safety_class_json.as_deref() {
-        match serde_json::from_str::<std::collections::HashMap<String, String>>(raw) {
-            Ok(map) => {
-                for (device_id, cls) in map {
-                    let cls = match cls.as_str() {
-                        "CRITICAL" => SafetyClass::Critical,
-                        "NON_CRITICAL" => SafetyClass::NonCritical,
-                        other => {
-                            warn!(device_id=%device_id, class=%other, "invalid DEVICE_SAFETY_CLASS_JSON value");
-                            continue;
-                        }
-                    };
-                    merged.insert(
-                        device_id,
-                        DeviceRegistryEntry {
-                            safety_class: cls,
-                            enabled: true,
-                        },
-                    );
-                }
-            }
-            Err(err) => warn!(error=%err, "failed to parse DEVICE_SAFETY_CLASS_JSON"),
+/// Fixed-bucket histogram (Prometheus-style cumulative buckets when exported).
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [u64],
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [u64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            count: 0,
+            sum: 0,
+            max: 0,
         }
     }
 
-    if db.is_some() {
-        match load_device_registry_from_db(&config.database_url).await {
-            Ok(from_db) => {
-                // DB overrides env, since it's the intended source of truth.
-                merged.extend(from_db);
+    fn observe(&mut self, value: u64) {
+        let idx = self
+            .bounds
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(self.bounds.len());
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Upper bound of the bucket holding quantile `q` (the max for the `+Inf` bucket).
+    fn quantile_bound(&self, q: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (idx, c) in self.counts.iter().enumerate() {
+            seen += c;
+            if seen >= rank {
+                return Some(
+                    self.bounds
+                        .get(idx)
+                        .copied()
+                        .unwrap_or(self.max)
+                        .min(self.max),
+                );
             }
-            Err(err) => warn!(error=%err, "failed to load device registry from DB"),
         }
+        Some(self.max)
     }
 
-    runtime.device_registry = merged;
-    info!(
-        device_count = runtime.device_registry.len(),
-        "device registry loaded"
-    );
+    fn to_json(&self) -> serde_json::Value {
+        let mut cumulative = 0;
+        let mut buckets: Vec<serde_json::Value> = self
+            .bounds
+            .iter()
+            .zip(&self.counts)
+            .map(|(le, c)| {
+                cumulative += c;
+                serde_json::json!({ "le": le, "count": cumulative })
+            })
+            .collect();
+        buckets.push(serde_json::json!({ "le": "+Inf", "count": self.count }));
+        serde_json::json!({
+            "count": self.count,
+            "sum": self.sum,
+            "max": self.max,
+            "buckets": buckets,
+        })
+    }
 }
 
-async fn load_device_registry_from_db(
-    database_url: &str,
-) -> anyhow::Result<std::collections::HashMap<String, DeviceRegistryEntry>> {
-    let (client, connection) = tokio_postgres::connect(database_url, tokio_postgres::NoTls)
-        .await
-        .context("connect postgres (registry)")?;
-    tokio::spawn(async move {
-        if let Err(err) = connection.await {
-            warn!(error=%err, "postgres connection error (registry)");
+/// Dispatch latency histograms per (device, action), tick-loop jitter and the rolling accept
+/// latency window behind the SLO fault.
+#[derive(Debug)]
+struct LatencyMetrics {
+    /// First publish -> `ACCEPTED`, ms.
+    accept: std::collections::BTreeMap<(String, &'static str), Histogram>,
+    /// First publish (or execute time, if later) -> `COMPLETED`, ms.
+    complete: std::collections::BTreeMap<(String, &'static str), Histogram>,
+    /// `|actual tick interval - tick_ms|`, us, since start.
+    tick_jitter: Histogram,
+    /// Same as `tick_jitter`, reset on every `core/status` publish.
+    tick_jitter_recent: Histogram,
+    last_tick_at: Option<Instant>,
+    accept_window: std::collections::VecDeque<(Instant, u64)>,
+    slo_breached: bool,
+}
+
+impl Default for LatencyMetrics {
+    fn default() -> Self {
+        Self {
+            accept: std::collections::BTreeMap::new(),
+            complete: std::collections::BTreeMap::new(),
+            tick_jitter: Histogram::new(TICK_JITTER_BUCKETS_US),
+            tick_jitter_recent: Histogram::new(TICK_JITTER_BUCKETS_US),
+            last_tick_at: None,
+            accept_window: std::collections::VecDeque::new(),
+            slo_breached: false,
         }
-    });
+    }
+}
 
-    let mut out: std::collections::HashMap<String, DeviceRegistryEntry> =
-        std::collections::HashMap::new();
-    let rows = client
-        .query("SELECT device_id, safety_class, enabled FROM devices", &[])
-        .await
-        .context("query devices")?;
-    for row in rows {
-        let device_id: String = row.get(0);
-        let safety_class: String = row.get(1);
-        let enabled: bool = row.get(2);
-        let cls = match safety_class.as_str() {
-            "CRITICAL" => SafetyClass::Critical,
-            "NON_CRITICAL" => SafetyClass::NonCritical,
-            other => {
-                warn!(device_id=%device_id, safety_class=%other, "unknown safety_class in DB");
-                continue;
+impl LatencyMetrics {
+    fn observe_accept(&mut self, device_id: &str, action: CommandAction, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        self.accept
+            .entry((device_id.to_string(), action.as_str()))
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS_MS))
+            .observe(ms);
+        if self.accept_window.len() >= SLO_WINDOW_MAX_SAMPLES {
+            self.accept_window.pop_front();
+        }
+        self.accept_window.push_back((Instant::now(), ms));
+    }
+
+    fn observe_complete(&mut self, device_id: &str, action: CommandAction, latency: Duration) {
+        self.complete
+            .entry((device_id.to_string(), action.as_str()))
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS_MS))
+            .observe(latency.as_millis() as u64);
+    }
+
+    fn observe_tick(&mut self, config: &Config) {
+        let now = Instant::now();
+        if let Some(prev) = self.last_tick_at.replace(now) {
+            let actual_us = now.duration_since(prev).as_micros() as u64;
+            let jitter_us = actual_us.abs_diff(config.tick_ms.saturating_mul(1000));
+            self.tick_jitter.observe(jitter_us);
+            self.tick_jitter_recent.observe(jitter_us);
+        }
+    }
+
+    fn prune_window(&mut self, config: &Config) {
+        let window = Duration::from_millis(config.slo_window_ms);
+        while self
+            .accept_window
+            .front()
+            .is_some_and(|(at, _)| at.elapsed() > window)
+        {
+            self.accept_window.pop_front();
+        }
+    }
+
+    /// Re-evaluates the SLO over the pruned window. Returns the summary it decided on when
+    /// `slo_breached` flipped; nothing while the window holds fewer than `CORE_SLO_MIN_SAMPLES`.
+    fn update_slo(&mut self, config: &Config) -> Option<sentient_protocol::DispatchLatencySummary> {
+        self.prune_window(config);
+        let summary = self.summary(config);
+        if summary.window_samples < config.slo_min_samples.max(1) {
+            return None;
+        }
+        let breached = summary.window_within_target_pct < config.slo_target_pct;
+        if breached == self.slo_breached {
+            return None;
+        }
+        self.slo_breached = breached;
+        Some(summary)
+    }
+
+    /// Summary for `core/status` (callers prune the window first).
+    fn summary(&self, config: &Config) -> sentient_protocol::DispatchLatencySummary {
+        let mut window: Vec<u64> = self.accept_window.iter().map(|(_, ms)| *ms).collect();
+        window.sort_unstable();
+        let percentile = |q: f64| -> Option<u64> {
+            if window.is_empty() {
+                return None;
             }
+            let rank = ((window.len() as f64) * q).ceil().max(1.0) as usize;
+            window.get(rank - 1).copied()
         };
-        out.insert(
-            device_id,
-            DeviceRegistryEntry {
-                safety_class: cls,
-                enabled,
-            },
+        let within = window
+            .iter()
+            .filter(|ms| **ms <= config.slo_accept_ms)
+            .count();
+        let window_within_target_pct = if window.is_empty() {
+            100.0
+        } else {
+            (within as f64) * 100.0 / (window.len() as f64)
+        };
+        sentient_protocol::DispatchLatencySummary {
+            slo_target_ms: config.slo_accept_ms,
+            slo_target_pct: config.slo_target_pct,
+            window_ms: config.slo_window_ms,
+            window_samples: window.len() as u64,
+            window_within_target_pct,
+            slo_breached: self.slo_breached,
+            accept_p50_ms: percentile(0.50),
+            accept_p95_ms: percentile(0.95),
+            accept_p99_ms: percentile(0.99),
+            accept_max_ms: window.last().copied(),
+            tick_jitter_p99_us: self.tick_jitter_recent.quantile_bound(0.99),
+            tick_jitter_max_us: (self.tick_jitter_recent.count > 0)
+                .then_some(self.tick_jitter_recent.max),
+        }
+    }
+
+    /// Worst (device, action) pairs by max accept latency, for the SLO fault details.
+    fn worst_accept(&self, limit: usize) -> Vec<serde_json::Value> {
+        let mut worst: Vec<(&(String, &'static str), &Histogram)> = self.accept.iter().collect();
+        worst.sort_by_key(|(_, h)| std::cmp::Reverse(h.max));
+        worst
+            .into_iter()
+            .take(limit)
+            .map(|((device_id, action), h)| {
+                serde_json::json!({
+                    "device_id": device_id,
+                    "action": action,
+                    "max_ms": h.max,
+                })
+            })
+            .collect()
+    }
+
+    /// Prometheus histograms (seconds) for `/metrics`.
+    fn exposition(&self) -> String {
+        let ms_bounds: Vec<f64> = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|b| *b as f64 / 1000.0)
+            .collect();
+        let mut out = Exposition::new();
+        for (name, help, map) in [
+            (
+                "sentient_core_dispatch_accept_seconds",
+                "First publish -> ACCEPTED latency by device and action.",
+                &self.accept,
+            ),
+            (
+                "sentient_core_dispatch_complete_seconds",
+                "First publish -> COMPLETED latency by device and action (scheduled wait excluded).",
+                &self.complete,
+            ),
+        ] {
+            out.family(name, "histogram", help);
+            for ((device_id, action), h) in map {
+                out.histogram(
+                    name,
+                    &[("device_id", device_id), ("action", action)],
+                    &ms_bounds,
+                    &h.counts,
+                    h.sum as f64 / 1000.0,
+                );
+            }
+        }
+        out.family(
+            "sentient_core_tick_jitter_seconds",
+            "histogram",
+            "Core loop lateness: |actual tick interval - tick_ms|.",
+        );
+        let us_bounds: Vec<f64> = TICK_JITTER_BUCKETS_US
+            .iter()
+            .map(|b| *b as f64 / 1_000_000.0)
+            .collect();
+        out.histogram(
+            "sentient_core_tick_jitter_seconds",
+            &[],
+            &us_bounds,
+            &self.tick_jitter.counts,
+            self.tick_jitter.sum as f64 / 1_000_000.0,
+        );
+        out.family(
+            "sentient_core_dispatch_latency_slo_breached",
+            "gauge",
+            "1 while DISPATCH_LATENCY_SLO_BREACHED is active.",
         );
+        out.sample(
+            "sentient_core_dispatch_latency_slo_breached",
+            &[],
+            self.slo_breached as u8,
+        );
+        out.finish()
+    }
+
+    fn snapshot_json(&self, config: &Config) -> serde_json::Value {
+        let series = |map: &std::collections::BTreeMap<(String, &'static str), Histogram>| {
+            map.iter()
+                .map(|((device_id, action), h)| {
+                    let mut v = h.to_json();
+                    v["device_id"] = serde_json::json!(device_id);
+                    v["action"] = serde_json::json!(action);
+                    v
+                })
+                .collect::<Vec<_>>()
+        };
+        serde_json::json!({
+            "schema": SCHEMA_VERSION,
+            "room_id": config.room_id,
+            "tick_ms": config.tick_ms,
+            "accept_ms": series(&self.accept),
+            "complete_ms": series(&self.complete),
+            "tick_jitter_us": self.tick_jitter.to_json(),
+            "slo": self.summary(config),
+            "computed_at_unix_ms": unix_ms_now(),
+        })
     }
-    Ok(out)
 }
 
-#[derive(Debug, Clone)]
-struct DeviceRegistryEntry {
-    safety_class: SafetyClass,
-    enabled: bool,
+/// Device registry and HMAC keys as loaded from env and the room DB.
+#[derive(Debug, Default)]
+struct LoadedDeviceRegistry {
+    devices: std::collections::HashMap<String, DeviceRegistryEntry>,
+    hmac_keys: std::collections::HashMap<String, Vec<u8>>,
 }
 
-fn safety_class_rank(s: SafetyClass) -> u8 {
-    match s {
-        SafetyClass::NonCritical => 0,
-        SafetyClass::Critical => 1,
+/// Startup load (before the scheduler loop; reloads go through [`start_device_registry_reload`]).
+async fn load_device_registry(config: &Config, db: Option<&DbWriter>, runtime: &mut RuntimeState) {
+    let mut loaded = device_registry_from_env(config);
+    if let Some(db) = db {
+        overlay_device_registry_from_db(&db.pool, &mut loaded).await;
     }
+    apply_device_registry(runtime, loaded);
 }
 
-fn effective_safety_class(requested: SafetyClass, registry: SafetyClass) -> SafetyClass {
-    if safety_class_rank(registry) > safety_class_rank(requested) {
-        registry
-    } else {
-        requested
+fn device_registry_from_env(config: &Config) -> LoadedDeviceRegistry {
+    let mut merged: std::collections::HashMap<String, DeviceRegistryEntry> =
+        std::collections::HashMap::new();
+
+    if let Some(raw) = config.device_safety_class_json.as_deref() {
+        match serde_json::from_str::<std::collections::HashMap<String, String>>(raw) {
+            Ok(map) => {
+                for (device_id, cls) in map {
+                    let cls = match cls.as_str() {
+                        "CRITICAL" => SafetyClass::Critical,
+                        "NON_CRITICAL" => SafetyClass::NonCritical,
+                        other => {
+                            warn!(device_id=%device_id, class=%other, "invalid DEVICE_SAFETY_CLASS_JSON value");
+                            continue;
+                        }
+                    };
+                    merged.entry(device_id).or_default().safety_class = cls;
+                }
+            }
+            Err(err) => warn!(error=%err, "failed to parse DEVICE_SAFETY_CLASS_JSON"),
+        }
     }
-}
 
-async fn publish_core_heartbeat(
-    mqtt: &MqttHandle,
-    room_id: &str,
-    uptime: Duration,
-    safety: &SafetyState,
-) {
-    let topic = format!("room/{}/core/heartbeat", room_id);
-    let msg = serde_json::json!({
-        "schema": SCHEMA_VERSION,
-        "room_id": room_id,
-        "uptime_ms": uptime.as_millis() as u64,
-        "observed_at_unix_ms": unix_ms_now(),
-        "safety_state": safety,
-    });
+    if let Some(raw) = config.device_concurrency_json.as_deref() {
+        match serde_json::from_str::<std::collections::HashMap<String, String>>(raw) {
+            Ok(map) => {
+                for (device_id, policy) in map {
+                    let Some(policy) = ConcurrencyPolicy::parse(&policy) else {
+                        warn!(device_id=%device_id, policy=%policy, "invalid DEVICE_CONCURRENCY_JSON value");
+                        continue;
+                    };
+                    merged.entry(device_id).or_default().concurrency = Some(policy);
+                }
+            }
+            Err(err) => warn!(error=%err, "failed to parse DEVICE_CONCURRENCY_JSON"),
+        }
+    }
 
-    match serde_json::to_vec(&msg) {
-        Ok(payload) => {
-            if let Err(err) = mqtt
-                .client
-                .publish(topic, rumqttc::QoS::AtMostOnce, false, payload)
-                .await
-            {
-                warn!(error = %err, "failed to publish core heartbeat");
+    if let Some(raw) = config.device_zone_json.as_deref() {
+        match serde_json::from_str::<std::collections::HashMap<String, String>>(raw) {
+            Ok(map) => {
+                for (device_id, zone) in map {
+                    let Some(zone) = normalize_zone(&zone) else {
+                        warn!(device_id=%device_id, "empty zone in DEVICE_ZONE_JSON");
+                        continue;
+                    };
+                    merged.entry(device_id).or_default().zone = Some(zone);
+                }
             }
+            Err(err) => warn!(error=%err, "failed to parse DEVICE_ZONE_JSON"),
         }
-        Err(err) => warn!(error = %err, "failed to serialize core heartbeat"),
+    }
+
+    if let Some(raw) = config.device_offline_policy_json.as_deref() {
+        match serde_json::from_str::<std::collections::HashMap<String, String>>(raw) {
+            Ok(map) => {
+                for (device_id, policy) in map {
+                    let Some(policy) = OfflinePolicy::parse(&policy) else {
+                        warn!(device_id=%device_id, policy=%policy, "invalid DEVICE_OFFLINE_POLICY_JSON value");
+                        continue;
+                    };
+                    merged.entry(device_id).or_default().offline_policy = policy;
+                }
+            }
+            Err(err) => warn!(error=%err, "failed to parse DEVICE_OFFLINE_POLICY_JSON"),
+        }
+    }
+
+    if let Some(raw) = config.device_liveness_json.as_deref() {
+        match serde_json::from_str::<std::collections::HashMap<String, DeviceLivenessJson>>(raw) {
+            Ok(map) => {
+                for (device_id, liveness) in map {
+                    let entry = merged.entry(device_id).or_default();
+                    entry.heartbeat_interval_ms = liveness.heartbeat_interval_ms.filter(|v| *v > 0);
+                    entry.offline_ms = liveness.offline_ms.filter(|v| *v > 0);
+                }
+            }
+            Err(err) => warn!(error=%err, "failed to parse DEVICE_LIVENESS_JSON"),
+        }
+    }
+
+    LoadedDeviceRegistry {
+        devices: merged,
+        hmac_keys: config.device_hmac_keys.clone(),
     }
 }
 
-fn compute_room_safety(
-    devices: &std::collections::HashMap<String, DeviceStatus>,
-    safety_latched: bool,
-) -> SafetyState {
-    let mut worst: SafetyStateKind = SafetyStateKind::Safe;
-    for d in devices.values() {
-        let Some(k) = d.last_reported_safety.as_ref().map(|s| s.kind) else {
-            continue;
-        };
-        worst = match (worst, k) {
-            (SafetyStateKind::EStop, _) => SafetyStateKind::EStop,
-            (_, SafetyStateKind::EStop) => SafetyStateKind::EStop,
-            (SafetyStateKind::Fault, _) => SafetyStateKind::Fault,
-            (_, SafetyStateKind::Fault) => SafetyStateKind::Fault,
-            (SafetyStateKind::Blocked, _) => SafetyStateKind::Blocked,
-            (_, SafetyStateKind::Blocked) => SafetyStateKind::Blocked,
-            (SafetyStateKind::Maintenance, _) => SafetyStateKind::Maintenance,
-            (_, SafetyStateKind::Maintenance) => SafetyStateKind::Maintenance,
-            (_, SafetyStateKind::Safe) => worst,
-        };
+/// DB rows override env, since the DB is the intended source of truth.
+async fn overlay_device_registry_from_db(pool: &DbPool, loaded: &mut LoadedDeviceRegistry) {
+    match load_device_registry_from_db(pool).await {
+        Ok(from_db) => loaded.devices.extend(from_db),
+        Err(err) => warn!(error=%err, "failed to load device registry from DB"),
+    }
+    match load_device_keys_from_db(pool).await {
+        Ok(from_db) => loaded.hmac_keys.extend(from_db),
+        Err(err) => warn!(error=%err, "failed to load device HMAC keys from DB"),
     }
+}
 
-    let mut out = SafetyState {
-        kind: worst,
-        reason_code: None,
-        latched: false,
+fn apply_device_registry(runtime: &mut RuntimeState, loaded: LoadedDeviceRegistry) {
+    for (device_id, reg) in &loaded.devices {
+        if let (Some(interval), Some(offline)) = (reg.heartbeat_interval_ms, reg.offline_ms) {
+            if offline <= interval {
+                warn!(device_id=%device_id, heartbeat_interval_ms = interval, offline_ms = offline, "offline_ms not above heartbeat_interval_ms; device will flap");
+            }
+        }
+    }
+    runtime.device_registry = loaded.devices;
+    runtime.device_hmac_keys = loaded.hmac_keys;
+    info!(
+        device_count = runtime.device_registry.len(),
+        hmac_keys = runtime.device_hmac_keys.len(),
+        "device registry loaded"
+    );
+}
+
+/// `RELOAD_DEVICE_REGISTRY`: the DB queries run in a spawned task (never wait on the DB from the
+/// scheduler loop); [`finish_device_registry_reload`] applies the result. A newer request
+/// replaces one still running.
+fn start_device_registry_reload(
+    config: &Config,
+    db: Option<&DbWriter>,
+    runtime: &mut RuntimeState,
+) {
+    let mut loaded = device_registry_from_env(config);
+    let task = match db {
+        Some(db) => {
+            let pool = db.pool.clone();
+            tokio::spawn(async move {
+                overlay_device_registry_from_db(&pool, &mut loaded).await;
+                loaded
+            })
+        }
+        None => tokio::spawn(async move { loaded }),
     };
+    if let Some(previous) = runtime.device_registry_reload.replace(task) {
+        previous.abort();
+    }
+}
 
-    if safety_latched {
-        out.latched = true;
-        out.kind = match out.kind {
-            SafetyStateKind::EStop => SafetyStateKind::EStop,
-            _ => SafetyStateKind::Fault,
-        };
+/// Applies a finished registry reload (called every tick) and announces it.
+async fn finish_device_registry_reload(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+) {
+    if !runtime
+        .device_registry_reload
+        .as_ref()
+        .is_some_and(|task| task.is_finished())
+    {
+        return;
+    }
+    let Some(task) = runtime.device_registry_reload.take() else {
+        return;
+    };
+    let loaded = match task.await {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            warn!(error=%err, "device registry reload task failed");
+            return;
+        }
+    };
+    apply_device_registry(runtime, loaded);
+    let mut adopted: Vec<String> = runtime
+        .unregistered_devices
+        .keys()
+        .filter(|d| runtime.device_registry.contains_key(*d))
+        .cloned()
+        .collect();
+    adopted.sort();
+    for device_id in &adopted {
+        runtime.unregistered_devices.remove(device_id);
     }
+    info!(adopted = ?adopted, "device registry reloaded via core control request");
+    publish_core_fault(
+        client,
+        &config.room_id,
+        CoreFault {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: config.room_id.clone(),
+            kind: FaultKind::DeviceRegistryReloaded,
+            severity: Severity::Info,
+            message: "Device registry reloaded".to_string(),
+            observed_at_unix_ms: unix_ms_now(),
+            details: serde_json::json!({
+                "devices": runtime.device_registry.len(),
+                "hmac_keys": runtime.device_hmac_keys.len(),
+                "adopted": adopted,
+            }),
+        },
+    )
+    .await;
+}
 
-    out
+/// Keys provisioned by adopting a device (`009_unregistered_devices.sql`); none on older DBs.
+async fn load_device_keys_from_db(
+    pool: &DbPool,
+) -> anyhow::Result<std::collections::HashMap<String, Vec<u8>>> {
+    let client = pool.get().await.context("connect postgres (device keys)")?;
+    let exists = client
+        .query_one("SELECT to_regclass('device_keys') IS NOT NULL", &[])
+        .await;
+    pool.note_result(&exists);
+    if !exists.context("check device_keys")?.get::<_, bool>(0) {
+        return Ok(std::collections::HashMap::new());
+    }
+    let rows = client
+        .query("SELECT device_id, key_hex FROM device_keys", &[])
+        .await;
+    pool.note_result(&rows);
+    let rows = rows.context("query device_keys")?;
+    let mut out = std::collections::HashMap::new();
+    for row in rows {
+        let device_id: String = row.get(0);
+        let key_hex: String = row.get(1);
+        match hex::decode(key_hex.trim()) {
+            Ok(key) if !key.is_empty() => {
+                out.insert(device_id, key);
+            }
+            _ => warn!(device_id=%device_id, "invalid key_hex in device_keys"),
+        }
+    }
+    Ok(out)
 }
 
-async fn maybe_latch_safety(
+/// How often a heartbeating unregistered device refreshes its inbox row (`last_seen`).
+const UNREGISTERED_INBOX_REFRESH_MS: u64 = 30_000;
+
+/// Heartbeat from a device missing from the registry: `DEVICE_UNREGISTERED` on the first one
+/// since core started, and an upsert into the `unregistered_devices` inbox (throttled, written
+/// by a spawned task).
+async fn note_unregistered_device(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     runtime: &mut RuntimeState,
     db: Option<&DbWriter>,
     device_id: &str,
-    safety: &SafetyState,
-    observed_at_unix_ms: u64,
+    hb: &Heartbeat,
+    topic: &str,
 ) {
-    if runtime.safety_latched_since_unix_ms.is_some() {
+    if runtime.device_registry.contains_key(device_id) {
         return;
     }
-
-    let should_latch =
-        safety.latched || matches!(safety.kind, SafetyStateKind::Fault | SafetyStateKind::EStop);
-    if !should_latch {
+    let now = unix_ms_now();
+    let last_written = runtime.unregistered_devices.get(device_id).copied();
+    if last_written.is_some_and(|at| now.saturating_sub(at) < UNREGISTERED_INBOX_REFRESH_MS) {
         return;
     }
-
-    runtime.safety_latched_since_unix_ms = Some(observed_at_unix_ms);
-    runtime.recompute_dispatch_pause_reason();
-
-    let fault = CoreFault {
-        schema: SCHEMA_VERSION.to_string(),
-        room_id: config.room_id.clone(),
-        kind: "SAFETY_LATCHED".to_string(),
-        severity: "CRITICAL".to_string(),
-        message: "Room safety latched; dispatch paused until manually reset".to_string(),
-        observed_at_unix_ms,
-        details: serde_json::json!({
-            "device_id": device_id,
-            "safety_kind": format!("{:?}", safety.kind),
-            "reason_code": safety.reason_code,
-            "latched": safety.latched,
-        }),
-    };
-    publish_core_fault(client, &config.room_id, fault.clone()).await;
+    runtime
+        .unregistered_devices
+        .insert(device_id.to_string(), now);
+    if last_written.is_none() {
+        warn!(device_id = %device_id, fw = %hb.firmware_version, "unregistered device heartbeat");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            device_id,
+            FaultKind::DeviceUnregistered,
+            Severity::Warn,
+            "Device is not in the registry; adopt it or remove it from the network",
+            serde_json::json!({
+                "device_id": device_id,
+                "firmware_version": hb.firmware_version,
+                "supported_schemas": hb.supported_schemas,
+                "topic": topic,
+            }),
+        )
+        .await;
+    }
     if let Some(db) = db {
-        if let Ok(v) = serde_json::to_value(&fault) {
-            db.enqueue_json(
-                &config.room_id,
-                None,
-                &format!("room/{}/core/fault", config.room_id),
-                "CORE_FAULT",
-                fault.observed_at_unix_ms,
-                v,
-            );
-        }
+        // Never wait on the DB from the scheduler loop: `pool.get()` may sit out a connect.
+        let (pool, device_id, hb) = (db.pool.clone(), device_id.to_string(), hb.clone());
+        tokio::spawn(async move { save_unregistered_device(&pool, &device_id, &hb, now).await });
     }
 }
 
-async fn publish_core_status(
-    client: &rumqttc::AsyncClient,
-    config: &Config,
-    runtime: &RuntimeState,
-    uptime: Duration,
-    graph_runner: &GraphRunner,
-    devices: &std::collections::HashMap<String, DeviceStatus>,
-) {
-    let topic = format!("room/{}/core/status", config.room_id);
-    let offline_device_count = devices.values().filter(|d| d.is_offline).count() as u64;
-
-    let graph_active_nodes: Vec<String> = graph_runner
-        .active_nodes
-        .iter()
-        .map(|n| n.node_id.clone())
-        .collect();
-    let graph_active_node = graph_active_nodes.first().cloned();
-
-    let msg = CoreStatus {
-        schema: SCHEMA_VERSION.to_string(),
-        room_id: config.room_id.clone(),
-        uptime_ms: uptime.as_millis() as u64,
-        tick_ms: config.tick_ms,
-        dry_run: config.dry_run,
-        dispatch_enabled: config.dispatch_enabled,
-        dispatch_paused_reason: runtime.dispatch_paused_reason.clone(),
-        broker_outage_since_unix_ms: runtime.broker_outage_since_unix_ms,
-        safety_latched_since_unix_ms: runtime.safety_latched_since_unix_ms,
-        room_safety: runtime.room_safety.clone(),
-        device_count: devices.len() as u64,
-        offline_device_count,
-        graph_active_node,
-        graph_active_nodes,
-        graph_version: graph_runner.graph_version,
-        observed_at_unix_ms: unix_ms_now(),
+/// Upserts the inbox row; a device that was adopted and later removed from `devices` reappears.
+async fn save_unregistered_device(pool: &DbPool, device_id: &str, hb: &Heartbeat, now: u64) {
+    let client = match pool.get().await {
+        Ok(c) => c,
+        Err(err) => {
+            warn!(error=%err, "unregistered device not recorded (connect)");
+            return;
+        }
     };
+    let now_ms = now as i64;
+    let schemas = serde_json::json!(hb.supported_schemas);
+    let res = client
+        .execute(
+            "INSERT INTO unregistered_devices \
+                 (device_id, first_seen, last_seen, firmware_version, supported_schemas) \
+             VALUES ($1, to_timestamp($2::bigint / 1000.0), to_timestamp($2::bigint / 1000.0), $3, $4) \
+             ON CONFLICT (device_id) DO UPDATE \
+             SET last_seen = EXCLUDED.last_seen, firmware_version = EXCLUDED.firmware_version, \
+                 supported_schemas = EXCLUDED.supported_schemas, \
+                 first_seen = CASE WHEN unregistered_devices.adopted_at IS NULL \
+                                   THEN unregistered_devices.first_seen ELSE EXCLUDED.first_seen END, \
+                 adopted_at = NULL, adopted_by = NULL",
+            &[&device_id, &now_ms, &hb.firmware_version, &schemas],
+        )
+        .await;
+    pool.note_result(&res);
+    if let Err(err) = res {
+        warn!(error=%err, device_id=%device_id, "unregistered device not recorded");
+    }
+}
 
-    match serde_json::to_vec(&msg) {
-        Ok(payload) => {
-            if let Err(err) = client
-                .publish(topic, rumqttc::QoS::AtLeastOnce, true, payload)
-                .await
-            {
-                warn!(error=%err, "failed to publish core status");
+async fn load_device_registry_from_db(
+    pool: &DbPool,
+) -> anyhow::Result<std::collections::HashMap<String, DeviceRegistryEntry>> {
+    let client = pool.get().await.context("connect postgres (registry)")?;
+
+    let mut out: std::collections::HashMap<String, DeviceRegistryEntry> =
+        std::collections::HashMap::new();
+    // Newer columns are read through `to_jsonb` so DBs initialized before
+    // `004_device_concurrency.sql` .. `008_device_liveness.sql` still load (as NULL = default).
+    let rows = client
+        .query(
+            "SELECT device_id, safety_class, enabled, \
+                    to_jsonb(d) ->> 'concurrency_policy', \
+                    (to_jsonb(d) ->> 'queue_max_depth')::INT, \
+                    to_jsonb(d) ->> 'zone', \
+                    to_jsonb(d) ->> 'offline_policy', \
+                    (to_jsonb(d) ->> 'heartbeat_interval_ms')::BIGINT, \
+                    (to_jsonb(d) ->> 'offline_ms')::BIGINT \
+             FROM devices d",
+            &[],
+        )
+        .await;
+    pool.note_result(&rows);
+    let rows = rows.context("query devices")?;
+    for row in rows {
+        let device_id: String = row.get(0);
+        let safety_class: String = row.get(1);
+        let enabled: bool = row.get(2);
+        let concurrency_policy: Option<String> = row.get(3);
+        let queue_max_depth: Option<i32> = row.get(4);
+        let zone: Option<String> = row.get(5);
+        let offline_policy: Option<String> = row.get(6);
+        let heartbeat_interval_ms: Option<i64> = row.get(7);
+        let offline_ms: Option<i64> = row.get(8);
+        let offline_policy = match offline_policy.as_deref() {
+            None => OfflinePolicy::default(),
+            Some(raw) => OfflinePolicy::parse(raw).unwrap_or_else(|| {
+                warn!(device_id=%device_id, offline_policy=%raw, "unknown offline_policy in DB");
+                OfflinePolicy::default()
+            }),
+        };
+        let concurrency = match concurrency_policy.as_deref() {
+            None => None,
+            Some(raw) => match ConcurrencyPolicy::parse(raw) {
+                Some(policy) => Some(policy),
+                None => {
+                    warn!(device_id=%device_id, concurrency_policy=%raw, "unknown concurrency_policy in DB");
+                    None
+                }
+            },
+        };
+        let cls = match safety_class.as_str() {
+            "CRITICAL" => SafetyClass::Critical,
+            "NON_CRITICAL" => SafetyClass::NonCritical,
+            other => {
+                warn!(device_id=%device_id, safety_class=%other, "unknown safety_class in DB");
+                continue;
             }
-        }
-        Err(err) => warn!(error=%err, "failed to serialize core status"),
+        };
+        out.insert(
+            device_id,
+            DeviceRegistryEntry {
+                safety_class: cls,
+                enabled,
+                concurrency,
+                queue_max_depth: queue_max_depth
+                    .and_then(|d| usize::try_from(d).ok())
+                    .filter(|d| *d > 0),
+                zone: zone.as_deref().and_then(normalize_zone),
+                offline_policy,
+                heartbeat_interval_ms: heartbeat_interval_ms
+                    .and_then(|v| u64::try_from(v).ok())
+                    .filter(|v| *v > 0),
+                offline_ms: offline_ms
+                    .and_then(|v| u64::try_from(v).ok())
+                    .filter(|v| *v > 0),
+            },
+        );
     }
+    Ok(out)
 }
 
-fn unix_ms_now() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::ZERO)
-        .as_millis() as u64
+#[derive(Debug, Clone)]
+struct DeviceRegistryEntry {
+    safety_class: SafetyClass,
+    enabled: bool,
+    /// `None` = `CORE_DEVICE_CONCURRENCY_DEFAULT`.
+    concurrency: Option<ConcurrencyPolicy>,
+    /// `None` = `CORE_DEVICE_QUEUE_MAX_DEPTH`.
+    queue_max_depth: Option<usize>,
+    /// Safety zone; `None` = room-wide (its safety events latch the whole room).
+    zone: Option<String>,
+    offline_policy: OfflinePolicy,
+    /// How often the device heartbeats; `offline_ms` defaults to 3x this.
+    heartbeat_interval_ms: Option<u64>,
+    /// Heartbeat silence before the device counts as offline; `None` = derived from
+    /// `heartbeat_interval_ms`, else `DEVICE_OFFLINE_MS`.
+    offline_ms: Option<u64>,
 }
 
-async fn subscribe_default_topics(
-    client: &rumqttc::AsyncClient,
-    room_id: &str,
-) -> anyhow::Result<()> {
-    // Align subscriptions with `docs/protocol/QOS_RETAIN.md`.
-    client
-        .subscribe(
-            format!("room/{}/device/+/heartbeat", room_id),
-            rumqttc::QoS::AtMostOnce,
-        )
-        .await?;
-    client
-        .subscribe(
-            format!("room/{}/device/+/telemetry", room_id),
-            rumqttc::QoS::AtMostOnce,
-        )
-        .await?;
-
-    client
-        .subscribe(
-            format!("room/{}/device/+/ack", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
-        .await?;
-    client
-        .subscribe(
-            format!("room/{}/device/+/presence", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
-        .await?;
-    client
-        .subscribe(
-            format!("room/{}/device/+/state", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
-        .await?;
+/// A device only named in some env maps: NON_CRITICAL, enabled, core defaults for the rest.
+impl Default for DeviceRegistryEntry {
+    fn default() -> Self {
+        Self {
+            safety_class: SafetyClass::NonCritical,
+            enabled: true,
+            concurrency: None,
+            queue_max_depth: None,
+            zone: None,
+            offline_policy: OfflinePolicy::default(),
+            heartbeat_interval_ms: None,
+            offline_ms: None,
+        }
+    }
+}
 
-    // MVP control plane: tools → core command dispatch.
-    client
-        .subscribe(
-            format!("room/{}/core/dispatch", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
-        .await?;
+/// One `DEVICE_LIVENESS_JSON` value.
+#[derive(Debug, serde::Deserialize)]
+struct DeviceLivenessJson {
+    #[serde(default)]
+    heartbeat_interval_ms: Option<u64>,
+    #[serde(default)]
+    offline_ms: Option<u64>,
+}
 
-    // Ops control plane: pause/resume dispatch (manual).
-    client
-        .subscribe(
-            format!("room/{}/core/control", room_id),
-            rumqttc::QoS::AtLeastOnce,
-        )
-        .await?;
+/// Missed heartbeats tolerated when a device only declares `heartbeat_interval_ms`.
+const HEARTBEAT_MISSES_BEFORE_OFFLINE: u64 = 3;
+
+/// Heartbeat silence after which `device_id` counts as offline.
+fn device_offline_ms(config: &Config, runtime: &RuntimeState, device_id: &str) -> u64 {
+    match runtime.device_registry.get(device_id) {
+        Some(DeviceRegistryEntry {
+            offline_ms: Some(ms),
+            ..
+        }) => *ms,
+        Some(DeviceRegistryEntry {
+            heartbeat_interval_ms: Some(interval),
+            ..
+        }) => interval.saturating_mul(HEARTBEAT_MISSES_BEFORE_OFFLINE),
+        _ => config.device_offline_ms,
+    }
+}
 
-    Ok(())
+/// What losing a device (presence OFFLINE / heartbeat timeout) does beyond `DEVICE_OFFLINE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum OfflinePolicy {
+    /// Keep dispatching; the command pipeline's timeouts/retries deal with it.
+    Ignore,
+    /// Refuse dispatch to the device while offline (pre-policy behaviour).
+    #[default]
+    BlockDevice,
+    /// Latch the device's safety zone (the room if it has none, or if escalated).
+    LatchZone,
+    /// Latch room safety, as a device FAULT would.
+    LatchRoom,
 }
 
-fn load_device_hmac_keys_from_env() -> anyhow::Result<std::collections::HashMap<String, Vec<u8>>> {
-    let raw = std::env::var("DEVICE_HMAC_KEYS_JSON")
-        .ok()
-        .filter(|v| !v.trim().is_empty());
-    let Some(raw) = raw else {
-        return Ok(std::collections::HashMap::new());
-    };
+impl OfflinePolicy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "IGNORE" => Some(Self::Ignore),
+            "BLOCK_DEVICE" => Some(Self::BlockDevice),
+            "LATCH_ZONE" => Some(Self::LatchZone),
+            "LATCH_ROOM" => Some(Self::LatchRoom),
+            _ => None,
+        }
+    }
 
-    let map: std::collections::HashMap<String, String> = serde_json::from_str(&raw)?;
-    let mut out: std::collections::HashMap<String, Vec<u8>> = std::collections::HashMap::new();
-    for (device_id, hex_key) in map {
-        let key = hex::decode(hex_key.trim())
-            .with_context(|| format!("invalid hex for device_id={}", device_id))?;
-        out.insert(device_id, key);
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ignore => "IGNORE",
+            Self::BlockDevice => "BLOCK_DEVICE",
+            Self::LatchZone => "LATCH_ZONE",
+            Self::LatchRoom => "LATCH_ROOM",
+        }
+    }
+
+    fn blocks_dispatch(self) -> bool {
+        self != Self::Ignore
     }
-    Ok(out)
 }
 
-#[derive(Debug, Clone)]
-struct DeviceStatus {
-    last_heartbeat_at_unix_ms: Option<u64>,
-    last_ack_at_unix_ms: Option<u64>,
-    last_presence_at_unix_ms: Option<u64>,
-    last_state_at_unix_ms: Option<u64>,
-    last_state: Option<serde_json::Value>,
-    presence: Option<PresenceStatus>,
-    last_reported_safety: Option<SafetyState>,
-    is_offline: bool,
+fn device_offline_policy(runtime: &RuntimeState, device_id: &str) -> OfflinePolicy {
+    runtime
+        .device_registry
+        .get(device_id)
+        .map(|r| r.offline_policy)
+        .unwrap_or_default()
 }
 
-#[derive(Debug)]
-enum DeviceTopicKind {
-    Heartbeat,
-    Ack,
-    State,
-    Telemetry,
-    Presence,
+fn normalize_zone(raw: &str) -> Option<String> {
+    let zone = raw.trim();
+    (!zone.is_empty()).then(|| zone.to_string())
 }
 
-fn parse_device_topic(room_id: &str, topic: &str) -> Option<(String, DeviceTopicKind)> {
-    // room/{room_id}/device/{device_id}/{kind}
-    let prefix = format!("room/{}/device/", room_id);
-    let rest = topic.strip_prefix(&prefix)?;
-    let mut parts = rest.split('/');
-    let device_id = parts.next()?.to_string();
-    let kind = parts.next()?;
-    if parts.next().is_some() {
-        return None;
+/// Which zone-level safety events also latch the whole room (`CORE_SAFETY_ESCALATION`).
+/// Events from devices without a zone always latch the room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum SafetyEscalation {
+    /// Zone latches never leave their zone.
+    None,
+    /// An `E_STOP` in any zone latches the room.
+    EStop,
+    /// `E_STOP`, or any latch raised by a device with safety class CRITICAL.
+    Critical,
+}
+
+impl SafetyEscalation {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "NONE" => Some(Self::None),
+            "ESTOP" | "E_STOP" => Some(Self::EStop),
+            "CRITICAL" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+
+    fn escalates(self, kind: SafetyStateKind, device_class: SafetyClass) -> bool {
+        match self {
+            Self::None => false,
+            Self::EStop => kind == SafetyStateKind::EStop,
+            Self::Critical => {
+                kind == SafetyStateKind::EStop || device_class == SafetyClass::Critical
+            }
+        }
     }
-    let kind = match kind {
-        "heartbeat" => DeviceTopicKind::Heartbeat,
-        "ack" => DeviceTopicKind::Ack,
-        "state" => DeviceTopicKind::State,
-        "telemetry" => DeviceTopicKind::Telemetry,
-        "presence" => DeviceTopicKind::Presence,
-        _ => return None,
-    };
-    Some((device_id, kind))
 }
 
-async fn handle_incoming_mqtt(
-    config: &Config,
-    client: &rumqttc::AsyncClient,
-    msg: rumqttc::Publish,
-    db: Option<&DbWriter>,
-    runtime: &mut RuntimeState,
-    graph_runner: &mut GraphRunner,
-    devices: &mut std::collections::HashMap<String, DeviceStatus>,
-    device_sequences: &mut std::collections::HashMap<String, u64>,
-    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
-    dispatch_tracker: &mut DispatchTracker,
-) {
-    if msg.topic == format!("room/{}/core/dispatch", config.room_id) {
-        handle_dispatch_request(
-            config,
-            client,
-            runtime,
-            db,
-            &msg.payload,
-            devices,
-            device_sequences,
-            pending,
-            dispatch_tracker,
-        )
-        .await;
-        return;
+/// What to do with a dispatch for a device that still has a command in flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum ConcurrencyPolicy {
+    /// Publish immediately (pre-policy behaviour).
+    Parallel,
+    /// Refuse with `DISPATCH_BLOCKED_DEVICE_BUSY`.
+    Reject,
+    /// Hold in a per-device FIFO until the device is idle.
+    Queue,
+    /// Cancel the in-flight command(s), then publish (needs a `CANCEL`-capable device).
+    Supersede,
+}
+
+impl ConcurrencyPolicy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "PARALLEL" => Some(Self::Parallel),
+            "REJECT" => Some(Self::Reject),
+            "QUEUE" => Some(Self::Queue),
+            "SUPERSEDE" => Some(Self::Supersede),
+            _ => None,
+        }
     }
 
-    if msg.topic == format!("room/{}/core/control", config.room_id) {
-        handle_core_control(
-            config,
-            client,
-            runtime,
-            graph_runner,
-            db,
-            devices,
-            &msg.payload,
-        )
-        .await;
-        return;
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Parallel => "PARALLEL",
+            Self::Reject => "REJECT",
+            Self::Queue => "QUEUE",
+            Self::Supersede => "SUPERSEDE",
+        }
     }
+}
 
-    let Some((device_id, kind)) = parse_device_topic(&config.room_id, &msg.topic) else {
-        return;
-    };
+/// Effective (policy, max queue depth) for a device.
+fn device_concurrency(
+    config: &Config,
+    runtime: &RuntimeState,
+    device_id: &str,
+) -> (ConcurrencyPolicy, usize) {
+    let reg = runtime.device_registry.get(device_id);
+    (
+        reg.and_then(|r| r.concurrency)
+            .unwrap_or(config.device_concurrency_default),
+        reg.and_then(|r| r.queue_max_depth)
+            .unwrap_or(config.device_queue_max_depth),
+    )
+}
 
-    let status = devices.entry(device_id.clone()).or_insert(DeviceStatus {
-        last_heartbeat_at_unix_ms: None,
-        last_ack_at_unix_ms: None,
-        last_presence_at_unix_ms: None,
-        last_state_at_unix_ms: None,
-        last_state: None,
-        presence: None,
-        last_reported_safety: None,
-        is_offline: true,
-    });
+fn safety_class_rank(s: SafetyClass) -> u8 {
+    match s {
+        SafetyClass::NonCritical => 0,
+        SafetyClass::Critical => 1,
+    }
+}
 
-    match kind {
-        DeviceTopicKind::Heartbeat => match serde_json::from_slice::<Heartbeat>(&msg.payload) {
-            Ok(hb) => {
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&hb) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&device_id),
-                            &msg.topic,
-                            "HEARTBEAT",
+fn effective_safety_class(requested: SafetyClass, registry: SafetyClass) -> SafetyClass {
+    if safety_class_rank(registry) > safety_class_rank(requested) {
+        registry
+    } else {
+        requested
+    }
+}
+
+async fn publish_core_heartbeat(
+    mqtt: &MqttHandle,
+    config: &Config,
+    uptime: Duration,
+    runtime: &RuntimeState,
+) {
+    let topic = format!("room/{}/core/heartbeat", config.room_id);
+    let msg = serde_json::json!({
+        "schema": SCHEMA_VERSION,
+        "room_id": config.room_id,
+        "uptime_ms": uptime.as_millis() as u64,
+        "observed_at_unix_ms": unix_ms_now(),
+        "safety_state": runtime.room_safety,
+        "boot_id": runtime.watchdog_boot_id,
+        "watchdog_interval_ms": config.watchdog_interval_ms,
+        "watchdog_timeout_ms": config.watchdog_timeout_ms,
+    });
+
+    match serde_json::to_vec(&msg) {
+        Ok(payload) => {
+            if let Err(err) = mqtt
+                .client
+                .publish(topic, QoS::AtMostOnce, false, payload)
+                .await
+            {
+                warn!(error = %err, "failed to publish core heartbeat");
+            }
+        }
+        Err(err) => warn!(error = %err, "failed to serialize core heartbeat"),
+    }
+}
+
+/// Worst of `kinds` (`E_STOP` > `FAULT` > `BLOCKED` > `MAINTENANCE` > `SAFE`).
+fn worst_safety_kind(kinds: impl IntoIterator<Item = SafetyStateKind>) -> SafetyStateKind {
+    kinds
+        .into_iter()
+        .fold(SafetyStateKind::Safe, |worst, k| match (worst, k) {
+            (SafetyStateKind::EStop, _) => SafetyStateKind::EStop,
+            (_, SafetyStateKind::EStop) => SafetyStateKind::EStop,
+            (SafetyStateKind::Fault, _) => SafetyStateKind::Fault,
+            (_, SafetyStateKind::Fault) => SafetyStateKind::Fault,
+            (SafetyStateKind::Blocked, _) => SafetyStateKind::Blocked,
+            (_, SafetyStateKind::Blocked) => SafetyStateKind::Blocked,
+            (SafetyStateKind::Maintenance, _) => SafetyStateKind::Maintenance,
+            (_, SafetyStateKind::Maintenance) => SafetyStateKind::Maintenance,
+            (_, SafetyStateKind::Safe) => worst,
+        })
+}
+
+/// Aggregated state of `kind`; a latch forces at least `FAULT`.
+fn aggregate_safety_state(kind: SafetyStateKind, latched: bool) -> SafetyState {
+    let mut out = SafetyState {
+        kind,
+        reason_code: None,
+        latched: false,
+    };
+    if latched {
+        out.latched = true;
+        out.kind = match out.kind {
+            SafetyStateKind::EStop => SafetyStateKind::EStop,
+            _ => SafetyStateKind::Fault,
+        };
+    }
+    out
+}
+
+/// Room-wide safety: devices without a safety zone, plus the room latch. Zoned devices are
+/// reported per zone by [`compute_zone_safety`].
+fn compute_room_safety(
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    registry: &std::collections::HashMap<String, DeviceRegistryEntry>,
+    safety_latched: bool,
+) -> SafetyState {
+    let worst = worst_safety_kind(
+        devices
+            .iter()
+            .filter(|(id, _)| registry.get(*id).is_none_or(|r| r.zone.is_none()))
+            .filter_map(|(_, d)| d.last_reported_safety.as_ref().map(|s| s.kind)),
+    );
+    aggregate_safety_state(worst, safety_latched)
+}
+
+/// Safety per zone, for every zone in the registry or currently latched.
+fn compute_zone_safety(
+    runtime: &RuntimeState,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+) -> Vec<SafetyZoneStatus> {
+    let zones: std::collections::BTreeSet<&str> = runtime
+        .device_registry
+        .values()
+        .filter_map(|r| r.zone.as_deref())
+        .chain(runtime.zone_latches.keys().map(String::as_str))
+        .collect();
+    zones
+        .into_iter()
+        .map(|zone| {
+            let device_ids = runtime.zone_device_ids(zone);
+            let worst = worst_safety_kind(device_ids.iter().filter_map(|id| {
+                devices
+                    .get(id)
+                    .and_then(|d| d.last_reported_safety.as_ref())
+                    .map(|s| s.kind)
+            }));
+            let latch = runtime.zone_latches.get(zone);
+            SafetyZoneStatus {
+                zone: zone.to_string(),
+                safety: aggregate_safety_state(worst, latch.is_some()),
+                latched_since_unix_ms: latch.map(|l| l.since_unix_ms),
+                device_ids,
+            }
+        })
+        .collect()
+}
+
+/// Where a new safety latch applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SafetyLatchScope {
+    Room,
+    Zone(String),
+}
+
+/// Records the device's reported safety and raises `DEVICE_SAFETY_STATE` when it changed (kind,
+/// reason or latch), whichever message carried it. A first report after core start only counts
+/// when it is not SAFE.
+#[allow(clippy::too_many_arguments)]
+async fn note_safety_transition(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    status: &mut DeviceStatus,
+    safety: &SafetyState,
+    source: &str,
+    observed_at_unix_ms: u64,
+) {
+    let previous = status.last_reported_safety.replace(safety.clone());
+    let changed = match &previous {
+        Some(prev) => prev != safety,
+        None => safety.kind != SafetyStateKind::Safe,
+    };
+    if !changed {
+        return;
+    }
+    let (severity, message) = if safety.kind == SafetyStateKind::Safe {
+        (Severity::Info, "Device safety_state back to SAFE")
+    } else {
+        (Severity::Warn, "Device reported non-SAFE safety_state")
+    };
+    let fault = CoreFault {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        kind: FaultKind::DeviceSafetyState,
+        severity,
+        message: message.to_string(),
+        observed_at_unix_ms,
+        details: serde_json::json!({
+            "device_id": device_id,
+            "safety_state": format!("{:?}", safety.kind),
+            "reason_code": safety.reason_code,
+            "latched": safety.latched,
+            "previous_safety_state": previous.as_ref().map(|p| format!("{:?}", p.kind)),
+            "source": source,
+        }),
+    };
+    emit_device_fault(config, client, db, device_id, &fault).await;
+}
+
+/// Latches safety when `device_id` reports FAULT/E_STOP or `latched=true`: its zone if it has
+/// one (unless `CORE_SAFETY_ESCALATION` sends the event room-wide), otherwise the whole room.
+/// Returns the scope that was newly latched.
+async fn maybe_latch_safety(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    safety: &SafetyState,
+    observed_at_unix_ms: u64,
+) -> Option<SafetyLatchScope> {
+    let should_latch =
+        safety.latched || matches!(safety.kind, SafetyStateKind::Fault | SafetyStateKind::EStop);
+    if !should_latch {
+        return None;
+    }
+
+    let reg = runtime.device_registry.get(device_id);
+    let device_class = reg
+        .map(|r| r.safety_class)
+        .unwrap_or(SafetyClass::NonCritical);
+    let escalate = reg.is_none_or(|r| r.zone.is_none())
+        || config
+            .safety_escalation
+            .escalates(safety.kind, device_class);
+    latch_safety(
+        config,
+        client,
+        runtime,
+        db,
+        device_id,
+        safety,
+        escalate,
+        observed_at_unix_ms,
+    )
+    .await
+}
+
+/// Applies the device's `offline_policy` after it went offline; a latch is raised as a device
+/// FAULT with reason `DEVICE_OFFLINE` (zone escalation rules apply to `LATCH_ZONE`).
+async fn apply_offline_policy(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    observed_at_unix_ms: u64,
+) -> Option<SafetyLatchScope> {
+    let reg = runtime.device_registry.get(device_id);
+    let policy = reg.map(|r| r.offline_policy).unwrap_or_default();
+    let escalate = match policy {
+        OfflinePolicy::Ignore | OfflinePolicy::BlockDevice => return None,
+        OfflinePolicy::LatchRoom => true,
+        OfflinePolicy::LatchZone => reg.is_none_or(|r| {
+            r.zone.is_none()
+                || config
+                    .safety_escalation
+                    .escalates(SafetyStateKind::Fault, r.safety_class)
+        }),
+    };
+    warn!(
+        device_id,
+        offline_policy = policy.as_str(),
+        escalate,
+        "device offline: applying offline policy"
+    );
+    let safety = SafetyState {
+        kind: SafetyStateKind::Fault,
+        reason_code: Some("DEVICE_OFFLINE".to_string()),
+        latched: false,
+    };
+    latch_safety(
+        config,
+        client,
+        runtime,
+        db,
+        device_id,
+        &safety,
+        escalate,
+        observed_at_unix_ms,
+    )
+    .await
+}
+
+/// Latches the room (`escalate`) or `device_id`'s zone and publishes `SAFETY_LATCHED`; `None` if
+/// that latch was already held.
+#[allow(clippy::too_many_arguments)]
+async fn latch_safety(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    safety: &SafetyState,
+    escalate: bool,
+    observed_at_unix_ms: u64,
+) -> Option<SafetyLatchScope> {
+    let zone = runtime
+        .device_registry
+        .get(device_id)
+        .and_then(|r| r.zone.clone());
+    let (scope, message) = if escalate {
+        if runtime.safety_latched_since_unix_ms.is_some() {
+            return None;
+        }
+        runtime.safety_latched_since_unix_ms = Some(observed_at_unix_ms);
+        runtime.recompute_dispatch_pause_reason();
+        (
+            SafetyLatchScope::Room,
+            "Room safety latched; dispatch paused until manually reset".to_string(),
+        )
+    } else {
+        let zone = zone.clone().unwrap_or_default();
+        if runtime.zone_latches.contains_key(&zone) {
+            return None;
+        }
+        runtime.zone_latches.insert(
+            zone.clone(),
+            ZoneLatch {
+                since_unix_ms: observed_at_unix_ms,
+                device_id: device_id.to_string(),
+                safety_kind: safety.kind,
+            },
+        );
+        let message = format!(
+            "Safety zone '{zone}' latched; dispatch to its devices blocked until manually reset"
+        );
+        (SafetyLatchScope::Zone(zone), message)
+    };
+    warn!(device_id, scope=?scope, safety=?safety.kind, "safety latched");
+
+    let fault = CoreFault {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        kind: FaultKind::SafetyLatched,
+        severity: Severity::Critical,
+        message,
+        observed_at_unix_ms,
+        details: serde_json::json!({
+            "device_id": device_id,
+            "safety_kind": format!("{:?}", safety.kind),
+            "reason_code": safety.reason_code,
+            "latched": safety.latched,
+            "zone": match &scope {
+                SafetyLatchScope::Zone(z) => Some(z.as_str()),
+                SafetyLatchScope::Room => None,
+            },
+            "escalated_from_zone": match &scope {
+                SafetyLatchScope::Room => zone.as_deref(),
+                SafetyLatchScope::Zone(_) => None,
+            },
+        }),
+    };
+    emit_core_fault(config, client, db, &fault).await;
+    Some(scope)
+}
+
+async fn publish_core_status(
+    client: &AsyncClient,
+    config: &Config,
+    runtime: &RuntimeState,
+    uptime: Duration,
+    graph_runner: &GraphRunner,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    db: Option<&DbWriter>,
+) {
+    let topic = format!("room/{}/core/status", config.room_id);
+    let offline_device_count = devices.values().filter(|d| d.is_offline).count() as u64;
+
+    let graph_active_nodes: Vec<String> = graph_runner
+        .active_nodes
+        .iter()
+        .map(|n| n.node_id.clone())
+        .collect();
+    let graph_active_node = graph_active_nodes.first().cloned();
+
+    let msg = CoreStatus {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        uptime_ms: uptime.as_millis() as u64,
+        tick_ms: config.tick_ms,
+        dry_run: config.dry_run,
+        dispatch_enabled: config.dispatch_enabled,
+        dispatch_paused_reason: runtime.dispatch_paused_reason.clone(),
+        broker_outage_since_unix_ms: runtime.broker_outage_since_unix_ms,
+        safety_latched_since_unix_ms: runtime.safety_latched_since_unix_ms,
+        room_safety: runtime.room_safety.clone(),
+        safety_zones: compute_zone_safety(runtime, devices),
+        maintenance: runtime.maintenance.values().cloned().collect(),
+        device_count: devices.len() as u64,
+        offline_device_count,
+        graph_active_node,
+        graph_active_nodes,
+        graph_version: graph_runner.graph_version,
+        dispatch_latency: Some(runtime.latency.summary(config)),
+        db: db.map(DbWriter::health_status),
+        watchdog: (config.watchdog_interval_ms > 0).then_some(CoreWatchdogStatus {
+            boot_id: runtime.watchdog_boot_id,
+            interval_ms: config.watchdog_interval_ms,
+            timeout_ms: config.watchdog_timeout_ms,
+            counter: runtime.watchdog_counter,
+        }),
+        observed_at_unix_ms: unix_ms_now(),
+    };
+
+    match serde_json::to_vec(&msg) {
+        Ok(payload) => {
+            if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, payload).await {
+                warn!(error=%err, "failed to publish core status");
+            }
+        }
+        Err(err) => warn!(error=%err, "failed to serialize core status"),
+    }
+}
+
+/// Retained `core/metrics` snapshot: cumulative latency histograms per device/action, tick
+/// jitter and the current SLO summary.
+async fn publish_core_metrics(config: &Config, client: &AsyncClient, runtime: &RuntimeState) {
+    let topic = format!("room/{}/core/metrics", config.room_id);
+    match serde_json::to_vec(&runtime.latency.snapshot_json(config)) {
+        Ok(payload) => {
+            if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, payload).await {
+                warn!(error=%err, "failed to publish core metrics");
+            }
+        }
+        Err(err) => warn!(error=%err, "failed to serialize core metrics"),
+    }
+}
+
+/// Raises `DISPATCH_LATENCY_SLO_BREACHED` / `DISPATCH_LATENCY_SLO_RECOVERED` when the share of
+/// accepts within `CORE_SLO_ACCEPT_MS` crosses `CORE_SLO_TARGET_PCT`. Windows with fewer than
+/// `CORE_SLO_MIN_SAMPLES` accepts leave the state unchanged.
+async fn evaluate_latency_slo(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    runtime: &mut RuntimeState,
+) {
+    let Some(summary) = runtime.latency.update_slo(config) else {
+        return;
+    };
+    let breached = runtime.latency.slo_breached;
+
+    let (kind, severity, message) = if breached {
+        warn!(
+            within_pct = summary.window_within_target_pct,
+            samples = summary.window_samples,
+            p99_ms = ?summary.accept_p99_ms,
+            "dispatch latency SLO breached"
+        );
+        (
+            FaultKind::DispatchLatencySloBreached,
+            Severity::Warn,
+            "Dispatch accept latency above CORE_SLO_ACCEPT_MS for too many commands",
+        )
+    } else {
+        info!(
+            within_pct = summary.window_within_target_pct,
+            samples = summary.window_samples,
+            "dispatch latency SLO recovered"
+        );
+        (
+            FaultKind::DispatchLatencySloRecovered,
+            Severity::Info,
+            "Dispatch accept latency back within SLO",
+        )
+    };
+    raise_core_fault(
+        config,
+        client,
+        db,
+        kind,
+        severity,
+        message,
+        serde_json::json!({
+            "slo_target_ms": summary.slo_target_ms,
+            "slo_target_pct": summary.slo_target_pct,
+            "window_ms": summary.window_ms,
+            "window_samples": summary.window_samples,
+            "window_within_target_pct": summary.window_within_target_pct,
+            "accept_p99_ms": summary.accept_p99_ms,
+            "worst": runtime.latency.worst_accept(5),
+        }),
+    )
+    .await;
+}
+
+/// Raises `DB_OUTAGE` when the pool reports Postgres down and `DB_RESTORED` once it is back.
+async fn evaluate_db_health(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    runtime: &mut RuntimeState,
+) {
+    let Some(db) = db else {
+        return;
+    };
+    let health = db.pool.health();
+    let fault = match (health.up, runtime.db_outage_since_unix_ms) {
+        (Some(false), None) => {
+            let down_since = health.down_since_unix_ms.unwrap_or_else(unix_ms_now);
+            runtime.db_outage_since_unix_ms = Some(down_since);
+            CoreFault {
+                schema: SCHEMA_VERSION.to_string(),
+                room_id: config.room_id.clone(),
+                kind: FaultKind::DbOutage,
+                severity: Severity::Critical,
+                message:
+                    "Postgres unreachable; events are queued and CRITICAL faults spilled to disk"
+                        .to_string(),
+                observed_at_unix_ms: unix_ms_now(),
+                details: serde_json::json!({
+                    "down_since_unix_ms": down_since,
+                    "error": health.last_error,
+                }),
+            }
+        }
+        (Some(true), Some(down_since)) => {
+            runtime.db_outage_since_unix_ms = None;
+            let recovered_at = health.last_ok_unix_ms.unwrap_or_else(unix_ms_now);
+            CoreFault {
+                schema: SCHEMA_VERSION.to_string(),
+                room_id: config.room_id.clone(),
+                kind: FaultKind::DbRestored,
+                severity: Severity::Info,
+                message: "Postgres reachable again".to_string(),
+                observed_at_unix_ms: unix_ms_now(),
+                details: serde_json::json!({
+                    "down_since_unix_ms": down_since,
+                    "recovered_at_unix_ms": recovered_at,
+                    "outage_ms": recovered_at.saturating_sub(down_since),
+                    "spill_pending": db.spill_pending(),
+                }),
+            }
+        }
+        _ => return,
+    };
+    publish_core_fault(client, &config.room_id, fault.clone()).await;
+    if let Ok(v) = serde_json::to_value(&fault) {
+        db.enqueue_json(
+            &config.room_id,
+            None,
+            &format!("room/{}/core/fault", config.room_id),
+            "CORE_FAULT",
+            fault.observed_at_unix_ms,
+            v,
+        );
+    }
+}
+
+fn unix_ms_now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_millis() as u64
+}
+
+async fn subscribe_default_topics(client: &AsyncClient, room_id: &str) -> anyhow::Result<()> {
+    // Align subscriptions with `docs/protocol/QOS_RETAIN.md`.
+    // Each device topic is also subscribed with every binary codec suffix (e.g. `/msgpack`).
+    for codec in WireCodec::ALL {
+        for (kind, qos) in [
+            ("heartbeat", QoS::AtMostOnce),
+            ("telemetry", QoS::AtMostOnce),
+            ("pong", QoS::AtMostOnce),
+            ("ack", QoS::AtLeastOnce),
+            ("presence", QoS::AtLeastOnce),
+            ("state", QoS::AtLeastOnce),
+        ] {
+            let base = format!("room/{}/device/+/{}", room_id, kind);
+            client.subscribe(codec.topic(&base), qos).await?;
+        }
+    }
+
+    // MVP control plane: tools → core command dispatch.
+    client
+        .subscribe(format!("room/{}/core/dispatch", room_id), QoS::AtLeastOnce)
+        .await?;
+
+    // Batch ("scene") dispatch: several devices gated together.
+    client
+        .subscribe(
+            format!("room/{}/core/dispatch/batch", room_id),
+            QoS::AtLeastOnce,
+        )
+        .await?;
+
+    // Ops control plane: pause/resume dispatch (manual).
+    client
+        .subscribe(format!("room/{}/core/control", room_id), QoS::AtLeastOnce)
+        .await?;
+
+    Ok(())
+}
+
+fn load_device_hmac_keys_from_env() -> anyhow::Result<std::collections::HashMap<String, Vec<u8>>> {
+    let raw = std::env::var("DEVICE_HMAC_KEYS_JSON")
+        .ok()
+        .filter(|v| !v.trim().is_empty());
+    let Some(raw) = raw else {
+        return Ok(std::collections::HashMap::new());
+    };
+
+    let map: std::collections::HashMap<String, String> = serde_json::from_str(&raw)?;
+    let mut out: std::collections::HashMap<String, Vec<u8>> = std::collections::HashMap::new();
+    for (device_id, hex_key) in map {
+        let key = hex::decode(hex_key.trim())
+            .with_context(|| format!("invalid hex for device_id={}", device_id))?;
+        out.insert(device_id, key);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone)]
+struct DeviceStatus {
+    last_heartbeat_at_unix_ms: Option<u64>,
+    last_ack_at_unix_ms: Option<u64>,
+    last_presence_at_unix_ms: Option<u64>,
+    last_state_at_unix_ms: Option<u64>,
+    last_state: Option<serde_json::Value>,
+    presence: Option<PresenceStatus>,
+    last_reported_safety: Option<SafetyState>,
+    is_offline: bool,
+    /// From the last heartbeat (upgraded, so v8 devices report `["v8"]`); empty until one arrives.
+    supported_schemas: Vec<String>,
+    /// Wire codec of the last heartbeat; commands are sent back in the same encoding.
+    codec: WireCodec,
+    /// Outstanding `TimePing`: (ping_id, core clock when sent).
+    time_ping: Option<(Uuid, u64)>,
+    /// Recent ping/pong measurements, newest last.
+    clock_samples: std::collections::VecDeque<ClockSample>,
+    /// Lowest-RTT sample of `clock_samples` (least disturbed by queuing delay).
+    clock: Option<ClockSample>,
+    clock_synced_at_unix_ms: Option<u64>,
+    clock_offset_exceeded: bool,
+    /// Mirror of [`RuntimeState::maintenance_for`], for the published device status.
+    maintenance: Option<MaintenanceStatus>,
+    /// ONLINE/OFFLINE changes within `DEVICE_FLAP_WINDOW_MS`, oldest first.
+    liveness_changes: std::collections::VecDeque<u64>,
+    /// Set while the device is flapping (its ONLINE/OFFLINE faults are suppressed).
+    flapping: Option<FlapState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FlapState {
+    since_unix_ms: u64,
+    suppressed_offline: u32,
+    suppressed_online: u32,
+}
+
+/// Ping/pong samples kept per device.
+const CLOCK_SAMPLE_WINDOW: usize = 8;
+
+impl DeviceStatus {
+    fn new() -> Self {
+        Self {
+            last_heartbeat_at_unix_ms: None,
+            last_ack_at_unix_ms: None,
+            last_presence_at_unix_ms: None,
+            last_state_at_unix_ms: None,
+            last_state: None,
+            presence: None,
+            last_reported_safety: None,
+            is_offline: true,
+            supported_schemas: Vec::new(),
+            codec: WireCodec::Json,
+            time_ping: None,
+            clock_samples: std::collections::VecDeque::new(),
+            clock: None,
+            clock_synced_at_unix_ms: None,
+            clock_offset_exceeded: false,
+            maintenance: None,
+            liveness_changes: std::collections::VecDeque::new(),
+            flapping: None,
+        }
+    }
+
+    /// Schema to stamp on commands for this device (newest both sides understand).
+    fn command_schema(&self) -> &'static str {
+        negotiate_schema(&self.supported_schemas)
+    }
+
+    /// A recent measurement puts the device clock within `CORE_CLOCK_OFFSET_MAX_MS` of core.
+    fn clock_in_sync(&self, config: &Config, now: u64) -> bool {
+        let fresh = self.clock_synced_at_unix_ms.is_some_and(|at| {
+            now.saturating_sub(at) <= config.time_sync_interval_ms.saturating_mul(3)
+        });
+        fresh && self.clock.is_some() && !self.clock_offset_exceeded
+    }
+}
+
+#[derive(Debug)]
+enum DeviceTopicKind {
+    Heartbeat,
+    Ack,
+    State,
+    Telemetry,
+    Presence,
+    Pong,
+}
+
+fn parse_device_topic(room_id: &str, topic: &str) -> Option<(String, DeviceTopicKind, WireCodec)> {
+    // room/{room_id}/device/{device_id}/{kind}[/{codec}]
+    let (topic, codec) = WireCodec::split_topic(topic);
+    let prefix = format!("room/{}/device/", room_id);
+    let rest = topic.strip_prefix(&prefix)?;
+    let mut parts = rest.split('/');
+    let device_id = parts.next()?.to_string();
+    let kind = parts.next()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let kind = match kind {
+        "heartbeat" => DeviceTopicKind::Heartbeat,
+        "ack" => DeviceTopicKind::Ack,
+        "state" => DeviceTopicKind::State,
+        "telemetry" => DeviceTopicKind::Telemetry,
+        "presence" => DeviceTopicKind::Presence,
+        "pong" => DeviceTopicKind::Pong,
+        _ => return None,
+    };
+    Some((device_id, kind, codec))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_incoming_mqtt(
+    config: &Config,
+    client: &AsyncClient,
+    msg: InboundPublish,
+    db: Option<&DbWriter>,
+    runtime: &mut RuntimeState,
+    graph_runner: &mut GraphRunner,
+    devices: &mut std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+) {
+    if msg.topic == format!("room/{}/core/dispatch", config.room_id) {
+        handle_dispatch_request(
+            config,
+            client,
+            runtime,
+            db,
+            &msg.payload,
+            devices,
+            device_sequences,
+            pending,
+            dispatch_tracker,
+            false,
+            msg.properties.as_ref(),
+        )
+        .await;
+        return;
+    }
+
+    if msg.topic == format!("room/{}/core/dispatch/batch", config.room_id) {
+        handle_batch_dispatch_request(
+            config,
+            client,
+            runtime,
+            db,
+            &msg.payload,
+            devices,
+            device_sequences,
+            pending,
+            dispatch_tracker,
+            msg.properties.as_ref(),
+        )
+        .await;
+        return;
+    }
+
+    if msg.topic == format!("room/{}/core/control", config.room_id) {
+        handle_core_control(
+            config,
+            client,
+            runtime,
+            graph_runner,
+            db,
+            devices,
+            device_sequences,
+            pending,
+            dispatch_tracker,
+            &msg.payload,
+        )
+        .await;
+        return;
+    }
+
+    let Some((device_id, kind, codec)) = parse_device_topic(&config.room_id, &msg.topic) else {
+        return;
+    };
+
+    let status = devices
+        .entry(device_id.clone())
+        .or_insert_with(|| DeviceStatus {
+            maintenance: runtime.maintenance_for(&device_id).cloned(),
+            ..DeviceStatus::new()
+        });
+
+    let mut latched_now: Vec<SafetyLatchScope> = Vec::new();
+    match kind {
+        DeviceTopicKind::Heartbeat => match decode_message_as::<Heartbeat>(codec, &msg.payload) {
+            Ok(hb) => {
+                if let Some(db) = db {
+                    if let Ok(v) = serde_json::to_value(&hb) {
+                        db.enqueue_json(
+                            &config.room_id,
+                            Some(&device_id),
+                            &msg.topic,
+                            "HEARTBEAT",
+                            hb.observed_at_unix_ms,
+                            v,
+                        );
+                    }
+                }
+                status.last_heartbeat_at_unix_ms = Some(hb.observed_at_unix_ms);
+                note_unregistered_device(config, client, runtime, db, &device_id, &hb, &msg.topic)
+                    .await;
+                note_safety_transition(
+                    config,
+                    client,
+                    db,
+                    &device_id,
+                    status,
+                    &hb.safety_state,
+                    "HEARTBEAT",
+                    hb.observed_at_unix_ms,
+                )
+                .await;
+                if status.supported_schemas != hb.supported_schemas {
+                    info!(
+                        device_id = %device_id,
+                        supported_schemas = ?hb.supported_schemas,
+                        command_schema = negotiate_schema(&hb.supported_schemas),
+                        "device schema support changed"
+                    );
+                    status.supported_schemas = hb.supported_schemas.clone();
+                }
+                if status.codec != codec {
+                    info!(device_id = %device_id, codec = %codec, "device wire codec changed");
+                    status.codec = codec;
+                }
+                latched_now.extend(
+                    maybe_latch_safety(
+                        config,
+                        client,
+                        runtime,
+                        db,
+                        &device_id,
+                        &hb.safety_state,
+                        hb.observed_at_unix_ms,
+                    )
+                    .await,
+                );
+                if recompute_device_liveness(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    &device_id,
+                    status,
+                    "heartbeat",
+                )
+                .await
+                {
+                    latched_now.extend(
+                        apply_offline_policy(
+                            config,
+                            client,
+                            runtime,
+                            db,
+                            &device_id,
                             hb.observed_at_unix_ms,
+                        )
+                        .await,
+                    );
+                }
+                info!(
+                    device_id = %device_id,
+                    uptime_ms = hb.uptime_ms,
+                    fw = %hb.firmware_version,
+                    safety = ?hb.safety_state.kind,
+                    "device heartbeat"
+                );
+            }
+            Err(err) => warn!(device_id = %device_id, error = %err, "invalid heartbeat payload"),
+        },
+        DeviceTopicKind::Ack => {
+            match decode_message_as::<CommandAck>(codec, &msg.payload) {
+                Ok(ack) => {
+                    if let Some(db) = db {
+                        if let Ok(v) = serde_json::to_value(&ack) {
+                            db.enqueue_json(
+                                &config.room_id,
+                                Some(&device_id),
+                                &msg.topic,
+                                "ACK",
+                                ack.observed_at_unix_ms,
+                                v,
+                            );
+                        }
+                    }
+                    status.last_ack_at_unix_ms = Some(ack.observed_at_unix_ms);
+                    note_safety_transition(
+                        config,
+                        client,
+                        db,
+                        &device_id,
+                        status,
+                        &ack.safety_state,
+                        "ACK",
+                        ack.observed_at_unix_ms,
+                    )
+                    .await;
+                    latched_now.extend(
+                        maybe_latch_safety(
+                            config,
+                            client,
+                            runtime,
+                            db,
+                            &device_id,
+                            &ack.safety_state,
+                            ack.observed_at_unix_ms,
+                        )
+                        .await,
+                    );
+                    if let Some(p) = pending.get_mut(&ack.command_id) {
+                        p.last_update = Instant::now();
+                        match ack.status {
+                            sentient_protocol::AckStatus::Accepted => {
+                                if !p.accepted {
+                                    runtime.latency.observe_accept(
+                                        &device_id,
+                                        p.cmd.action,
+                                        p.dispatched_at.elapsed(),
+                                    );
+                                }
+                                p.accepted = true;
+                                p.accepted_at.get_or_insert(Instant::now());
+                            }
+                            sentient_protocol::AckStatus::Completed => {
+                                if !p.completed {
+                                    // Time spent waiting for `execute_at_unix_ms` is not latency.
+                                    let wait_ms = p
+                                        .cmd
+                                        .execute_at_unix_ms
+                                        .map(|at| at.saturating_sub(p.cmd.issued_at_unix_ms))
+                                        .unwrap_or(0);
+                                    runtime.latency.observe_complete(
+                                        &device_id,
+                                        p.cmd.action,
+                                        p.dispatched_at
+                                            .elapsed()
+                                            .saturating_sub(Duration::from_millis(wait_ms)),
+                                    );
+                                }
+                                p.completed = true;
+                            }
+                            sentient_protocol::AckStatus::Rejected => {
+                                p.rejected = true;
+                                p.reason_code = ack.reason_code.clone();
+                            }
+                            sentient_protocol::AckStatus::Cancelled => {
+                                p.cancelled = true;
+                            }
+                            sentient_protocol::AckStatus::InProgress => {
+                                // Progress implies acceptance (the ACCEPTED ack may have been
+                                // lost) and keeps a slow but alive command from timing out.
+                                if !p.accepted {
+                                    runtime.latency.observe_accept(
+                                        &device_id,
+                                        p.cmd.action,
+                                        p.dispatched_at.elapsed(),
+                                    );
+                                }
+                                p.accepted = true;
+                                p.accepted_at = Some(Instant::now());
+                                p.last_progress = ack.progress.clone();
+                            }
+                        }
+
+                        // If an ack arrives after we lost inflight tracking (e.g. core restart),
+                        // backfill the correlation mapping so duplicate control-plane dispatches
+                        // won't generate multiple physical actions.
+                        dispatch_tracker.track_inflight(p.cmd.correlation_id, ack.command_id);
+                    }
+                    // 3.1.1 firmware cannot echo the trace id; fall back to the command's.
+                    let trace_id = msg.trace_id().or_else(|| {
+                        pending
+                            .get(&ack.command_id)
+                            .and_then(|p| p.trace_id.as_deref())
+                    });
+                    info!(
+                        device_id = %device_id,
+                        status = ?ack.status,
+                        command_id = %ack.command_id,
+                        trace_id,
+                        "device ack"
+                    );
+                }
+                Err(err) => warn!(device_id = %device_id, error = %err, "invalid ack payload"),
+            }
+        }
+        DeviceTopicKind::Presence => match decode_message_as::<Presence>(codec, &msg.payload) {
+            Ok(p) => {
+                if let Some(db) = db {
+                    if let Ok(v) = serde_json::to_value(&p) {
+                        db.enqueue_json(
+                            &config.room_id,
+                            Some(&device_id),
+                            &msg.topic,
+                            "PRESENCE",
+                            p.observed_at_unix_ms,
                             v,
                         );
                     }
                 }
-                status.last_heartbeat_at_unix_ms = Some(hb.observed_at_unix_ms);
-                status.last_reported_safety = Some(hb.safety_state.clone());
-                maybe_latch_safety(
+                status.last_presence_at_unix_ms = Some(p.observed_at_unix_ms);
+                status.presence = Some(p.status);
+                if recompute_device_liveness(
+                    config, client, runtime, db, &device_id, status, "presence",
+                )
+                .await
+                {
+                    latched_now.extend(
+                        apply_offline_policy(
+                            config,
+                            client,
+                            runtime,
+                            db,
+                            &device_id,
+                            p.observed_at_unix_ms,
+                        )
+                        .await,
+                    );
+                }
+            }
+            Err(err) => warn!(device_id = %device_id, error = %err, "invalid presence payload"),
+        },
+        DeviceTopicKind::State => match decode_message_as::<DeviceState>(codec, &msg.payload) {
+            Ok(st) => {
+                if let Some(db) = db {
+                    if let Ok(v) = serde_json::to_value(&st) {
+                        db.enqueue_json(
+                            &config.room_id,
+                            Some(&device_id),
+                            &msg.topic,
+                            "STATE",
+                            st.observed_at_unix_ms,
+                            v,
+                        );
+                    }
+                }
+                status.last_state_at_unix_ms = Some(st.observed_at_unix_ms);
+                status.last_state = Some(st.state.clone());
+                note_safety_transition(
+                    config,
+                    client,
+                    db,
+                    &device_id,
+                    status,
+                    &st.safety_state,
+                    "STATE",
+                    st.observed_at_unix_ms,
+                )
+                .await;
+                latched_now.extend(
+                    maybe_latch_safety(
+                        config,
+                        client,
+                        runtime,
+                        db,
+                        &device_id,
+                        &st.safety_state,
+                        st.observed_at_unix_ms,
+                    )
+                    .await,
+                );
+                info!(
+                    device_id = %device_id,
+                    safety = ?st.safety_state.kind,
+                    bytes = msg.payload.len(),
+                    "device state"
+                );
+
+                publish_device_status(config, client, &device_id, status).await;
+            }
+            Err(err) => warn!(device_id = %device_id, error = %err, "invalid state payload"),
+        },
+        DeviceTopicKind::Telemetry => {
+            if let Some(db) = db {
+                if let Ok(v) = codec.decode_value(&msg.payload) {
+                    db.enqueue_json(
+                        &config.room_id,
+                        Some(&device_id),
+                        &msg.topic,
+                        "TELEMETRY",
+                        unix_ms_now(),
+                        v,
+                    );
+                }
+            }
+            info!(device_id = %device_id, codec = %codec, bytes = msg.payload.len(), "device telemetry (raw)");
+        }
+        DeviceTopicKind::Pong => match decode_message_as::<TimePong>(codec, &msg.payload) {
+            Ok(pong) => handle_time_pong(config, client, db, &device_id, status, &pong).await,
+            Err(err) => warn!(device_id = %device_id, error = %err, "invalid pong payload"),
+        },
+    }
+
+    abort_for_safety_latches(
+        config,
+        client,
+        runtime,
+        db,
+        graph_runner,
+        devices,
+        device_sequences,
+        pending,
+        dispatch_tracker,
+        latched_now,
+    )
+    .await;
+}
+
+/// Cancels in-flight work for new latches: everything for a room latch, the zone's devices for
+/// a zone latch. Retries stay frozen while a latch holds.
+#[allow(clippy::too_many_arguments)]
+async fn abort_for_safety_latches(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    graph_runner: &GraphRunner,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+    latched_now: Vec<SafetyLatchScope>,
+) {
+    if latched_now.is_empty() {
+        return;
+    }
+    if let Some(db) = db {
+        record_latch_snapshot(config, runtime, db, graph_runner, pending, &latched_now);
+    }
+    if latched_now.contains(&SafetyLatchScope::Room) {
+        cancel_pending_commands(
+            config,
+            client,
+            runtime,
+            db,
+            devices,
+            device_sequences,
+            pending,
+            &CancelScope::All,
+            "SAFETY_LATCHED",
+        )
+        .await;
+        drop_dispatch_queues(
+            config,
+            client,
+            runtime,
+            db,
+            dispatch_tracker,
+            None,
+            "SAFETY_LATCHED",
+        )
+        .await;
+    } else {
+        // A zone latch only aborts work for that zone's devices.
+        for scope in latched_now {
+            let SafetyLatchScope::Zone(zone) = scope else {
+                continue;
+            };
+            for zone_device_id in runtime.zone_device_ids(&zone) {
+                cancel_pending_commands(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    devices,
+                    device_sequences,
+                    pending,
+                    &CancelScope::Device(zone_device_id.clone()),
+                    "SAFETY_ZONE_LATCHED",
+                )
+                .await;
+                drop_dispatch_queues(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    dispatch_tracker,
+                    Some(&zone_device_id),
+                    "SAFETY_ZONE_LATCHED",
+                )
+                .await;
+            }
+        }
+    }
+}
+
+/// Stores what was running when safety latched (`SAFETY_LATCH_SNAPSHOT`, events table only), before
+/// the latch aborts it, so incident reports can show in-flight commands and graph position.
+fn record_latch_snapshot(
+    config: &Config,
+    runtime: &RuntimeState,
+    db: &DbWriter,
+    graph_runner: &GraphRunner,
+    pending: &std::collections::HashMap<Uuid, PendingCommand>,
+    latched_now: &[SafetyLatchScope],
+) {
+    let room_latched = latched_now.contains(&SafetyLatchScope::Room);
+    let zones: Vec<&str> = latched_now
+        .iter()
+        .filter_map(|scope| match scope {
+            SafetyLatchScope::Zone(zone) => Some(zone.as_str()),
+            SafetyLatchScope::Room => None,
+        })
+        .collect();
+    let mut in_flight: Vec<&PendingCommand> = pending
+        .values()
+        .filter(|p| !p.completed && !p.rejected && !p.cancelled)
+        .filter(|p| {
+            room_latched
+                || runtime
+                    .device_registry
+                    .get(&p.device_id)
+                    .and_then(|r| r.zone.as_deref())
+                    .is_some_and(|zone| zones.contains(&zone))
+        })
+        .collect();
+    in_flight.sort_by_key(|p| (p.cmd.issued_at_unix_ms, p.cmd.command_id));
+    let in_flight_commands: Vec<serde_json::Value> = in_flight
+        .into_iter()
+        .map(|p| {
+            serde_json::json!({
+                "command_id": p.cmd.command_id,
+                "correlation_id": p.cmd.correlation_id,
+                "device_id": p.device_id,
+                "action": p.cmd.action,
+                "sequence": p.cmd.sequence,
+                "issued_at_unix_ms": p.cmd.issued_at_unix_ms,
+                "accepted": p.accepted,
+                "progress": p.last_progress,
+                "cancels": p.cancels,
+            })
+        })
+        .collect();
+    let now = unix_ms_now();
+    db.enqueue_json(
+        &config.room_id,
+        None,
+        &format!("room/{}/core/safety", config.room_id),
+        "SAFETY_LATCH_SNAPSHOT",
+        now,
+        serde_json::json!({
+            "schema": SCHEMA_VERSION,
+            "room_id": config.room_id,
+            "room_latched": room_latched,
+            "zones": zones,
+            "in_flight_commands": in_flight_commands,
+            "graph_version": graph_runner.graph_version,
+            "graph_running": graph_runner.is_running(),
+            "graph_active_nodes": graph_runner
+                .active_nodes
+                .iter()
+                .map(|n| n.node_id.clone())
+                .collect::<Vec<_>>(),
+            "observed_at_unix_ms": now,
+        }),
+    );
+}
+
+#[derive(Debug, Clone)]
+struct PendingCommand {
+    device_id: String,
+    cmd: CommandEnvelope,
+    codec: WireCodec,
+    /// Last (re)transmission; drives the ack timeout.
+    published_at: Instant,
+    /// First transmission; latency metrics are measured from here (retries included).
+    dispatched_at: Instant,
+    last_update: Instant,
+    retries_left: u32,
+    ack_timeout_ms: u64,
+    complete_timeout_ms: u64,
+    accepted: bool,
+    accepted_at: Option<Instant>,
+    completed: bool,
+    rejected: bool,
+    reason_code: Option<String>,
+    /// Set on `CANCEL` envelopes: the command they abort.
+    cancels: Option<Uuid>,
+    /// Set on a command once a `CANCEL` for it has been published (never resent afterwards).
+    cancel_reason: Option<String>,
+    cancelled: bool,
+    /// Latest `IN_PROGRESS` report; each one restarts the completion window.
+    last_progress: Option<CommandProgress>,
+    /// Dispatch reply topic still waiting for a decisive status (cleared once answered).
+    reply_topic: Option<String>,
+    /// Trace id of the dispatch, sent with every (re)transmission as an MQTT v5 user property.
+    trace_id: Option<String>,
+}
+
+/// Upper bound on remembered dispatch results (idempotency window entries).
+const DISPATCH_RECENT_MAX: usize = 10_000;
+
+/// How long a finished dispatch answers retries with the same `correlation_id`.
+const DISPATCH_IDEMPOTENCY_TTL: Duration = Duration::from_secs(60 * 10);
+
+/// Finished results kept for [`DISPATCH_IDEMPOTENCY_TTL`], at most [`DISPATCH_RECENT_MAX`] of
+/// them, evicted oldest first.
+#[derive(Debug)]
+struct RecentResults<T> {
+    by_id: std::collections::HashMap<Uuid, (Instant, T)>,
+    // Insertion order. An id inserted again leaves a stale entry behind, skipped on eviction.
+    order: std::collections::VecDeque<(Instant, Uuid)>,
+}
+
+impl<T> Default for RecentResults<T> {
+    fn default() -> Self {
+        Self {
+            by_id: std::collections::HashMap::new(),
+            order: std::collections::VecDeque::new(),
+        }
+    }
+}
+
+impl<T> RecentResults<T> {
+    fn insert(&mut self, id: Uuid, result: T) {
+        let now = Instant::now();
+        self.by_id.insert(id, (now, result));
+        self.order.push_back((now, id));
+        // Evict the oldest entries only: clearing everything would let a retry inside the TTL
+        // dispatch a second time.
+        while self.by_id.len() > DISPATCH_RECENT_MAX {
+            self.pop_oldest();
+        }
+    }
+
+    fn get(&self, id: Uuid) -> Option<&T> {
+        self.by_id
+            .get(&id)
+            .filter(|(t, _)| t.elapsed() <= DISPATCH_IDEMPOTENCY_TTL)
+            .map(|(_, result)| result)
+    }
+
+    fn sweep(&mut self) {
+        while self
+            .order
+            .front()
+            .is_some_and(|(t, _)| t.elapsed() > DISPATCH_IDEMPOTENCY_TTL)
+        {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some((at, id)) = self.order.pop_front() {
+            if self.by_id.get(&id).is_some_and(|(t, _)| *t == at) {
+                self.by_id.remove(&id);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DispatchTracker {
+    // correlation_id -> command_id
+    inflight: std::collections::HashMap<Uuid, Uuid>,
+    // correlation_id -> final result
+    recent: RecentResults<CoreDispatchResult>,
+    // device_id -> dispatches waiting for the device to go idle (QUEUE policy)
+    queued: std::collections::HashMap<String, std::collections::VecDeque<QueuedDispatch>>,
+    // dispatches held until their release time (execute_at_unix_ms, less any lead)
+    scheduled: Vec<ScheduledDispatch>,
+    // correlation_id -> batch still running
+    batches: std::collections::HashMap<Uuid, BatchState>,
+    // correlation_id -> aggregate result
+    recent_batches: RecentResults<CoreBatchDispatchResult>,
+    // correlation_id -> MQTT v5 request metadata echoed on results
+    request_meta: std::collections::HashMap<Uuid, DispatchRequestMeta>,
+}
+
+/// MQTT v5 properties a dispatch request arrived with: its correlation data goes back on every
+/// result, its trace id onto the device commands and results.
+#[derive(Debug, Clone)]
+struct DispatchRequestMeta {
+    correlation_data: Option<bytes::Bytes>,
+    trace_id: Option<String>,
+    received_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct BatchState {
+    members: Vec<BatchMemberResult>,
+    reply_topic: Option<String>,
+}
+
+impl BatchState {
+    fn is_finished(&self) -> bool {
+        self.members.iter().all(|m| m.status.is_some())
+    }
+
+    /// `COMPLETED` only if every member completed; otherwise the worst member outcome.
+    fn aggregate_status(&self) -> DispatchResultStatus {
+        [
+            DispatchResultStatus::Rejected,
+            DispatchResultStatus::Timeout,
+            DispatchResultStatus::Cancelled,
+        ]
+        .into_iter()
+        .find(|worst| self.members.iter().any(|m| m.status == Some(*worst)))
+        .unwrap_or(DispatchResultStatus::Completed)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct QueuedDispatch {
+    /// `correlation_id` is always set (assigned on enqueue if the caller omitted it).
+    req: CoreDispatchRequest,
+    queued_at_unix_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledDispatch {
+    /// `correlation_id` and `execute_at_unix_ms` are always set; `execute_in_ms` is cleared.
+    req: CoreDispatchRequest,
+    /// When core hands it to dispatch: the execute time, or earlier by `CORE_SCHEDULE_LEAD_MS`
+    /// for devices that honor `CommandEnvelope.execute_at_unix_ms`.
+    release_at_unix_ms: u64,
+}
+
+impl DispatchTracker {
+    fn track_inflight(&mut self, correlation_id: Uuid, command_id: Uuid) {
+        self.inflight.insert(correlation_id, command_id);
+    }
+
+    fn inflight_command_id(&self, correlation_id: Uuid) -> Option<Uuid> {
+        self.inflight.get(&correlation_id).copied()
+    }
+
+    fn mark_done(&mut self, result: CoreDispatchResult) {
+        if let Some(batch) = self.batches.get_mut(&result.correlation_id) {
+            // Batch members are aggregated by `tick_dispatch_batches` instead.
+            if let Some(member) = batch
+                .members
+                .iter_mut()
+                .find(|m| m.command_id.is_some() && m.command_id == result.command_id)
+            {
+                member.status = Some(result.status);
+                member.fault_kind = result.fault_kind;
+                member.reason_code = result.reason_code;
+            }
+            return;
+        }
+        self.inflight.remove(&result.correlation_id);
+        self.recent.insert(result.correlation_id, result);
+    }
+
+    fn recent_result(&self, correlation_id: Uuid) -> Option<&CoreDispatchResult> {
+        self.recent.get(correlation_id)
+    }
+
+    fn sweep_recent(&mut self) {
+        self.recent.sweep();
+        self.recent_batches.sweep();
+        // Request metadata outlives the window while its dispatch can still produce results.
+        let live: std::collections::HashSet<Uuid> = self
+            .inflight
+            .keys()
+            .chain(self.batches.keys())
+            .copied()
+            .chain(
+                self.queued
+                    .values()
+                    .flatten()
+                    .filter_map(|q| q.req.correlation_id),
+            )
+            .chain(self.scheduled.iter().filter_map(|s| s.req.correlation_id))
+            .collect();
+        self.request_meta.retain(|id, m| {
+            m.received_at.elapsed() <= DISPATCH_IDEMPOTENCY_TTL || live.contains(id)
+        });
+    }
+
+    /// Remembers the MQTT v5 correlation data and trace id of a dispatch request (latest wins,
+    /// so a retried request is answered the way its sender expects).
+    fn note_request_meta(&mut self, correlation_id: Uuid, properties: Option<&PublishProperties>) {
+        let Some(properties) = properties else {
+            return;
+        };
+        let correlation_data = properties.correlation_data.clone();
+        let trace_id =
+            trace_id_from_user_properties(&properties.user_properties).map(str::to_string);
+        if correlation_data.is_none() && trace_id.is_none() {
+            return;
+        }
+        self.request_meta.insert(
+            correlation_id,
+            DispatchRequestMeta {
+                correlation_data,
+                trace_id,
+                received_at: Instant::now(),
+            },
+        );
+    }
+
+    fn trace_id(&self, correlation_id: Uuid) -> Option<String> {
+        self.request_meta
+            .get(&correlation_id)
+            .and_then(|m| m.trace_id.clone())
+    }
+
+    /// MQTT v5 properties for a result on `correlation_id`: the request's correlation data and
+    /// trace id, if it had any.
+    fn reply_properties(&self, correlation_id: Uuid) -> PublishProperties {
+        let meta = self.request_meta.get(&correlation_id);
+        PublishProperties {
+            correlation_data: meta.and_then(|m| m.correlation_data.clone()),
+            ..trace_properties(meta.and_then(|m| m.trace_id.as_deref()))
+        }
+    }
+
+    fn remember_batch(&mut self, result: CoreBatchDispatchResult) {
+        self.recent_batches.insert(result.correlation_id, result);
+    }
+
+    fn recent_batch_result(&self, correlation_id: Uuid) -> Option<&CoreBatchDispatchResult> {
+        self.recent_batches.get(correlation_id)
+    }
+
+    fn queue_len(&self, device_id: &str) -> usize {
+        self.queued.get(device_id).map_or(0, |q| q.len())
+    }
+
+    fn is_queued(&self, correlation_id: Uuid) -> bool {
+        self.queued
+            .values()
+            .flatten()
+            .any(|q| q.req.correlation_id == Some(correlation_id))
+    }
+
+    fn is_scheduled(&self, correlation_id: Uuid) -> bool {
+        self.scheduled
+            .iter()
+            .any(|s| s.req.correlation_id == Some(correlation_id))
+    }
+
+    /// Removes and returns scheduled dispatches whose release time has come, earliest first.
+    fn take_due_scheduled(&mut self, now_unix_ms: u64) -> Vec<ScheduledDispatch> {
+        let (mut due, later): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scheduled)
+            .into_iter()
+            .partition(|s| s.release_at_unix_ms <= now_unix_ms);
+        self.scheduled = later;
+        due.sort_by_key(|s| s.release_at_unix_ms);
+        due
+    }
+
+    fn take_scheduled(&mut self, device_id: &str) -> Vec<ScheduledDispatch> {
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scheduled)
+            .into_iter()
+            .partition(|s| s.req.device_id == device_id);
+        self.scheduled = kept;
+        taken
+    }
+
+    fn enqueue(&mut self, device_id: &str, queued: QueuedDispatch) {
+        self.queued
+            .entry(device_id.to_string())
+            .or_default()
+            .push_back(queued);
+    }
+
+    fn pop_queued(&mut self, device_id: &str) -> Option<QueuedDispatch> {
+        let queue = self.queued.get_mut(device_id)?;
+        let next = queue.pop_front();
+        if queue.is_empty() {
+            self.queued.remove(device_id);
+        }
+        next
+    }
+
+    fn take_queue(&mut self, device_id: &str) -> Vec<QueuedDispatch> {
+        self.queued
+            .remove(device_id)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `device_id` has a command in flight (cancel envelopes excluded; a command that is
+/// being cancelled stays in flight until the device confirms).
+fn device_has_inflight(
+    pending: &std::collections::HashMap<Uuid, PendingCommand>,
+    device_id: &str,
+) -> bool {
+    pending.values().any(|p| {
+        p.device_id == device_id
+            && p.cancels.is_none()
+            && !p.completed
+            && !p.rejected
+            && !p.cancelled
+    })
+}
+
+fn dispatch_result(
+    config: &Config,
+    device_id: &str,
+    correlation_id: Uuid,
+    status: DispatchResultStatus,
+    command_id: Option<Uuid>,
+    fault_kind: Option<FaultKind>,
+    reason_code: Option<String>,
+) -> CoreDispatchResult {
+    CoreDispatchResult {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        device_id: device_id.to_string(),
+        correlation_id,
+        status,
+        command_id,
+        fault_kind,
+        reason_code,
+        duplicate: false,
+        observed_at_unix_ms: unix_ms_now(),
+    }
+}
+
+fn pending_result(
+    config: &Config,
+    command_id: Uuid,
+    p: &PendingCommand,
+    status: DispatchResultStatus,
+    fault_kind: Option<FaultKind>,
+) -> CoreDispatchResult {
+    dispatch_result(
+        config,
+        &p.device_id,
+        p.cmd.correlation_id,
+        status,
+        Some(command_id),
+        fault_kind,
+        p.reason_code.clone().or_else(|| p.cancel_reason.clone()),
+    )
+}
+
+/// Answers a dispatch caller on its reply topic (QoS1, never retained). `properties` come from
+/// [`DispatchTracker::reply_properties`].
+async fn publish_dispatch_result<T: serde::Serialize>(
+    client: &AsyncClient,
+    reply_topic: &str,
+    properties: PublishProperties,
+    result: &T,
+) {
+    match serde_json::to_vec(result) {
+        Ok(bytes) => {
+            if let Err(err) = client
+                .publish_with_properties(reply_topic, QoS::AtLeastOnce, false, bytes, properties)
+                .await
+            {
+                warn!(error=%err, "failed to publish dispatch result");
+            }
+        }
+        Err(err) => warn!(error=%err, "failed to serialize dispatch result"),
+    }
+}
+
+/// Retained snapshot of a device's dispatch queue on `core/device/{device_id}/queue`.
+async fn publish_device_queue(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    dispatch_tracker: &DispatchTracker,
+    device_id: &str,
+) {
+    let (policy, max_depth) = device_concurrency(config, runtime, device_id);
+    let queued: Vec<serde_json::Value> = dispatch_tracker
+        .queued
+        .get(device_id)
+        .into_iter()
+        .flatten()
+        .map(|q| {
+            serde_json::json!({
+                "correlation_id": q.req.correlation_id,
+                "action": q.req.action,
+                "parameters": q.req.parameters,
+                "safety_class": q.req.safety_class,
+                "queued_at_unix_ms": q.queued_at_unix_ms,
+            })
+        })
+        .collect();
+    let scheduled: Vec<serde_json::Value> = dispatch_tracker
+        .scheduled
+        .iter()
+        .filter(|s| s.req.device_id == device_id)
+        .map(|s| {
+            serde_json::json!({
+                "correlation_id": s.req.correlation_id,
+                "action": s.req.action,
+                "execute_at_unix_ms": s.req.execute_at_unix_ms,
+                "release_at_unix_ms": s.release_at_unix_ms,
+            })
+        })
+        .collect();
+    let payload = serde_json::json!({
+        "schema": SCHEMA_VERSION,
+        "room_id": config.room_id,
+        "device_id": device_id,
+        "policy": policy.as_str(),
+        "max_depth": max_depth,
+        "queued": queued,
+        "scheduled": scheduled,
+        "computed_at_unix_ms": unix_ms_now(),
+    });
+    let topic = format!("room/{}/core/device/{}/queue", config.room_id, device_id);
+    if let Ok(bytes) = serde_json::to_vec(&payload) {
+        if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, bytes).await {
+            warn!(error=%err, "failed to publish device queue");
+        }
+    }
+}
+
+/// Drops queued and scheduled dispatches (every device when `device_id` is `None`), raising one
+/// `DISPATCH_QUEUE_DROPPED` per device.
+async fn drop_dispatch_queues(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    dispatch_tracker: &mut DispatchTracker,
+    device_id: Option<&str>,
+    reason: &str,
+) {
+    let device_ids: Vec<String> = match device_id {
+        Some(d) => vec![d.to_string()],
+        None => {
+            let mut ids: Vec<String> = dispatch_tracker
+                .queued
+                .keys()
+                .cloned()
+                .chain(
+                    dispatch_tracker
+                        .scheduled
+                        .iter()
+                        .map(|s| s.req.device_id.clone()),
+                )
+                .collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        }
+    };
+    for device_id in device_ids {
+        let dropped: Vec<CoreDispatchRequest> = dispatch_tracker
+            .take_queue(&device_id)
+            .into_iter()
+            .map(|q| q.req)
+            .chain(
+                dispatch_tracker
+                    .take_scheduled(&device_id)
+                    .into_iter()
+                    .map(|s| s.req),
+            )
+            .collect();
+        if dropped.is_empty() {
+            continue;
+        }
+        let correlation_ids: Vec<Uuid> = dropped.iter().filter_map(|r| r.correlation_id).collect();
+        warn!(device_id=%device_id, count=dropped.len(), reason=%reason, "dropping queued dispatches");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchQueueDropped,
+            Severity::Info,
+            "Queued dispatches dropped",
+            serde_json::json!({
+                "device_id": device_id,
+                "correlation_ids": correlation_ids,
+                "reason": reason,
+            }),
+        )
+        .await;
+        for r in &dropped {
+            if let (Some(reply_topic), Some(correlation_id)) = (
+                valid_reply_topic(config, r.reply_topic.as_deref()),
+                r.correlation_id,
+            ) {
+                let result = dispatch_result(
+                    config,
+                    &device_id,
+                    correlation_id,
+                    DispatchResultStatus::Cancelled,
+                    None,
+                    Some(FaultKind::DispatchQueueDropped),
+                    Some(reason.to_string()),
+                );
+                publish_dispatch_result(
+                    client,
+                    reply_topic,
+                    dispatch_tracker.reply_properties(correlation_id),
+                    &result,
+                )
+                .await;
+            }
+        }
+        publish_device_queue(config, client, runtime, dispatch_tracker, &device_id).await;
+    }
+}
+
+/// Hands the head of each device queue to dispatch once that device is idle.
+#[allow(clippy::too_many_arguments)]
+async fn drain_dispatch_queues(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+) {
+    // Pauses hold the queue; it resumes draining with dispatch.
+    if dispatch_tracker.queued.is_empty() || runtime.dispatch_is_paused() {
+        return;
+    }
+    let ready: Vec<String> = dispatch_tracker
+        .queued
+        .keys()
+        .filter(|d| !device_has_inflight(pending, d))
+        .cloned()
+        .collect();
+    for device_id in ready {
+        let Some(next) = dispatch_tracker.pop_queued(&device_id) else {
+            continue;
+        };
+        info!(device_id=%device_id, correlation_id=?next.req.correlation_id, "dequeued dispatch");
+        match serde_json::to_vec(&next.req) {
+            Ok(payload) => {
+                handle_dispatch_request(
                     config,
                     client,
                     runtime,
                     db,
-                    &device_id,
-                    &hb.safety_state,
-                    hb.observed_at_unix_ms,
+                    &payload,
+                    devices,
+                    device_sequences,
+                    pending,
+                    dispatch_tracker,
+                    true,
+                    None,
                 )
                 .await;
-                recompute_device_liveness(config, client, db, &device_id, status, "heartbeat")
-                    .await;
-                info!(
-                    device_id = %device_id,
-                    uptime_ms = hb.uptime_ms,
-                    fw = %hb.firmware_version,
-                    safety = ?hb.safety_state.kind,
-                    "device heartbeat"
-                );
             }
-            Err(err) => warn!(device_id = %device_id, error = %err, "invalid heartbeat payload"),
-        },
-        DeviceTopicKind::Ack => {
-            match serde_json::from_slice::<CommandAck>(&msg.payload) {
-                Ok(ack) => {
-                    if let Some(db) = db {
-                        if let Ok(v) = serde_json::to_value(&ack) {
-                            db.enqueue_json(
-                                &config.room_id,
-                                Some(&device_id),
-                                &msg.topic,
-                                "ACK",
-                                ack.observed_at_unix_ms,
-                                v,
-                            );
-                        }
-                    }
-                    status.last_ack_at_unix_ms = Some(ack.observed_at_unix_ms);
-                    status.last_reported_safety = Some(ack.safety_state.clone());
-                    maybe_latch_safety(
+            Err(err) => warn!(error=%err, "failed to serialize queued dispatch"),
+        }
+        publish_device_queue(config, client, runtime, dispatch_tracker, &device_id).await;
+    }
+}
+
+/// Hands scheduled dispatches to dispatch once their release time has come. Gates (pause, safety,
+/// device state) are checked then, not when the request was scheduled.
+#[allow(clippy::too_many_arguments)]
+async fn release_scheduled_dispatches(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+) {
+    if dispatch_tracker.scheduled.is_empty() {
+        return;
+    }
+    for due in dispatch_tracker.take_due_scheduled(unix_ms_now()) {
+        let device_id = due.req.device_id.clone();
+        info!(
+            device_id=%device_id,
+            correlation_id=?due.req.correlation_id,
+            execute_at_unix_ms=?due.req.execute_at_unix_ms,
+            "releasing scheduled dispatch"
+        );
+        match serde_json::to_vec(&due.req) {
+            Ok(payload) => {
+                handle_dispatch_request(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    &payload,
+                    devices,
+                    device_sequences,
+                    pending,
+                    dispatch_tracker,
+                    false,
+                    None,
+                )
+                .await;
+            }
+            Err(err) => warn!(error=%err, "failed to serialize scheduled dispatch"),
+        }
+        publish_device_queue(config, client, runtime, dispatch_tracker, &device_id).await;
+    }
+}
+
+/// Resolves `execute_in_ms` into `execute_at_unix_ms` and holds `req` until its release time.
+/// `None` means dispatch now (unscheduled, due, or a duplicate for `dispatch_request` to answer).
+#[allow(clippy::too_many_arguments)]
+async fn hold_scheduled_dispatch(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    req: &mut CoreDispatchRequest,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    pending: &std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+) -> Option<DispatchOutcome> {
+    let now = unix_ms_now();
+    if let Some(delay_ms) = req.execute_in_ms.take() {
+        req.execute_at_unix_ms
+            .get_or_insert(now.saturating_add(delay_ms));
+    }
+    let execute_at = req.execute_at_unix_ms?;
+    let correlation_id = req.correlation_id?;
+    let known = dispatch_tracker.recent_result(correlation_id).is_some()
+        || dispatch_tracker
+            .inflight_command_id(correlation_id)
+            .is_some_and(|command_id| pending.contains_key(&command_id))
+        || dispatch_tracker.is_queued(correlation_id)
+        || dispatch_tracker.is_scheduled(correlation_id);
+    if known {
+        return None;
+    }
+    let device_id = req.device_id.clone();
+
+    if execute_at > now.saturating_add(config.schedule_max_ahead_ms) {
+        warn!(device_id=%device_id, correlation_id=%correlation_id, execute_at, "dispatch blocked: execute time too far ahead");
+        raise_core_fault(
+            config,
+            client,
+            db,
+            FaultKind::DispatchRequestInvalid,
+            Severity::Warn,
+            "Invalid core dispatch: execute_at_unix_ms beyond CORE_SCHEDULE_MAX_AHEAD_MS",
+            serde_json::json!({
+                "error": "execute_at_unix_ms too far ahead",
+                "topic": format!("room/{}/core/dispatch", config.room_id),
+                "device_id": device_id,
+                "correlation_id": correlation_id,
+                "execute_at_unix_ms": execute_at,
+                "max_ahead_ms": config.schedule_max_ahead_ms,
+            }),
+        )
+        .await;
+        return Some(DispatchOutcome::Blocked(FaultKind::DispatchRequestInvalid));
+    }
+
+    if now > execute_at.saturating_add(config.schedule_max_late_ms) {
+        let late_ms = now - execute_at;
+        warn!(device_id=%device_id, correlation_id=%correlation_id, late_ms, "dispatch blocked: execute time already passed");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchScheduleMissed,
+            Severity::Warn,
+            "Scheduled dispatch missed its execute time",
+            serde_json::json!({
+                "device_id": device_id,
+                "correlation_id": correlation_id,
+                "execute_at_unix_ms": execute_at,
+                "late_ms": late_ms,
+            }),
+        )
+        .await;
+        return Some(DispatchOutcome::Blocked(FaultKind::DispatchScheduleMissed));
+    }
+
+    // Early release only helps if the device's clock is known to agree with ours.
+    let device_keeps_time = devices.get(&device_id).is_some_and(|d| {
+        schema_at_least(d.command_schema(), SCHEDULE_MIN_SCHEMA) && d.clock_in_sync(config, now)
+    });
+    let release_at_unix_ms = if device_keeps_time {
+        execute_at.saturating_sub(config.schedule_lead_ms)
+    } else {
+        execute_at
+    };
+    if now >= release_at_unix_ms {
+        return None;
+    }
+    info!(
+        device_id=%device_id,
+        correlation_id=%correlation_id,
+        execute_at,
+        release_at_unix_ms,
+        "dispatch scheduled"
+    );
+    dispatch_tracker.scheduled.push(ScheduledDispatch {
+        req: req.clone(),
+        release_at_unix_ms,
+    });
+    publish_device_queue(config, client, runtime, dispatch_tracker, &device_id).await;
+    Some(DispatchOutcome::Scheduled)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_dispatch_request(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    payload: &[u8],
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+    // True when draining a device queue: skips the FIFO check so the head can run.
+    from_queue: bool,
+    // MQTT v5 properties of the request; `None` for internal (queue, graph) dispatches.
+    properties: Option<&PublishProperties>,
+) {
+    let mut req: CoreDispatchRequest = match decode_message(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error=%err, "invalid core dispatch payload");
+            raise_dispatch_request_invalid(
+                config,
+                client,
+                db,
+                "core/dispatch",
+                "Invalid core dispatch payload (JSON)",
+                &err.to_string(),
+            )
+            .await;
+            return;
+        }
+    };
+
+    if req.room_id != config.room_id {
+        warn!(schema=%req.schema, room_id=%req.room_id, "ignoring dispatch request for wrong room");
+        return;
+    }
+
+    // Replies are keyed by correlation_id, so make sure there is one before dispatching.
+    let correlation_id = *req.correlation_id.get_or_insert_with(Uuid::new_v4);
+    apply_request_properties(
+        &mut req.reply_topic,
+        correlation_id,
+        properties,
+        dispatch_tracker,
+    );
+    let reply_topic = valid_reply_topic(config, req.reply_topic.as_deref()).map(str::to_string);
+    if req.reply_topic.is_some() && reply_topic.is_none() {
+        warn!(reply_topic=?req.reply_topic, "ignoring dispatch reply_topic outside the room reply prefix");
+    }
+    let device_id = req.device_id.clone();
+
+    let outcome = match hold_scheduled_dispatch(
+        config,
+        client,
+        runtime,
+        db,
+        &mut req,
+        devices,
+        pending,
+        dispatch_tracker,
+    )
+    .await
+    {
+        Some(outcome) => outcome,
+        None => {
+            dispatch_request(
+                config,
+                client,
+                runtime,
+                db,
+                req,
+                devices,
+                device_sequences,
+                pending,
+                dispatch_tracker,
+                if from_queue {
+                    DispatchOrigin::Queue
+                } else {
+                    DispatchOrigin::Request
+                },
+            )
+            .await
+        }
+    };
+    DISPATCHES.inc(&[outcome.metric_label()]);
+    let Some(reply_topic) = reply_topic else {
+        return;
+    };
+
+    let result = match outcome {
+        DispatchOutcome::Published(command_id) => {
+            if let Some(p) = pending.get_mut(&command_id) {
+                p.reply_topic = Some(reply_topic);
+            }
+            return;
+        }
+        DispatchOutcome::Queued => dispatch_result(
+            config,
+            &device_id,
+            correlation_id,
+            DispatchResultStatus::Queued,
+            None,
+            None,
+            None,
+        ),
+        DispatchOutcome::Scheduled => dispatch_result(
+            config,
+            &device_id,
+            correlation_id,
+            DispatchResultStatus::Scheduled,
+            None,
+            None,
+            None,
+        ),
+        DispatchOutcome::Blocked(kind) => dispatch_result(
+            config,
+            &device_id,
+            correlation_id,
+            DispatchResultStatus::Blocked,
+            None,
+            Some(kind),
+            None,
+        ),
+        DispatchOutcome::Failed => return,
+        DispatchOutcome::Duplicate => {
+            if let Some(recent) = dispatch_tracker.recent_result(correlation_id) {
+                CoreDispatchResult {
+                    duplicate: true,
+                    ..recent.clone()
+                }
+            } else if let Some(p) = dispatch_tracker
+                .inflight_command_id(correlation_id)
+                .and_then(|command_id| pending.get_mut(&command_id))
+            {
+                // Still in flight: answer once the device does.
+                p.reply_topic = Some(reply_topic);
+                return;
+            } else if dispatch_tracker.is_scheduled(correlation_id) {
+                CoreDispatchResult {
+                    duplicate: true,
+                    ..dispatch_result(
                         config,
-                        client,
-                        runtime,
-                        db,
                         &device_id,
-                        &ack.safety_state,
-                        ack.observed_at_unix_ms,
+                        correlation_id,
+                        DispatchResultStatus::Scheduled,
+                        None,
+                        None,
+                        None,
                     )
-                    .await;
-                    if let Some(p) = pending.get_mut(&ack.command_id) {
-                        p.last_update = Instant::now();
-                        match ack.status {
-                            sentient_protocol::AckStatus::Accepted => {
-                                p.accepted = true;
-                                p.accepted_at.get_or_insert(Instant::now());
-                            }
-                            sentient_protocol::AckStatus::Completed => {
-                                p.completed = true;
-                            }
-                            sentient_protocol::AckStatus::Rejected => {
-                                p.rejected = true;
-                                p.reason_code = ack.reason_code.clone();
-                            }
-                        }
-
-                        // If an ack arrives after we lost inflight tracking (e.g. core restart),
-                        // backfill the correlation mapping so duplicate control-plane dispatches
-                        // won't generate multiple physical actions.
-                        dispatch_tracker.track_inflight(p.cmd.correlation_id, ack.command_id);
-                    }
-                    info!(
-                        device_id = %device_id,
-                        status = ?ack.status,
-                        command_id = %ack.command_id,
-                        "device ack"
-                    );
                 }
-                Err(err) => warn!(device_id = %device_id, error = %err, "invalid ack payload"),
+            } else {
+                CoreDispatchResult {
+                    duplicate: true,
+                    ..dispatch_result(
+                        config,
+                        &device_id,
+                        correlation_id,
+                        DispatchResultStatus::Queued,
+                        None,
+                        None,
+                        None,
+                    )
+                }
+            }
+        }
+    };
+    publish_dispatch_result(
+        client,
+        &reply_topic,
+        dispatch_tracker.reply_properties(correlation_id),
+        &result,
+    )
+    .await;
+}
+
+/// Folds a (batch) dispatch request's MQTT v5 properties in: the response topic replaces the
+/// payload `reply_topic` (still validated like it), correlation data and trace id are kept for
+/// the results.
+fn apply_request_properties(
+    reply_topic: &mut Option<String>,
+    correlation_id: Uuid,
+    properties: Option<&PublishProperties>,
+    dispatch_tracker: &mut DispatchTracker,
+) {
+    if let Some(response_topic) = properties.and_then(|p| p.response_topic.as_deref()) {
+        *reply_topic = Some(response_topic.to_string());
+    }
+    dispatch_tracker.note_request_meta(correlation_id, properties);
+}
+
+/// `reply_topic` if it sits under this room's dispatch reply prefix.
+fn valid_reply_topic<'a>(config: &Config, reply_topic: Option<&'a str>) -> Option<&'a str> {
+    let prefix = dispatch_reply_prefix(&config.room_id);
+    reply_topic
+        .filter(|t| t.len() > prefix.len() && t.starts_with(&prefix) && !t.contains(['+', '#']))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DispatchOrigin {
+    Request,
+    /// Head of a device queue: skips the FIFO check so it can run.
+    Queue,
+    /// Member of a batch that already passed gating as a whole; tracked by the batch, not by
+    /// correlation_id (members share one).
+    Batch,
+}
+
+#[derive(Debug)]
+enum DispatchOutcome {
+    Published(Uuid),
+    Queued,
+    /// Held until its execute time (see [`hold_scheduled_dispatch`]).
+    Scheduled,
+    /// Refused up front; the fault has already been raised.
+    Blocked(FaultKind),
+    /// Same correlation_id is queued, in flight or recently finished; nothing was published.
+    Duplicate,
+    Failed,
+}
+
+impl DispatchOutcome {
+    fn metric_label(&self) -> &'static str {
+        match self {
+            DispatchOutcome::Published(_) => "published",
+            DispatchOutcome::Queued => "queued",
+            DispatchOutcome::Scheduled => "scheduled",
+            DispatchOutcome::Blocked(_) => "blocked",
+            DispatchOutcome::Duplicate => "duplicate",
+            DispatchOutcome::Failed => "failed",
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn dispatch_request(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    req: CoreDispatchRequest,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+    origin: DispatchOrigin,
+) -> DispatchOutcome {
+    if req.action == CommandAction::Cancel {
+        warn!(device_id=%req.device_id, "ignoring dispatch request: CANCEL must use core control");
+        raise_dispatch_request_invalid(
+            config,
+            client,
+            db,
+            "core/dispatch",
+            "Invalid core dispatch: use CANCEL_COMMAND / CANCEL_DEVICE_COMMANDS",
+            "action CANCEL is not dispatchable",
+        )
+        .await;
+        return DispatchOutcome::Blocked(FaultKind::DispatchRequestInvalid);
+    }
+
+    let device_id = req.device_id.clone();
+    let correlation_id = req.correlation_id.unwrap_or_else(Uuid::new_v4);
+
+    // Control-plane idempotency: if tools retry the dispatch request with the same correlation_id,
+    // do not generate new command_ids while the original is inflight (or recently completed).
+    // Checked before any gate, so a retry learns the original outcome even if a gate has closed
+    // since.
+    dispatch_tracker.sweep_recent();
+    if dispatch_tracker.recent_result(correlation_id).is_some() {
+        warn!(device_id=%device_id, correlation_id=%correlation_id, "duplicate dispatch request (recently completed)");
+        return DispatchOutcome::Duplicate;
+    }
+    if let Some(existing_cmd_id) = dispatch_tracker.inflight_command_id(correlation_id) {
+        if pending.contains_key(&existing_cmd_id) {
+            warn!(
+                device_id=%device_id,
+                correlation_id=%correlation_id,
+                command_id=%existing_cmd_id,
+                "duplicate dispatch request (already inflight)"
+            );
+            return DispatchOutcome::Duplicate;
+        }
+        // stale inflight mapping (e.g. removed by timeout); allow a fresh dispatch
+        dispatch_tracker.inflight.remove(&correlation_id);
+    }
+    if origin == DispatchOrigin::Request && dispatch_tracker.is_queued(correlation_id) {
+        warn!(device_id=%device_id, correlation_id=%correlation_id, "duplicate dispatch request (already queued)");
+        return DispatchOutcome::Duplicate;
+    }
+    if dispatch_tracker.is_scheduled(correlation_id) {
+        warn!(device_id=%device_id, correlation_id=%correlation_id, "duplicate dispatch request (already scheduled)");
+        return DispatchOutcome::Duplicate;
+    }
+
+    if runtime.dispatch_is_paused() {
+        warn!(reason=?runtime.dispatch_paused_reason, device_id=%req.device_id, "ignoring core dispatch request: room dispatch is paused");
+        let fault = CoreFault {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: config.room_id.clone(),
+            kind: FaultKind::DispatchBlockedPaused,
+            severity: Severity::Warn,
+            message: "Dispatch blocked: room dispatch is paused".to_string(),
+            observed_at_unix_ms: unix_ms_now(),
+            details: serde_json::json!({
+                "device_id": req.device_id,
+                "reason": runtime.dispatch_paused_reason,
+            }),
+        };
+        publish_core_fault(client, &config.room_id, fault.clone()).await;
+        if let Some(db) = db {
+            if let Ok(v) = serde_json::to_value(&fault) {
+                db.enqueue_json(
+                    &config.room_id,
+                    None,
+                    &format!("room/{}/core/fault", config.room_id),
+                    "CORE_FAULT",
+                    fault.observed_at_unix_ms,
+                    v,
+                );
+            }
+        }
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedPaused);
+    }
+    if !config.dispatch_enabled {
+        warn!(device_id=%req.device_id, "core dispatch is disabled (CORE_DISPATCH_ENABLED=false)");
+        let fault = CoreFault {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: config.room_id.clone(),
+            kind: FaultKind::DispatchBlockedDisabled,
+            severity: Severity::Warn,
+            message: "Dispatch blocked: CORE_DISPATCH_ENABLED=false".to_string(),
+            observed_at_unix_ms: unix_ms_now(),
+            details: serde_json::json!({"device_id": req.device_id}),
+        };
+        publish_core_fault(client, &config.room_id, fault.clone()).await;
+        if let Some(db) = db {
+            if let Ok(v) = serde_json::to_value(&fault) {
+                db.enqueue_json(
+                    &config.room_id,
+                    None,
+                    &format!("room/{}/core/fault", config.room_id),
+                    "CORE_FAULT",
+                    fault.observed_at_unix_ms,
+                    v,
+                );
+            }
+        }
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDisabled);
+    }
+
+    if config.dry_run {
+        warn!(device_id=%req.device_id, "ignoring core dispatch request because DRY_RUN=true");
+        let fault = CoreFault {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: config.room_id.clone(),
+            kind: FaultKind::DispatchBlockedDryRun,
+            severity: Severity::Info,
+            message: "Dispatch ignored: DRY_RUN=true".to_string(),
+            observed_at_unix_ms: unix_ms_now(),
+            details: serde_json::json!({"device_id": req.device_id}),
+        };
+        publish_core_fault(client, &config.room_id, fault.clone()).await;
+        if let Some(db) = db {
+            if let Ok(v) = serde_json::to_value(&fault) {
+                db.enqueue_json(
+                    &config.room_id,
+                    None,
+                    &format!("room/{}/core/fault", config.room_id),
+                    "CORE_FAULT",
+                    fault.observed_at_unix_ms,
+                    v,
+                );
             }
         }
-        DeviceTopicKind::Presence => match serde_json::from_slice::<Presence>(&msg.payload) {
-            Ok(p) => {
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&p) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&device_id),
-                            &msg.topic,
-                            "PRESENCE",
-                            p.observed_at_unix_ms,
-                            v,
-                        );
-                    }
-                }
-                status.last_presence_at_unix_ms = Some(p.observed_at_unix_ms);
-                status.presence = Some(p.status);
-                recompute_device_liveness(config, client, db, &device_id, status, "presence").await;
-            }
-            Err(err) => warn!(device_id = %device_id, error = %err, "invalid presence payload"),
-        },
-        DeviceTopicKind::State => match serde_json::from_slice::<DeviceState>(&msg.payload) {
-            Ok(st) => {
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&st) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&device_id),
-                            &msg.topic,
-                            "STATE",
-                            st.observed_at_unix_ms,
-                            v,
-                        );
-                    }
-                }
-                status.last_state_at_unix_ms = Some(st.observed_at_unix_ms);
-                status.last_state = Some(st.state.clone());
-                status.last_reported_safety = Some(st.safety_state.clone());
-                maybe_latch_safety(
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDryRun);
+    }
+
+    let reg = runtime.device_registry.get(&device_id).cloned();
+    if let Some(reg) = reg.as_ref() {
+        if !reg.enabled {
+            warn!(device_id=%device_id, "dispatch blocked: device disabled in registry");
+            raise_device_fault(
+                config,
+                client,
+                db,
+                &device_id,
+                FaultKind::DispatchBlockedDeviceDisabled,
+                Severity::Warn,
+                "Dispatch blocked: device disabled",
+                serde_json::json!({"device_id": device_id}),
+            )
+            .await;
+            return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDeviceDisabled);
+        }
+    }
+
+    if let Some((zone, latch)) = runtime.zone_latch_for(&device_id) {
+        warn!(device_id=%device_id, zone, "dispatch blocked: safety zone latched");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchBlockedZoneLatched,
+            Severity::Warn,
+            format!("Dispatch blocked: safety zone '{zone}' is latched"),
+            serde_json::json!({
+                "device_id": device_id,
+                "zone": zone,
+                "latched_since_unix_ms": latch.since_unix_ms,
+            }),
+        )
+        .await;
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedZoneLatched);
+    }
+
+    if let Some((lock, reported_by_device)) = maintenance_block(config, runtime, devices, &req) {
+        warn!(device_id=%device_id, action=%req.action.as_str(), reported_by_device, "dispatch blocked: maintenance");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchBlockedMaintenance,
+            Severity::Warn,
+            "Dispatch blocked: device in maintenance",
+            serde_json::json!({
+                "device_id": device_id,
+                "action": req.action.as_str(),
+                "maintenance": lock,
+                "reported_by_device": reported_by_device,
+                "operator": req.operator.as_ref().map(|op| serde_json::json!({
+                    "sub": op.sub,
+                    "role": op.role,
+                    "trusted": trusted_operator(config, Some(op)).is_some(),
+                })),
+            }),
+        )
+        .await;
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedMaintenance);
+    }
+
+    if let Some(violation) = runtime.interlock_violation(&device_id, req.action, devices) {
+        warn!(
+            device_id=%device_id,
+            action=%req.action.as_str(),
+            rule=%violation.rule.id,
+            on_device=%violation.condition.device_id,
+            reason=violation.reason,
+            "dispatch blocked: interlock"
+        );
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchBlockedInterlock,
+            Severity::Warn,
+            format!("Dispatch blocked: interlock '{}'", violation.rule.id),
+            serde_json::json!({
+                "device_id": device_id,
+                "action": req.action.as_str(),
+                "rule_id": violation.rule.id,
+                "rule": violation.rule,
+                "condition": violation.condition,
+                "reason": violation.reason,
+                "actual": violation.actual,
+                "graph_version": runtime.interlocks_graph_version,
+            }),
+        )
+        .await;
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedInterlock);
+    }
+
+    let req_safety_class = req.safety_class;
+    let effective_req_safety_class = effective_safety_class(
+        req_safety_class,
+        reg.as_ref()
+            .map(|r| r.safety_class)
+            .unwrap_or(SafetyClass::NonCritical),
+    );
+
+    if let Some(status) = devices.get(&device_id) {
+        if status.is_offline && device_offline_policy(runtime, &device_id).blocks_dispatch() {
+            warn!(device_id=%device_id, "ignoring dispatch request: device offline");
+            raise_device_fault(
+                config,
+                client,
+                db,
+                &device_id,
+                FaultKind::DispatchBlockedDeviceOffline,
+                Severity::Warn,
+                "Dispatch blocked: device offline",
+                serde_json::json!({"device_id": device_id}),
+            )
+            .await;
+            return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDeviceOffline);
+        }
+        if effective_req_safety_class == SafetyClass::Critical {
+            if !config.critical_dispatch_armed {
+                warn!(device_id=%device_id, "blocking CRITICAL dispatch (CORE_CRITICAL_ARMED=false)");
+                raise_device_fault(
                     config,
                     client,
-                    runtime,
                     db,
                     &device_id,
-                    &st.safety_state,
-                    st.observed_at_unix_ms,
+                    FaultKind::DispatchBlockedCriticalNotArmed,
+                    Severity::Warn,
+                    "Dispatch blocked: CRITICAL requires CORE_CRITICAL_ARMED=true",
+                    serde_json::json!({"device_id": device_id}),
                 )
                 .await;
-                info!(
-                    device_id = %device_id,
-                    safety = ?st.safety_state.kind,
-                    bytes = msg.payload.len(),
-                    "device state"
+                return DispatchOutcome::Blocked(FaultKind::DispatchBlockedCriticalNotArmed);
+            }
+            if status
+                .last_reported_safety
+                .as_ref()
+                .is_none_or(|s| s.kind != SafetyStateKind::Safe || s.latched)
+            {
+                warn!(
+                    device_id=%device_id,
+                    reported_safety=?status.last_reported_safety.as_ref().map(|s| s.kind),
+                    "blocking CRITICAL dispatch (device not SAFE)"
                 );
+                raise_device_fault(
+                    config,
+                    client,
+                    db,
+                    &device_id,
+                    FaultKind::DispatchBlockedDeviceNotSafe,
+                    Severity::Warn,
+                    "Dispatch blocked: device not SAFE",
+                    serde_json::json!({
+                        "device_id": device_id,
+                        "reported_safety": status.last_reported_safety.as_ref().map(|s| format!("{:?}", s.kind)),
+                        "reported_latched": status.last_reported_safety.as_ref().map(|s| s.latched).unwrap_or(false),
+                    }),
+                ).await;
+                return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDeviceNotSafe);
+            }
+        }
+    }
 
-                if st.safety_state.kind != SafetyStateKind::Safe {
-                    let fault = CoreFault {
-                        schema: SCHEMA_VERSION.to_string(),
-                        room_id: config.room_id.clone(),
-                        kind: "DEVICE_SAFETY_STATE".to_string(),
-                        severity: "WARN".to_string(),
-                        message: "Device reported non-SAFE safety_state".to_string(),
-                        observed_at_unix_ms: st.observed_at_unix_ms,
-                        details: serde_json::json!({
-                            "device_id": device_id,
-                            "safety_state": format!("{:?}", st.safety_state.kind),
-                            "reason_code": st.safety_state.reason_code,
-                            "latched": st.safety_state.latched,
-                        }),
-                    };
-                    publish_device_fault(client, &config.room_id, &device_id, &fault).await;
-                    if let Some(db) = db {
-                        if let Ok(v) = serde_json::to_value(&fault) {
-                            db.enqueue_json(
-                                &config.room_id,
-                                Some(&device_id),
-                                &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                                "DEVICE_FAULT",
-                                fault.observed_at_unix_ms,
-                                v,
-                            );
-                        }
-                    }
-                }
+    let Some(key) = runtime.device_hmac_keys.get(&device_id) else {
+        warn!(device_id=%device_id, "ignoring dispatch request: missing device HMAC key");
+        raise_device_fault(
+            config,
+            client,
+            db,
+            &device_id,
+            FaultKind::DispatchBlockedMissingDeviceKey,
+            Severity::Warn,
+            "Dispatch blocked: missing device HMAC key on core",
+            serde_json::json!({"device_id": device_id}),
+        )
+        .await;
+        return DispatchOutcome::Blocked(FaultKind::DispatchBlockedMissingDeviceKey);
+    };
 
-                publish_device_status(config, client, &device_id, status).await;
+    // Per-device concurrency policy.
+    let (policy, max_depth) = device_concurrency(config, runtime, &device_id);
+    let busy = device_has_inflight(pending, &device_id);
+    let must_wait =
+        busy || (origin != DispatchOrigin::Queue && dispatch_tracker.queue_len(&device_id) > 0);
+    match policy {
+        ConcurrencyPolicy::Parallel => {}
+        ConcurrencyPolicy::Queue if must_wait => {
+            let depth = dispatch_tracker.queue_len(&device_id);
+            if depth >= max_depth {
+                warn!(device_id=%device_id, correlation_id=%correlation_id, depth, "dispatch blocked: device queue full");
+                raise_device_fault(
+                    config,
+                    client,
+                    db,
+                    &device_id,
+                    FaultKind::DispatchQueueFull,
+                    Severity::Warn,
+                    "Dispatch blocked: device queue is full",
+                    serde_json::json!({
+                        "device_id": device_id,
+                        "correlation_id": correlation_id,
+                        "max_depth": max_depth,
+                    }),
+                )
+                .await;
+                return DispatchOutcome::Blocked(FaultKind::DispatchQueueFull);
             }
-            Err(err) => warn!(device_id = %device_id, error = %err, "invalid state payload"),
-        },
-        DeviceTopicKind::Telemetry => {
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::from_slice::<serde_json::Value>(&msg.payload) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(&device_id),
-                        &msg.topic,
-                        "TELEMETRY",
-                        unix_ms_now(),
-                        v,
-                    );
-                }
+            info!(device_id=%device_id, correlation_id=%correlation_id, depth = depth + 1, "device busy: dispatch queued");
+            let mut req = req;
+            req.correlation_id = Some(correlation_id);
+            let queued = QueuedDispatch {
+                req,
+                queued_at_unix_ms: unix_ms_now(),
+            };
+            dispatch_tracker.enqueue(&device_id, queued);
+            publish_device_queue(config, client, runtime, dispatch_tracker, &device_id).await;
+            return DispatchOutcome::Queued;
+        }
+        ConcurrencyPolicy::Queue => {}
+        ConcurrencyPolicy::Reject | ConcurrencyPolicy::Supersede if !busy => {}
+        ConcurrencyPolicy::Reject | ConcurrencyPolicy::Supersede => {
+            let command_schema = devices
+                .get(&device_id)
+                .map(DeviceStatus::command_schema)
+                .unwrap_or(DEFAULT_DEVICE_SCHEMA);
+            let can_supersede = policy == ConcurrencyPolicy::Supersede
+                && schema_at_least(command_schema, CANCEL_MIN_SCHEMA);
+            if can_supersede {
+                let cancelled = cancel_pending_commands(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    devices,
+                    device_sequences,
+                    pending,
+                    &CancelScope::Device(device_id.clone()),
+                    "SUPERSEDED",
+                )
+                .await;
+                info!(device_id=%device_id, correlation_id=%correlation_id, cancelled, "superseding in-flight command(s)");
+            } else {
+                let inflight: Vec<Uuid> = pending
+                    .iter()
+                    .filter(|(_, p)| p.device_id == device_id && p.cancels.is_none())
+                    .map(|(command_id, _)| *command_id)
+                    .collect();
+                warn!(device_id=%device_id, correlation_id=%correlation_id, policy=%policy.as_str(), "dispatch blocked: device busy");
+                raise_device_fault(
+                    config,
+                    client,
+                    db,
+                    &device_id,
+                    FaultKind::DispatchBlockedDeviceBusy,
+                    Severity::Warn,
+                    "Dispatch blocked: device has a command in flight",
+                    serde_json::json!({
+                        "device_id": device_id,
+                        "correlation_id": correlation_id,
+                        "policy": policy.as_str(),
+                        "inflight_command_ids": inflight,
+                        "command_schema": command_schema,
+                    }),
+                )
+                .await;
+                return DispatchOutcome::Blocked(FaultKind::DispatchBlockedDeviceBusy);
             }
-            info!(device_id = %device_id, bytes = msg.payload.len(), "device telemetry (raw)");
         }
     }
-}
 
-#[derive(Debug, Clone)]
-struct PendingCommand {
-    device_id: String,
-    cmd: CommandEnvelope,
-    published_at: Instant,
-    last_update: Instant,
-    retries_left: u32,
-    ack_timeout_ms: u64,
-    complete_timeout_ms: u64,
-    accepted: bool,
-    accepted_at: Option<Instant>,
-    completed: bool,
-    rejected: bool,
-    reason_code: Option<String>,
-}
+    let next_seq = device_sequences
+        .get(&device_id)
+        .copied()
+        .unwrap_or(0)
+        .wrapping_add(1);
+    device_sequences.insert(device_id.clone(), next_seq);
 
-#[derive(Debug, Default)]
-struct DispatchTracker {
-    // correlation_id -> command_id
-    inflight: std::collections::HashMap<Uuid, Uuid>,
-    // correlation_id -> completed_at
-    recent: std::collections::HashMap<Uuid, Instant>,
-}
+    let command_schema = devices
+        .get(&device_id)
+        .map(DeviceStatus::command_schema)
+        .unwrap_or(DEFAULT_DEVICE_SCHEMA);
+    let codec = devices.get(&device_id).map(|s| s.codec).unwrap_or_default();
+    let issued_at_unix_ms = unix_ms_now();
+    // Released early (CORE_SCHEDULE_LEAD_MS) to a device that keeps room time: let it wait.
+    let execute_at_unix_ms = req.execute_at_unix_ms.filter(|at| {
+        *at > issued_at_unix_ms && schema_at_least(command_schema, SCHEDULE_MIN_SCHEMA)
+    });
+    let mut cmd = CommandEnvelope {
+        schema: command_schema.to_string(),
+        room_id: config.room_id.clone(),
+        device_id: device_id.clone(),
+        command_id: Uuid::new_v4(),
+        correlation_id,
+        sequence: next_seq,
+        issued_at_unix_ms,
+        action: req.action,
+        parameters: req.parameters,
+        safety_class: effective_req_safety_class,
+        execute_at_unix_ms,
+        auth: None,
+    };
 
-impl DispatchTracker {
-    fn track_inflight(&mut self, correlation_id: Uuid, command_id: Uuid) {
-        self.inflight.insert(correlation_id, command_id);
+    if let Err(err) = sign_command_hmac_sha256(&mut cmd, key, None) {
+        warn!(device_id=%device_id, error=%err, "failed to sign dispatch command");
+        return DispatchOutcome::Failed;
     }
 
-    fn inflight_command_id(&self, correlation_id: Uuid) -> Option<Uuid> {
-        self.inflight.get(&correlation_id).copied()
+    let retries_left = req.retries.unwrap_or(config.dispatch_default_retries);
+    let ack_timeout_ms = req.ack_timeout_ms.unwrap_or(config.dispatch_ack_timeout_ms);
+    // The completion window starts at the execute time, not at publish.
+    let complete_timeout_ms = req
+        .complete_timeout_ms
+        .unwrap_or(config.dispatch_complete_timeout_ms)
+        + execute_at_unix_ms.map_or(0, |at| at - issued_at_unix_ms);
+
+    let trace_id = dispatch_tracker.trace_id(correlation_id);
+    if !publish_device_command(
+        client,
+        &config.room_id,
+        &device_id,
+        &cmd,
+        codec,
+        ack_timeout_ms,
+        trace_id.as_deref(),
+    )
+    .await
+    {
+        return DispatchOutcome::Failed;
     }
-
-    fn mark_done(&mut self, correlation_id: Uuid) {
-        self.inflight.remove(&correlation_id);
-        self.recent.insert(correlation_id, Instant::now());
-        // bound memory without requiring a separate sweep loop
-        if self.recent.len() > 10_000 {
-            self.recent.clear();
+    if let Some(db) = db {
+        if let Ok(v) = serde_json::to_value(&cmd) {
+            db.enqueue_json(
+                &config.room_id,
+                Some(&device_id),
+                &format!("room/{}/device/{}/cmd", config.room_id, device_id),
+                "CMD",
+                cmd.issued_at_unix_ms,
+                v,
+            );
         }
     }
-
-    fn is_recent(&self, correlation_id: Uuid, ttl: Duration) -> bool {
-        self.recent
-            .get(&correlation_id)
-            .is_some_and(|t| t.elapsed() <= ttl)
-    }
-
-    fn sweep_recent(&mut self, ttl: Duration) {
-        self.recent.retain(|_, t| t.elapsed() <= ttl);
+    if origin != DispatchOrigin::Batch {
+        dispatch_tracker.track_inflight(correlation_id, cmd.command_id);
     }
+    let command_id = cmd.command_id;
+    pending.insert(
+        cmd.command_id,
+        PendingCommand {
+            device_id,
+            cmd,
+            codec,
+            published_at: Instant::now(),
+            dispatched_at: Instant::now(),
+            last_update: Instant::now(),
+            retries_left,
+            ack_timeout_ms,
+            complete_timeout_ms,
+            accepted: false,
+            accepted_at: None,
+            completed: false,
+            rejected: false,
+            reason_code: None,
+            cancels: None,
+            cancel_reason: None,
+            cancelled: false,
+            last_progress: None,
+            reply_topic: None,
+            trace_id,
+        },
+    );
+    DispatchOutcome::Published(command_id)
 }
 
-async fn handle_dispatch_request(
+/// Upper bound on commands in one batch dispatch.
+const DISPATCH_BATCH_MAX_COMMANDS: usize = 32;
+
+/// Why [`dispatch_request`] would refuse `req` right now, without raising anything. Mirrors its
+/// gates so a batch can be refused as a whole. A device that would have to wait (`QUEUE` policy)
+/// counts as blocked: batch members are published together or not at all.
+fn dispatch_block_kind(
     config: &Config,
-    client: &rumqttc::AsyncClient,
     runtime: &RuntimeState,
-    db: Option<&DbWriter>,
-    payload: &[u8],
     devices: &std::collections::HashMap<String, DeviceStatus>,
-    device_sequences: &mut std::collections::HashMap<String, u64>,
-    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
-    dispatch_tracker: &mut DispatchTracker,
-) {
-    let req: CoreDispatchRequest = match serde_json::from_slice(payload) {
-        Ok(v) => v,
-        Err(err) => {
-            warn!(error=%err, "invalid core dispatch payload (json)");
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "DISPATCH_REQUEST_INVALID".to_string(),
-                severity: "WARN".to_string(),
-                message: "Invalid core dispatch payload (JSON)".to_string(),
-                observed_at_unix_ms: unix_ms_now(),
-                details: serde_json::json!({
-                    "error": err.to_string(),
-                    "topic": format!("room/{}/core/dispatch", config.room_id),
-                }),
-            };
-            publish_core_fault(client, &config.room_id, fault.clone()).await;
-            if let Some(db) = db {
-                db.enqueue_json(
-                    &config.room_id,
-                    None,
-                    &format!("room/{}/core/fault", config.room_id),
-                    "CORE_FAULT",
-                    fault.observed_at_unix_ms,
-                    serde_json::to_value(fault)
-                        .unwrap_or_else(|_| serde_json::json!({"kind":"DISPATCH_REQUEST_INVALID"})),
-                );
+    pending: &std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &DispatchTracker,
+    req: &CoreDispatchRequest,
+) -> Option<FaultKind> {
+    if req.action == CommandAction::Cancel {
+        return Some(FaultKind::DispatchRequestInvalid);
+    }
+    if runtime.dispatch_is_paused() {
+        return Some(FaultKind::DispatchBlockedPaused);
+    }
+    if !config.dispatch_enabled {
+        return Some(FaultKind::DispatchBlockedDisabled);
+    }
+    if config.dry_run {
+        return Some(FaultKind::DispatchBlockedDryRun);
+    }
+
+    let device_id = req.device_id.as_str();
+    let reg = runtime.device_registry.get(device_id);
+    if reg.is_some_and(|r| !r.enabled) {
+        return Some(FaultKind::DispatchBlockedDeviceDisabled);
+    }
+    if runtime.zone_latch_for(device_id).is_some() {
+        return Some(FaultKind::DispatchBlockedZoneLatched);
+    }
+    if maintenance_block(config, runtime, devices, req).is_some() {
+        return Some(FaultKind::DispatchBlockedMaintenance);
+    }
+    if runtime
+        .interlock_violation(device_id, req.action, devices)
+        .is_some()
+    {
+        return Some(FaultKind::DispatchBlockedInterlock);
+    }
+    let safety_class = effective_safety_class(
+        req.safety_class,
+        reg.map(|r| r.safety_class)
+            .unwrap_or(SafetyClass::NonCritical),
+    );
+    if let Some(status) = devices.get(device_id) {
+        if status.is_offline && device_offline_policy(runtime, device_id).blocks_dispatch() {
+            return Some(FaultKind::DispatchBlockedDeviceOffline);
+        }
+        if safety_class == SafetyClass::Critical {
+            if !config.critical_dispatch_armed {
+                return Some(FaultKind::DispatchBlockedCriticalNotArmed);
+            }
+            if status
+                .last_reported_safety
+                .as_ref()
+                .is_none_or(|s| s.kind != SafetyStateKind::Safe || s.latched)
+            {
+                return Some(FaultKind::DispatchBlockedDeviceNotSafe);
             }
+        }
+    }
+    if !runtime.device_hmac_keys.contains_key(device_id) {
+        return Some(FaultKind::DispatchBlockedMissingDeviceKey);
+    }
+
+    let (policy, _) = device_concurrency(config, runtime, device_id);
+    let busy = device_has_inflight(pending, device_id);
+    let command_schema = devices
+        .get(device_id)
+        .map(DeviceStatus::command_schema)
+        .unwrap_or(DEFAULT_DEVICE_SCHEMA);
+    let blocked = match policy {
+        ConcurrencyPolicy::Parallel => false,
+        ConcurrencyPolicy::Queue => busy || dispatch_tracker.queue_len(device_id) > 0,
+        ConcurrencyPolicy::Reject => busy,
+        ConcurrencyPolicy::Supersede => busy && !schema_at_least(command_schema, CANCEL_MIN_SCHEMA),
+    };
+    blocked.then_some(FaultKind::DispatchBlockedDeviceBusy)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_batch_dispatch_request(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    payload: &[u8],
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
+    // MQTT v5 properties of the request; `None` for graph dispatches.
+    properties: Option<&PublishProperties>,
+) {
+    let mut req: CoreBatchDispatchRequest = match decode_message(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error=%err, "invalid core batch dispatch payload");
+            raise_dispatch_request_invalid(
+                config,
+                client,
+                db,
+                "core/dispatch/batch",
+                "Invalid core batch dispatch payload (JSON)",
+                &err.to_string(),
+            )
+            .await;
             return;
         }
     };
 
-    if req.schema != SCHEMA_VERSION || req.room_id != config.room_id {
-        warn!(schema=%req.schema, room_id=%req.room_id, "ignoring dispatch request for wrong schema/room");
+    if req.room_id != config.room_id {
+        warn!(schema=%req.schema, room_id=%req.room_id, "ignoring batch dispatch request for wrong room");
         return;
     }
 
-    if runtime.dispatch_is_paused() {
-        warn!(reason=?runtime.dispatch_paused_reason, device_id=%req.device_id, "ignoring core dispatch request: room dispatch is paused");
-        let fault = CoreFault {
-            schema: SCHEMA_VERSION.to_string(),
-            room_id: config.room_id.clone(),
-            kind: "DISPATCH_BLOCKED_PAUSED".to_string(),
-            severity: "WARN".to_string(),
-            message: "Dispatch blocked: room dispatch is paused".to_string(),
-            observed_at_unix_ms: unix_ms_now(),
-            details: serde_json::json!({
-                "device_id": req.device_id,
-                "reason": runtime.dispatch_paused_reason,
-            }),
-        };
-        publish_core_fault(client, &config.room_id, fault.clone()).await;
-        if let Some(db) = db {
-            if let Ok(v) = serde_json::to_value(&fault) {
-                db.enqueue_json(
-                    &config.room_id,
-                    None,
-                    &format!("room/{}/core/fault", config.room_id),
-                    "CORE_FAULT",
-                    fault.observed_at_unix_ms,
-                    v,
-                );
-            }
+    let correlation_id = *req.correlation_id.get_or_insert_with(Uuid::new_v4);
+    apply_request_properties(
+        &mut req.reply_topic,
+        correlation_id,
+        properties,
+        dispatch_tracker,
+    );
+    let reply_topic = valid_reply_topic(config, req.reply_topic.as_deref()).map(str::to_string);
+    if req.reply_topic.is_some() && reply_topic.is_none() {
+        warn!(reply_topic=?req.reply_topic, "ignoring dispatch reply_topic outside the room reply prefix");
+    }
+
+    // Same idempotency rules as single dispatches, keyed by the shared correlation_id.
+    dispatch_tracker.sweep_recent();
+    if let Some(batch) = dispatch_tracker.batches.get_mut(&correlation_id) {
+        warn!(correlation_id=%correlation_id, "duplicate batch dispatch request (already inflight)");
+        if reply_topic.is_some() {
+            batch.reply_topic = reply_topic;
         }
         return;
     }
-    if !config.dispatch_enabled {
-        warn!(device_id=%req.device_id, "core dispatch is disabled (CORE_DISPATCH_ENABLED=false)");
-        let fault = CoreFault {
-            schema: SCHEMA_VERSION.to_string(),
-            room_id: config.room_id.clone(),
-            kind: "DISPATCH_BLOCKED_DISABLED".to_string(),
-            severity: "WARN".to_string(),
-            message: "Dispatch blocked: CORE_DISPATCH_ENABLED=false".to_string(),
-            observed_at_unix_ms: unix_ms_now(),
-            details: serde_json::json!({"device_id": req.device_id}),
-        };
-        publish_core_fault(client, &config.room_id, fault.clone()).await;
-        if let Some(db) = db {
-            if let Ok(v) = serde_json::to_value(&fault) {
-                db.enqueue_json(
-                    &config.room_id,
-                    None,
-                    &format!("room/{}/core/fault", config.room_id),
-                    "CORE_FAULT",
-                    fault.observed_at_unix_ms,
-                    v,
-                );
-            }
+    if let Some(recent) = dispatch_tracker.recent_batch_result(correlation_id) {
+        warn!(correlation_id=%correlation_id, "duplicate batch dispatch request (recently completed)");
+        if let Some(reply_topic) = reply_topic {
+            let result = CoreBatchDispatchResult {
+                duplicate: true,
+                ..recent.clone()
+            };
+            publish_dispatch_result(
+                client,
+                &reply_topic,
+                dispatch_tracker.reply_properties(correlation_id),
+                &result,
+            )
+            .await;
         }
         return;
     }
 
-    if config.dry_run {
-        warn!(device_id=%req.device_id, "ignoring core dispatch request because DRY_RUN=true");
-        let fault = CoreFault {
+    let member_reqs: Vec<CoreDispatchRequest> = req
+        .commands
+        .iter()
+        .map(|c| CoreDispatchRequest {
             schema: SCHEMA_VERSION.to_string(),
             room_id: config.room_id.clone(),
-            kind: "DISPATCH_BLOCKED_DRY_RUN".to_string(),
-            severity: "INFO".to_string(),
-            message: "Dispatch ignored: DRY_RUN=true".to_string(),
-            observed_at_unix_ms: unix_ms_now(),
-            details: serde_json::json!({"device_id": req.device_id}),
-        };
-        publish_core_fault(client, &config.room_id, fault.clone()).await;
-        if let Some(db) = db {
-            if let Ok(v) = serde_json::to_value(&fault) {
-                db.enqueue_json(
-                    &config.room_id,
-                    None,
-                    &format!("room/{}/core/fault", config.room_id),
-                    "CORE_FAULT",
-                    fault.observed_at_unix_ms,
-                    v,
-                );
-            }
-        }
-        return;
-    }
-
-    let device_id = req.device_id.clone();
+            device_id: c.device_id.clone(),
+            action: c.action,
+            parameters: c.parameters.clone(),
+            safety_class: c.safety_class,
+            correlation_id: Some(correlation_id),
+            retries: req.retries,
+            ack_timeout_ms: req.ack_timeout_ms,
+            complete_timeout_ms: req.complete_timeout_ms,
+            reply_topic: None,
+            execute_at_unix_ms: None,
+            execute_in_ms: None,
+            operator: req.operator.clone(),
+        })
+        .collect();
+    let mut members: Vec<BatchMemberResult> = member_reqs
+        .iter()
+        .map(|r| BatchMemberResult {
+            device_id: r.device_id.clone(),
+            status: None,
+            command_id: None,
+            fault_kind: None,
+            reason_code: None,
+        })
+        .collect();
 
-    let reg = runtime.device_registry.get(&device_id).cloned();
-    if let Some(reg) = reg.as_ref() {
-        if !reg.enabled {
-            warn!(device_id=%device_id, "dispatch blocked: device disabled in registry");
-            let fault = CoreFault {
+    let mut device_ids: Vec<&str> = member_reqs.iter().map(|r| r.device_id.as_str()).collect();
+    device_ids.sort_unstable();
+    let invalid = if member_reqs.is_empty() {
+        Some("commands is empty".to_string())
+    } else if member_reqs.len() > DISPATCH_BATCH_MAX_COMMANDS {
+        Some(format!(
+            "too many commands (max {DISPATCH_BATCH_MAX_COMMANDS})"
+        ))
+    } else if device_ids.windows(2).any(|w| w[0] == w[1]) {
+        Some("more than one command for a device".to_string())
+    } else {
+        None
+    };
+    if let Some(error) = invalid {
+        warn!(correlation_id=%correlation_id, error=%error, "invalid core batch dispatch");
+        raise_dispatch_request_invalid(
+            config,
+            client,
+            db,
+            "core/dispatch/batch",
+            "Invalid core batch dispatch",
+            &error,
+        )
+        .await;
+        if let Some(reply_topic) = reply_topic {
+            let result = CoreBatchDispatchResult {
                 schema: SCHEMA_VERSION.to_string(),
                 room_id: config.room_id.clone(),
-                kind: "DISPATCH_BLOCKED_DEVICE_DISABLED".to_string(),
-                severity: "WARN".to_string(),
-                message: "Dispatch blocked: device disabled".to_string(),
+                correlation_id,
+                status: DispatchResultStatus::Blocked,
+                members,
+                duplicate: false,
                 observed_at_unix_ms: unix_ms_now(),
-                details: serde_json::json!({"device_id": device_id}),
             };
-            publish_device_fault(client, &config.room_id, &device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(&device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
-                }
-            }
-            return;
+            publish_dispatch_result(
+                client,
+                &reply_topic,
+                dispatch_tracker.reply_properties(correlation_id),
+                &result,
+            )
+            .await;
         }
+        return;
     }
 
-    let req_safety_class = req.safety_class;
-    let effective_req_safety_class = effective_safety_class(
-        req_safety_class,
-        reg.as_ref()
-            .map(|r| r.safety_class)
-            .unwrap_or(SafetyClass::NonCritical),
-    );
-
-    if let Some(status) = devices.get(&device_id) {
-        if status.is_offline {
-            warn!(device_id=%device_id, "ignoring dispatch request: device offline");
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "DISPATCH_BLOCKED_DEVICE_OFFLINE".to_string(),
-                severity: "WARN".to_string(),
-                message: "Dispatch blocked: device offline".to_string(),
-                observed_at_unix_ms: unix_ms_now(),
-                details: serde_json::json!({"device_id": device_id}),
-            };
-            publish_device_fault(client, &config.room_id, &device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(&device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
-                }
-            }
-            return;
-        }
-        if effective_req_safety_class == SafetyClass::Critical {
-            if !config.critical_dispatch_armed {
-                warn!(device_id=%device_id, "blocking CRITICAL dispatch (CORE_CRITICAL_ARMED=false)");
-                let fault = CoreFault {
-                    schema: SCHEMA_VERSION.to_string(),
-                    room_id: config.room_id.clone(),
-                    kind: "DISPATCH_BLOCKED_CRITICAL_NOT_ARMED".to_string(),
-                    severity: "WARN".to_string(),
-                    message: "Dispatch blocked: CRITICAL requires CORE_CRITICAL_ARMED=true"
-                        .to_string(),
-                    observed_at_unix_ms: unix_ms_now(),
-                    details: serde_json::json!({"device_id": device_id}),
-                };
-                publish_device_fault(client, &config.room_id, &device_id, &fault).await;
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&fault) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&device_id),
-                            &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                            "DEVICE_FAULT",
-                            fault.observed_at_unix_ms,
-                            v,
-                        );
-                    }
-                }
-                return;
-            }
-            if status
-                .last_reported_safety
-                .as_ref()
-                .is_none_or(|s| s.kind != SafetyStateKind::Safe || s.latched)
-            {
-                warn!(
-                    device_id=%device_id,
-                    reported_safety=?status.last_reported_safety.as_ref().map(|s| s.kind),
-                    "blocking CRITICAL dispatch (device not SAFE)"
-                );
-                let fault = CoreFault {
-                    schema: SCHEMA_VERSION.to_string(),
-                    room_id: config.room_id.clone(),
-                    kind: "DISPATCH_BLOCKED_DEVICE_NOT_SAFE".to_string(),
-                    severity: "WARN".to_string(),
-                    message: "Dispatch blocked: device not SAFE".to_string(),
-                    observed_at_unix_ms: unix_ms_now(),
-                    details: serde_json::json!({
-                        "device_id": device_id,
-                        "reported_safety": status.last_reported_safety.as_ref().map(|s| format!("{:?}", s.kind)),
-                        "reported_latched": status.last_reported_safety.as_ref().map(|s| s.latched).unwrap_or(false),
-                    }),
-                };
-                publish_device_fault(client, &config.room_id, &device_id, &fault).await;
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&fault) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&device_id),
-                            &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                            "DEVICE_FAULT",
-                            fault.observed_at_unix_ms,
-                            v,
-                        );
-                    }
-                }
-                return;
-            }
+    // All-or-nothing: gate every member before publishing any.
+    for (member, member_req) in members.iter_mut().zip(&member_reqs) {
+        if let Some(kind) = dispatch_block_kind(
+            config,
+            runtime,
+            devices,
+            pending,
+            dispatch_tracker,
+            member_req,
+        ) {
+            member.status = Some(DispatchResultStatus::Blocked);
+            member.fault_kind = Some(kind);
         }
     }
-
-    let Some(key) = config.device_hmac_keys.get(&device_id) else {
-        warn!(device_id=%device_id, "ignoring dispatch request: missing device HMAC key");
+    if members.iter().any(|m| m.status.is_some()) {
+        let blocked: Vec<serde_json::Value> = members
+            .iter()
+            .filter_map(|m| {
+                m.fault_kind
+                    .as_ref()
+                    .map(|kind| serde_json::json!({"device_id": m.device_id, "kind": kind}))
+            })
+            .collect();
+        warn!(correlation_id=%correlation_id, blocked=?blocked, "batch dispatch blocked");
         let fault = CoreFault {
             schema: SCHEMA_VERSION.to_string(),
             room_id: config.room_id.clone(),
-            kind: "DISPATCH_BLOCKED_MISSING_DEVICE_KEY".to_string(),
-            severity: "WARN".to_string(),
-            message: "Dispatch blocked: missing device HMAC key on core".to_string(),
+            kind: FaultKind::DispatchBatchBlocked,
+            severity: Severity::Warn,
+            message: "Batch dispatch blocked: no member was published".to_string(),
             observed_at_unix_ms: unix_ms_now(),
-            details: serde_json::json!({"device_id": device_id}),
+            details: serde_json::json!({
+                "correlation_id": correlation_id,
+                "blocked": blocked,
+            }),
         };
-        publish_device_fault(client, &config.room_id, &device_id, &fault).await;
+        publish_core_fault(client, &config.room_id, fault.clone()).await;
         if let Some(db) = db {
             if let Ok(v) = serde_json::to_value(&fault) {
                 db.enqueue_json(
                     &config.room_id,
-                    Some(&device_id),
-                    &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                    "DEVICE_FAULT",
+                    None,
+                    &format!("room/{}/core/fault", config.room_id),
+                    "CORE_FAULT",
                     fault.observed_at_unix_ms,
                     v,
                 );
             }
         }
-        return;
-    };
-
-    let next_seq = device_sequences
-        .get(&device_id)
-        .copied()
-        .unwrap_or(0)
-        .wrapping_add(1);
-    device_sequences.insert(device_id.clone(), next_seq);
-
-    let correlation_id = req.correlation_id.unwrap_or_else(Uuid::new_v4);
-
-    // Control-plane idempotency: if tools retry the dispatch request with the same correlation_id,
-    // do not generate new command_ids while the original is inflight (or recently completed).
-    dispatch_tracker.sweep_recent(Duration::from_secs(60 * 10));
-    if dispatch_tracker.is_recent(correlation_id, Duration::from_secs(60 * 10)) {
-        warn!(device_id=%device_id, correlation_id=%correlation_id, "duplicate dispatch request (recently completed)");
-        return;
-    }
-    if let Some(existing_cmd_id) = dispatch_tracker.inflight_command_id(correlation_id) {
-        if pending.contains_key(&existing_cmd_id) {
-            warn!(
-                device_id=%device_id,
-                correlation_id=%correlation_id,
-                command_id=%existing_cmd_id,
-                "duplicate dispatch request (already inflight)"
-            );
-            return;
+        if let Some(reply_topic) = reply_topic {
+            let result = CoreBatchDispatchResult {
+                schema: SCHEMA_VERSION.to_string(),
+                room_id: config.room_id.clone(),
+                correlation_id,
+                status: DispatchResultStatus::Blocked,
+                members,
+                duplicate: false,
+                observed_at_unix_ms: unix_ms_now(),
+            };
+            publish_dispatch_result(
+                client,
+                &reply_topic,
+                dispatch_tracker.reply_properties(correlation_id),
+                &result,
+            )
+            .await;
         }
-        // stale inflight mapping (e.g. removed by timeout); allow a fresh dispatch
-        dispatch_tracker.inflight.remove(&correlation_id);
+        return;
     }
 
-    let mut cmd = CommandEnvelope {
-        schema: SCHEMA_VERSION.to_string(),
-        room_id: config.room_id.clone(),
-        device_id: device_id.clone(),
-        command_id: Uuid::new_v4(),
+    let member_count = member_reqs.len();
+    dispatch_tracker.batches.insert(
         correlation_id,
-        sequence: next_seq,
-        issued_at_unix_ms: unix_ms_now(),
-        action: req.action,
-        parameters: req.parameters,
-        safety_class: effective_req_safety_class,
-        auth: None,
-    };
-
-    if let Err(err) = sign_command_hmac_sha256(&mut cmd, key, None) {
-        warn!(device_id=%device_id, error=%err, "failed to sign dispatch command");
+        BatchState {
+            members,
+            reply_topic,
+        },
+    );
+    let mut published: Vec<Uuid> = Vec::new();
+    let mut aborted = false;
+    for (idx, member_req) in member_reqs.into_iter().enumerate() {
+        let outcome = if aborted {
+            DispatchOutcome::Failed
+        } else {
+            dispatch_request(
+                config,
+                client,
+                runtime,
+                db,
+                member_req,
+                devices,
+                device_sequences,
+                pending,
+                dispatch_tracker,
+                DispatchOrigin::Batch,
+            )
+            .await
+        };
+        DISPATCHES.inc(&[outcome.metric_label()]);
+        let Some(member) = dispatch_tracker
+            .batches
+            .get_mut(&correlation_id)
+            .and_then(|b| b.members.get_mut(idx))
+        else {
+            continue;
+        };
+        match outcome {
+            DispatchOutcome::Published(command_id) => {
+                member.command_id = Some(command_id);
+                published.push(command_id);
+            }
+            outcome => {
+                // Gating passed a moment ago, so this is a sign/publish failure.
+                if !aborted {
+                    warn!(correlation_id=%correlation_id, device_id=%member.device_id, ?outcome, "batch member not published; aborting batch");
+                }
+                aborted = true;
+                member.status = Some(DispatchResultStatus::Cancelled);
+                member.reason_code = Some("BATCH_ABORTED".to_string());
+            }
+        }
+    }
+    if aborted {
+        for command_id in published {
+            cancel_pending_commands(
+                config,
+                client,
+                runtime,
+                db,
+                devices,
+                device_sequences,
+                pending,
+                &CancelScope::Command(command_id),
+                "BATCH_ABORTED",
+            )
+            .await;
+        }
         return;
     }
+    info!(correlation_id=%correlation_id, members = member_count, "batch dispatched");
+}
 
-    let retries_left = req.retries.unwrap_or(config.dispatch_default_retries);
-    let ack_timeout_ms = req.ack_timeout_ms.unwrap_or(config.dispatch_ack_timeout_ms);
-    let complete_timeout_ms = req
-        .complete_timeout_ms
-        .unwrap_or(config.dispatch_complete_timeout_ms);
-
-    if publish_device_command(client, &config.room_id, &device_id, &cmd).await {
-        if let Some(db) = db {
-            if let Ok(v) = serde_json::to_value(&cmd) {
-                db.enqueue_json(
-                    &config.room_id,
-                    Some(&device_id),
-                    &format!("room/{}/device/{}/cmd", config.room_id, device_id),
-                    "CMD",
-                    cmd.issued_at_unix_ms,
-                    v,
-                );
+/// Publishes the aggregate result of every batch whose members have all finished.
+async fn tick_dispatch_batches(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    dispatch_tracker: &mut DispatchTracker,
+) {
+    let finished: Vec<Uuid> = dispatch_tracker
+        .batches
+        .iter()
+        .filter(|(_, b)| b.is_finished())
+        .map(|(id, _)| *id)
+        .collect();
+    for correlation_id in finished {
+        let Some(batch) = dispatch_tracker.batches.remove(&correlation_id) else {
+            continue;
+        };
+        let status = batch.aggregate_status();
+        let result = CoreBatchDispatchResult {
+            schema: SCHEMA_VERSION.to_string(),
+            room_id: config.room_id.clone(),
+            correlation_id,
+            status,
+            members: batch.members,
+            duplicate: false,
+            observed_at_unix_ms: unix_ms_now(),
+        };
+        if status == DispatchResultStatus::Completed {
+            info!(correlation_id=%correlation_id, "batch completed");
+        } else {
+            warn!(correlation_id=%correlation_id, status=?status, "batch finished incomplete");
+            let fault = CoreFault {
+                schema: SCHEMA_VERSION.to_string(),
+                room_id: config.room_id.clone(),
+                kind: FaultKind::DispatchBatchFailed,
+                severity: Severity::Warn,
+                message: "Batch dispatch finished with members not completed".to_string(),
+                observed_at_unix_ms: unix_ms_now(),
+                details: serde_json::json!({
+                    "correlation_id": correlation_id,
+                    "status": status,
+                    "members": result.members,
+                }),
+            };
+            publish_core_fault(client, &config.room_id, fault.clone()).await;
+            if let Some(db) = db {
+                if let Ok(v) = serde_json::to_value(&fault) {
+                    db.enqueue_json(
+                        &config.room_id,
+                        None,
+                        &format!("room/{}/core/fault", config.room_id),
+                        "CORE_FAULT",
+                        fault.observed_at_unix_ms,
+                        v,
+                    );
+                }
             }
         }
-        dispatch_tracker.track_inflight(correlation_id, cmd.command_id);
-        pending.insert(
-            cmd.command_id,
-            PendingCommand {
-                device_id,
-                cmd,
-                published_at: Instant::now(),
-                last_update: Instant::now(),
-                retries_left,
-                ack_timeout_ms,
-                complete_timeout_ms,
-                accepted: false,
-                accepted_at: None,
-                completed: false,
-                rejected: false,
-                reason_code: None,
-            },
-        );
+        if let Some(reply_topic) = batch.reply_topic.as_deref() {
+            publish_dispatch_result(
+                client,
+                reply_topic,
+                dispatch_tracker.reply_properties(correlation_id),
+                &result,
+            )
+            .await;
+        }
+        dispatch_tracker.remember_batch(result);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_core_control(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     runtime: &mut RuntimeState,
     graph_runner: &mut GraphRunner,
     db: Option<&DbWriter>,
-    devices: &std::collections::HashMap<String, DeviceStatus>,
+    devices: &mut std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    dispatch_tracker: &mut DispatchTracker,
     payload: &[u8],
 ) {
-    let req: CoreControlRequest = match serde_json::from_slice(payload) {
+    let req: CoreControlRequest = match decode_message(payload) {
         Ok(v) => v,
         Err(err) => {
-            warn!(error=%err, "invalid core control payload (json)");
+            warn!(error=%err, "invalid core control payload");
             return;
         }
     };
 
-    if req.schema != SCHEMA_VERSION || req.room_id != config.room_id {
-        warn!(schema=%req.schema, room_id=%req.room_id, "ignoring control request for wrong schema/room");
+    if req.room_id != config.room_id {
+        warn!(schema=%req.schema, room_id=%req.room_id, "ignoring control request for wrong room");
         return;
     }
 
@@ -1746,8 +5505,8 @@ async fn handle_core_control(
                 CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "CONTROL_UNAUTHORIZED".to_string(),
-                    severity: "WARN".to_string(),
+                    kind: FaultKind::ControlUnauthorized,
+                    severity: Severity::Warn,
                     message: "Core control request denied (token mismatch)".to_string(),
                     observed_at_unix_ms: unix_ms_now(),
                     details: serde_json::json!({"op": req.op}),
@@ -1759,7 +5518,11 @@ async fn handle_core_control(
     }
 
     if let Some(db) = db {
-        if let Ok(v) = serde_json::to_value(&req) {
+        if let Ok(mut v) = serde_json::to_value(&req) {
+            // Keep `CORE_CONTROL_TOKEN` out of the event log (and incident reports).
+            if let Some(params) = v.get_mut("parameters").and_then(|p| p.as_object_mut()) {
+                params.remove("token");
+            }
             db.enqueue_json(
                 &config.room_id,
                 None,
@@ -1782,8 +5545,8 @@ async fn handle_core_control(
                 CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "DISPATCH_PAUSED".to_string(),
-                    severity: "WARN".to_string(),
+                    kind: FaultKind::DispatchPaused,
+                    severity: Severity::Warn,
                     message: "Dispatch paused by operator".to_string(),
                     observed_at_unix_ms: unix_ms_now(),
                     details: serde_json::json!({}),
@@ -1802,8 +5565,8 @@ async fn handle_core_control(
                 CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "DISPATCH_RESUMED".to_string(),
-                    severity: "INFO".to_string(),
+                    kind: FaultKind::DispatchResumed,
+                    severity: Severity::Info,
                     message: "Dispatch resumed by operator".to_string(),
                     observed_at_unix_ms: unix_ms_now(),
                     details: serde_json::json!({}),
@@ -1812,62 +5575,136 @@ async fn handle_core_control(
             .await;
         }
         CORE_CONTROL_OP_RESET_SAFETY_LATCH => {
-            if runtime.safety_latched_since_unix_ms.is_none() {
-                warn!("safety reset requested but no latch is active");
+            // Optional `zone`: reset only that zone. Without it the room latch and every zone
+            // latch are reset together, which needs every device SAFE.
+            let zone = req
+                .parameters
+                .get("zone")
+                .and_then(|v| v.as_str())
+                .and_then(normalize_zone);
+            let latched = match zone.as_deref() {
+                Some(z) => runtime.zone_latches.contains_key(z),
+                None => {
+                    runtime.safety_latched_since_unix_ms.is_some()
+                        || !runtime.zone_latches.is_empty()
+                }
+            };
+            if !latched {
+                warn!(zone=?zone, "safety reset requested but no latch is active");
                 return;
             }
+            let zone_devices = zone.as_deref().map(|z| runtime.zone_device_ids(z));
+            // Who asked for the reset (set by sentient-api's dual-confirm flow); recorded as-is.
+            let operators = serde_json::json!({
+                "reset_id": req.parameters.get("reset_id"),
+                "requested_by": req.parameters.get("requested_by"),
+                "confirmed_by": req.parameters.get("confirmed_by"),
+                "reason": req.parameters.get("reason"),
+            });
 
+            // Preconditions, one checklist entry per device in scope.
+            let mut checked: Vec<(&String, &DeviceStatus)> = devices
+                .iter()
+                .filter(|(device_id, _)| {
+                    zone_devices
+                        .as_ref()
+                        .is_none_or(|ids| ids.contains(*device_id))
+                })
+                .collect();
+            checked.sort_by(|a, b| a.0.cmp(b.0));
             let mut blockers: Vec<String> = Vec::new();
-            for (device_id, st) in devices {
-                if st.is_offline {
-                    blockers.push(format!("{device_id}:OFFLINE"));
-                    continue;
-                }
-                let Some(safety) = st.last_reported_safety.as_ref() else {
-                    blockers.push(format!("{device_id}:UNKNOWN"));
-                    continue;
+            let mut checklist: Vec<serde_json::Value> = Vec::with_capacity(checked.len());
+            for (device_id, st) in checked {
+                let safety = st.last_reported_safety.as_ref();
+                let blocker = if st.is_offline {
+                    device_offline_policy(runtime, device_id)
+                        .blocks_dispatch()
+                        .then(|| "OFFLINE".to_string())
+                } else {
+                    match safety {
+                        None => Some("UNKNOWN".to_string()),
+                        Some(s) if s.latched || s.kind != SafetyStateKind::Safe => {
+                            Some(format!("{:?}", s.kind))
+                        }
+                        Some(_) => None,
+                    }
                 };
-                if safety.latched || safety.kind != SafetyStateKind::Safe {
-                    blockers.push(format!("{device_id}:{:?}", safety.kind));
+                checklist.push(serde_json::json!({
+                    "device_id": device_id,
+                    "online": !st.is_offline,
+                    "safety_kind": safety.map(|s| format!("{:?}", s.kind)),
+                    "latched": safety.map(|s| s.latched),
+                    "reason_code": safety.and_then(|s| s.reason_code.clone()),
+                    "ok": blocker.is_none(),
+                }));
+                if let Some(state) = blocker {
+                    blockers.push(format!("{device_id}:{state}"));
                 }
             }
 
             if !blockers.is_empty() {
-                blockers.sort();
-                warn!(blockers=?blockers, "safety reset denied (devices not SAFE/offline)");
-                publish_core_fault(
+                warn!(zone=?zone, blockers=?blockers, "safety reset denied (devices not SAFE/offline)");
+                raise_core_fault(
+                    config,
                     client,
-                    &config.room_id,
-                    CoreFault {
-                        schema: SCHEMA_VERSION.to_string(),
-                        room_id: config.room_id.clone(),
-                        kind: "SAFETY_RESET_DENIED".to_string(),
-                        severity: "WARN".to_string(),
-                        message: "Safety reset denied: devices not SAFE/offline".to_string(),
-                        observed_at_unix_ms: unix_ms_now(),
-                        details: serde_json::json!({ "blockers": blockers }),
-                    },
+                    db,
+                    FaultKind::SafetyResetDenied,
+                    Severity::Warn,
+                    "Safety reset denied: devices not SAFE/offline",
+                    serde_json::json!({
+                        "blockers": blockers,
+                        "zone": zone,
+                        "checklist": checklist,
+                        "operators": operators,
+                    }),
                 )
                 .await;
                 return;
             }
 
-            runtime.safety_latched_since_unix_ms = None;
-            runtime.recompute_dispatch_pause_reason();
-            runtime.room_safety = compute_room_safety(devices, false);
-            warn!("safety latch reset via core control request");
-            publish_core_fault(
+            let cleared: Vec<(String, ZoneLatch)> = match zone.as_deref() {
+                Some(z) => runtime.zone_latches.remove_entry(z).into_iter().collect(),
+                None => {
+                    runtime.safety_latched_since_unix_ms = None;
+                    runtime.recompute_dispatch_pause_reason();
+                    std::mem::take(&mut runtime.zone_latches)
+                        .into_iter()
+                        .collect()
+                }
+            };
+            runtime.room_safety = compute_room_safety(
+                devices,
+                &runtime.device_registry,
+                runtime.safety_latched_since_unix_ms.is_some(),
+            );
+            let cleared: Vec<serde_json::Value> = cleared
+                .into_iter()
+                .map(|(zone, latch)| {
+                    serde_json::json!({
+                        "zone": zone,
+                        "device_id": latch.device_id,
+                        "safety_kind": format!("{:?}", latch.safety_kind),
+                        "latched_since_unix_ms": latch.since_unix_ms,
+                    })
+                })
+                .collect();
+            warn!(zone=?zone, zones=cleared.len(), "safety latch reset via core control request");
+            raise_core_fault(
+                config,
                 client,
-                &config.room_id,
-                CoreFault {
-                    schema: SCHEMA_VERSION.to_string(),
-                    room_id: config.room_id.clone(),
-                    kind: "SAFETY_LATCH_RESET".to_string(),
-                    severity: "INFO".to_string(),
-                    message: "Safety latch reset by operator".to_string(),
-                    observed_at_unix_ms: unix_ms_now(),
-                    details: serde_json::json!({}),
+                db,
+                FaultKind::SafetyLatchReset,
+                Severity::Info,
+                match zone.as_deref() {
+                    Some(z) => format!("Safety zone '{z}' latch reset by operator"),
+                    None => "Safety latch reset by operator".to_string(),
                 },
+                serde_json::json!({
+                    "zone": zone,
+                    "zones": cleared,
+                    "checklist": checklist,
+                    "operators": operators,
+                }),
             )
             .await;
         }
@@ -1879,8 +5716,8 @@ async fn handle_core_control(
                     CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_START_DENIED".to_string(),
-                        severity: "WARN".to_string(),
+                        kind: FaultKind::GraphStartDenied,
+                        severity: Severity::Warn,
                         message: "Graph start denied: dispatch is paused".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({}),
@@ -1896,8 +5733,8 @@ async fn handle_core_control(
                     CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_START_DENIED".to_string(),
-                        severity: "WARN".to_string(),
+                        kind: FaultKind::GraphStartDenied,
+                        severity: Severity::Warn,
                         message: "Graph start denied: no graph loaded".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({}),
@@ -1923,8 +5760,8 @@ async fn handle_core_control(
                 CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "GRAPH_STARTED".to_string(),
-                    severity: "INFO".to_string(),
+                    kind: FaultKind::GraphStarted,
+                    severity: Severity::Info,
                     message: "Graph started".to_string(),
                     observed_at_unix_ms: unix_ms_now(),
                     details: serde_json::json!({
@@ -1946,8 +5783,8 @@ async fn handle_core_control(
                 CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "GRAPH_STOPPED".to_string(),
-                    severity: "WARN".to_string(),
+                    kind: FaultKind::GraphStopped,
+                    severity: Severity::Warn,
                     message: "Graph stopped by operator".to_string(),
                     observed_at_unix_ms: unix_ms_now(),
                     details: serde_json::json!({ "version": graph_runner.graph_version }),
@@ -1955,6 +5792,9 @@ async fn handle_core_control(
             )
             .await;
         }
+        CORE_CONTROL_OP_RELOAD_DEVICE_REGISTRY => {
+            start_device_registry_reload(config, db, runtime);
+        }
         CORE_CONTROL_OP_RELOAD_GRAPH => {
             if graph_runner.is_running() {
                 publish_core_fault(
@@ -1963,8 +5803,8 @@ async fn handle_core_control(
                     CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_RELOAD_DENIED".to_string(),
-                        severity: "WARN".to_string(),
+                        kind: FaultKind::GraphReloadDenied,
+                        severity: Severity::Warn,
                         message: "Graph reload denied: graph is running".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({}),
@@ -1980,8 +5820,8 @@ async fn handle_core_control(
                     CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_RELOAD_DENIED".to_string(),
-                        severity: "WARN".to_string(),
+                        kind: FaultKind::GraphReloadDenied,
+                        severity: Severity::Warn,
                         message: "Graph reload denied: pause dispatch first".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({}),
@@ -1990,15 +5830,15 @@ async fn handle_core_control(
                 .await;
                 return;
             }
-            if !config.db_enabled {
+            let Some(db) = db else {
                 publish_core_fault(
                     client,
                     &config.room_id,
                     CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_RELOAD_FAILED".to_string(),
-                        severity: "WARN".to_string(),
+                        kind: FaultKind::GraphReloadFailed,
+                        severity: Severity::Warn,
                         message: "Graph reload failed: CORE_DB_ENABLED is false".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({}),
@@ -2006,18 +5846,18 @@ async fn handle_core_control(
                 )
                 .await;
                 return;
-            }
-            match load_active_graph_from_db(&config.database_url, &config.room_id).await {
+            };
+            match load_active_graph_from_db(&db.pool, &config.room_id).await {
                 Ok(Some((g, version))) => {
-                    if g.schema != SCHEMA_VERSION || g.room_id != config.room_id {
+                    if !is_accepted_schema(&g.schema) || g.room_id != config.room_id {
                         publish_core_fault(
                             client,
                             &config.room_id,
                             CoreFault {
                                 schema: SCHEMA_VERSION.to_string(),
                                 room_id: config.room_id.clone(),
-                                kind: "GRAPH_RELOAD_FAILED".to_string(),
-                                severity: "WARN".to_string(),
+                                kind: FaultKind::GraphReloadFailed,
+                                severity: Severity::Warn,
                                 message: "Graph reload failed: wrong schema/room_id".to_string(),
                                 observed_at_unix_ms: unix_ms_now(),
                                 details: serde_json::json!({
@@ -2031,17 +5871,21 @@ async fn handle_core_control(
                     }
                     graph_runner.graph = Some(g);
                     graph_runner.graph_version = Some(version);
+                    runtime.set_interlocks(graph_runner);
                     publish_core_fault(
                         client,
                         &config.room_id,
                         CoreFault {
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "GRAPH_RELOADED".to_string(),
-                            severity: "INFO".to_string(),
+                            kind: FaultKind::GraphReloaded,
+                            severity: Severity::Info,
                             message: "Graph reloaded from DB".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
-                            details: serde_json::json!({ "version": version }),
+                            details: serde_json::json!({
+                                "version": version,
+                                "interlocks": runtime.interlocks.len(),
+                            }),
                         },
                     )
                     .await;
@@ -2053,8 +5897,8 @@ async fn handle_core_control(
                         CoreFault {
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "GRAPH_RELOAD_FAILED".to_string(),
-                            severity: "WARN".to_string(),
+                            kind: FaultKind::GraphReloadFailed,
+                            severity: Severity::Warn,
                             message: "Graph reload failed: no active graph in DB".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
                             details: serde_json::json!({}),
@@ -2070,8 +5914,8 @@ async fn handle_core_control(
                         CoreFault {
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "GRAPH_RELOAD_FAILED".to_string(),
-                            severity: "WARN".to_string(),
+                            kind: FaultKind::GraphReloadFailed,
+                            severity: Severity::Warn,
                             message: "Graph reload failed (db error)".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
                             details: serde_json::json!({ "error": err.to_string() }),
@@ -2081,20 +5925,462 @@ async fn handle_core_control(
                 }
             }
         }
-        other => {
-            warn!(op=%other, "unknown core control op");
+        CORE_CONTROL_OP_CANCEL_COMMAND => {
+            let Some(command_id) = req
+                .parameters
+                .get(CANCEL_PARAM_COMMAND_ID)
+                .and_then(|v| v.as_str())
+                .and_then(|s| Uuid::parse_str(s).ok())
+            else {
+                warn!("CANCEL_COMMAND requires parameters.command_id (uuid)");
+                return;
+            };
+            let sent = cancel_pending_commands(
+                config,
+                client,
+                runtime,
+                db,
+                devices,
+                device_sequences,
+                pending,
+                &CancelScope::Command(command_id),
+                "OPERATOR",
+            )
+            .await;
+            if sent == 0 {
+                warn!(command_id=%command_id, "CANCEL_COMMAND: no cancellable pending command");
+            }
+        }
+        CORE_CONTROL_OP_CANCEL_DEVICE_COMMANDS => {
+            let Some(device_id) = req.parameters.get("device_id").and_then(|v| v.as_str()) else {
+                warn!("CANCEL_DEVICE_COMMANDS requires parameters.device_id");
+                return;
+            };
+            let sent = cancel_pending_commands(
+                config,
+                client,
+                runtime,
+                db,
+                devices,
+                device_sequences,
+                pending,
+                &CancelScope::Device(device_id.to_string()),
+                "OPERATOR",
+            )
+            .await;
+            // Queued work for the device goes too; otherwise it would start right after.
+            drop_dispatch_queues(
+                config,
+                client,
+                runtime,
+                db,
+                dispatch_tracker,
+                Some(device_id),
+                "OPERATOR",
+            )
+            .await;
+            info!(device_id, sent, "CANCEL_DEVICE_COMMANDS");
+        }
+        CORE_CONTROL_OP_SET_MAINTENANCE | CORE_CONTROL_OP_RELEASE_MAINTENANCE => {
+            handle_maintenance_op(config, client, runtime, db, &req).await;
+        }
+        other => {
+            warn!(op=%other, "unknown core control op");
+        }
+    }
+}
+
+/// Upper bound on one maintenance lock write; past it the op is refused (`NOT_PERSISTED`).
+const MAINTENANCE_DB_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A `SET_MAINTENANCE` / `RELEASE_MAINTENANCE` that passed the lock rules and waits for its DB
+/// write; [`finish_maintenance_writes`] applies it once the write is confirmed.
+#[derive(Debug)]
+struct MaintenanceWrite {
+    op: String,
+    key: (MaintenanceScope, String),
+    /// The new lock (`SET_MAINTENANCE`); `None` for a release.
+    lock: Option<MaintenanceStatus>,
+    /// Lock held when the op was accepted.
+    existing: Option<MaintenanceStatus>,
+    actor: String,
+    actor_role: String,
+    task: tokio::task::JoinHandle<anyhow::Result<()>>,
+}
+
+/// `SET_MAINTENANCE` / `RELEASE_MAINTENANCE`. One lock per device/zone: the owner may update
+/// its note, anyone else is refused until it is released (by the owner or an ADMIN).
+///
+/// The actor and its role are trusted under the same rule as a dispatch `operator` (see
+/// [`trusted_operator`]): [`handle_core_control`] has already checked `CORE_CONTROL_TOKEN`.
+///
+/// A lock only changes once it is stored: the write runs off the scheduler loop and the op is
+/// refused while Postgres is down, so a lock never exists only in memory.
+async fn handle_maintenance_op(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    db: Option<&DbWriter>,
+    req: &CoreControlRequest,
+) {
+    let param = |key: &str| {
+        req.parameters
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let setting = req.op == CORE_CONTROL_OP_SET_MAINTENANCE;
+    let (actor, actor_role) = if setting {
+        (param("owner"), param("owner_role"))
+    } else {
+        (param("released_by"), param("released_by_role"))
+    };
+    let target = match (param("device_id"), param("zone")) {
+        (Some(device_id), None) => Some((MaintenanceScope::Device, device_id)),
+        (None, Some(zone)) => Some((MaintenanceScope::Zone, zone)),
+        _ => None,
+    };
+    let existing = target
+        .as_ref()
+        .and_then(|key| runtime.maintenance.get(key))
+        .cloned();
+    let pool = db
+        .map(|db| &db.pool)
+        .filter(|pool| pool.health().up == Some(true));
+
+    let denied = match (&target, &actor, &actor_role, &existing) {
+        (None, ..) => Some("INVALID_TARGET"),
+        (_, None, ..) | (_, _, None, _) => Some("MISSING_OPERATOR"),
+        (Some(key), ..) if runtime.maintenance_writes.iter().any(|w| w.key == *key) => {
+            Some("WRITE_IN_PROGRESS")
+        }
+        (_, Some(actor), Some(_), Some(lock)) if setting && lock.owner != *actor => {
+            Some("LOCKED_BY_OTHER")
+        }
+        (_, _, _, None) if !setting => Some("NOT_LOCKED"),
+        (_, Some(actor), Some(role), Some(lock))
+            if !setting && lock.owner != *actor && role != "ADMIN" =>
+        {
+            Some("NOT_OWNER")
+        }
+        _ if pool.is_none() => Some("DB_UNAVAILABLE"),
+        _ => None,
+    };
+    if let Some(reason) = denied {
+        deny_maintenance_op(
+            config,
+            client,
+            db,
+            &req.op,
+            target.as_ref(),
+            actor.as_deref(),
+            reason,
+            existing.as_ref(),
+            None,
+        )
+        .await;
+        return;
+    }
+    let (Some(key), Some(actor), Some(actor_role), Some(pool)) = (target, actor, actor_role, pool)
+    else {
+        return;
+    };
+
+    let lock = setting.then(|| MaintenanceStatus {
+        scope: key.0,
+        target: key.1.clone(),
+        owner: actor.clone(),
+        owner_role: actor_role.clone(),
+        note: param("note"),
+        since_unix_ms: existing
+            .as_ref()
+            .map(|l| l.since_unix_ms)
+            .unwrap_or_else(unix_ms_now),
+    });
+    let task = {
+        let pool = pool.clone();
+        let lock = lock.clone();
+        let (scope, target) = key.clone();
+        tokio::spawn(async move {
+            let write = async {
+                match &lock {
+                    Some(lock) => save_maintenance_lock(&pool, lock).await,
+                    None => delete_maintenance_lock(&pool, scope, &target).await,
+                }
+            };
+            tokio::time::timeout(MAINTENANCE_DB_TIMEOUT, write)
+                .await
+                .unwrap_or_else(|_| {
+                    anyhow::bail!("timed out after {}ms", MAINTENANCE_DB_TIMEOUT.as_millis())
+                })
+        })
+    };
+    runtime.maintenance_writes.push(MaintenanceWrite {
+        op: req.op.clone(),
+        key,
+        lock,
+        existing,
+        actor,
+        actor_role,
+        task,
+    });
+}
+
+/// Applies the maintenance ops whose DB write has finished (called every tick): a stored lock
+/// change takes effect and is announced, a failed write refuses the op with `NOT_PERSISTED`.
+async fn finish_maintenance_writes(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    db: Option<&DbWriter>,
+    devices: &mut std::collections::HashMap<String, DeviceStatus>,
+) {
+    if !runtime
+        .maintenance_writes
+        .iter()
+        .any(|w| w.task.is_finished())
+    {
+        return;
+    }
+    let (finished, waiting) = std::mem::take(&mut runtime.maintenance_writes)
+        .into_iter()
+        .partition::<Vec<_>, _>(|w| w.task.is_finished());
+    runtime.maintenance_writes = waiting;
+
+    for write in finished {
+        let stored = match write.task.await {
+            Ok(res) => res,
+            Err(err) => Err(anyhow::Error::new(err)),
+        };
+        if let Err(err) = stored {
+            warn!(op=%write.op, target=%write.key.1, error=%err, "maintenance lock not persisted");
+            deny_maintenance_op(
+                config,
+                client,
+                db,
+                &write.op,
+                Some(&write.key),
+                Some(&write.actor),
+                "NOT_PERSISTED",
+                write.existing.as_ref(),
+                Some(&format!("{err:#}")),
+            )
+            .await;
+            continue;
+        }
+        let (key, actor) = (write.key, write.actor);
+        match write.lock {
+            Some(lock) => {
+                runtime.maintenance.insert(key, lock.clone());
+                warn!(scope=?lock.scope, target=%lock.target, owner=%lock.owner, "maintenance set");
+                raise_core_fault(
+                    config,
+                    client,
+                    db,
+                    FaultKind::MaintenanceSet,
+                    Severity::Info,
+                    format!("{} put into maintenance by {}", lock.target, lock.owner),
+                    serde_json::json!({ "maintenance": lock }),
+                )
+                .await;
+            }
+            None => {
+                runtime.maintenance.remove(&key);
+                info!(scope=?key.0, target=%key.1, released_by=%actor, "maintenance released");
+                raise_core_fault(
+                    config,
+                    client,
+                    db,
+                    FaultKind::MaintenanceReleased,
+                    Severity::Info,
+                    format!("{} released from maintenance by {actor}", key.1),
+                    serde_json::json!({
+                        "maintenance": write.existing,
+                        "released_by": actor,
+                        "released_by_role": write.actor_role,
+                    }),
+                )
+                .await;
+            }
+        }
+    }
+
+    for (device_id, status) in devices.iter_mut() {
+        let next = runtime.maintenance_for(device_id).cloned();
+        if status.maintenance != next {
+            status.maintenance = next;
+            publish_device_status(config, client, device_id, status).await;
         }
     }
 }
 
-async fn publish_core_fault(client: &rumqttc::AsyncClient, room_id: &str, fault: CoreFault) {
+#[allow(clippy::too_many_arguments)]
+async fn deny_maintenance_op(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    op: &str,
+    target: Option<&(MaintenanceScope, String)>,
+    actor: Option<&str>,
+    reason: &str,
+    existing: Option<&MaintenanceStatus>,
+    error: Option<&str>,
+) {
+    warn!(op, target=?target, actor=?actor, reason, "maintenance op denied");
+    let mut details = serde_json::json!({
+        "op": op,
+        "scope": target.map(|(scope, _)| scope),
+        "target": target.map(|(_, t)| t),
+        "reason": reason,
+        "actor": actor,
+        "maintenance": existing,
+    });
+    if let Some(error) = error {
+        details["error"] = serde_json::json!(error);
+    }
+    raise_core_fault(
+        config,
+        client,
+        db,
+        FaultKind::MaintenanceDenied,
+        Severity::Warn,
+        format!("{op} denied: {reason}"),
+        details,
+    )
+    .await;
+}
+
+fn maintenance_scope_str(scope: MaintenanceScope) -> &'static str {
+    match scope {
+        MaintenanceScope::Device => "DEVICE",
+        MaintenanceScope::Zone => "ZONE",
+    }
+}
+
+async fn load_maintenance_from_db(
+    pool: &DbPool,
+) -> anyhow::Result<std::collections::BTreeMap<(MaintenanceScope, String), MaintenanceStatus>> {
+    let client = pool.get().await.context("connect postgres (maintenance)")?;
+    let rows = client
+        .query(
+            "SELECT scope, target, owner, owner_role, note, \
+                    (EXTRACT(EPOCH FROM since) * 1000)::BIGINT \
+             FROM maintenance_locks",
+            &[],
+        )
+        .await;
+    pool.note_result(&rows);
+    let rows = rows.context("query maintenance_locks")?;
+    let mut out = std::collections::BTreeMap::new();
+    for row in rows {
+        let scope: String = row.get(0);
+        let scope = match scope.as_str() {
+            "DEVICE" => MaintenanceScope::Device,
+            "ZONE" => MaintenanceScope::Zone,
+            other => {
+                warn!(scope=%other, "unknown maintenance scope in DB");
+                continue;
+            }
+        };
+        let target: String = row.get(1);
+        let since_ms: i64 = row.get(5);
+        out.insert(
+            (scope, target.clone()),
+            MaintenanceStatus {
+                scope,
+                target,
+                owner: row.get(2),
+                owner_role: row.get(3),
+                note: row.get(4),
+                since_unix_ms: since_ms.max(0) as u64,
+            },
+        );
+    }
+    Ok(out)
+}
+
+async fn save_maintenance_lock(pool: &DbPool, lock: &MaintenanceStatus) -> anyhow::Result<()> {
+    let client = pool.get().await.context("connect postgres (maintenance)")?;
+    let since_ms = lock.since_unix_ms as i64;
+    let res = client
+        .execute(
+            "INSERT INTO maintenance_locks (scope, target, owner, owner_role, note, since) \
+             VALUES ($1,$2,$3,$4,$5,to_timestamp($6::bigint / 1000.0)) \
+             ON CONFLICT (scope, target) DO UPDATE \
+             SET owner = EXCLUDED.owner, owner_role = EXCLUDED.owner_role, note = EXCLUDED.note",
+            &[
+                &maintenance_scope_str(lock.scope),
+                &lock.target,
+                &lock.owner,
+                &lock.owner_role,
+                &lock.note,
+                &since_ms,
+            ],
+        )
+        .await;
+    pool.note_result(&res);
+    res.context("upsert maintenance_locks")?;
+    Ok(())
+}
+
+async fn delete_maintenance_lock(
+    pool: &DbPool,
+    scope: MaintenanceScope,
+    target: &str,
+) -> anyhow::Result<()> {
+    let client = pool.get().await.context("connect postgres (maintenance)")?;
+    let res = client
+        .execute(
+            "DELETE FROM maintenance_locks WHERE scope = $1 AND target = $2",
+            &[&maintenance_scope_str(scope), &target],
+        )
+        .await;
+    pool.note_result(&res);
+    res.context("delete from maintenance_locks")?;
+    Ok(())
+}
+
+/// `operator` if core may act on its role: same rule as control ops, so with
+/// `CORE_CONTROL_TOKEN` set it must carry the token.
+fn trusted_operator<'a>(
+    config: &Config,
+    operator: Option<&'a DispatchOperator>,
+) -> Option<&'a DispatchOperator> {
+    operator.filter(|op| match config.core_control_token.as_deref() {
+        Some(expected) => op.token.as_deref() == Some(expected),
+        None => true,
+    })
+}
+
+/// Why a dispatch may not reach a device in maintenance: (lock, device reports `MAINTENANCE`).
+/// Trusted TECH/ADMIN operators (manual dispatch through sentient-api) are let through.
+fn maintenance_block<'a>(
+    config: &Config,
+    runtime: &'a RuntimeState,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    req: &CoreDispatchRequest,
+) -> Option<(Option<&'a MaintenanceStatus>, bool)> {
+    let lock = runtime.maintenance_for(&req.device_id);
+    let reported = devices
+        .get(&req.device_id)
+        .and_then(|d| d.last_reported_safety.as_ref())
+        .is_some_and(|s| s.kind == SafetyStateKind::Maintenance);
+    if lock.is_none() && !reported {
+        return None;
+    }
+    let technician = trusted_operator(config, req.operator.as_ref())
+        .is_some_and(|op| matches!(op.role.as_str(), "TECH" | "ADMIN"));
+    (!technician).then_some((lock, reported))
+}
+
+async fn publish_core_fault(client: &AsyncClient, room_id: &str, fault: CoreFault) {
+    FAULTS.inc(&[fault.kind.as_str(), fault.severity.as_str()]);
     let topic = format!("room/{}/core/fault", room_id);
     match serde_json::to_vec(&fault) {
         Ok(payload) => {
-            if let Err(err) = client
-                .publish(topic, rumqttc::QoS::AtLeastOnce, true, payload)
-                .await
-            {
+            if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, payload).await {
                 warn!(error=%err, "failed to publish core fault");
             }
         }
@@ -2103,18 +6389,16 @@ async fn publish_core_fault(client: &rumqttc::AsyncClient, room_id: &str, fault:
 }
 
 async fn publish_device_fault(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     room_id: &str,
     device_id: &str,
     fault: &CoreFault,
 ) {
+    FAULTS.inc(&[fault.kind.as_str(), fault.severity.as_str()]);
     let topic = format!("room/{}/core/device/{}/fault", room_id, device_id);
     match serde_json::to_vec(fault) {
         Ok(payload) => {
-            if let Err(err) = client
-                .publish(topic, rumqttc::QoS::AtLeastOnce, true, payload)
-                .await
-            {
+            if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, payload).await {
                 warn!(error=%err, "failed to publish device fault");
             }
         }
@@ -2122,9 +6406,129 @@ async fn publish_device_fault(
     }
 }
 
+/// Builds a room-level fault, publishes it on `core/fault` (retained) and records it in the
+/// events table.
+async fn raise_core_fault(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    kind: FaultKind,
+    severity: Severity,
+    message: impl Into<String>,
+    details: serde_json::Value,
+) -> CoreFault {
+    let fault = CoreFault {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        kind,
+        severity,
+        message: message.into(),
+        observed_at_unix_ms: unix_ms_now(),
+        details,
+    };
+    emit_core_fault(config, client, db, &fault).await;
+    fault
+}
+
+/// Publishes an already built room-level fault (e.g. stamped with the device's observation time)
+/// on `core/fault` and records it, exactly like [`raise_core_fault`].
+async fn emit_core_fault(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    fault: &CoreFault,
+) {
+    publish_core_fault(client, &config.room_id, fault.clone()).await;
+    if let Some(db) = db {
+        if let Ok(v) = serde_json::to_value(fault) {
+            db.enqueue_json(
+                &config.room_id,
+                None,
+                &format!("room/{}/core/fault", config.room_id),
+                "CORE_FAULT",
+                fault.observed_at_unix_ms,
+                v,
+            );
+        }
+    }
+}
+
+/// `DISPATCH_REQUEST_INVALID` for a request on `room/{room_id}/{topic}`.
+async fn raise_dispatch_request_invalid(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    topic: &str,
+    message: &str,
+    error: &str,
+) {
+    raise_core_fault(
+        config,
+        client,
+        db,
+        FaultKind::DispatchRequestInvalid,
+        Severity::Warn,
+        message,
+        serde_json::json!({
+            "error": error,
+            "topic": format!("room/{}/{}", config.room_id, topic),
+        }),
+    )
+    .await;
+}
+
+/// Builds a device fault, publishes it (retained) and records it in the events table.
+#[allow(clippy::too_many_arguments)]
+async fn raise_device_fault(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    kind: FaultKind,
+    severity: Severity,
+    message: impl Into<String>,
+    details: serde_json::Value,
+) -> CoreFault {
+    let fault = CoreFault {
+        schema: SCHEMA_VERSION.to_string(),
+        room_id: config.room_id.clone(),
+        kind,
+        severity,
+        message: message.into(),
+        observed_at_unix_ms: unix_ms_now(),
+        details,
+    };
+    emit_device_fault(config, client, db, device_id, &fault).await;
+    fault
+}
+
+/// Device-topic counterpart of [`emit_core_fault`].
+async fn emit_device_fault(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    fault: &CoreFault,
+) {
+    publish_device_fault(client, &config.room_id, device_id, fault).await;
+    if let Some(db) = db {
+        if let Ok(v) = serde_json::to_value(fault) {
+            db.enqueue_json(
+                &config.room_id,
+                Some(device_id),
+                &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
+                "DEVICE_FAULT",
+                fault.observed_at_unix_ms,
+                v,
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn tick_graph_runner(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     runtime: &RuntimeState,
     db: Option<&DbWriter>,
     runner: &mut GraphRunner,
@@ -2165,6 +6569,61 @@ async fn tick_graph_runner(
             continue;
         }
 
+        if let Some(correlation_id) = state.waiting_on_queued_correlation_id {
+            if let Some(cmd_id) = dispatch_tracker.inflight_command_id(correlation_id) {
+                state.waiting_on_queued_correlation_id = None;
+                state.waiting_on_command_id = Some(cmd_id);
+                next_active.push(state);
+                continue;
+            }
+            if dispatch_tracker.is_queued(correlation_id)
+                || dispatch_tracker.is_scheduled(correlation_id)
+            {
+                next_active.push(state);
+                continue;
+            }
+            // Dropped from the queue / schedule (or blocked when released).
+            let fault = CoreFault {
+                schema: SCHEMA_VERSION.to_string(),
+                room_id: config.room_id.clone(),
+                kind: FaultKind::GraphDispatchFailed,
+                severity: Severity::Warn,
+                message: "Queued graph dispatch did not create an inflight command".to_string(),
+                observed_at_unix_ms: unix_ms_now(),
+                details: serde_json::json!({
+                    "node_id": state.node_id,
+                    "correlation_id": correlation_id,
+                }),
+            };
+            publish_core_fault(client, &config.room_id, fault.clone()).await;
+            if let Some(db) = db {
+                if let Ok(v) = serde_json::to_value(&fault) {
+                    db.enqueue_json(
+                        &config.room_id,
+                        None,
+                        &format!("room/{}/core/fault", config.room_id),
+                        "CORE_FAULT",
+                        fault.observed_at_unix_ms,
+                        v,
+                    );
+                }
+            }
+            runner.active_nodes.clear();
+            return;
+        }
+
+        if let Some(correlation_id) = state.waiting_on_batch_correlation_id {
+            if dispatch_tracker.batches.contains_key(&correlation_id) {
+                next_active.push(state);
+                continue;
+            }
+            let next = state.next_after_wait.take();
+            state.waiting_on_batch_correlation_id = None;
+            transitions_this_tick += 1;
+            push_next(&mut next_active, next.as_ref());
+            continue;
+        }
+
         if let Some(cmd_id) = state.waiting_on_command_id {
             if pending.contains_key(&cmd_id) {
                 next_active.push(state);
@@ -2211,8 +6670,8 @@ async fn tick_graph_runner(
                         let fault = CoreFault {
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "GRAPH_TIMEOUT".to_string(),
-                            severity: "WARN".to_string(),
+                            kind: FaultKind::GraphTimeout,
+                            severity: Severity::Warn,
                             message: "Graph node timed out waiting for device state".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
                             details: serde_json::json!({
@@ -2236,55 +6695,151 @@ async fn tick_graph_runner(
                                 );
                             }
                         }
-                        runner.active_nodes.clear();
-                        return;
+                        runner.active_nodes.clear();
+                        return;
+                    }
+                }
+
+                let Some(st) = devices.get(device_id).and_then(|d| d.last_state.as_ref()) else {
+                    next_active.push(state);
+                    continue;
+                };
+                let Some(actual) = st.pointer(pointer) else {
+                    next_active.push(state);
+                    continue;
+                };
+                if actual == equals {
+                    transitions_this_tick += 1;
+                    push_next(&mut next_active, next.as_ref());
+                } else {
+                    next_active.push(state);
+                }
+            }
+            GraphNode::Dispatch {
+                device_id,
+                action,
+                parameters,
+                safety_class,
+                execute_in_ms,
+                execute_at_unix_ms,
+                next,
+            } => {
+                let correlation_id = Uuid::new_v4();
+                let req = CoreDispatchRequest {
+                    schema: SCHEMA_VERSION.to_string(),
+                    room_id: config.room_id.clone(),
+                    device_id: device_id.clone(),
+                    action: *action,
+                    parameters: parameters.clone(),
+                    safety_class: *safety_class,
+                    correlation_id: Some(correlation_id),
+                    retries: None,
+                    ack_timeout_ms: None,
+                    complete_timeout_ms: None,
+                    reply_topic: None,
+                    execute_at_unix_ms: *execute_at_unix_ms,
+                    execute_in_ms: *execute_in_ms,
+                    // Automation: never bypasses maintenance.
+                    operator: None,
+                };
+                let payload = match serde_json::to_vec(&req) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        warn!(error=%err, node_id=%state.node_id, "graph error: failed to serialize dispatch request");
+                        runner.active_nodes.clear();
+                        return;
+                    }
+                };
+                handle_dispatch_request(
+                    config,
+                    client,
+                    runtime,
+                    db,
+                    &payload,
+                    devices,
+                    device_sequences,
+                    pending,
+                    dispatch_tracker,
+                    false,
+                    None,
+                )
+                .await;
+
+                if dispatch_tracker
+                    .inflight_command_id(correlation_id)
+                    .is_none()
+                    && (dispatch_tracker.is_queued(correlation_id)
+                        || dispatch_tracker.is_scheduled(correlation_id))
+                {
+                    // Device busy (QUEUE policy) or not yet due: wait until it is handed to the
+                    // device.
+                    transitions_this_tick += 1;
+                    state.waiting_on_queued_correlation_id = Some(correlation_id);
+                    state.next_after_wait = next.clone();
+                    state.entered_at = None;
+                    next_active.push(state);
+                    continue;
+                }
+
+                let Some(cmd_id) = dispatch_tracker.inflight_command_id(correlation_id) else {
+                    let fault = CoreFault {
+                        schema: SCHEMA_VERSION.to_string(),
+                        room_id: config.room_id.clone(),
+                        kind: FaultKind::GraphDispatchFailed,
+                        severity: Severity::Warn,
+                        message: "Graph dispatch did not create an inflight command".to_string(),
+                        observed_at_unix_ms: unix_ms_now(),
+                        details: serde_json::json!({
+                            "node_id": state.node_id,
+                            "device_id": device_id,
+                            "correlation_id": correlation_id,
+                        }),
+                    };
+                    publish_core_fault(client, &config.room_id, fault.clone()).await;
+                    if let Some(db) = db {
+                        if let Ok(v) = serde_json::to_value(&fault) {
+                            db.enqueue_json(
+                                &config.room_id,
+                                None,
+                                &format!("room/{}/core/fault", config.room_id),
+                                "CORE_FAULT",
+                                fault.observed_at_unix_ms,
+                                v,
+                            );
+                        }
                     }
-                }
-
-                let Some(st) = devices.get(device_id).and_then(|d| d.last_state.as_ref()) else {
-                    next_active.push(state);
-                    continue;
-                };
-                let Some(actual) = st.pointer(pointer) else {
-                    next_active.push(state);
-                    continue;
+                    runner.active_nodes.clear();
+                    return;
                 };
-                if actual == equals {
-                    transitions_this_tick += 1;
-                    push_next(&mut next_active, next.as_ref());
-                } else {
-                    next_active.push(state);
-                }
+
+                transitions_this_tick += 1;
+                state.waiting_on_command_id = Some(cmd_id);
+                state.next_after_wait = next.clone();
+                state.entered_at = None;
+                next_active.push(state);
             }
-            GraphNode::Dispatch {
-                device_id,
-                action,
-                parameters,
-                safety_class,
-                next,
-            } => {
+            GraphNode::DispatchBatch { commands, next } => {
                 let correlation_id = Uuid::new_v4();
-                let req = CoreDispatchRequest {
+                let req = CoreBatchDispatchRequest {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    device_id: device_id.clone(),
-                    action: *action,
-                    parameters: parameters.clone(),
-                    safety_class: *safety_class,
                     correlation_id: Some(correlation_id),
+                    commands: commands.clone(),
                     retries: None,
                     ack_timeout_ms: None,
                     complete_timeout_ms: None,
+                    reply_topic: None,
+                    operator: None,
                 };
                 let payload = match serde_json::to_vec(&req) {
                     Ok(v) => v,
                     Err(err) => {
-                        warn!(error=%err, node_id=%state.node_id, "graph error: failed to serialize dispatch request");
+                        warn!(error=%err, node_id=%state.node_id, "graph error: failed to serialize batch dispatch request");
                         runner.active_nodes.clear();
                         return;
                     }
                 };
-                handle_dispatch_request(
+                handle_batch_dispatch_request(
                     config,
                     client,
                     runtime,
@@ -2294,20 +6849,23 @@ async fn tick_graph_runner(
                     device_sequences,
                     pending,
                     dispatch_tracker,
+                    None,
                 )
                 .await;
 
-                let Some(cmd_id) = dispatch_tracker.inflight_command_id(correlation_id) else {
+                if !dispatch_tracker.batches.contains_key(&correlation_id) {
+                    let device_ids: Vec<&str> =
+                        commands.iter().map(|c| c.device_id.as_str()).collect();
                     let fault = CoreFault {
                         schema: SCHEMA_VERSION.to_string(),
                         room_id: config.room_id.clone(),
-                        kind: "GRAPH_DISPATCH_FAILED".to_string(),
-                        severity: "WARN".to_string(),
-                        message: "Graph dispatch did not create an inflight command".to_string(),
+                        kind: FaultKind::GraphDispatchFailed,
+                        severity: Severity::Warn,
+                        message: "Graph batch dispatch was not published".to_string(),
                         observed_at_unix_ms: unix_ms_now(),
                         details: serde_json::json!({
                             "node_id": state.node_id,
-                            "device_id": device_id,
+                            "device_ids": device_ids,
                             "correlation_id": correlation_id,
                         }),
                     };
@@ -2326,10 +6884,10 @@ async fn tick_graph_runner(
                     }
                     runner.active_nodes.clear();
                     return;
-                };
+                }
 
                 transitions_this_tick += 1;
-                state.waiting_on_command_id = Some(cmd_id);
+                state.waiting_on_batch_correlation_id = Some(correlation_id);
                 state.next_after_wait = next.clone();
                 state.entered_at = None;
                 next_active.push(state);
@@ -2340,17 +6898,48 @@ async fn tick_graph_runner(
     runner.active_nodes = next_active;
 }
 
+/// MQTT v5 properties carrying `trace_id` (if any) as a user property.
+fn trace_properties(trace_id: Option<&str>) -> PublishProperties {
+    PublishProperties {
+        user_properties: trace_id
+            .map(|t| vec![(MQTT_USER_PROPERTY_TRACE_ID.to_string(), t.to_string())])
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// MQTT v5 properties for a device command. It expires at the broker once its ack timeout has
+/// passed: by then core has resent it (or given up), so an older copy still queued for a slow
+/// or reconnecting device is stale.
+fn command_properties(ack_timeout_ms: u64, trace_id: Option<&str>) -> PublishProperties {
+    PublishProperties {
+        message_expiry_interval: Some(
+            ack_timeout_ms.div_ceil(1000).clamp(1, u32::MAX as u64) as u32
+        ),
+        ..trace_properties(trace_id)
+    }
+}
+
 async fn publish_device_command(
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     room_id: &str,
     device_id: &str,
     cmd: &CommandEnvelope,
+    codec: WireCodec,
+    ack_timeout_ms: u64,
+    trace_id: Option<&str>,
 ) -> bool {
-    let topic = format!("room/{}/device/{}/cmd", room_id, device_id);
-    match serde_json::to_vec(cmd) {
+    let topic = codec.topic(&format!("room/{}/device/{}/cmd", room_id, device_id));
+    match codec.encode(cmd) {
         Ok(bytes) => {
             if let Err(err) = client
-                .publish(topic, rumqttc::QoS::AtLeastOnce, false, bytes)
+                .publish_with_properties(
+                    topic,
+                    QoS::AtLeastOnce,
+                    false,
+                    bytes,
+                    command_properties(ack_timeout_ms, trace_id),
+                )
                 .await
             {
                 warn!(device_id, error=%err, "failed to publish device command");
@@ -2361,6 +6950,8 @@ async fn publish_device_command(
                 command_id=%cmd.command_id,
                 correlation_id=%cmd.correlation_id,
                 sequence=cmd.sequence,
+                codec=%codec,
+                trace_id,
                 "published device command"
             );
             true
@@ -2374,7 +6965,7 @@ async fn publish_device_command(
 
 async fn tick_pending_commands(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     runtime: &RuntimeState,
     db: Option<&DbWriter>,
     pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
@@ -2383,14 +6974,75 @@ async fn tick_pending_commands(
     if pending.is_empty() {
         return;
     }
-    if runtime.dispatch_is_paused() {
-        return;
-    }
+    // While paused only cancels make progress; everything else waits for resume.
+    let paused = runtime.dispatch_is_paused();
 
     let now = Instant::now();
     let mut to_remove: Vec<Uuid> = Vec::new();
+    // Correlation ids of cancelled commands, for faults raised on their `CANCEL` envelopes.
+    let cancel_targets: std::collections::HashMap<Uuid, Uuid> = pending
+        .values()
+        .filter_map(|p| p.cancels)
+        .filter_map(|target| pending.get(&target).map(|t| (target, t.cmd.correlation_id)))
+        .collect();
 
     for (command_id, p) in pending.iter_mut() {
+        // Answer a waiting dispatch caller with the first decisive status.
+        if p.reply_topic.is_some() {
+            let status = if p.cancelled {
+                Some(DispatchResultStatus::Cancelled)
+            } else if p.rejected {
+                Some(DispatchResultStatus::Rejected)
+            } else if p.completed {
+                Some(DispatchResultStatus::Completed)
+            } else if p.accepted {
+                Some(DispatchResultStatus::Accepted)
+            } else {
+                None
+            };
+            if let (Some(status), Some(reply_topic)) = (status, p.reply_topic.as_deref()) {
+                let result = pending_result(config, *command_id, p, status, None);
+                publish_dispatch_result(
+                    client,
+                    reply_topic,
+                    dispatch_tracker.reply_properties(p.cmd.correlation_id),
+                    &result,
+                )
+                .await;
+                p.reply_topic = None;
+            }
+        }
+        if paused && p.cancels.is_none() && !p.cancelled {
+            continue;
+        }
+        if p.cancelled {
+            info!(device_id=%p.device_id, command_id=%command_id, reason=?p.cancel_reason, "command cancelled");
+            raise_device_fault(
+                config,
+                client,
+                db,
+                &p.device_id,
+                FaultKind::CommandCancelled,
+                Severity::Info,
+                "Device cancelled command",
+                serde_json::json!({
+                    "device_id": p.device_id,
+                    "command_id": command_id,
+                    "correlation_id": p.cmd.correlation_id,
+                    "cancel_reason": p.cancel_reason,
+                }),
+            )
+            .await;
+            dispatch_tracker.mark_done(pending_result(
+                config,
+                *command_id,
+                p,
+                DispatchResultStatus::Cancelled,
+                None,
+            ));
+            to_remove.push(*command_id);
+            continue;
+        }
         if p.rejected {
             warn!(
                 device_id=%p.device_id,
@@ -2398,40 +7050,41 @@ async fn tick_pending_commands(
                 reason_code=?p.reason_code,
                 "command rejected"
             );
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "COMMAND_REJECTED".to_string(),
-                severity: "WARN".to_string(),
-                message: "Device rejected command".to_string(),
-                observed_at_unix_ms: unix_ms_now(),
-                details: serde_json::json!({
+            raise_device_fault(
+                config,
+                client,
+                db,
+                &p.device_id,
+                FaultKind::CommandRejected,
+                Severity::Warn,
+                "Device rejected command",
+                serde_json::json!({
                     "device_id": p.device_id,
                     "command_id": command_id,
                     "correlation_id": p.cmd.correlation_id,
                     "reason_code": p.reason_code,
                 }),
-            };
-            publish_device_fault(client, &config.room_id, &p.device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(&p.device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, p.device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
-                }
-            }
-            dispatch_tracker.mark_done(p.cmd.correlation_id);
+            )
+            .await;
+            dispatch_tracker.mark_done(pending_result(
+                config,
+                *command_id,
+                p,
+                DispatchResultStatus::Rejected,
+                None,
+            ));
             to_remove.push(*command_id);
             continue;
         }
         if p.completed {
             info!(device_id=%p.device_id, command_id=%command_id, "command completed");
-            dispatch_tracker.mark_done(p.cmd.correlation_id);
+            dispatch_tracker.mark_done(pending_result(
+                config,
+                *command_id,
+                p,
+                DispatchResultStatus::Completed,
+                None,
+            ));
             to_remove.push(*command_id);
             continue;
         }
@@ -2439,38 +7092,60 @@ async fn tick_pending_commands(
         if p.accepted {
             if let Some(accepted_at) = p.accepted_at {
                 if now.duration_since(accepted_at).as_millis() as u64 > p.complete_timeout_ms {
-                    warn!(device_id=%p.device_id, command_id=%command_id, "command completion timeout (no retry after ACCEPTED)");
-                    let fault = CoreFault {
-                        schema: SCHEMA_VERSION.to_string(),
-                        room_id: config.room_id.clone(),
-                        kind: "COMMAND_COMPLETE_TIMEOUT".to_string(),
-                        severity: "WARN".to_string(),
-                        message: "Command completion timeout after ACCEPTED".to_string(),
-                        observed_at_unix_ms: unix_ms_now(),
-                        details: serde_json::json!({
-                            "device_id": p.device_id,
-                            "command_id": command_id,
-                            "correlation_id": p.cmd.correlation_id,
-                            "complete_timeout_ms": p.complete_timeout_ms,
-                        }),
+                    let kind = if let Some((message, details)) =
+                        cancel_timeout_fault(*command_id, p, &cancel_targets, p.complete_timeout_ms)
+                    {
+                        warn!(device_id=%p.device_id, command_id=%command_id, "cancel not confirmed before completion timeout");
+                        raise_device_fault(
+                            config,
+                            client,
+                            db,
+                            &p.device_id,
+                            FaultKind::CommandCancelTimeout,
+                            Severity::Warn,
+                            message,
+                            details,
+                        )
+                        .await;
+                        FaultKind::CommandCancelTimeout
+                    } else {
+                        warn!(device_id=%p.device_id, command_id=%command_id, "command completion timeout (no retry after ACCEPTED)");
+                        raise_device_fault(
+                            config,
+                            client,
+                            db,
+                            &p.device_id,
+                            FaultKind::CommandCompleteTimeout,
+                            Severity::Warn,
+                            "Command completion timeout after ACCEPTED",
+                            serde_json::json!({
+                                "device_id": p.device_id,
+                                "command_id": command_id,
+                                "correlation_id": p.cmd.correlation_id,
+                                "complete_timeout_ms": p.complete_timeout_ms,
+                                "last_progress": p.last_progress,
+                            }),
+                        )
+                        .await;
+                        FaultKind::CommandCompleteTimeout
                     };
-                    publish_device_fault(client, &config.room_id, &p.device_id, &fault).await;
-                    if let Some(db) = db {
-                        if let Ok(v) = serde_json::to_value(&fault) {
-                            db.enqueue_json(
-                                &config.room_id,
-                                Some(&p.device_id),
-                                &format!(
-                                    "room/{}/core/device/{}/fault",
-                                    config.room_id, p.device_id
-                                ),
-                                "DEVICE_FAULT",
-                                fault.observed_at_unix_ms,
-                                v,
-                            );
-                        }
+                    let result = pending_result(
+                        config,
+                        *command_id,
+                        p,
+                        DispatchResultStatus::Timeout,
+                        Some(kind),
+                    );
+                    if let Some(reply_topic) = p.reply_topic.take() {
+                        publish_dispatch_result(
+                            client,
+                            &reply_topic,
+                            dispatch_tracker.reply_properties(p.cmd.correlation_id),
+                            &result,
+                        )
+                        .await;
                     }
-                    dispatch_tracker.mark_done(p.cmd.correlation_id);
+                    dispatch_tracker.mark_done(result);
                     to_remove.push(*command_id);
                 }
             }
@@ -2478,43 +7153,78 @@ async fn tick_pending_commands(
         }
 
         if now.duration_since(p.published_at).as_millis() as u64 > p.ack_timeout_ms {
-            if p.retries_left == 0 {
-                warn!(device_id=%p.device_id, command_id=%command_id, "command ack timeout (exhausted retries)");
-                let fault = CoreFault {
-                    schema: SCHEMA_VERSION.to_string(),
-                    room_id: config.room_id.clone(),
-                    kind: "COMMAND_ACK_TIMEOUT".to_string(),
-                    severity: "WARN".to_string(),
-                    message: "Command ACK timeout (exhausted retries)".to_string(),
-                    observed_at_unix_ms: unix_ms_now(),
-                    details: serde_json::json!({
-                        "device_id": p.device_id,
-                        "command_id": command_id,
-                        "correlation_id": p.cmd.correlation_id,
-                        "ack_timeout_ms": p.ack_timeout_ms,
-                    }),
+            // Never resend a command that has been cancelled: it would undo the cancel.
+            if p.retries_left == 0 || p.cancel_reason.is_some() {
+                let kind = if let Some((message, details)) =
+                    cancel_timeout_fault(*command_id, p, &cancel_targets, p.ack_timeout_ms)
+                {
+                    warn!(device_id=%p.device_id, command_id=%command_id, "cancel not confirmed before ack timeout");
+                    raise_device_fault(
+                        config,
+                        client,
+                        db,
+                        &p.device_id,
+                        FaultKind::CommandCancelTimeout,
+                        Severity::Warn,
+                        message,
+                        details,
+                    )
+                    .await;
+                    FaultKind::CommandCancelTimeout
+                } else {
+                    warn!(device_id=%p.device_id, command_id=%command_id, "command ack timeout (exhausted retries)");
+                    raise_device_fault(
+                        config,
+                        client,
+                        db,
+                        &p.device_id,
+                        FaultKind::CommandAckTimeout,
+                        Severity::Warn,
+                        "Command ACK timeout (exhausted retries)",
+                        serde_json::json!({
+                            "device_id": p.device_id,
+                            "command_id": command_id,
+                            "correlation_id": p.cmd.correlation_id,
+                            "ack_timeout_ms": p.ack_timeout_ms,
+                        }),
+                    )
+                    .await;
+                    FaultKind::CommandAckTimeout
                 };
-                publish_device_fault(client, &config.room_id, &p.device_id, &fault).await;
-                if let Some(db) = db {
-                    if let Ok(v) = serde_json::to_value(&fault) {
-                        db.enqueue_json(
-                            &config.room_id,
-                            Some(&p.device_id),
-                            &format!("room/{}/core/device/{}/fault", config.room_id, p.device_id),
-                            "DEVICE_FAULT",
-                            fault.observed_at_unix_ms,
-                            v,
-                        );
-                    }
+                let result = pending_result(
+                    config,
+                    *command_id,
+                    p,
+                    DispatchResultStatus::Timeout,
+                    Some(kind),
+                );
+                if let Some(reply_topic) = p.reply_topic.take() {
+                    publish_dispatch_result(
+                        client,
+                        &reply_topic,
+                        dispatch_tracker.reply_properties(p.cmd.correlation_id),
+                        &result,
+                    )
+                    .await;
                 }
-                dispatch_tracker.mark_done(p.cmd.correlation_id);
+                dispatch_tracker.mark_done(result);
                 to_remove.push(*command_id);
                 continue;
             }
 
             p.retries_left = p.retries_left.saturating_sub(1);
             p.published_at = now;
-            if publish_device_command(client, &config.room_id, &p.device_id, &p.cmd).await {
+            if publish_device_command(
+                client,
+                &config.room_id,
+                &p.device_id,
+                &p.cmd,
+                p.codec,
+                p.ack_timeout_ms,
+                p.trace_id.as_deref(),
+            )
+            .await
+            {
                 info!(
                     device_id=%p.device_id,
                     command_id=%command_id,
@@ -2530,7 +7240,214 @@ async fn tick_pending_commands(
     }
 }
 
-fn should_be_offline(config: &Config, status: &DeviceStatus, now: u64) -> bool {
+/// `COMMAND_CANCEL_TIMEOUT` message and details when a timed-out entry involves a cancel: a
+/// `CANCEL` envelope the device never acked, or a cancelled command it never confirmed with
+/// `CANCELLED`. `None` for plain ack/completion timeouts.
+fn cancel_timeout_fault(
+    command_id: Uuid,
+    p: &PendingCommand,
+    cancel_targets: &std::collections::HashMap<Uuid, Uuid>,
+    timeout_ms: u64,
+) -> Option<(&'static str, serde_json::Value)> {
+    if let Some(target) = p.cancels {
+        return Some((
+            "CANCEL not acknowledged by the device (exhausted retries)",
+            serde_json::json!({
+                "device_id": p.device_id,
+                "command_id": target,
+                "cancel_command_id": command_id,
+                "correlation_id": cancel_targets.get(&target),
+                "cancel_reason": null,
+                "timeout_ms": timeout_ms,
+                "reason": "CANCEL_NOT_ACKED",
+            }),
+        ));
+    }
+    let cancel_reason = p.cancel_reason.as_ref()?;
+    Some((
+        "Cancelled command not confirmed by the device",
+        serde_json::json!({
+            "device_id": p.device_id,
+            "command_id": command_id,
+            "cancel_command_id": null,
+            "correlation_id": p.cmd.correlation_id,
+            "cancel_reason": cancel_reason,
+            "timeout_ms": timeout_ms,
+            "reason": "CANCEL_NOT_CONFIRMED",
+        }),
+    ))
+}
+
+#[derive(Debug, Clone)]
+enum CancelScope {
+    Command(Uuid),
+    Device(String),
+    All,
+}
+
+/// Publishes a signed `CANCEL` for every matching in-flight command. Returns how many were sent.
+#[allow(clippy::too_many_arguments)]
+async fn cancel_pending_commands(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
+    db: Option<&DbWriter>,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+    device_sequences: &mut std::collections::HashMap<String, u64>,
+    pending: &mut std::collections::HashMap<Uuid, PendingCommand>,
+    scope: &CancelScope,
+    reason: &str,
+) -> usize {
+    let targets: Vec<Uuid> = pending
+        .iter()
+        .filter(|(_, p)| {
+            p.cancels.is_none()
+                && p.cancel_reason.is_none()
+                && !p.completed
+                && !p.rejected
+                && !p.cancelled
+        })
+        .filter(|(command_id, p)| match scope {
+            CancelScope::Command(id) => *command_id == id,
+            CancelScope::Device(device_id) => p.device_id == *device_id,
+            CancelScope::All => true,
+        })
+        .map(|(command_id, _)| *command_id)
+        .collect();
+
+    let mut sent = 0;
+    for target_id in targets {
+        let Some(target) = pending.get(&target_id) else {
+            continue;
+        };
+        let device_id = target.device_id.clone();
+        let (command_schema, codec) = devices
+            .get(&device_id)
+            .map(|d| (d.command_schema(), d.codec))
+            .unwrap_or((DEFAULT_DEVICE_SCHEMA, WireCodec::Json));
+
+        let key = runtime.device_hmac_keys.get(&device_id);
+        let error = if !schema_at_least(command_schema, CANCEL_MIN_SCHEMA) {
+            Some(format!(
+                "device schema {} predates CANCEL ({}+)",
+                command_schema, CANCEL_MIN_SCHEMA
+            ))
+        } else if key.is_none() {
+            Some("missing device HMAC key on core".to_string())
+        } else {
+            None
+        };
+        let (Some(key), None) = (key, error.as_ref()) else {
+            let error = error.unwrap_or_default();
+            warn!(device_id=%device_id, command_id=%target_id, error=%error, "cannot cancel command");
+            raise_device_fault(
+                config,
+                client,
+                db,
+                &device_id,
+                FaultKind::CommandCancelUnsupported,
+                Severity::Warn,
+                "Command could not be cancelled",
+                serde_json::json!({
+                    "device_id": device_id,
+                    "command_id": target_id,
+                    "command_schema": command_schema,
+                    "cancel_reason": reason,
+                    "error": error,
+                }),
+            )
+            .await;
+            continue;
+        };
+
+        let next_seq = device_sequences
+            .get(&device_id)
+            .copied()
+            .unwrap_or(0)
+            .wrapping_add(1);
+        device_sequences.insert(device_id.clone(), next_seq);
+
+        let mut cmd = CommandEnvelope {
+            schema: command_schema.to_string(),
+            room_id: config.room_id.clone(),
+            device_id: device_id.clone(),
+            command_id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
+            sequence: next_seq,
+            issued_at_unix_ms: unix_ms_now(),
+            action: CommandAction::Cancel,
+            parameters: serde_json::json!({ CANCEL_PARAM_COMMAND_ID: target_id }),
+            safety_class: SafetyClass::NonCritical,
+            execute_at_unix_ms: None,
+            auth: None,
+        };
+        if let Err(err) = sign_command_hmac_sha256(&mut cmd, key, None) {
+            warn!(device_id=%device_id, error=%err, "failed to sign cancel command");
+            continue;
+        }
+
+        // The cancel belongs to the same trace as the command it aborts.
+        let trace_id = pending.get(&target_id).and_then(|t| t.trace_id.clone());
+        if !publish_device_command(
+            client,
+            &config.room_id,
+            &device_id,
+            &cmd,
+            codec,
+            config.dispatch_ack_timeout_ms,
+            trace_id.as_deref(),
+        )
+        .await
+        {
+            continue;
+        }
+        warn!(device_id=%device_id, command_id=%target_id, cancel_command_id=%cmd.command_id, reason, "cancel requested");
+        if let Some(db) = db {
+            if let Ok(v) = serde_json::to_value(&cmd) {
+                db.enqueue_json(
+                    &config.room_id,
+                    Some(&device_id),
+                    &format!("room/{}/device/{}/cmd", config.room_id, device_id),
+                    "CMD",
+                    cmd.issued_at_unix_ms,
+                    v,
+                );
+            }
+        }
+        if let Some(target) = pending.get_mut(&target_id) {
+            target.cancel_reason = Some(reason.to_string());
+        }
+        pending.insert(
+            cmd.command_id,
+            PendingCommand {
+                device_id,
+                cmd,
+                codec,
+                published_at: Instant::now(),
+                dispatched_at: Instant::now(),
+                last_update: Instant::now(),
+                retries_left: config.dispatch_default_retries,
+                ack_timeout_ms: config.dispatch_ack_timeout_ms,
+                complete_timeout_ms: config.dispatch_complete_timeout_ms,
+                accepted: false,
+                accepted_at: None,
+                completed: false,
+                rejected: false,
+                reason_code: None,
+                cancels: Some(target_id),
+                cancel_reason: None,
+                cancelled: false,
+                last_progress: None,
+                reply_topic: None,
+                trace_id,
+            },
+        );
+        sent += 1;
+    }
+    sent
+}
+
+fn should_be_offline(offline_ms: u64, status: &DeviceStatus, now: u64) -> bool {
     if let Some(p) = status.presence {
         if p == PresenceStatus::Offline {
             return true;
@@ -2539,136 +7456,414 @@ fn should_be_offline(config: &Config, status: &DeviceStatus, now: u64) -> bool {
     let Some(last) = status.last_heartbeat_at_unix_ms else {
         return status.is_offline;
     };
-    now.saturating_sub(last) > config.device_offline_ms
+    now.saturating_sub(last) > offline_ms
+}
+
+/// Records an ONLINE/OFFLINE change for flapping detection. Returns whether the change's own
+/// `DEVICE_ONLINE`/`DEVICE_OFFLINE` fault should go out (not while the device is flapping).
+async fn note_liveness_change(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    status: &mut DeviceStatus,
+    now: u64,
+) -> bool {
+    if config.device_flap_threshold == 0 {
+        return true;
+    }
+    status.liveness_changes.push_back(now);
+    while status
+        .liveness_changes
+        .front()
+        .is_some_and(|at| now.saturating_sub(*at) > config.device_flap_window_ms)
+    {
+        status.liveness_changes.pop_front();
+    }
+    if let Some(flap) = status.flapping.as_mut() {
+        if status.is_offline {
+            flap.suppressed_offline += 1;
+        } else {
+            flap.suppressed_online += 1;
+        }
+        return false;
+    }
+    if status.liveness_changes.len() < config.device_flap_threshold as usize {
+        return true;
+    }
+    warn!(
+        device_id = %device_id,
+        transitions = status.liveness_changes.len(),
+        "device flapping; suppressing online/offline faults"
+    );
+    status.flapping = Some(FlapState {
+        since_unix_ms: now,
+        suppressed_offline: 0,
+        suppressed_online: 0,
+    });
+    if let Some(details) = flapping_fault_details(config, device_id, status, true) {
+        publish_flapping_fault(config, client, db, device_id, true, details).await;
+    }
+    false
+}
+
+/// Ends flapping once a full `DEVICE_FLAP_WINDOW_MS` passed without an ONLINE/OFFLINE change.
+async fn maybe_clear_flapping(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    status: &mut DeviceStatus,
+    now: u64,
+) {
+    if let Some(details) = end_flapping(config, device_id, status, now) {
+        publish_flapping_fault(config, client, db, device_id, false, details).await;
+    }
+}
+
+/// Ends flapping once a whole window passed without a transition. Returns the INFO
+/// `DEVICE_FLAPPING` details, taken before the transitions are cleared.
+fn end_flapping(
+    config: &Config,
+    device_id: &str,
+    status: &mut DeviceStatus,
+    now: u64,
+) -> Option<serde_json::Value> {
+    if status
+        .liveness_changes
+        .back()
+        .is_some_and(|at| now.saturating_sub(*at) <= config.device_flap_window_ms)
+    {
+        return None;
+    }
+    let details = flapping_fault_details(config, device_id, status, false)?;
+    info!(device_id = %device_id, is_offline = status.is_offline, "device no longer flapping");
+    status.liveness_changes.clear();
+    status.flapping = None;
+    Some(details)
+}
+
+/// `DEVICE_FLAPPING`: `WARN` when flapping starts, `INFO` (with the suppressed counts) when it ends.
+async fn publish_flapping_fault(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    flapping: bool,
+    details: serde_json::Value,
+) {
+    raise_device_fault(
+        config,
+        client,
+        db,
+        device_id,
+        FaultKind::DeviceFlapping,
+        if flapping {
+            Severity::Warn
+        } else {
+            Severity::Info
+        },
+        if flapping {
+            "Device is flapping online/offline; DEVICE_ONLINE/DEVICE_OFFLINE suppressed".to_string()
+        } else {
+            "Device stopped flapping".to_string()
+        },
+        details,
+    )
+    .await;
+}
+
+/// `None` unless `status` is flapping.
+fn flapping_fault_details(
+    config: &Config,
+    device_id: &str,
+    status: &DeviceStatus,
+    flapping: bool,
+) -> Option<serde_json::Value> {
+    let flap = status.flapping?;
+    Some(serde_json::json!({
+        "device_id": device_id,
+        "flapping": flapping,
+        "is_offline": status.is_offline,
+        "transitions": status.liveness_changes.len(),
+        "window_ms": config.device_flap_window_ms,
+        "threshold": config.device_flap_threshold,
+        "since_unix_ms": flap.since_unix_ms,
+        "suppressed_offline": flap.suppressed_offline,
+        "suppressed_online": flap.suppressed_online,
+    }))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn recompute_device_liveness(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
     db: Option<&DbWriter>,
     device_id: &str,
     status: &mut DeviceStatus,
     source: &'static str,
-) {
+) -> bool {
     let now = unix_ms_now();
-    let next_offline = should_be_offline(config, status, now);
+    let offline_ms = device_offline_ms(config, runtime, device_id);
+    let next_offline = should_be_offline(offline_ms, status, now);
+    let went_offline = next_offline && !status.is_offline;
     if next_offline != status.is_offline {
         status.is_offline = next_offline;
-        if status.is_offline {
+        if !note_liveness_change(config, client, db, device_id, status, now).await {
+            // Flapping: `DEVICE_FLAPPING` stands in for the individual transitions.
+        } else if status.is_offline {
             warn!(device_id = %device_id, source, "device offline");
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "DEVICE_OFFLINE".to_string(),
-                severity: "WARN".to_string(),
-                message: "Device is offline (presence/heartbeat)".to_string(),
-                observed_at_unix_ms: now,
-                details: serde_json::json!({
+            raise_device_fault(
+                config,
+                client,
+                db,
+                device_id,
+                FaultKind::DeviceOffline,
+                Severity::Warn,
+                "Device is offline (presence/heartbeat)",
+                serde_json::json!({
                     "device_id": device_id,
                     "source": source,
                     "presence": status.presence.map(|p| format!("{:?}", p)),
                     "last_heartbeat_at_unix_ms": status.last_heartbeat_at_unix_ms,
-                    "device_offline_ms": config.device_offline_ms,
+                    "device_offline_ms": offline_ms,
                 }),
-            };
-            publish_device_fault(client, &config.room_id, device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
-                }
-            }
+            )
+            .await;
         } else {
             info!(device_id = %device_id, source, "device online");
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "DEVICE_ONLINE".to_string(),
-                severity: "INFO".to_string(),
-                message: "Device is online".to_string(),
-                observed_at_unix_ms: now,
-                details: serde_json::json!({
+            raise_device_fault(
+                config,
+                client,
+                db,
+                device_id,
+                FaultKind::DeviceOnline,
+                Severity::Info,
+                "Device is online",
+                serde_json::json!({
                     "device_id": device_id,
                     "source": source,
                     "presence": status.presence.map(|p| format!("{:?}", p)),
                     "last_heartbeat_at_unix_ms": status.last_heartbeat_at_unix_ms,
                 }),
-            };
-            publish_device_fault(client, &config.room_id, device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
-                }
-            }
+            )
+            .await;
         }
         publish_device_status(config, client, device_id, status).await;
     } else if source == "presence" {
         // Presence changes may arrive even if offline state stays constant; publish updated status.
         publish_device_status(config, client, device_id, status).await;
     }
+    went_offline
 }
 
 async fn sweep_device_offline(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
+    runtime: &RuntimeState,
     db: Option<&DbWriter>,
     devices: &mut std::collections::HashMap<String, DeviceStatus>,
-) {
+) -> Vec<String> {
     let now = unix_ms_now();
+    let mut went_offline = Vec::new();
     for (device_id, status) in devices.iter_mut() {
-        let next_offline = should_be_offline(config, status, now);
+        maybe_clear_flapping(config, client, db, device_id, status, now).await;
+        let offline_ms = device_offline_ms(config, runtime, device_id);
+        let next_offline = should_be_offline(offline_ms, status, now);
         if next_offline && !status.is_offline {
             status.is_offline = true;
+            went_offline.push(device_id.clone());
+            if !note_liveness_change(config, client, db, device_id, status, now).await {
+                publish_device_status(config, client, device_id, status).await;
+                continue;
+            }
             warn!(device_id = %device_id, "device offline");
             // Publish offline fault when the sweep detects the transition.
-            let fault = CoreFault {
-                schema: SCHEMA_VERSION.to_string(),
-                room_id: config.room_id.clone(),
-                kind: "DEVICE_OFFLINE".to_string(),
-                severity: "WARN".to_string(),
-                message: "Device is offline (timeout sweep)".to_string(),
-                observed_at_unix_ms: now,
-                details: serde_json::json!({
+            raise_device_fault(
+                config,
+                client,
+                db,
+                device_id,
+                FaultKind::DeviceOffline,
+                Severity::Warn,
+                "Device is offline (timeout sweep)",
+                serde_json::json!({
                     "device_id": device_id,
                     "source": "sweep",
                     "last_heartbeat_at_unix_ms": status.last_heartbeat_at_unix_ms,
-                    "device_offline_ms": config.device_offline_ms,
+                    "device_offline_ms": offline_ms,
                 }),
-            };
-            publish_device_fault(client, &config.room_id, device_id, &fault).await;
-            if let Some(db) = db {
-                if let Ok(v) = serde_json::to_value(&fault) {
-                    db.enqueue_json(
-                        &config.room_id,
-                        Some(device_id),
-                        &format!("room/{}/core/device/{}/fault", config.room_id, device_id),
-                        "DEVICE_FAULT",
-                        fault.observed_at_unix_ms,
-                        v,
-                    );
+            )
+            .await;
+            publish_device_status(config, client, device_id, status).await;
+        }
+    }
+    went_offline
+}
+
+/// Sends a `TimePing` to every online device that negotiated v8.5+. A ping still unanswered from
+/// the previous round is abandoned.
+async fn publish_time_pings(
+    config: &Config,
+    client: &AsyncClient,
+    devices: &mut std::collections::HashMap<String, DeviceStatus>,
+) {
+    for (device_id, status) in devices.iter_mut() {
+        let command_schema = status.command_schema();
+        if status.is_offline || !schema_at_least(command_schema, TIME_SYNC_MIN_SCHEMA) {
+            status.time_ping = None;
+            continue;
+        }
+        let ping = TimePing {
+            schema: command_schema.to_string(),
+            room_id: config.room_id.clone(),
+            device_id: device_id.clone(),
+            ping_id: Uuid::new_v4(),
+            core_sent_at_unix_ms: unix_ms_now(),
+        };
+        let topic = status.codec.topic(&format!(
+            "room/{}/device/{}/ping",
+            config.room_id, device_id
+        ));
+        match status.codec.encode(&ping) {
+            Ok(bytes) => {
+                if let Err(err) = client.publish(topic, QoS::AtMostOnce, false, bytes).await {
+                    warn!(device_id = %device_id, error = %err, "failed to publish time ping");
+                    continue;
                 }
+                status.time_ping = Some((ping.ping_id, ping.core_sent_at_unix_ms));
+            }
+            Err(err) => {
+                warn!(device_id = %device_id, error = %err, "failed to serialize time ping")
+            }
+        }
+    }
+}
+
+/// Sends one `CoreWatchdog` round: every known v8.6+ device gets a beacon with the next counter,
+/// signed with its HMAC key. Devices core currently considers offline are included, so a lost
+/// heartbeat path never also trips the device's dead-man switch.
+async fn publish_watchdogs(
+    config: &Config,
+    client: &AsyncClient,
+    runtime: &mut RuntimeState,
+    devices: &std::collections::HashMap<String, DeviceStatus>,
+) {
+    runtime.watchdog_counter += 1;
+    let issued_at_unix_ms = unix_ms_now();
+    for (device_id, status) in devices {
+        let command_schema = status.command_schema();
+        if !schema_at_least(command_schema, WATCHDOG_MIN_SCHEMA) {
+            continue;
+        }
+        let mut beacon = CoreWatchdog {
+            schema: command_schema.to_string(),
+            room_id: config.room_id.clone(),
+            device_id: device_id.clone(),
+            boot_id: runtime.watchdog_boot_id,
+            counter: runtime.watchdog_counter,
+            interval_ms: config.watchdog_interval_ms,
+            timeout_ms: config.watchdog_timeout_ms,
+            issued_at_unix_ms,
+            auth: None,
+        };
+        // Unsigned without a key; devices enforcing auth ignore it and fail safe.
+        if let Some(key) = runtime.device_hmac_keys.get(device_id) {
+            sign_watchdog_hmac_sha256(&mut beacon, key, None);
+        }
+        let topic = status.codec.topic(&format!(
+            "room/{}/device/{}/watchdog",
+            config.room_id, device_id
+        ));
+        match status.codec.encode(&beacon) {
+            Ok(bytes) => {
+                if let Err(err) = client.publish(topic, QoS::AtMostOnce, false, bytes).await {
+                    warn!(device_id = %device_id, error = %err, "failed to publish watchdog");
+                }
+            }
+            Err(err) => {
+                warn!(device_id = %device_id, error = %err, "failed to serialize watchdog")
             }
-            publish_device_status(config, client, device_id, status).await;
         }
     }
 }
 
+/// Folds a pong into the device's clock estimate and raises `DEVICE_CLOCK_OFFSET` /
+/// `DEVICE_CLOCK_SYNCED` when the offset crosses `CORE_CLOCK_OFFSET_MAX_MS`.
+async fn handle_time_pong(
+    config: &Config,
+    client: &AsyncClient,
+    db: Option<&DbWriter>,
+    device_id: &str,
+    status: &mut DeviceStatus,
+    pong: &TimePong,
+) {
+    let now = unix_ms_now();
+    // Only the outstanding ping counts; late or replayed pongs would skew the estimate.
+    let Some((ping_id, sent_at)) = status.time_ping else {
+        return;
+    };
+    if pong.ping_id != ping_id {
+        return;
+    }
+    status.time_ping = None;
+
+    let sample = clock_sample(pong, sent_at, now);
+    status.clock_samples.push_back(sample);
+    while status.clock_samples.len() > CLOCK_SAMPLE_WINDOW {
+        status.clock_samples.pop_front();
+    }
+    let best = status
+        .clock_samples
+        .iter()
+        .min_by_key(|s| s.rtt_ms)
+        .copied()
+        .unwrap_or(sample);
+    status.clock = Some(best);
+    status.clock_synced_at_unix_ms = Some(now);
+
+    let exceeded = best.offset_ms.unsigned_abs() > config.clock_offset_max_ms;
+    if exceeded != status.clock_offset_exceeded {
+        status.clock_offset_exceeded = exceeded;
+        let (kind, severity, message) = if exceeded {
+            warn!(device_id = %device_id, offset_ms = best.offset_ms, rtt_ms = best.rtt_ms, "device clock offset too large");
+            (
+                FaultKind::DeviceClockOffset,
+                Severity::Warn,
+                "Device clock offset exceeds CORE_CLOCK_OFFSET_MAX_MS",
+            )
+        } else {
+            info!(device_id = %device_id, offset_ms = best.offset_ms, rtt_ms = best.rtt_ms, "device clock back in sync");
+            (
+                FaultKind::DeviceClockSynced,
+                Severity::Info,
+                "Device clock offset back in range",
+            )
+        };
+        raise_device_fault(
+            config,
+            client,
+            db,
+            device_id,
+            kind,
+            severity,
+            message,
+            serde_json::json!({
+                "device_id": device_id,
+                "offset_ms": best.offset_ms,
+                "rtt_ms": best.rtt_ms,
+                "max_offset_ms": config.clock_offset_max_ms,
+            }),
+        )
+        .await;
+    }
+    publish_device_status(config, client, device_id, status).await;
+}
+
 async fn publish_device_status(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     device_id: &str,
     status: &DeviceStatus,
 ) {
@@ -2691,13 +7886,19 @@ async fn publish_device_status(
         "last_heartbeat_at_unix_ms": status.last_heartbeat_at_unix_ms.unwrap_or(0),
         "last_presence_at_unix_ms": status.last_presence_at_unix_ms.unwrap_or(0),
         "last_state_at_unix_ms": status.last_state_at_unix_ms.unwrap_or(0),
-        "presence": presence
+        "presence": presence,
+        "supported_schemas": status.supported_schemas,
+        "command_schema": status.command_schema(),
+        "codec": status.codec.as_str(),
+        "clock_offset_ms": status.clock.map(|c| c.offset_ms),
+        "clock_rtt_ms": status.clock.map(|c| c.rtt_ms),
+        "clock_synced_at_unix_ms": status.clock_synced_at_unix_ms,
+        "clock_offset_exceeded": status.clock_offset_exceeded,
+        "maintenance": status.maintenance,
+        "flapping": status.flapping.is_some(),
     });
     if let Ok(bytes) = serde_json::to_vec(&payload) {
-        if let Err(err) = client
-            .publish(topic, rumqttc::QoS::AtLeastOnce, true, bytes)
-            .await
-        {
+        if let Err(err) = client.publish(topic, QoS::AtLeastOnce, true, bytes).await {
             warn!(error = %err, "failed to publish device status");
         }
     }
@@ -2705,7 +7906,7 @@ async fn publish_device_status(
 
 async fn maybe_publish_dev_test_command(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     runtime: &RuntimeState,
     devices: &std::collections::HashMap<String, DeviceStatus>,
     device_sequences: &mut std::collections::HashMap<String, u64>,
@@ -2734,7 +7935,7 @@ async fn maybe_publish_dev_test_command(
         return;
     }
 
-    let Some(key) = config.device_hmac_keys.get(device_id) else {
+    let Some(key) = runtime.device_hmac_keys.get(device_id) else {
         warn!(
             device_id,
             "DEV_TEST_COMMAND_DEVICE_ID set but no key present in DEVICE_HMAC_KEYS_JSON"
@@ -2750,7 +7951,7 @@ async fn maybe_publish_dev_test_command(
     device_sequences.insert(device_id.to_string(), next_seq);
 
     let mut cmd = CommandEnvelope {
-        schema: SCHEMA_VERSION.to_string(),
+        schema: status.command_schema().to_string(),
         room_id: config.room_id.clone(),
         device_id: device_id.to_string(),
         command_id: Uuid::new_v4(),
@@ -2760,6 +7961,7 @@ async fn maybe_publish_dev_test_command(
         action: CommandAction::Set,
         parameters: serde_json::json!({"kind":"DEV_TEST"}),
         safety_class: SafetyClass::NonCritical,
+        execute_at_unix_ms: None,
         auth: None,
     };
 
@@ -2768,11 +7970,19 @@ async fn maybe_publish_dev_test_command(
         return;
     }
 
-    let topic = format!("room/{}/device/{}/cmd", config.room_id, device_id);
-    match serde_json::to_vec(&cmd) {
+    let topic = status
+        .codec
+        .topic(&format!("room/{}/device/{}/cmd", config.room_id, device_id));
+    match status.codec.encode(&cmd) {
         Ok(bytes) => {
             if let Err(err) = client
-                .publish(topic, rumqttc::QoS::AtLeastOnce, false, bytes)
+                .publish_with_properties(
+                    topic,
+                    QoS::AtLeastOnce,
+                    false,
+                    bytes,
+                    command_properties(config.dispatch_ack_timeout_ms, None),
+                )
                 .await
             {
                 warn!(device_id, error=%err, "failed to publish dev test command");
@@ -2791,9 +8001,10 @@ async fn maybe_publish_dev_test_command(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_mqtt_event(
     config: &Config,
-    client: &rumqttc::AsyncClient,
+    client: &AsyncClient,
     ev: MqttEvent,
     db: Option<&DbWriter>,
     runtime: &mut RuntimeState,
@@ -2835,12 +8046,13 @@ async fn handle_mqtt_event(
         }
         MqttEvent::Connected => {
             if let Some(down_since) = runtime.broker_outage_since_unix_ms {
+                MQTT_RECONNECTS.inc();
                 let recovered_at = unix_ms_now();
                 let fault = CoreFault {
                     schema: SCHEMA_VERSION.to_string(),
                     room_id: config.room_id.clone(),
-                    kind: "BROKER_OUTAGE".to_string(),
-                    severity: "CRITICAL".to_string(),
+                    kind: FaultKind::BrokerOutage,
+                    severity: Severity::Critical,
                     message: "MQTT broker outage detected; dispatch remains paused until manually resumed".to_string(),
                     observed_at_unix_ms: recovered_at,
                     details: serde_json::json!({
@@ -2866,7 +8078,244 @@ async fn handle_mqtt_event(
     }
 }
 
-#[derive(Debug)]
+/// How long a single Postgres connect may take before it counts as a failure.
+const DB_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Small supervised Postgres pool shared by the event writer, graph loader and registry loader.
+///
+/// Connections are opened lazily (at most `CORE_DB_POOL_SIZE`) and discarded once closed, so a
+/// dead connection is replaced on the next [`DbPool::get`]. [`supervise_db`] pings the DB on an
+/// interval (backing off while it is down) and owns nothing else; health is recorded by whoever
+/// touches the DB first.
+#[derive(Clone)]
+struct DbPool {
+    inner: std::sync::Arc<DbPoolInner>,
+}
+
+struct DbPoolInner {
+    database_url: String,
+    size: usize,
+    permits: std::sync::Arc<tokio::sync::Semaphore>,
+    idle: std::sync::Mutex<Vec<tokio_postgres::Client>>,
+    open: std::sync::atomic::AtomicUsize,
+    health: std::sync::Mutex<DbHealth>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DbHealth {
+    /// `None` until the first connect attempt finishes.
+    up: Option<bool>,
+    down_since_unix_ms: Option<u64>,
+    last_error: Option<String>,
+    last_ok_unix_ms: Option<u64>,
+}
+
+/// A pooled connection; goes back to the idle list on drop unless it has closed.
+struct PooledClient {
+    client: Option<tokio_postgres::Client>,
+    pool: DbPool,
+    _permit: tokio::sync::OwnedSemaphorePermit,
+}
+
+impl std::ops::Deref for PooledClient {
+    type Target = tokio_postgres::Client;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("pooled client taken")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        if client.is_closed() {
+            self.pool
+                .inner
+                .open
+                .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        } else {
+            self.pool
+                .inner
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(client);
+        }
+    }
+}
+
+impl DbPool {
+    fn new(config: &Config) -> Self {
+        Self {
+            inner: std::sync::Arc::new(DbPoolInner {
+                database_url: config.database_url.clone(),
+                size: config.db_pool_size,
+                permits: std::sync::Arc::new(tokio::sync::Semaphore::new(config.db_pool_size)),
+                idle: std::sync::Mutex::new(Vec::new()),
+                open: std::sync::atomic::AtomicUsize::new(0),
+                health: std::sync::Mutex::new(DbHealth::default()),
+            }),
+        }
+    }
+
+    /// Checks out an idle connection or opens a new one (waiting if all are in use).
+    async fn get(&self) -> anyhow::Result<PooledClient> {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .context("db pool closed")?;
+        loop {
+            let idle = self
+                .inner
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pop();
+            match idle {
+                Some(client) if client.is_closed() => {
+                    self.inner
+                        .open
+                        .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                }
+                Some(client) => {
+                    return Ok(PooledClient {
+                        client: Some(client),
+                        pool: self.clone(),
+                        _permit: permit,
+                    })
+                }
+                None => break,
+            }
+        }
+
+        let connected = tokio::time::timeout(
+            DB_CONNECT_TIMEOUT,
+            tokio_postgres::connect(&self.inner.database_url, tokio_postgres::NoTls),
+        )
+        .await;
+        let (client, connection) = match connected {
+            Ok(Ok(pair)) => pair,
+            Ok(Err(err)) => {
+                self.mark_down(&err.to_string());
+                return Err(anyhow::Error::new(err).context("connect postgres"));
+            }
+            Err(_) => {
+                let msg = format!(
+                    "connect timed out after {}ms",
+                    DB_CONNECT_TIMEOUT.as_millis()
+                );
+                self.mark_down(&msg);
+                anyhow::bail!("connect postgres: {msg}");
+            }
+        };
+        let pool = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                warn!(error=%err, "postgres connection error");
+                pool.mark_down(&err.to_string());
+            }
+        });
+        self.inner
+            .open
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.mark_up();
+        Ok(PooledClient {
+            client: Some(client),
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+
+    fn mark_up(&self) {
+        let mut health = self.inner.health.lock().unwrap_or_else(|e| e.into_inner());
+        if health.up != Some(true) {
+            info!("postgres reachable");
+        }
+        health.up = Some(true);
+        health.down_since_unix_ms = None;
+        health.last_ok_unix_ms = Some(unix_ms_now());
+    }
+
+    fn mark_down(&self, error: &str) {
+        let mut health = self.inner.health.lock().unwrap_or_else(|e| e.into_inner());
+        if health.up != Some(false) {
+            warn!(error, "postgres unreachable");
+            health.down_since_unix_ms = Some(unix_ms_now());
+        }
+        health.up = Some(false);
+        health.last_error = Some(error.to_string());
+    }
+
+    /// Records the outcome of a query: connection-level errors mark the DB down, errors
+    /// reported by the server (bad row, constraint, ...) do not.
+    fn note_result<T>(&self, result: &Result<T, tokio_postgres::Error>) {
+        match result {
+            Ok(_) => self.mark_up(),
+            Err(err) if err.as_db_error().is_none() => self.mark_down(&err.to_string()),
+            Err(_) => {}
+        }
+    }
+
+    fn health(&self) -> DbHealth {
+        self.inner
+            .health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn open_count(&self) -> usize {
+        self.inner.open.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn idle_count(&self) -> usize {
+        self.inner
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+}
+
+/// Pings Postgres every `CORE_DB_HEALTH_INTERVAL_MS` while it is up; while it is down, retries
+/// from 500ms doubling up to `CORE_DB_RECONNECT_MAX_MS`.
+async fn supervise_db(pool: DbPool, interval: Duration, reconnect_max: Duration) {
+    let initial_backoff = Duration::from_millis(500).min(reconnect_max);
+    let mut backoff = initial_backoff;
+    loop {
+        let ok = match pool.get().await {
+            Ok(client) => {
+                let res =
+                    tokio::time::timeout(DB_CONNECT_TIMEOUT, client.simple_query("SELECT 1")).await;
+                match res {
+                    Ok(res) => {
+                        pool.note_result(&res);
+                        res.is_ok()
+                    }
+                    Err(_) => {
+                        pool.mark_down("health ping timed out");
+                        false
+                    }
+                }
+            }
+            Err(_) => false,
+        };
+        if ok {
+            backoff = initial_backoff;
+            tokio::time::sleep(interval).await;
+        } else {
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(reconnect_max);
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Deserialize)]
 struct DbEvent {
     room_id: String,
     device_id: Option<String>,
@@ -2876,49 +8325,230 @@ struct DbEvent {
     payload: serde_json::Value,
 }
 
+impl DbEvent {
+    /// CRITICAL core/device faults: the audit trail that must survive a DB outage.
+    fn is_critical_fault(&self) -> bool {
+        matches!(self.kind.as_str(), "CORE_FAULT" | "DEVICE_FAULT")
+            && self.payload.get("severity").and_then(|v| v.as_str()) == Some("CRITICAL")
+    }
+}
+
+/// Upper bound on rows per multi-row INSERT (6 params each; Postgres allows 65535).
+const DB_BATCH_MAX_LIMIT: usize = 1000;
+
+/// Append-only JSONL file of CRITICAL fault events that could not be written to Postgres. The
+/// writer task replays (and removes) it after its next successful insert.
+///
+/// All file I/O (including `sync_data`) runs on a dedicated `db-spill` thread fed by a bounded
+/// channel, so neither the scheduler loop nor the writer task ever blocks on the disk.
+struct DbSpill {
+    tx: std::sync::mpsc::SyncSender<SpillOp>,
+    pending: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+enum SpillOp {
+    /// Appends the events; replies with those that could not be written (counted as
+    /// `spill_failed` drops when there is no reply channel).
+    Append(
+        Vec<DbEvent>,
+        Option<tokio::sync::oneshot::Sender<Vec<DbEvent>>>,
+    ),
+    /// Reads and removes every spilled event.
+    TakeAll(tokio::sync::oneshot::Sender<Vec<DbEvent>>),
+}
+
+/// Events of an append the spill thread did not take.
+fn unsent_events(err: std::sync::mpsc::TrySendError<SpillOp>) -> Vec<DbEvent> {
+    let (std::sync::mpsc::TrySendError::Full(op) | std::sync::mpsc::TrySendError::Disconnected(op)) =
+        err;
+    match op {
+        SpillOp::Append(events, _) => events,
+        SpillOp::TakeAll(_) => Vec::new(),
+    }
+}
+
+/// Spill operations waiting for the `db-spill` thread; past it CRITICAL faults are not spilled.
+const DB_SPILL_QUEUE_CAPACITY: usize = 1024;
+
+impl DbSpill {
+    fn open(path: std::path::PathBuf, max_bytes: u64) -> Self {
+        let pending = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let (tx, rx) = std::sync::mpsc::sync_channel(DB_SPILL_QUEUE_CAPACITY);
+        let file = DbSpillFile {
+            path,
+            max_bytes,
+            pending: pending.clone(),
+        };
+        // Without the thread every send fails and CRITICAL faults are dropped as `queue_full`.
+        if let Err(err) = std::thread::Builder::new()
+            .name("db-spill".to_string())
+            .spawn(move || file.run(rx))
+        {
+            warn!(error=%err, "failed to start db spill thread");
+        }
+        Self { tx, pending }
+    }
+
+    fn pending(&self) -> u64 {
+        self.pending.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Hands one event to the spill thread without waiting; `false` if its queue is full.
+    fn try_append(&self, ev: DbEvent) -> bool {
+        self.tx.try_send(SpillOp::Append(vec![ev], None)).is_ok()
+    }
+
+    /// Appends `events`; returns those not written (queue full, file full or write error).
+    async fn append_all(&self, events: Vec<DbEvent>) -> Vec<DbEvent> {
+        if events.is_empty() {
+            return events;
+        }
+        let (reply, written) = tokio::sync::oneshot::channel();
+        match self.tx.try_send(SpillOp::Append(events, Some(reply))) {
+            Ok(()) => written.await.unwrap_or_default(),
+            Err(err) => unsent_events(err),
+        }
+    }
+
+    /// Reads and removes every spilled event (put back with [`DbSpill::append_all`] on failure).
+    async fn take_all(&self) -> Vec<DbEvent> {
+        let (reply, taken) = tokio::sync::oneshot::channel();
+        if self.tx.try_send(SpillOp::TakeAll(reply)).is_err() {
+            return Vec::new();
+        }
+        taken.await.unwrap_or_default()
+    }
+}
+
+/// The spill file itself; only touched from the `db-spill` thread.
+struct DbSpillFile {
+    path: std::path::PathBuf,
+    max_bytes: u64,
+    pending: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl DbSpillFile {
+    fn run(self, rx: std::sync::mpsc::Receiver<SpillOp>) {
+        // Lines left by a previous run count as pending so they are replayed.
+        let pending = std::fs::read_to_string(&self.path)
+            .map(|text| text.lines().filter(|l| !l.trim().is_empty()).count() as u64)
+            .unwrap_or(0);
+        if pending > 0 {
+            warn!(path=%self.path.display(), pending, "db spill file has events to replay");
+        }
+        self.pending
+            .fetch_add(pending, std::sync::atomic::Ordering::Relaxed);
+        while let Ok(op) = rx.recv() {
+            match op {
+                SpillOp::Append(events, reply) => {
+                    let failed: Vec<DbEvent> =
+                        events.into_iter().filter(|ev| !self.append(ev)).collect();
+                    match reply {
+                        Some(reply) => {
+                            let _ = reply.send(failed);
+                        }
+                        None => {
+                            for _ in &failed {
+                                DB_EVENTS_DROPPED.inc(&["spill_failed"]);
+                            }
+                        }
+                    }
+                }
+                SpillOp::TakeAll(reply) => {
+                    let _ = reply.send(self.take_all());
+                }
+            }
+        }
+    }
+
+    /// Appends one event; `false` if the file is at `max_bytes` or cannot be written.
+    fn append(&self, ev: &DbEvent) -> bool {
+        use std::io::Write as _;
+        let Ok(mut line) = serde_json::to_string(ev) else {
+            return false;
+        };
+        line.push('\n');
+        let size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size + line.len() as u64 > self.max_bytes {
+            warn!(path=%self.path.display(), max_bytes=self.max_bytes, "db spill file full");
+            return false;
+        }
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(line.as_bytes()).and_then(|_| f.sync_data()));
+        match written {
+            Ok(()) => {
+                self.pending
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                DB_EVENTS_SPILLED.inc();
+                true
+            }
+            Err(err) => {
+                warn!(error=%err, path=%self.path.display(), "failed to write db spill file");
+                false
+            }
+        }
+    }
+
+    fn take_all(&self) -> Vec<DbEvent> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(t) => t,
+            Err(_) => return Vec::new(),
+        };
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(error=%err, path=%self.path.display(), "failed to remove db spill file");
+            return Vec::new();
+        }
+        self.pending.store(0, std::sync::atomic::Ordering::Relaxed);
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| match serde_json::from_str::<DbEvent>(l) {
+                Ok(ev) => Some(ev),
+                Err(err) => {
+                    warn!(error=%err, "skipping unreadable db spill line");
+                    DB_EVENTS_DROPPED.inc(&["spill_corrupt"]);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone)]
 struct DbWriter {
     tx: mpsc::Sender<DbEvent>,
+    pool: DbPool,
+    spill: Option<std::sync::Arc<DbSpill>>,
+    /// Drops since the last [`DbWriter::report_drops`] (for a periodic warning, not per event).
+    dropped_unreported: std::sync::Arc<std::sync::atomic::AtomicU64>,
 }
 
 impl DbWriter {
-    async fn connect(database_url: &str) -> anyhow::Result<Self> {
-        let (client, connection) = tokio_postgres::connect(database_url, tokio_postgres::NoTls)
-            .await
-            .context("connect postgres")?;
-        tokio::spawn(async move {
-            if let Err(err) = connection.await {
-                warn!(error=%err, "postgres connection error");
-            }
-        });
-
-        let (tx, mut rx) = mpsc::channel::<DbEvent>(4096);
-        tokio::spawn(async move {
-            while let Some(ev) = rx.recv().await {
-                let observed_at: std::time::SystemTime = std::time::UNIX_EPOCH
-                    .checked_add(Duration::from_millis(ev.observed_at_unix_ms))
-                    .unwrap_or_else(std::time::SystemTime::now);
-
-                if let Err(err) = client
-                    .execute(
-                        "INSERT INTO events (room_id, device_id, topic, kind, observed_at, payload) VALUES ($1,$2,$3,$4,$5,$6)",
-                        &[
-                            &ev.room_id,
-                            &ev.device_id,
-                            &ev.topic,
-                            &ev.kind,
-                            &observed_at,
-                            &ev.payload,
-                        ],
-                    )
-                    .await
-                {
-                    warn!(error=%err, kind=%ev.kind, topic=%ev.topic, "failed to insert event");
-                }
-            }
-        });
+    /// Starts the writer task on `pool`; never fails; events queue until Postgres is reachable.
+    fn start(config: &Config, pool: DbPool) -> Self {
+        let spill = config
+            .db_spill_path
+            .as_ref()
+            .map(|path| std::sync::Arc::new(DbSpill::open(path.into(), config.db_spill_max_bytes)));
+        let (tx, rx) = mpsc::channel::<DbEvent>(config.db_queue_capacity);
+        tokio::spawn(run_db_writer(
+            pool.clone(),
+            rx,
+            config.db_batch_max,
+            spill.clone(),
+        ));
 
-        Ok(Self { tx })
+        Self {
+            tx,
+            pool,
+            spill,
+            dropped_unreported: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        }
     }
 
     fn enqueue_json(
@@ -2938,6 +8568,825 @@ impl DbWriter {
             observed_at_unix_ms,
             payload,
         };
-        let _ = self.tx.try_send(ev);
+        // Never wait on the DB from the scheduler loop: a full queue drops the event, except
+        // CRITICAL faults, which go to the spill file.
+        if let Err(err) = self.tx.try_send(ev) {
+            let ev = err.into_inner();
+            if ev.is_critical_fault() {
+                if let Some(spill) = self.spill.as_deref() {
+                    if spill.try_append(ev) {
+                        return;
+                    }
+                }
+            }
+            DB_EVENTS_DROPPED.inc(&["queue_full"]);
+            self.dropped_unreported
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn queue_depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    fn spill_pending(&self) -> u64 {
+        self.spill.as_ref().map(|s| s.pending()).unwrap_or(0)
+    }
+
+    fn health_status(&self) -> DbHealthStatus {
+        let health = self.pool.health();
+        DbHealthStatus {
+            up: health.up == Some(true),
+            down_since_unix_ms: health.down_since_unix_ms,
+            last_error: health.last_error,
+            last_ok_unix_ms: health.last_ok_unix_ms,
+            pool_size: self.pool.inner.size as u32,
+            pool_open: self.pool.open_count() as u32,
+            pool_idle: self.pool.idle_count() as u32,
+            queue_depth: self.queue_depth() as u64,
+            spill_pending: self.spill_pending(),
+        }
+    }
+
+    /// Logs (once per call) how many events the enqueue side dropped since the last call.
+    fn report_drops(&self) {
+        let dropped = self
+            .dropped_unreported
+            .swap(0, std::sync::atomic::Ordering::Relaxed);
+        if dropped > 0 {
+            warn!(
+                dropped,
+                queue_depth = self.queue_depth(),
+                "db writer queue full; events dropped"
+            );
+        }
+    }
+}
+
+/// Drains the queue in batches of up to `batch_max` rows. A batch that fails for a transient
+/// reason (no connection, connection lost) is retried with backoff while new events keep
+/// queueing, after its CRITICAL faults were moved to the spill file. A batch Postgres rejects is
+/// retried row by row so one bad event cannot block the rest.
+async fn run_db_writer(
+    pool: DbPool,
+    mut rx: mpsc::Receiver<DbEvent>,
+    batch_max: usize,
+    spill: Option<std::sync::Arc<DbSpill>>,
+) {
+    let mut batch: Vec<DbEvent> = Vec::with_capacity(batch_max);
+    let mut backoff = Duration::from_millis(250);
+    let mut failing = false;
+    loop {
+        if batch.is_empty() {
+            let Some(ev) = rx.recv().await else {
+                break;
+            };
+            batch.push(ev);
+            while batch.len() < batch_max {
+                match rx.try_recv() {
+                    Ok(ev) => batch.push(ev),
+                    Err(_) => break,
+                }
+            }
+        }
+
+        let failure = match pool.get().await {
+            Err(err) => Some(format!("{err:#}")),
+            Ok(client) => {
+                let res = insert_db_events(&client, &batch).await;
+                pool.note_result(&res);
+                match res {
+                    Ok(()) => {
+                        batch.clear();
+                        if let Some(spill) = spill.as_deref().filter(|s| s.pending() > 0) {
+                            let res = replay_db_spill(&client, spill, batch_max).await;
+                            pool.note_result(&res);
+                            if let Err(err) = res {
+                                warn!(error=%err, "db spill replay failed; will retry");
+                            }
+                        }
+                        None
+                    }
+                    Err(err) if err.as_db_error().is_some() => {
+                        DB_INSERT_FAILURES.inc();
+                        warn!(error=%err, rows = batch.len(), "db rejected event batch; inserting rows one by one");
+                        let mut rows = std::mem::take(&mut batch).into_iter();
+                        let mut failure = None;
+                        while let Some(ev) = rows.next() {
+                            match insert_db_events(&client, std::slice::from_ref(&ev)).await {
+                                Ok(()) => {}
+                                Err(err) if err.as_db_error().is_some() => {
+                                    warn!(error=%err, kind=%ev.kind, topic=%ev.topic, "failed to insert event");
+                                    DB_EVENTS_DROPPED.inc(&["rejected"]);
+                                }
+                                Err(err) => {
+                                    // Connection lost mid-way: keep the rest for the retry.
+                                    pool.mark_down(&err.to_string());
+                                    batch.push(ev);
+                                    batch.extend(rows);
+                                    failure = Some(err.to_string());
+                                    break;
+                                }
+                            }
+                        }
+                        failure
+                    }
+                    Err(err) => Some(err.to_string()),
+                }
+            }
+        };
+
+        match failure {
+            None => {
+                backoff = Duration::from_millis(250);
+                if failing {
+                    failing = false;
+                    info!("db writer inserts succeeding again");
+                }
+            }
+            Some(error) => {
+                DB_INSERT_FAILURES.inc();
+                if !failing {
+                    failing = true;
+                    warn!(error=%error, rows = batch.len(), "db insert failed; holding batch and retrying");
+                }
+                // Spill CRITICAL faults now so a crash during the outage cannot lose them.
+                if let Some(spill) = spill.as_deref() {
+                    let (critical, rest): (Vec<DbEvent>, Vec<DbEvent>) = std::mem::take(&mut batch)
+                        .into_iter()
+                        .partition(DbEvent::is_critical_fault);
+                    batch = rest;
+                    batch.extend(spill.append_all(critical).await);
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(5));
+            }
+        }
+    }
+}
+
+async fn insert_db_events(
+    client: &tokio_postgres::Client,
+    events: &[DbEvent],
+) -> Result<(), tokio_postgres::Error> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let observed_at: Vec<std::time::SystemTime> = events
+        .iter()
+        .map(|ev| {
+            std::time::UNIX_EPOCH
+                .checked_add(Duration::from_millis(ev.observed_at_unix_ms))
+                .unwrap_or_else(std::time::SystemTime::now)
+        })
+        .collect();
+    let mut sql = String::from(
+        "INSERT INTO events (room_id, device_id, topic, kind, observed_at, payload) VALUES ",
+    );
+    let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+        Vec::with_capacity(events.len() * 6);
+    for (i, ev) in events.iter().enumerate() {
+        let b = i * 6;
+        if i > 0 {
+            sql.push(',');
+        }
+        sql.push_str(&format!(
+            "(${},${},${},${},${},${})",
+            b + 1,
+            b + 2,
+            b + 3,
+            b + 4,
+            b + 5,
+            b + 6
+        ));
+        params.push(&ev.room_id);
+        params.push(&ev.device_id);
+        params.push(&ev.topic);
+        params.push(&ev.kind);
+        params.push(&observed_at[i]);
+        params.push(&ev.payload);
+    }
+    client.execute(sql.as_str(), &params).await.map(|_| ())
+}
+
+/// Inserts every spilled event; whatever was not inserted goes back into the spill file.
+async fn replay_db_spill(
+    client: &tokio_postgres::Client,
+    spill: &DbSpill,
+    batch_max: usize,
+) -> Result<(), tokio_postgres::Error> {
+    let mut events = spill.take_all().await;
+    let total = events.len();
+    while !events.is_empty() {
+        let n = events.len().min(batch_max);
+        if let Err(err) = insert_db_events(client, &events[..n]).await {
+            for _ in spill.append_all(events).await {
+                DB_EVENTS_DROPPED.inc(&["spill_failed"]);
+            }
+            return Err(err);
+        }
+        events.drain(..n);
+        DB_EVENTS_REPLAYED.add(n as u64);
+    }
+    if total > 0 {
+        info!(events = total, "replayed spilled db events");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_results_evict_oldest_first() {
+        let mut recent = RecentResults::default();
+        let ids: Vec<Uuid> = (0..=DISPATCH_RECENT_MAX).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            recent.insert(*id, i);
+        }
+        assert_eq!(recent.by_id.len(), DISPATCH_RECENT_MAX);
+        assert_eq!(recent.get(ids[0]), None);
+        assert_eq!(recent.get(ids[1]), Some(&1));
+        assert_eq!(
+            recent.get(ids[DISPATCH_RECENT_MAX]),
+            Some(&DISPATCH_RECENT_MAX)
+        );
+    }
+
+    #[test]
+    fn recent_results_reinsert_is_not_evicted_by_stale_order() {
+        let mut recent = RecentResults::default();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        recent.insert(first, 0);
+        recent.insert(second, 1);
+        recent.insert(first, 2);
+        for i in 0..DISPATCH_RECENT_MAX - 2 {
+            recent.insert(Uuid::new_v4(), i + 3);
+        }
+        recent.insert(Uuid::new_v4(), 0);
+        // `first`'s original slot is stale; `second` is the oldest live entry.
+        assert_eq!(recent.by_id.len(), DISPATCH_RECENT_MAX);
+        assert_eq!(recent.get(second), None);
+        assert_eq!(recent.get(first), Some(&2));
+    }
+
+    #[test]
+    fn recent_results_sweep_keeps_fresh_entries() {
+        let mut recent = RecentResults::default();
+        let id = Uuid::new_v4();
+        recent.insert(id, "done");
+        recent.sweep();
+        assert_eq!(recent.get(id), Some(&"done"));
+    }
+
+    fn test_config() -> Config {
+        Config::from_env().expect("default config")
+    }
+
+    #[test]
+    fn histogram_buckets_are_upper_bound_inclusive() {
+        let mut h = Histogram::new(LATENCY_BUCKETS_MS);
+        for v in [0, 1, 2, 3, 5, 6, 5000, 5001] {
+            h.observe(v);
+        }
+        // le=1: 0, 1 | le=2: 2 | le=5: 3, 5 | le=10: 6 | le=5000: 5000 | +Inf: 5001
+        assert_eq!(&h.counts[..4], &[2, 1, 2, 1]);
+        assert_eq!(h.counts[LATENCY_BUCKETS_MS.len() - 1], 1);
+        assert_eq!(h.counts[LATENCY_BUCKETS_MS.len()], 1);
+        assert_eq!(h.count, 8);
+        assert_eq!(h.sum, 10_018);
+        assert_eq!(h.max, 5001);
+    }
+
+    #[test]
+    fn histogram_quantile_bound() {
+        let mut h = Histogram::new(LATENCY_BUCKETS_MS);
+        assert_eq!(h.quantile_bound(0.99), None);
+        h.observe(3);
+        // Clamped to the max seen, not the bucket bound (5).
+        assert_eq!(h.quantile_bound(0.99), Some(3));
+        for _ in 0..98 {
+            h.observe(8);
+        }
+        h.observe(9000);
+        assert_eq!(h.quantile_bound(0.01), Some(5));
+        assert_eq!(h.quantile_bound(0.5), Some(10));
+        assert_eq!(h.quantile_bound(0.99), Some(10));
+        // +Inf bucket reports the max.
+        assert_eq!(h.quantile_bound(1.0), Some(9000));
+    }
+
+    #[test]
+    fn histogram_json_buckets_are_cumulative() {
+        let mut h = Histogram::new(&[10, 100]);
+        for v in [5, 50, 60, 500] {
+            h.observe(v);
+        }
+        let json = h.to_json();
+        let buckets: Vec<u64> = json["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(buckets, [1, 3, 4]);
+        assert_eq!(json["buckets"][2]["le"], "+Inf");
+        assert_eq!(json["count"], 4);
+    }
+
+    fn slo_config() -> Config {
+        let mut config = test_config();
+        config.slo_accept_ms = 50;
+        config.slo_window_ms = 60_000;
+        config.slo_target_pct = 90.0;
+        config.slo_min_samples = 10;
+        config
+    }
+
+    fn push_accepts(latency: &mut LatencyMetrics, at: Instant, ms: u64, n: usize) {
+        for _ in 0..n {
+            latency.accept_window.push_back((at, ms));
+        }
+    }
+
+    #[test]
+    fn slo_needs_min_samples() {
+        let config = slo_config();
+        let mut latency = LatencyMetrics::default();
+        push_accepts(&mut latency, Instant::now(), 500, 9);
+        assert!(latency.update_slo(&config).is_none());
+        assert!(!latency.slo_breached);
+    }
+
+    #[test]
+    fn slo_breaches_and_clears_once() {
+        let config = slo_config();
+        let mut latency = LatencyMetrics::default();
+        push_accepts(&mut latency, Instant::now(), 10, 10);
+        assert!(
+            latency.update_slo(&config).is_none(),
+            "healthy, nothing to report"
+        );
+
+        push_accepts(&mut latency, Instant::now(), 500, 2);
+        let summary = latency.update_slo(&config).expect("breach");
+        assert!(latency.slo_breached);
+        assert_eq!(summary.window_samples, 12);
+        assert!(summary.window_within_target_pct < 90.0);
+        assert!(
+            latency.update_slo(&config).is_none(),
+            "still breached, no repeat"
+        );
+
+        push_accepts(&mut latency, Instant::now(), 10, 20);
+        let summary = latency.update_slo(&config).expect("recovery");
+        assert!(!latency.slo_breached);
+        assert!(summary.window_within_target_pct >= 90.0);
+        assert!(latency.update_slo(&config).is_none());
+    }
+
+    #[test]
+    fn slo_window_drops_old_samples() {
+        let config = slo_config();
+        let mut latency = LatencyMetrics::default();
+        let Some(old) =
+            Instant::now().checked_sub(Duration::from_millis(config.slo_window_ms + 1000))
+        else {
+            return;
+        };
+        push_accepts(&mut latency, old, 500, 50);
+        push_accepts(&mut latency, Instant::now(), 10, 10);
+        assert!(
+            latency.update_slo(&config).is_none(),
+            "slow samples aged out"
+        );
+        assert_eq!(latency.accept_window.len(), 10);
+        assert!(!latency.slo_breached);
+    }
+
+    #[test]
+    fn accept_window_is_capped() {
+        let mut latency = LatencyMetrics::default();
+        for _ in 0..SLO_WINDOW_MAX_SAMPLES + 5 {
+            latency.observe_accept("dev", CommandAction::Open, Duration::from_millis(1));
+        }
+        assert_eq!(latency.accept_window.len(), SLO_WINDOW_MAX_SAMPLES);
+        assert_eq!(
+            latency.accept[&("dev".to_string(), "OPEN")].count,
+            SLO_WINDOW_MAX_SAMPLES as u64 + 5
+        );
+    }
+
+    fn interlock_graph(interlocks: serde_json::Value) -> Graph {
+        serde_json::from_value(serde_json::json!({
+            "schema": "sentient-graph-v1",
+            "room_id": "room1",
+            "start": "start",
+            "nodes": {},
+            "interlocks": interlocks,
+        }))
+        .expect("graph json")
+    }
+
+    /// Door may only open while the motor reports stopped.
+    fn door_interlock() -> serde_json::Value {
+        serde_json::json!([{
+            "id": "door_requires_motor_stopped",
+            "devices": ["door_*"],
+            "actions": ["OPEN"],
+            "require": [{ "device_id": "motor", "pointer": "/running", "equals": false }],
+        }])
+    }
+
+    fn online_with_state(state: serde_json::Value) -> DeviceStatus {
+        let mut status = DeviceStatus::new();
+        status.is_offline = false;
+        status.last_state = Some(state);
+        status
+    }
+
+    fn devices_with_motor(status: DeviceStatus) -> std::collections::HashMap<String, DeviceStatus> {
+        std::collections::HashMap::from([("motor".to_string(), status)])
+    }
+
+    fn runtime_with_graph(graph: Graph, version: i64) -> RuntimeState {
+        let runner = GraphRunner {
+            graph: Some(graph),
+            graph_version: Some(version),
+            ..Default::default()
+        };
+        let mut runtime = RuntimeState::default();
+        runtime.set_interlocks(&runner);
+        runtime
+    }
+
+    #[test]
+    fn interlock_rule_guards_device_patterns_and_actions() {
+        let rule: InterlockRule = serde_json::from_value(serde_json::json!({
+            "id": "r",
+            "devices": ["door_*", "lift"],
+            "actions": ["OPEN", "MOVE"],
+            "require": [{ "device_id": "motor", "pointer": "/running", "equals": false }],
+        }))
+        .unwrap();
+        assert!(rule.guards("door_north", CommandAction::Open));
+        assert!(rule.guards("lift", CommandAction::Move));
+        assert!(!rule.guards("lift_2", CommandAction::Move));
+        assert!(!rule.guards("door_north", CommandAction::Close));
+        assert!(!rule.guards("motor", CommandAction::Open));
+
+        let all: InterlockRule = serde_json::from_value(serde_json::json!({
+            "id": "all",
+            "devices": ["*"],
+            "require": [{ "device_id": "estop", "pointer": "/armed", "equals": false }],
+        }))
+        .unwrap();
+        assert!(all.guards("anything", CommandAction::Set));
+        assert!(all.guards("door_north", CommandAction::Cancel));
+    }
+
+    #[test]
+    fn interlock_condition_holds_or_reports_why_not() {
+        let cond: InterlockCondition = serde_json::from_value(
+            serde_json::json!({ "device_id": "motor", "pointer": "/running", "equals": false }),
+        )
+        .unwrap();
+        let holds = devices_with_motor(online_with_state(serde_json::json!({ "running": false })));
+        assert_eq!(cond.failure(&holds), None);
+
+        let mismatch =
+            devices_with_motor(online_with_state(serde_json::json!({ "running": true })));
+        assert_eq!(
+            cond.failure(&mismatch),
+            Some(("MISMATCH", Some(serde_json::json!(true))))
+        );
+
+        let mut offline = online_with_state(serde_json::json!({ "running": false }));
+        offline.is_offline = true;
+        assert_eq!(
+            cond.failure(&devices_with_motor(offline)),
+            Some(("DEVICE_OFFLINE", None))
+        );
+
+        let missing_pointer = devices_with_motor(online_with_state(serde_json::json!({})));
+        assert_eq!(cond.failure(&missing_pointer), Some(("NO_STATE", None)));
+        assert_eq!(
+            cond.failure(&std::collections::HashMap::new()),
+            Some(("NO_STATE", None))
+        );
+
+        let not_equals: InterlockCondition = serde_json::from_value(serde_json::json!({
+            "device_id": "motor", "pointer": "/mode", "not_equals": "MANUAL",
+        }))
+        .unwrap();
+        let auto = devices_with_motor(online_with_state(serde_json::json!({ "mode": "AUTO" })));
+        assert_eq!(not_equals.failure(&auto), None);
+        let manual = devices_with_motor(online_with_state(serde_json::json!({ "mode": "MANUAL" })));
+        assert_eq!(
+            not_equals.failure(&manual),
+            Some(("MISMATCH", Some(serde_json::json!("MANUAL"))))
+        );
+    }
+
+    #[test]
+    fn interlock_violation_allows_and_denies() {
+        let runtime = runtime_with_graph(interlock_graph(door_interlock()), 3);
+        let stopped =
+            devices_with_motor(online_with_state(serde_json::json!({ "running": false })));
+        let running = devices_with_motor(online_with_state(serde_json::json!({ "running": true })));
+
+        assert!(runtime
+            .interlock_violation("door_north", CommandAction::Open, &stopped)
+            .is_none());
+        // Unguarded action / device are never blocked, whatever the motor does.
+        assert!(runtime
+            .interlock_violation("door_north", CommandAction::Close, &running)
+            .is_none());
+        assert!(runtime
+            .interlock_violation("light", CommandAction::Open, &running)
+            .is_none());
+
+        let violation = runtime
+            .interlock_violation("door_north", CommandAction::Open, &running)
+            .expect("denied");
+        assert_eq!(violation.rule.id, "door_requires_motor_stopped");
+        assert_eq!(violation.condition.device_id, "motor");
+        assert_eq!(violation.reason, "MISMATCH");
+        assert_eq!(violation.actual, Some(serde_json::json!(true)));
+    }
+
+    #[test]
+    fn interlock_violation_reports_first_failing_rule() {
+        let graph = interlock_graph(serde_json::json!([
+            {
+                "id": "motor_stopped",
+                "devices": ["door_north"],
+                "require": [{ "device_id": "motor", "pointer": "/running", "equals": false }],
+            },
+            {
+                "id": "motor_auto",
+                "devices": ["door_north"],
+                "require": [{ "device_id": "motor", "pointer": "/mode", "equals": "AUTO" }],
+            },
+        ]));
+        let runtime = runtime_with_graph(graph, 1);
+        let devices = devices_with_motor(online_with_state(
+            serde_json::json!({ "running": false, "mode": "MANUAL" }),
+        ));
+        let violation = runtime
+            .interlock_violation("door_north", CommandAction::Open, &devices)
+            .expect("denied");
+        assert_eq!(violation.rule.id, "motor_auto");
+
+        let devices = devices_with_motor(online_with_state(
+            serde_json::json!({ "running": true, "mode": "MANUAL" }),
+        ));
+        let violation = runtime
+            .interlock_violation("door_north", CommandAction::Open, &devices)
+            .expect("denied");
+        assert_eq!(violation.rule.id, "motor_stopped");
+    }
+
+    #[test]
+    fn interlocks_follow_the_loaded_graph_version() {
+        let running = devices_with_motor(online_with_state(serde_json::json!({ "running": true })));
+        let mut runtime = runtime_with_graph(interlock_graph(door_interlock()), 3);
+        assert_eq!(runtime.interlocks_graph_version, Some(3));
+        assert!(runtime
+            .interlock_violation("door_north", CommandAction::Open, &running)
+            .is_some());
+
+        // A new graph version without the rule replaces the old rules outright.
+        let runner = GraphRunner {
+            graph: Some(interlock_graph(serde_json::json!([]))),
+            graph_version: Some(4),
+            ..Default::default()
+        };
+        runtime.set_interlocks(&runner);
+        assert_eq!(runtime.interlocks_graph_version, Some(4));
+        assert!(runtime
+            .interlock_violation("door_north", CommandAction::Open, &running)
+            .is_none());
+
+        // Unloading the graph clears both.
+        runtime.set_interlocks(&GraphRunner::default());
+        assert!(runtime.interlocks.is_empty());
+        assert_eq!(runtime.interlocks_graph_version, None);
+    }
+
+    #[test]
+    fn validate_interlocks_rejects_malformed_rules() {
+        assert!(interlock_graph(door_interlock())
+            .validate_interlocks()
+            .is_ok());
+        let cond =
+            serde_json::json!({ "device_id": "motor", "pointer": "/running", "equals": false });
+        let bad = [
+            serde_json::json!([{ "id": " ", "devices": ["d"], "require": [cond] }]),
+            serde_json::json!([
+                { "id": "dup", "devices": ["d"], "require": [cond] },
+                { "id": "dup", "devices": ["e"], "require": [cond] },
+            ]),
+            serde_json::json!([{ "id": "r", "devices": [], "require": [cond] }]),
+            serde_json::json!([{ "id": "r", "devices": ["d"], "require": [] }]),
+            serde_json::json!([{ "id": "r", "devices": ["d"], "require": [
+                { "device_id": "motor", "pointer": "/running" }
+            ] }]),
+            serde_json::json!([{ "id": "r", "devices": ["d"], "require": [
+                { "device_id": "motor", "pointer": "/running", "equals": false, "not_equals": true }
+            ] }]),
+            serde_json::json!([{ "id": "r", "devices": ["d"], "require": [
+                { "device_id": "motor", "pointer": "running", "equals": false }
+            ] }]),
+        ];
+        for interlocks in bad {
+            assert!(
+                interlock_graph(interlocks.clone())
+                    .validate_interlocks()
+                    .is_err(),
+                "accepted {interlocks}"
+            );
+        }
+    }
+
+    fn manual_dispatch(
+        device_id: &str,
+        operator: Option<(&str, &str, Option<&str>)>,
+    ) -> CoreDispatchRequest {
+        let mut req = serde_json::json!({
+            "schema": SCHEMA_VERSION,
+            "room_id": "room1",
+            "device_id": device_id,
+            "action": "OPEN",
+        });
+        if let Some((sub, role, token)) = operator {
+            req["operator"] = serde_json::json!({ "sub": sub, "role": role, "token": token });
+        }
+        serde_json::from_value(req).expect("dispatch json")
+    }
+
+    fn runtime_with_lift_lock() -> RuntimeState {
+        let mut runtime = RuntimeState::default();
+        runtime.maintenance.insert(
+            (MaintenanceScope::Device, "lift".to_string()),
+            MaintenanceStatus {
+                scope: MaintenanceScope::Device,
+                target: "lift".to_string(),
+                owner: "tech-anna".to_string(),
+                owner_role: "TECH".to_string(),
+                note: None,
+                since_unix_ms: 1,
+            },
+        );
+        runtime
+    }
+
+    #[test]
+    fn maintenance_lets_tech_and_admin_through() {
+        let mut config = test_config();
+        config.core_control_token = None;
+        let runtime = runtime_with_lift_lock();
+        let devices = std::collections::HashMap::new();
+        let blocked = |operator| {
+            maintenance_block(
+                &config,
+                &runtime,
+                &devices,
+                &manual_dispatch("lift", operator),
+            )
+            .is_some()
+        };
+        assert!(!blocked(Some(("tech-bob", "TECH", None))));
+        assert!(!blocked(Some(("admin", "ADMIN", None))));
+        assert!(blocked(Some(("gm", "GM", None))));
+        assert!(blocked(None));
+        assert!(
+            maintenance_block(&config, &runtime, &devices, &manual_dispatch("door", None))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn maintenance_bypass_requires_the_control_token_when_configured() {
+        let mut config = test_config();
+        config.core_control_token = Some("secret".to_string());
+        let runtime = runtime_with_lift_lock();
+        let devices = std::collections::HashMap::new();
+        let blocked = |operator| {
+            maintenance_block(
+                &config,
+                &runtime,
+                &devices,
+                &manual_dispatch("lift", operator),
+            )
+            .is_some()
+        };
+        assert!(!blocked(Some(("tech-bob", "TECH", Some("secret")))));
+        assert!(blocked(Some(("tech-bob", "TECH", None))));
+        assert!(blocked(Some(("admin", "ADMIN", Some("guess")))));
+    }
+
+    #[test]
+    fn device_reported_maintenance_lets_only_technicians_through() {
+        let mut config = test_config();
+        config.core_control_token = None;
+        let runtime = RuntimeState::default();
+        let mut status = DeviceStatus::new();
+        status.last_reported_safety = Some(SafetyState {
+            kind: SafetyStateKind::Maintenance,
+            reason_code: None,
+            latched: false,
+        });
+        let devices = std::collections::HashMap::from([("lift".to_string(), status)]);
+        let block = maintenance_block(&config, &runtime, &devices, &manual_dispatch("lift", None));
+        assert!(matches!(block, Some((None, true))));
+        let tech = manual_dispatch("lift", Some(("tech-bob", "TECH", None)));
+        assert!(maintenance_block(&config, &runtime, &devices, &tech).is_none());
+    }
+
+    fn critical_fault_event(n: u64) -> DbEvent {
+        DbEvent {
+            room_id: "room1".to_string(),
+            device_id: None,
+            topic: "room/room1/core/fault".to_string(),
+            kind: "CORE_FAULT".to_string(),
+            observed_at_unix_ms: n,
+            payload: serde_json::json!({ "severity": "CRITICAL" }),
+        }
+    }
+
+    #[tokio::test]
+    async fn db_spill_round_trips_on_its_own_thread() {
+        let path = std::env::temp_dir().join(format!("sentient-spill-{}.jsonl", Uuid::new_v4()));
+        let spill = DbSpill::open(path.clone(), 1 << 20);
+        let unwritten = spill
+            .append_all(vec![critical_fault_event(1), critical_fault_event(2)])
+            .await;
+        assert!(unwritten.is_empty());
+        assert!(spill.try_append(critical_fault_event(3)));
+        let taken = spill.take_all().await;
+        let observed: Vec<u64> = taken.iter().map(|ev| ev.observed_at_unix_ms).collect();
+        assert_eq!(observed, vec![1, 2, 3]);
+        assert_eq!(spill.pending(), 0);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn db_spill_gives_back_events_past_max_bytes() {
+        let path = std::env::temp_dir().join(format!("sentient-spill-{}.jsonl", Uuid::new_v4()));
+        let spill = DbSpill::open(path.clone(), 10);
+        let unwritten = spill.append_all(vec![critical_fault_event(1)]).await;
+        assert_eq!(unwritten.len(), 1);
+        assert_eq!(spill.pending(), 0);
+        assert!(spill.take_all().await.is_empty());
+    }
+
+    #[test]
+    fn end_flapping_reports_transitions_before_clearing() {
+        let mut config = test_config();
+        config.device_flap_window_ms = 1_000;
+        let mut status = DeviceStatus::new();
+        status.liveness_changes.extend([100, 200, 300, 400]);
+        status.flapping = Some(FlapState {
+            since_unix_ms: 400,
+            suppressed_offline: 2,
+            suppressed_online: 1,
+        });
+
+        assert!(end_flapping(&config, "dev", &mut status, 1_400).is_none());
+        assert!(status.flapping.is_some());
+
+        let details = end_flapping(&config, "dev", &mut status, 1_401).expect("ended");
+        assert_eq!(details["transitions"], 4);
+        assert_eq!(details["flapping"], false);
+        assert_eq!(details["suppressed_offline"], 2);
+        assert_eq!(details["suppressed_online"], 1);
+        assert!(status.flapping.is_none());
+        assert!(status.liveness_changes.is_empty());
+        assert!(end_flapping(&config, "dev", &mut status, 5_000).is_none());
+    }
+
+    #[test]
+    fn env_registry_merges_maps_onto_default_entries() {
+        let mut config = test_config();
+        config.device_safety_class_json = Some(r#"{"lift":"CRITICAL"}"#.to_string());
+        config.device_concurrency_json = None;
+        config.device_offline_policy_json = None;
+        config.device_zone_json = Some(r#"{"lift":"stage","door":"stage"}"#.to_string());
+        config.device_liveness_json = Some(r#"{"door":{"heartbeat_interval_ms":500}}"#.to_string());
+        let loaded = device_registry_from_env(&config);
+
+        let lift = &loaded.devices["lift"];
+        assert_eq!(lift.safety_class, SafetyClass::Critical);
+        assert_eq!(lift.zone.as_deref(), Some("stage"));
+        assert!(lift.enabled);
+
+        let door = &loaded.devices["door"];
+        assert_eq!(door.safety_class, SafetyClass::NonCritical);
+        assert_eq!(door.zone.as_deref(), Some("stage"));
+        assert_eq!(door.heartbeat_interval_ms, Some(500));
+        assert_eq!(door.offline_ms, None);
+        assert_eq!(door.offline_policy, OfflinePolicy::default());
     }
 }
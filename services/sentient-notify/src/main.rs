@@ -1,6 +1,7 @@
-use std::time::Duration;
+use std::{net::SocketAddr, time::Duration};
 
-use sentient_protocol::{CoreFault, CoreStatus, SCHEMA_VERSION};
+use sentient_metrics::{Counter, CounterVec, Exposition};
+use sentient_protocol::{CoreFault, CoreStatus, FaultKind, Severity, SCHEMA_VERSION};
 use tokio::sync::mpsc;
 use tracing::{info, warn};
 
@@ -15,6 +16,8 @@ struct Config {
     webhook_url: Option<String>,
     core_status_timeout_ms: u64,
     startup_grace_ms: u64,
+    min_severity: Severity,
+    metrics_bind: Option<SocketAddr>,
 }
 
 impl Config {
@@ -45,6 +48,18 @@ impl Config {
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(15000);
+        let min_severity = match std::env::var("NOTIFY_MIN_SEVERITY").ok().as_deref() {
+            None | Some("") | Some("INFO") => Severity::Info,
+            Some("WARN") => Severity::Warn,
+            Some("CRITICAL") => Severity::Critical,
+            Some(other) => anyhow::bail!("invalid NOTIFY_MIN_SEVERITY={other}"),
+        };
+        // Prometheus scrape endpoint; empty disables it.
+        let metrics_bind = match std::env::var("NOTIFY_METRICS_BIND") {
+            Ok(v) if v.trim().is_empty() => None,
+            Ok(v) => Some(v.trim().parse()?),
+            Err(_) => Some(SocketAddr::from(([0, 0, 0, 0], 9103))),
+        };
 
         Ok(Self {
             room_id,
@@ -56,6 +71,8 @@ impl Config {
             webhook_url,
             core_status_timeout_ms,
             startup_grace_ms,
+            min_severity,
+            metrics_bind,
         })
     }
 }
@@ -67,6 +84,51 @@ enum MqttEvent {
     Publish(rumqttc::Publish),
 }
 
+static FAULTS: CounterVec = CounterVec::new(
+    "sentient_notify_faults_total",
+    "Faults seen (from MQTT or raised by the notify watchdogs) by kind and severity.",
+    &["kind", "severity"],
+);
+static WEBHOOKS: CounterVec = CounterVec::new(
+    "sentient_notify_webhooks_total",
+    "Webhook deliveries by result (SENT, FAILED, FILTERED by NOTIFY_MIN_SEVERITY).",
+    &["result"],
+);
+static MQTT_RECONNECTS: Counter = Counter::new(
+    "sentient_notify_mqtt_reconnects_total",
+    "Broker reconnections after the first connect.",
+);
+
+async fn serve_metrics(bind: SocketAddr) {
+    let app = axum::Router::new().route(
+        "/metrics",
+        axum::routing::get(|| async {
+            let mut out = Exposition::new();
+            out.metric(&FAULTS)
+                .metric(&WEBHOOKS)
+                .metric(&MQTT_RECONNECTS);
+            (
+                [(
+                    axum::http::header::CONTENT_TYPE,
+                    sentient_metrics::CONTENT_TYPE,
+                )],
+                out.finish(),
+            )
+        }),
+    );
+    let listener = match tokio::net::TcpListener::bind(bind).await {
+        Ok(l) => l,
+        Err(err) => {
+            warn!(error=%err, bind=%bind, "metrics endpoint disabled (bind failed)");
+            return;
+        }
+    };
+    info!(bind=%bind, "metrics listening");
+    if let Err(err) = axum::serve(listener, app).await {
+        warn!(error=%err, "metrics endpoint stopped");
+    }
+}
+
 fn unix_ms_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -83,6 +145,9 @@ async fn main() -> anyhow::Result<()> {
 
     let config = Config::from_env()?;
     info!(?config, "sentient-notify starting");
+    if let Some(bind) = config.metrics_bind {
+        tokio::spawn(serve_metrics(bind));
+    }
 
     let http = reqwest::Client::builder()
         .timeout(Duration::from_secs(3))
@@ -156,8 +221,8 @@ async fn main() -> anyhow::Result<()> {
                         CoreFault{
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "CORE_UNHEALTHY".to_string(),
-                            severity: "CRITICAL".to_string(),
+                            kind: FaultKind::CoreUnhealthy,
+                            severity: Severity::Critical,
                             message: "sentient-core status not updating; room should be paused and handled manually".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
                             details: serde_json::json!({
@@ -175,8 +240,8 @@ async fn main() -> anyhow::Result<()> {
                         CoreFault{
                             schema: SCHEMA_VERSION.to_string(),
                             room_id: config.room_id.clone(),
-                            kind: "CORE_RESTORED".to_string(),
-                            severity: "INFO".to_string(),
+                            kind: FaultKind::CoreRestored,
+                            severity: Severity::Info,
                             message: "sentient-core status updates restored".to_string(),
                             observed_at_unix_ms: unix_ms_now(),
                             details: serde_json::json!({}),
@@ -189,7 +254,7 @@ async fn main() -> anyhow::Result<()> {
                 match ev {
                     MqttEvent::Publish(p) => {
                         if p.topic == format!("room/{}/core/status", config.room_id) {
-                            match serde_json::from_slice::<CoreStatus>(&p.payload) {
+                            match sentient_protocol::decode_message::<CoreStatus>(&p.payload) {
                                 Ok(_) => {
                                     last_core_status_seen_unix_ms = Some(unix_ms_now());
                                 }
@@ -197,9 +262,8 @@ async fn main() -> anyhow::Result<()> {
                                     warn!(error=%err, "invalid core status payload");
                                 }
                             }
-                        } else if p.topic == format!("room/{}/audio/fault", config.room_id) {
-                            handle_core_fault(&config, &http, &p.payload).await;
-                        } else if p.topic == format!("room/{}/core/fault", config.room_id)
+                        } else if p.topic == format!("room/{}/audio/fault", config.room_id)
+                            || p.topic == format!("room/{}/core/fault", config.room_id)
                             || p.topic.starts_with(&format!("room/{}/core/device/", config.room_id))
                                 && p.topic.ends_with("/fault")
                         {
@@ -219,8 +283,8 @@ async fn main() -> anyhow::Result<()> {
                             CoreFault{
                                 schema: SCHEMA_VERSION.to_string(),
                                 room_id: config.room_id.clone(),
-                                kind: "BROKER_UNREACHABLE".to_string(),
-                                severity: "CRITICAL".to_string(),
+                                kind: FaultKind::BrokerUnreachable,
+                                severity: Severity::Critical,
                                 message: "Room MQTT broker unreachable; room should be paused and handled manually".to_string(),
                                 observed_at_unix_ms: unix_ms_now(),
                                 details: serde_json::json!({"error": err}),
@@ -230,6 +294,8 @@ async fn main() -> anyhow::Result<()> {
                     MqttEvent::Connected => {
                         if first_connected_unix_ms.is_none() {
                             first_connected_unix_ms = Some(unix_ms_now());
+                        } else {
+                            MQTT_RECONNECTS.inc();
                         }
                         if !broker_outage_active {
                             continue;
@@ -242,8 +308,8 @@ async fn main() -> anyhow::Result<()> {
                             CoreFault{
                                 schema: SCHEMA_VERSION.to_string(),
                                 room_id: config.room_id.clone(),
-                                kind: "BROKER_RESTORED".to_string(),
-                                severity: "INFO".to_string(),
+                                kind: FaultKind::BrokerRestored,
+                                severity: Severity::Info,
                                 message: "Room MQTT broker connection restored".to_string(),
                                 observed_at_unix_ms: unix_ms_now(),
                                 details: serde_json::json!({}),
@@ -259,7 +325,7 @@ async fn main() -> anyhow::Result<()> {
 }
 
 async fn handle_core_fault(config: &Config, http: &reqwest::Client, payload: &[u8]) {
-    let fault: CoreFault = match serde_json::from_slice(payload) {
+    let fault: CoreFault = match sentient_protocol::decode_message(payload) {
         Ok(v) => v,
         Err(err) => {
             warn!(error=%err, "invalid core fault payload");
@@ -271,11 +337,26 @@ async fn handle_core_fault(config: &Config, http: &reqwest::Client, payload: &[u
 }
 
 async fn emit_webhook(config: &Config, http: &reqwest::Client, fault: CoreFault) {
+    FAULTS.inc(&[fault.kind.as_str(), fault.severity.as_str()]);
+    if fault.severity < config.min_severity {
+        WEBHOOKS.inc(&["FILTERED"]);
+        return;
+    }
     let Some(url) = config.webhook_url.as_deref() else {
         return;
     };
-    if let Err(err) = http.post(url).json(&fault).send().await {
-        warn!(error=%err, "failed to POST notify webhook");
+    match http
+        .post(url)
+        .json(&fault)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+    {
+        Ok(_) => WEBHOOKS.inc(&["SENT"]),
+        Err(err) => {
+            WEBHOOKS.inc(&["FAILED"]);
+            warn!(error=%err, "failed to POST notify webhook");
+        }
     }
 }
 